@@ -0,0 +1,79 @@
+//! # Admin Authentication
+//!
+//! Bearer-token middleware for the `/api/v1/admin` routes.
+//!
+//! Requests must send `Authorization: Bearer <ADMIN_API_KEY>`. Without an
+//! `ADMIN_API_KEY` the admin API is disabled and every request is rejected.
+
+use crate::handlers::payment_error_to_response;
+use crate::state::AppState;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use pay_core::{PaymentError, PaymentResult};
+use tracing::warn;
+
+/// Reject requests without the admin API key
+pub async fn require_admin(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Response {
+    match authorize(state.config.admin_api_key.as_deref(), &headers) {
+        Ok(()) => next.run(request).await,
+        Err(e) => {
+            warn!("Rejected admin request to {}: {}", request.uri().path(), e);
+            payment_error_to_response(e).into_response()
+        }
+    }
+}
+
+/// Check the `Authorization` header against the configured key
+pub(crate) fn authorize(api_key: Option<&str>, headers: &HeaderMap) -> PaymentResult<()> {
+    let Some(api_key) = api_key.filter(|k| !k.is_empty()) else {
+        return Err(PaymentError::Unauthorized(
+            "admin API is disabled (ADMIN_API_KEY not set)".to_string(),
+        ));
+    };
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(|| PaymentError::Unauthorized("missing bearer token".to_string()))?;
+
+    if constant_time_eq(token.as_bytes(), api_key.as_bytes()) {
+        Ok(())
+    } else {
+        Err(PaymentError::Unauthorized("invalid API key".to_string()))
+    }
+}
+
+/// Compare without leaking the position of the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_authorize() {
+        assert!(authorize(Some("s3cret"), &bearer("s3cret")).is_ok());
+        assert!(authorize(Some("s3cret"), &bearer("s3cre")).is_err());
+        assert!(authorize(Some("s3cret"), &HeaderMap::new()).is_err());
+        // No key configured: nothing gets in
+        assert!(authorize(None, &bearer("")).is_err());
+        assert!(authorize(Some(""), &bearer("")).is_err());
+    }
+}
@@ -3,18 +3,30 @@
 //! Axum request handlers for the payment API.
 //! Supports multi-tenant checkout with site-specific URLs and statement descriptors.
 
+use crate::auth;
+use crate::reload::ReloadStatus;
 use crate::state::AppState;
 use axum::{
     body::Bytes,
-    extract::{Path, State},
-    http::{HeaderMap, StatusCode},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
-use pay_core::{Currency, LineItem, Order, PaymentError};
-use pay_stripe::{dispatch_webhook_event, CheckoutCompletedData, LoggingWebhookHandler};
+use chrono::{DateTime, Utc};
+use pay_core::{
+    BoxedPaymentStrategy, CheckoutSession, CheckoutStatus, Currency, CurrencyDisplay, Customer,
+    EventClaim, EventOutcome, Locale, Order, OrderReceipt, OrderStatus, OrderTotals, OrderTrigger,
+    PaymentError, PaymentResult, PauseCollection, PaymentStatus, Plan, PlanChange, PortalSession,
+    Price, Product, ProductCatalog, ProrationBehavior, Refund, RefundReason, RefundRequest, RefundTarget, StoredOrder,
+    RESERVED_METADATA_KEYS,
+    Subscription, SubscriptionLine, SubscriptionQuery, SubscriptionStatus, TaxLocation,
+    UsageAction, UsageRecord, UsageReport, UsageType, WebhookEvent, WebhookEventType,
+};
+use pay_stripe::{dispatch_webhook_event, ChargeData, CheckoutCompletedData};
 use serde::{Deserialize, Serialize};
-use tracing::{error, info, instrument};
+use std::collections::HashMap;
+use tracing::{error, info, instrument, warn};
 
 // =============================================================================
 // Request/Response Types
@@ -29,19 +41,40 @@ pub struct CreateCheckoutRequest {
     /// Convenience: single product_id (alternative to items array for single-product checkout)
     #[serde(default)]
     pub product_id: Option<String>,
+    /// Variant of `product_id` (required if the product has variants)
+    #[serde(default)]
+    pub variant_id: Option<String>,
     /// Customer email (optional)
     #[serde(default)]
     pub customer_email: Option<String>,
+    /// Customer name (optional, stored on the customer record)
+    #[serde(default)]
+    pub customer_name: Option<String>,
     /// Payment provider (optional, defaults to "stripe")
     #[serde(default)]
     pub provider: Option<String>,
     /// Idempotency key (optional)
     #[serde(default)]
     pub idempotency_key: Option<String>,
+    /// Currency to charge in (optional, defaults to the first product's primary currency)
+    #[serde(default)]
+    pub currency: Option<String>,
     /// Site ID for multi-tenant (optional, can also be in URL path)
     #[serde(default)]
     pub site_id: Option<String>,
-    /// Custom metadata to pass through to Stripe (e.g., consultation booking details)
+    /// Promotion code (optional)
+    #[serde(default)]
+    pub promo_code: Option<String>,
+    /// Customer country for tax (ISO 3166-1 alpha-2, optional).
+    /// Client-supplied, so table tax rates follow it unchecked; Stripe collects the
+    /// billing address and completion warns when its country differs.
+    #[serde(default)]
+    pub country: Option<String>,
+    /// Customer region/state for tax (optional, client-supplied like `country`)
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Custom metadata to pass through to Stripe (e.g., consultation booking details).
+    /// `order_id`, `site_id`, `statement_descriptor_suffix` and `promo_code` are reserved.
     #[serde(default)]
     pub metadata: std::collections::HashMap<String, String>,
 }
@@ -51,6 +84,9 @@ pub struct CreateCheckoutRequest {
 pub struct CheckoutItem {
     /// Product ID
     pub product_id: String,
+    /// Variant ID (required if the product has variants)
+    #[serde(default)]
+    pub variant_id: Option<String>,
     /// Quantity
     #[serde(default = "default_quantity")]
     pub quantity: u32,
@@ -60,6 +96,26 @@ fn default_quantity() -> u32 {
     1
 }
 
+/// Query parameters for the checkout success page
+#[derive(Debug, Deserialize)]
+pub struct CheckoutSuccessQuery {
+    /// Provider session ID
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Site whose locale should format amounts
+    #[serde(default)]
+    pub site_id: Option<String>,
+    /// Amount paid in smallest currency unit (display only)
+    #[serde(default)]
+    pub amount: Option<i64>,
+    /// Currency of `amount` (ISO 4217 code)
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// Locale override (e.g., "de-DE")
+    #[serde(default)]
+    pub locale: Option<String>,
+}
+
 /// Create checkout response
 #[derive(Debug, Serialize)]
 pub struct CreateCheckoutResponse {
@@ -70,6 +126,122 @@ pub struct CreateCheckoutResponse {
     /// Session expiration time
     #[serde(skip_serializing_if = "Option::is_none")]
     pub expires_at: Option<String>,
+    /// Subtotal, discount, tax and grand total
+    pub totals: OrderTotals,
+}
+
+/// Refund request (admin)
+#[derive(Debug, Default, Deserialize)]
+pub struct CreateRefundRequest {
+    /// Amount in smallest currency unit (omit to refund everything not yet refunded)
+    #[serde(default)]
+    pub amount: Option<i64>,
+    /// Reason reported to the provider
+    #[serde(default)]
+    pub reason: Option<RefundReason>,
+    /// Metadata stored with the refund
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    /// Idempotency key (send one to retry a refund safely; without it every request refunds)
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+/// Refund response (admin)
+#[derive(Debug, Serialize)]
+pub struct CreateRefundResponse {
+    /// The refund issued by the provider
+    pub refund: Refund,
+    /// Order status after the refund
+    pub status: OrderStatus,
+    /// Total refunded so far
+    pub refunded: Price,
+    /// Amount that can still be refunded
+    pub refundable: Price,
+}
+
+/// Subscription list filters
+#[derive(Debug, Deserialize)]
+pub struct ListSubscriptionsQuery {
+    /// Only subscriptions of this provider customer
+    pub customer_id: Option<String>,
+    /// Only subscriptions with this status
+    pub status: Option<SubscriptionStatus>,
+    /// Maximum number of results
+    pub limit: Option<u32>,
+}
+
+/// Cancel subscription request
+#[derive(Debug, Deserialize)]
+pub struct CancelSubscriptionRequest {
+    /// Cancel at the end of the current period (default) instead of now
+    #[serde(default = "default_at_period_end")]
+    pub at_period_end: bool,
+}
+
+impl Default for CancelSubscriptionRequest {
+    fn default() -> Self {
+        Self {
+            at_period_end: default_at_period_end(),
+        }
+    }
+}
+
+fn default_at_period_end() -> bool {
+    true
+}
+
+/// Change plan request
+#[derive(Debug, Deserialize)]
+pub struct ChangePlanRequest {
+    /// Catalog product to switch to (a subscription product of the site)
+    #[serde(default)]
+    pub product_id: Option<String>,
+    /// Variant of `product_id` (required if the product has variants)
+    #[serde(default)]
+    pub variant_id: Option<String>,
+    /// New quantity
+    #[serde(default)]
+    pub quantity: Option<u32>,
+    /// Item to change (needed if the subscription has several)
+    #[serde(default)]
+    pub item_id: Option<String>,
+    /// How the change is charged
+    #[serde(default)]
+    pub proration: ProrationBehavior,
+}
+
+/// Usage report request
+#[derive(Debug, Deserialize)]
+pub struct ReportUsageRequest {
+    /// Units used
+    pub quantity: u64,
+    /// Metered item to report for (needed if the subscription has several)
+    #[serde(default)]
+    pub item_id: Option<String>,
+    /// Add to ("increment", default) or replace ("set") the usage
+    #[serde(default)]
+    pub action: UsageAction,
+    /// When the usage happened (default: now)
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Idempotency key (a retried report with the same key is recorded once)
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+/// Billing portal request (one of `session_id`, `order_id` or `email`)
+#[derive(Debug, Deserialize)]
+pub struct CreatePortalRequest {
+    /// Checkout session of one of the customer's orders on this site
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Order placed by the customer on this site (requires the admin bearer token)
+    #[serde(default)]
+    pub order_id: Option<String>,
+    /// Customer email (requires the admin bearer token)
+    #[serde(default)]
+    pub email: Option<String>,
 }
 
 /// Error response
@@ -97,7 +269,7 @@ impl ErrorResponse {
 }
 
 
-fn payment_error_to_response(err: PaymentError) -> (StatusCode, Json<ErrorResponse>) {
+pub(crate) fn payment_error_to_response(err: PaymentError) -> (StatusCode, Json<ErrorResponse>) {
     let code = err.status_code();
     let response = ErrorResponse::new(err.to_string(), code);
     (StatusCode::from_u16(code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR), Json(response))
@@ -117,46 +289,62 @@ pub async fn health() -> impl IntoResponse {
 }
 
 /// Create a checkout session (legacy route - uses default site)
-#[instrument(skip(state, request), fields(items = request.items.len()))]
+#[instrument(skip(state, headers, request), fields(items = request.items.len()))]
 pub async fn create_checkout(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(request): Json<CreateCheckoutRequest>,
 ) -> Result<Json<CreateCheckoutResponse>, (StatusCode, Json<ErrorResponse>)> {
     // Use site_id from request body, or default to chargegun
     let site_id = request.site_id.clone();
-    create_checkout_internal(&state, request, site_id.as_deref()).await
+    create_checkout_internal(&state, &headers, request, site_id.as_deref()).await
 }
 
 /// Create a checkout session for a specific site (multi-tenant route)
-#[instrument(skip(state, request), fields(site_id = %site_id, items = request.items.len()))]
+#[instrument(skip(state, headers, request), fields(site_id = %site_id, items = request.items.len()))]
 pub async fn create_checkout_for_site(
     State(state): State<AppState>,
     Path(site_id): Path<String>,
+    headers: HeaderMap,
     Json(request): Json<CreateCheckoutRequest>,
 ) -> Result<Json<CreateCheckoutResponse>, (StatusCode, Json<ErrorResponse>)> {
     // Validate site exists
-    if !state.sites.has_site(&site_id) {
+    if !state.loaded().sites.has_site(&site_id) {
         return Err((
             StatusCode::NOT_FOUND,
             Json(ErrorResponse::new(format!("Site not found: {}", site_id), 404)),
         ));
     }
 
-    create_checkout_internal(&state, request, Some(&site_id)).await
+    create_checkout_internal(&state, &headers, request, Some(&site_id)).await
 }
 
 /// Internal checkout creation (shared logic)
 async fn create_checkout_internal(
     state: &AppState,
+    headers: &HeaderMap,
     request: CreateCheckoutRequest,
     site_id: Option<&str>,
 ) -> Result<Json<CreateCheckoutResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // The order ID, site and descriptor in the metadata are ours to set
+    if let Some(key) = request
+        .metadata
+        .keys()
+        .find(|key| RESERVED_METADATA_KEYS.contains(&key.as_str()))
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(format!("Metadata key is reserved: {}", key), 400)),
+        ));
+    }
+
     // Support single product_id as shorthand for items array
     let items = if !request.items.is_empty() {
         request.items
     } else if let Some(ref pid) = request.product_id {
         vec![CheckoutItem {
             product_id: pid.clone(),
+            variant_id: request.variant_id.clone(),
             quantity: 1,
         }]
     } else {
@@ -178,8 +366,41 @@ async fn create_checkout_internal(
         )
     })?;
 
+    // Resolve products before building the order (their prices decide the currency).
+    // Only products sold on the checkout's site, whose descriptor the customer sees.
+    let loaded = state.loaded();
+    let checkout_site = loaded.sites.get_or_default(site_id).map(|s| s.id.as_str());
+    let mut products = Vec::with_capacity(items.len());
+    for item in &items {
+        let product =
+            site_product(&loaded.catalog, &item.product_id, checkout_site).map_err(payment_error_to_response)?;
+
+        if !product.active {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new(
+                    format!("Product is not available: {}", item.product_id),
+                    400,
+                )),
+            ));
+        }
+
+        products.push((product, item.variant_id.as_deref(), item.quantity));
+    }
+
+    // Requested currency, or the primary currency of the first product (or its variant)
+    let currency = match request.currency.as_deref() {
+        Some(code) => code.parse::<Currency>().map_err(payment_error_to_response)?,
+        None => {
+            let (product, variant_id, _) = products[0];
+            variant_id
+                .and_then(|id| product.variant(id))
+                .map_or(product.price.currency, |v| v.price.currency)
+        }
+    };
+
     // Build order
-    let mut order = Order::new(Currency::USD);
+    let mut order = Order::new(currency);
 
     if let Some(email) = &request.customer_email {
         order.customer_email = Some(email.clone());
@@ -204,29 +425,86 @@ async fn create_checkout_internal(
         order.metadata.insert(key.clone(), value.clone());
     }
 
-    // Add line items
-    for item in &items {
-        let product = state.catalog.get(&item.product_id).ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(
-                    format!("Product not found: {}", item.product_id),
-                    404,
-                )),
-            )
+    // Add line items (rejects products not sold in the order currency and unknown variants)
+    for (product, variant_id, quantity) in products {
+        let added = match variant_id {
+            Some(variant_id) => order.add_variant(product, variant_id, quantity),
+            None => order.add_product(product, quantity),
+        };
+        added.map_err(|e| {
+            if matches!(e, PaymentError::UnsupportedCurrency { .. }) {
+                error!(
+                    "Product {} has no {} price (sold in {:?})",
+                    product.id,
+                    currency,
+                    product.currencies().collect::<Vec<_>>()
+                );
+            }
+            payment_error_to_response(e)
         })?;
+    }
 
-        if !product.active {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new(
-                    format!("Product is not available: {}", item.product_id),
-                    400,
-                )),
-            ));
+    // Apply promotion code (counts a redemption; released again if checkout fails)
+    let promo_code = request
+        .promo_code
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty());
+    let mut redeemed = false;
+    if let Some(code) = promo_code {
+        let discount = state
+            .redeem_promotion(code, &order, site_id)
+            .await
+            .map_err(|e| {
+                info!("Promotion rejected: {}", e);
+                payment_error_to_response(e)
+            })?;
+        if let Err(e) = order.apply_discount(discount) {
+            state.release_promotion(&order.id).await;
+            return Err(payment_error_to_response(e));
+        }
+        redeemed = true;
+    }
+
+    // Calculate tax on the discounted order (per-site provider)
+    if let Some(calculator) = state.tax_calculator_for_site(site_id) {
+        let location = request
+            .country
+            .as_deref()
+            .map(|country| TaxLocation {
+                country: country.to_string(),
+                region: request.region.clone(),
+            });
+        let tax = calculator
+            .calculate(&order, location.as_ref())
+            .and_then(|tax| order.apply_tax(tax));
+        if let Err(e) = tax {
+            if redeemed {
+                state.release_promotion(&order.id).await;
+            }
+            return Err(payment_error_to_response(e));
+        }
+    }
+
+    // Reject orders whose total overflows
+    let totals = match order.totals() {
+        Ok(totals) => totals,
+        Err(e) => {
+            if redeemed {
+                state.release_promotion(&order.id).await;
+            }
+            return Err(payment_error_to_response(e));
         }
+    };
 
-        order.add_item(LineItem::from_product(product, item.quantity));
+    // Only callers with the admin key (e.g. a site's backend that has signed the
+    // buyer in) may attach the stored customer; anyone can type any email
+    if let Some(email) = &request.customer_email {
+        if auth::authorize(state.config.admin_api_key.as_deref(), headers).is_ok() {
+            order.customer_id =
+                checkout_customer(state, strategy, email, request.customer_name.as_deref(), site_id)
+                    .await;
+        }
     }
 
     // Get site-specific URLs
@@ -234,31 +512,105 @@ async fn create_checkout_internal(
     let cancel_url = state.cancel_url_for_site(site_id);
 
     info!(
-        "Creating checkout: site={:?}, {} items, total={}, success_url={}",
+        "Creating checkout: site={:?}, {} items, total={}, tax={}, success_url={}",
         site_id,
         order.item_count(),
-        order.total().display(),
+        totals.total.display(),
+        totals.tax.display(),
         success_url
     );
 
+    // Persist the order before handing off to the provider
+    if let Err(e) = state.orders.insert(&order, site_id).await {
+        error!("Failed to store order {}: {}", order.id, e);
+        if redeemed {
+            state.release_promotion(&order.id).await;
+        }
+        return Err(payment_error_to_response(e));
+    }
+
     // Create checkout session
-    let session = strategy
+    let session = match strategy
         .create_checkout(&order, &success_url, &cancel_url)
         .await
-        .map_err(|e| {
+    {
+        Ok(session) => session,
+        Err(e) => {
             error!("Failed to create checkout: {}", e);
-            payment_error_to_response(e)
-        })?;
+            if redeemed {
+                state.release_promotion(&order.id).await;
+            }
+            transition_order(
+                state,
+                &order.id,
+                OrderTrigger::CheckoutFailed,
+                Some("checkout creation failed"),
+            )
+            .await;
+            return Err(payment_error_to_response(e));
+        }
+    };
 
     info!("Created checkout session: {}", session.session_id);
 
+    // Webhooks find the order by its session, so this write must not be lost silently
+    if let Err(e) = state.orders.save_session(&order.id, &session).await {
+        error!("Failed to store session for order {}: {}", order.id, e);
+    }
+    transition_order(
+        state,
+        &order.id,
+        OrderTrigger::CheckoutCreated,
+        Some(&session.session_id),
+    )
+    .await;
+
     Ok(Json(CreateCheckoutResponse {
         session_id: session.session_id,
         checkout_url: session.checkout_url,
         expires_at: session.expires_at.map(|t| t.to_rfc3339()),
+        totals,
     }))
 }
 
+/// Find or create the provider customer for an authenticated checkout.
+///
+/// Failures are logged and the checkout falls back to the email alone.
+async fn checkout_customer(
+    state: &AppState,
+    strategy: &BoxedPaymentStrategy,
+    email: &str,
+    name: Option<&str>,
+    site_id: Option<&str>,
+) -> Option<String> {
+    let provider = strategy.provider_name();
+    let result = async {
+        let mut customer = Customer::new(email);
+        customer.name = name.map(String::from);
+        if let Some(site_id) = site_id {
+            customer.add_site(site_id);
+        }
+        let customer = state.customers.upsert(&customer).await?;
+        let provider_id = strategy.ensure_customer(&customer).await?;
+        if let Some(ref id) = provider_id {
+            if customer.provider_id(provider) != Some(id.as_str()) {
+                state
+                    .customers
+                    .save(&customer.clone().with_provider_id(provider, id.clone()))
+                    .await?;
+            }
+        }
+        Ok::<_, PaymentError>(provider_id)
+    };
+    match result.await {
+        Ok(provider_id) => provider_id,
+        Err(e) => {
+            warn!("Checking out without a {} customer: {}", provider, e);
+            None
+        }
+    }
+}
+
 /// Handle Stripe webhook
 #[instrument(skip(state, headers, body))]
 pub async fn stripe_webhook(
@@ -299,91 +651,302 @@ pub async fn stripe_webhook(
         event.event_type, event.event_id
     );
 
+    // Stripe retries deliveries: process each event ID once
+    let claim = state.webhook_ledger.begin(&event).await.map_err(|e| {
+        error!("Failed to record webhook {}: {}", event.event_id, e);
+        payment_error_to_response(e)
+    })?;
+    match claim {
+        EventClaim::New => {}
+        EventClaim::Duplicate(entry) => {
+            info!(
+                "Duplicate webhook {} (processed {}), skipping",
+                entry.event_id, entry.updated_at
+            );
+            return Ok(StatusCode::OK);
+        }
+        EventClaim::InProgress(entry) => {
+            // 409 makes Stripe retry later, in case the running attempt fails
+            warn!("Webhook {} is already being processed", entry.event_id);
+            return Err(payment_error_to_response(PaymentError::IdempotencyConflict {
+                key: entry.event_id,
+            }));
+        }
+    }
+
+    // Hand the event to the worker pool so Stripe gets its 200 right away
+    if let Err(e) = state.webhook_queue.enqueue(&event).await {
+        error!("Failed to queue webhook {}: {}", event.event_id, e);
+        let failure = e.to_string();
+        if let Err(e) = state
+            .webhook_ledger
+            .record(&event.event_id, EventOutcome::Failed, Some(&failure))
+            .await
+        {
+            error!("Failed to record outcome of webhook {}: {}", event.event_id, e);
+        }
+        return Err(payment_error_to_response(e));
+    }
+    if let Err(e) = state
+        .webhook_ledger
+        .record(&event.event_id, EventOutcome::Queued, None)
+        .await
+    {
+        error!("Failed to record outcome of webhook {}: {}", event.event_id, e);
+    }
+    state.webhook_notify.notify_one();
+
+    Ok(StatusCode::OK)
+}
+
+/// Process a queued Stripe webhook event (called by the webhook workers).
+///
+/// Errors make the job retriable, so every step must be safe to repeat.
+pub(crate) async fn process_stripe_webhook(
+    state: &AppState,
+    event: WebhookEvent,
+) -> PaymentResult<()> {
     // Extract site_id from event metadata if present
     let site_id = event
         .raw_data
         .as_ref()
         .and_then(|d| d.get("metadata"))
         .and_then(|m| m.get("site_id"))
-        .and_then(|v| v.as_str());
+        .and_then(|v| v.as_str())
+        .map(String::from);
 
-    if let Some(sid) = site_id {
+    if let Some(ref sid) = site_id {
         info!("Webhook for site: {}", sid);
     }
 
-    // Extract consultation data BEFORE dispatch consumes the event
-    let consultation_forward = if matches!(&event.event_type, pay_core::WebhookEventType::CheckoutCompleted) {
-        match CheckoutCompletedData::from_event(&event) {
-            Ok(data) if data.metadata.contains_key("appointment_date") => {
-                let forward_site = data.metadata.get("site_id").cloned()
-                    .unwrap_or_else(|| "chargegun".to_string());
-                Some((forward_site, data))
-            }
-            _ => None,
+    // Update the stored order
+    update_order_from_webhook(state, &event).await.map_err(|e| {
+        error!("Failed to update order from webhook {}: {}", event.event_id, e);
+        e
+    })?;
+
+    dispatch_webhook_event(state.webhook_handler.as_ref(), event.clone())
+        .await
+        .map_err(|e| {
+            error!("Webhook handler error: {}", e);
+            e
+        })?;
+
+    // Queue deliveries to the site's outbound webhooks (sent and retried by the workers)
+    if let Some(site) = state.get_site(site_id.as_deref()) {
+        state.outbound.dispatch(&site, &event).await?;
+    }
+
+    Ok(())
+}
+
+/// Apply a trigger during checkout, logging (not returning) failures
+async fn transition_order(
+    state: &AppState,
+    order_id: &str,
+    trigger: OrderTrigger,
+    reason: Option<&str>,
+) {
+    match state.orders.transition(order_id, trigger, reason).await {
+        Ok(Some(transition)) => state.emit_transition(&transition),
+        Ok(None) => {}
+        Err(e) => error!("Failed to update order {}: {}", order_id, e),
+    }
+}
+
+/// Find the stored order a webhook event refers to.
+///
+/// Orders are found by their session or payment intent. The `order_id` in the
+/// event metadata only finds an order whose session the event belongs to, so
+/// a checkout cannot claim someone else's order.
+async fn find_webhook_order(
+    state: &AppState,
+    event: &WebhookEvent,
+) -> PaymentResult<Option<StoredOrder>> {
+    let data = event.raw_data.as_ref();
+    let object_id = data.and_then(|d| d.get("id")).and_then(|v| v.as_str());
+
+    let session_id = event
+        .session_id
+        .as_deref()
+        .or(object_id.filter(|id| id.starts_with("cs_")));
+    if let Some(session_id) = session_id {
+        if let Some(stored) = state.orders.find_by_session(session_id).await? {
+            return Ok(Some(stored));
         }
-    } else {
-        None
-    };
+    }
+    let payment_intent_id = event
+        .payment_intent_id
+        .as_deref()
+        .or(object_id.filter(|id| id.starts_with("pi_")));
+    if let Some(payment_intent_id) = payment_intent_id {
+        if let Some(stored) = state.orders.find_by_payment_intent(payment_intent_id).await? {
+            return Ok(Some(stored));
+        }
+    }
 
-    // Dispatch to existing handler (unchanged — LoggingWebhookHandler just logs)
-    let handler = LoggingWebhookHandler;
-    dispatch_webhook_event(&handler, event).map_err(|e| {
-        error!("Webhook handler error: {}", e);
-        payment_error_to_response(e)
-    })?;
+    let order_id = data
+        .and_then(|d| d.get("metadata"))
+        .and_then(|m| m.get("order_id"))
+        .and_then(|v| v.as_str());
+    let Some(order_id) = order_id else {
+        return Ok(None);
+    };
+    let Some(stored) = state.orders.get(order_id).await? else {
+        return Ok(None);
+    };
+    // Payment intent events can arrive before the session records the intent
+    let belongs = stored.session.as_ref().is_some_and(|session| match session_id {
+        Some(session_id) => session.session_id == session_id,
+        None => payment_intent_id.is_some() && session.payment_intent_id.is_none(),
+    });
+    if !belongs {
+        warn!(
+            "Webhook {} names order {} but is not for its session",
+            event.event_id, order_id
+        );
+        return Ok(None);
+    }
+    Ok(Some(stored))
+}
 
-    // === Forward consultation bookings to Vercel ===
-    if let Some((forward_site, data)) = consultation_forward {
-        if let Some(webhook_url) = state.webhook_forward_urls.get(&forward_site) {
-            info!(
-                "Forwarding consultation to Vercel: site={}, payment={:?}, amount={}",
-                forward_site, data.payment_intent_id, data.amount_total
-            );
+/// Record a webhook event on the stored order
+async fn update_order_from_webhook(state: &AppState, event: &WebhookEvent) -> PaymentResult<()> {
+    let Some(trigger) = OrderTrigger::from_webhook(event) else {
+        return Ok(());
+    };
+    let Some(stored) = find_webhook_order(state, event).await? else {
+        info!("Webhook {} does not match a stored order", event.event_id);
+        return Ok(());
+    };
 
-            // Build payload matching Vercel consultation-webhook.js expectations
-            let payload = serde_json::json!({
-                "firstName": data.metadata.get("client_first_name").cloned().unwrap_or_default(),
-                "lastName": data.metadata.get("client_last_name").cloned().unwrap_or_default(),
-                "email": data.metadata.get("client_email").cloned().unwrap_or_default(),
-                "appointmentDate": data.metadata.get("appointment_date").cloned().unwrap_or_default(),
-                "appointmentTime": data.metadata.get("appointment_time").cloned().unwrap_or_default(),
-                "duration": data.metadata.get("duration").and_then(|d| d.parse::<i32>().ok()).unwrap_or(1),
-                "amountCents": data.amount_total,
-                "stripePaymentId": data.payment_intent_id.clone().unwrap_or_else(|| "unknown".to_string()),
-            });
+    // Keep refunds made outside the admin API (e.g. in the Stripe dashboard) on the order
+    if event.event_type == WebhookEventType::RefundIssued {
+        if let Ok(charge) = ChargeData::from_event(event) {
+            for refund in charge.refunds() {
+                state.orders.save_refund(&stored.order.id, &refund.to_refund()?).await?;
+            }
+        }
+    }
 
-            match state.http_client
-                .post(webhook_url)
-                .json(&payload)
-                .send()
-                .await
-            {
-                Ok(resp) => {
-                    let status = resp.status();
-                    let body = resp.text().await.unwrap_or_default();
-                    if status.is_success() {
-                        info!("Vercel webhook success: {} | {}", status, body);
-                    } else {
-                        error!("Vercel webhook error: {} | {}", status, body);
-                    }
-                }
-                Err(e) => {
-                    error!("Failed to forward to Vercel: {}", e);
-                    // Don't fail the Stripe webhook — we received the event successfully.
-                    // The Vercel call can be retried manually if needed.
+    // The tax calculated before checkout was an estimate; keep what was charged,
+    // and the total paid, which refunds are checked against
+    if event.event_type == WebhookEventType::CheckoutCompleted {
+        if let Ok(completed) = CheckoutCompletedData::from_event(event) {
+            let tax = Price::from_cents(completed.amount_tax, completed.currency);
+            state.orders.save_tax(&stored.order.id, &tax).await?;
+            let taxed_for = stored
+                .order
+                .tax
+                .as_ref()
+                .filter(|tax| !tax.automatic)
+                .and_then(|tax| tax.location.as_ref());
+            if let (Some(location), Some(country)) = (taxed_for, &completed.billing_country) {
+                if !location.country.eq_ignore_ascii_case(country) {
+                    warn!(
+                        "Order {} was taxed for {} but billed to {}",
+                        stored.order.id, location.country, country
+                    );
                 }
             }
-        } else {
-            info!("No webhook_forward_url configured for site: {}", forward_site);
+            let paid = Price::from_cents(completed.amount_total, completed.currency);
+            state.orders.save_payment(&stored.order.id, &paid).await?;
         }
     }
 
-    Ok(StatusCode::OK)
+    // Remember the provider customer on the customer record
+    let customer_id = event
+        .raw_data
+        .as_ref()
+        .and_then(|d| d.get("customer"))
+        .and_then(|v| v.as_str());
+    if let Some(customer_id) = customer_id {
+        if let Err(e) = link_customer(state, event, &stored, customer_id).await {
+            warn!("Failed to record customer {} for webhook {}: {}", customer_id, event.event_id, e);
+        }
+    }
+
+    // Keep the session in sync with what Stripe reports
+    if let Some(mut session) = stored.session.clone() {
+        if session.session_id == event.session_id.as_deref().unwrap_or_default() {
+            session.payment_intent_id = event
+                .payment_intent_id
+                .clone()
+                .or(session.payment_intent_id);
+            session.customer_id = customer_id.map(String::from).or(session.customer_id);
+            session.status = match trigger {
+                OrderTrigger::PaymentSucceeded => CheckoutStatus::Complete,
+                OrderTrigger::SessionExpired => CheckoutStatus::Expired,
+                OrderTrigger::PaymentFailed => CheckoutStatus::Failed,
+                _ => session.status,
+            };
+            state.orders.save_session(&stored.order.id, &session).await?;
+        }
+    }
+
+    match state
+        .orders
+        .transition(&stored.order.id, trigger, Some(&event.event_id))
+        .await
+    {
+        Ok(Some(transition)) => {
+            // An abandoned checkout frees its promotion redemption
+            if transition.to == OrderStatus::Expired {
+                state.release_promotion(&stored.order.id).await;
+            }
+            state.emit_transition(&transition);
+        }
+        Ok(None) => {}
+        // Out-of-order or stale events must not make Stripe retry forever
+        Err(e @ PaymentError::InvalidTransition { .. }) => {
+            warn!("Ignoring webhook {} for order {}: {}", event.event_id, stored.order.id, e);
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(())
+}
+
+/// Store a webhook's provider customer ID on the buyer's customer record
+async fn link_customer(
+    state: &AppState,
+    event: &WebhookEvent,
+    stored: &StoredOrder,
+    customer_id: &str,
+) -> PaymentResult<()> {
+    let email = event
+        .customer_email
+        .as_deref()
+        .or(stored.order.customer_email.as_deref());
+    let mut customer = match email {
+        Some(email) => Customer::new(email),
+        // Without an email only an existing record can be updated
+        None => match state.customers.find_by_provider_id(&event.provider, customer_id).await? {
+            Some(customer) => customer,
+            None => return Ok(()),
+        },
+    };
+    customer = customer.with_provider_id(&event.provider, customer_id);
+    // Keep a name we already have when the provider sends none
+    let name = event
+        .raw_data
+        .as_ref()
+        .and_then(|d| d.get("customer_details"))
+        .and_then(|d| d.get("name"))
+        .and_then(|v| v.as_str());
+    if let Some(name) = name {
+        customer.name = Some(name.to_string());
+    }
+    if let Some(ref site_id) = stored.site_id {
+        customer.add_site(site_id.clone());
+    }
+    state.customers.upsert(&customer).await?;
+    Ok(())
 }
 
 /// Get products list (all sites)
 pub async fn list_products(State(state): State<AppState>) -> impl IntoResponse {
-    let products: Vec<_> = state.catalog.active_products().collect();
+    let loaded = state.loaded();
+    let products: Vec<_> = loaded.catalog.active_products().collect();
     Json(serde_json::json!({
         "products": products,
         "count": products.len()
@@ -395,7 +958,8 @@ pub async fn list_products_for_site(
     State(state): State<AppState>,
     Path(site_id): Path<String>,
 ) -> impl IntoResponse {
-    let products: Vec<_> = state.catalog.active_products_for_site(&site_id).collect();
+    let loaded = state.loaded();
+    let products: Vec<_> = loaded.catalog.active_products_for_site(&site_id).collect();
     Json(serde_json::json!({
         "site_id": site_id,
         "products": products,
@@ -403,27 +967,69 @@ pub async fn list_products_for_site(
     }))
 }
 
-/// Get single product
+/// Query parameters for the legacy product route
+#[derive(Debug, Deserialize)]
+pub struct ProductQuery {
+    /// Site to look the product up on (default site when omitted)
+    pub site_id: Option<String>,
+}
+
+/// Get single product (legacy route - default site, or `?site_id=`)
 pub async fn get_product(
     State(state): State<AppState>,
     Path(product_id): Path<String>,
+    Query(query): Query<ProductQuery>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let product = state.catalog.get(&product_id).ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(
-                format!("Product not found: {}", product_id),
-                404,
-            )),
-        )
-    })?;
+    let loaded = state.loaded();
+    let site = match query.site_id {
+        Some(ref site_id) if !loaded.sites.has_site(site_id) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse::new(format!("Site not found: {}", site_id), 404)),
+            ));
+        }
+        Some(ref site_id) => Some(site_id.as_str()),
+        None => loaded.sites.default_site().map(|s| s.id.as_str()),
+    };
+    let product = site_product(&loaded.catalog, &product_id, site).map_err(payment_error_to_response)?;
+    Ok(Json(product.clone()))
+}
 
+/// Get a product sold on a specific site
+pub async fn get_product_for_site(
+    State(state): State<AppState>,
+    Path((site_id, product_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let loaded = state.loaded();
+    if !loaded.sites.has_site(&site_id) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse::new(format!("Site not found: {}", site_id), 404)),
+        ));
+    }
+    let product =
+        site_product(&loaded.catalog, &product_id, Some(&site_id)).map_err(payment_error_to_response)?;
     Ok(Json(product.clone()))
 }
 
+/// Look up a product on a site (any site when there is none configured)
+fn site_product<'a>(
+    catalog: &'a ProductCatalog,
+    product_id: &str,
+    site_id: Option<&str>,
+) -> PaymentResult<&'a Product> {
+    match site_id {
+        Some(site_id) => catalog.resolve_for_site(product_id, site_id),
+        None => catalog.get(product_id).ok_or_else(|| PaymentError::ProductNotFound {
+            product_id: product_id.to_string(),
+        }),
+    }
+}
+
 /// List all registered sites
 pub async fn list_sites(State(state): State<AppState>) -> impl IntoResponse {
-    let sites: Vec<_> = state.sites.active_sites().collect();
+    let loaded = state.loaded();
+    let sites: Vec<_> = loaded.sites.active_sites().collect();
     Json(serde_json::json!({
         "sites": sites,
         "count": sites.len()
@@ -435,7 +1041,8 @@ pub async fn get_site(
     State(state): State<AppState>,
     Path(site_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let site = state.sites.get(&site_id).ok_or_else(|| {
+    let loaded = state.loaded();
+    let site = loaded.sites.get(&site_id).ok_or_else(|| {
         (
             StatusCode::NOT_FOUND,
             Json(ErrorResponse::new(
@@ -448,25 +1055,623 @@ pub async fn get_site(
     Ok(Json(site.clone()))
 }
 
+/// Get an order from the order store (admin)
+pub async fn get_order(
+    State(state): State<AppState>,
+    Path(order_id): Path<String>,
+) -> Result<Json<OrderReceipt>, (StatusCode, Json<ErrorResponse>)> {
+    let stored = state
+        .orders
+        .get(&order_id)
+        .await
+        .map_err(payment_error_to_response)?
+        .ok_or_else(|| payment_error_to_response(PaymentError::OrderNotFound { order_id }))?;
+
+    OrderReceipt::from_stored(&stored)
+        .map(Json)
+        .map_err(payment_error_to_response)
+}
+
+/// Loaded catalog and site registry version (admin)
+pub async fn get_config_status(State(state): State<AppState>) -> Json<ReloadStatus> {
+    Json(state.config_handle.status())
+}
+
+/// Reload the catalog and site registry now (admin)
+///
+/// Fails (keeping the current version) if the files do not parse.
+#[instrument(skip(state))]
+pub async fn reload_config(
+    State(state): State<AppState>,
+) -> Result<Json<ReloadStatus>, (StatusCode, Json<ErrorResponse>)> {
+    state
+        .config_handle
+        .reload()
+        .map(|_| Json(state.config_handle.status()))
+        .map_err(|e| payment_error_to_response(PaymentError::Configuration(e.to_string())))
+}
+
+/// Mark a paid order as delivered (admin)
+#[instrument(skip(state))]
+pub async fn fulfill_order(
+    State(state): State<AppState>,
+    Path(order_id): Path<String>,
+) -> Result<Json<OrderReceipt>, (StatusCode, Json<ErrorResponse>)> {
+    let transition = state
+        .orders
+        .transition(&order_id, OrderTrigger::Fulfilled, Some("admin"))
+        .await
+        .map_err(payment_error_to_response)?;
+    if let Some(transition) = transition {
+        state.emit_transition(&transition);
+    }
+
+    let stored = state
+        .orders
+        .get(&order_id)
+        .await
+        .map_err(payment_error_to_response)?
+        .ok_or_else(|| payment_error_to_response(PaymentError::OrderNotFound { order_id }))?;
+    OrderReceipt::from_stored(&stored)
+        .map(Json)
+        .map_err(payment_error_to_response)
+}
+
+/// Refund all or part of an order's payment (admin)
+#[instrument(skip(state, request))]
+pub async fn create_refund(
+    State(state): State<AppState>,
+    Path(order_id): Path<String>,
+    request: Option<Json<CreateRefundRequest>>,
+) -> Result<(StatusCode, Json<CreateRefundResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let Json(request) = request.unwrap_or_default();
+    refund_order(&state, &order_id, request)
+        .await
+        .map(|response| (StatusCode::CREATED, Json(response)))
+        .map_err(payment_error_to_response)
+}
+
+/// Reserve a refund against the stored order, issue it and record it
+async fn refund_order(
+    state: &AppState,
+    order_id: &str,
+    request: CreateRefundRequest,
+) -> PaymentResult<CreateRefundResponse> {
+    let stored = state
+        .orders
+        .get(order_id)
+        .await?
+        .ok_or_else(|| PaymentError::OrderNotFound {
+            order_id: order_id.to_string(),
+        })?;
+
+    let session = stored.session.as_ref();
+    let target = match session {
+        Some(CheckoutSession {
+            payment_intent_id: Some(payment_intent_id),
+            ..
+        }) => RefundTarget::PaymentIntent(payment_intent_id.clone()),
+        Some(session) => RefundTarget::Session(session.session_id.clone()),
+        None => RefundTarget::Order(order_id.to_string()),
+    };
+    let strategy = state
+        .strategies
+        .get_or_default(session.map(|s| s.provider.as_str()))
+        .ok_or_else(|| PaymentError::Configuration("No payment provider configured".to_string()))?;
+
+    // Held until the provider answers, so a concurrent refund cannot pass the same check
+    let reservation = state.orders.reserve_refund(order_id, request.amount).await?;
+    let mut refund_request = RefundRequest::new(target)
+        .with_amount(reservation.amount.clone())
+        .with_metadata("order_id", order_id);
+    refund_request.idempotency_key = request.idempotency_key;
+    refund_request.reason = request.reason;
+    refund_request.metadata.extend(request.metadata);
+    if let Some(ref site_id) = stored.site_id {
+        refund_request = refund_request.with_metadata("site_id", site_id.as_str());
+    }
+
+    let refund = match strategy.refund(&refund_request).await {
+        Ok(refund) => refund,
+        Err(e) => {
+            if let Err(release) = state.orders.settle_refund(order_id, &reservation.id, None).await {
+                error!("Failed to release refund reservation on order {}: {}", order_id, release);
+            }
+            return Err(e);
+        }
+    };
+    state.orders.settle_refund(order_id, &reservation.id, Some(&refund)).await?;
+    info!(
+        "Refunded {} of order {} ({}, {:?})",
+        refund.amount.display(),
+        order_id,
+        refund.id,
+        refund.status
+    );
+
+    let stored = state
+        .orders
+        .get(order_id)
+        .await?
+        .ok_or_else(|| PaymentError::OrderNotFound {
+            order_id: order_id.to_string(),
+        })?;
+    let paid = stored.paid_amount()?;
+    let refunded = stored.refunded_amount();
+
+    let mut status = stored.status;
+    if refund.status.is_effective() {
+        let trigger = if refunded >= paid.amount {
+            OrderTrigger::Refunded
+        } else {
+            OrderTrigger::PartiallyRefunded
+        };
+        match state.orders.transition(order_id, trigger, Some(&refund.id)).await {
+            Ok(Some(transition)) => {
+                status = transition.to;
+                state.emit_transition(&transition);
+            }
+            Ok(None) => {}
+            Err(e) => error!("Failed to update order {}: {}", order_id, e),
+        }
+    }
+
+    Ok(CreateRefundResponse {
+        refund,
+        status,
+        refunded: Price::from_cents(refunded, paid.currency),
+        refundable: Price::from_cents((paid.amount - refunded).max(0), paid.currency),
+    })
+}
+
+/// List a site's subscriptions (admin)
+pub async fn list_subscriptions(
+    State(state): State<AppState>,
+    Path(site_id): Path<String>,
+    Query(params): Query<ListSubscriptionsQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    if !state.loaded().sites.has_site(&site_id) {
+        return Err(payment_error_to_response(PaymentError::SiteNotFound { site_id }));
+    }
+    let query = SubscriptionQuery {
+        site_id: Some(site_id.clone()),
+        customer_id: params.customer_id,
+        status: params.status,
+        limit: params.limit,
+    };
+    let subscriptions = state
+        .subscriptions
+        .list_subscriptions(&query)
+        .await
+        .map_err(payment_error_to_response)?;
+    Ok(Json(serde_json::json!({
+        "site_id": site_id,
+        "subscriptions": subscriptions,
+        "count": subscriptions.len()
+    })))
+}
+
+/// Get a site's subscription (admin)
+pub async fn get_subscription(
+    State(state): State<AppState>,
+    Path((site_id, subscription_id)): Path<(String, String)>,
+) -> Result<Json<Subscription>, (StatusCode, Json<ErrorResponse>)> {
+    site_subscription(&state, &site_id, &subscription_id)
+        .await
+        .map(Json)
+        .map_err(payment_error_to_response)
+}
+
+/// Cancel a subscription at period end (default) or now (admin)
+#[instrument(skip(state, request))]
+pub async fn cancel_subscription(
+    State(state): State<AppState>,
+    Path((site_id, subscription_id)): Path<(String, String)>,
+    request: Option<Json<CancelSubscriptionRequest>>,
+) -> Result<Json<Subscription>, (StatusCode, Json<ErrorResponse>)> {
+    let Json(request) = request.unwrap_or_default();
+    let result = async {
+        site_subscription(&state, &site_id, &subscription_id).await?;
+        if request.at_period_end {
+            state.subscriptions.cancel_at_period_end(&subscription_id).await
+        } else {
+            state.subscriptions.cancel_now(&subscription_id).await
+        }
+    };
+    result.await.map(Json).map_err(payment_error_to_response)
+}
+
+/// Pause payment collection (admin)
+#[instrument(skip(state, request))]
+pub async fn pause_subscription(
+    State(state): State<AppState>,
+    Path((site_id, subscription_id)): Path<(String, String)>,
+    request: Option<Json<PauseCollection>>,
+) -> Result<Json<Subscription>, (StatusCode, Json<ErrorResponse>)> {
+    let Json(pause) = request.unwrap_or_default();
+    let result = async {
+        site_subscription(&state, &site_id, &subscription_id).await?;
+        state.subscriptions.pause(&subscription_id, &pause).await
+    };
+    result.await.map(Json).map_err(payment_error_to_response)
+}
+
+/// Resume payment collection and undo a pending cancellation (admin)
+#[instrument(skip(state))]
+pub async fn resume_subscription(
+    State(state): State<AppState>,
+    Path((site_id, subscription_id)): Path<(String, String)>,
+) -> Result<Json<Subscription>, (StatusCode, Json<ErrorResponse>)> {
+    let result = async {
+        site_subscription(&state, &site_id, &subscription_id).await?;
+        state.subscriptions.resume(&subscription_id).await
+    };
+    result.await.map(Json).map_err(payment_error_to_response)
+}
+
+/// Switch a subscription to another plan and/or quantity (admin)
+#[instrument(skip(state, request))]
+pub async fn change_subscription_plan(
+    State(state): State<AppState>,
+    Path((site_id, subscription_id)): Path<(String, String)>,
+    Json(request): Json<ChangePlanRequest>,
+) -> Result<Json<Subscription>, (StatusCode, Json<ErrorResponse>)> {
+    let result = async {
+        let current = site_subscription(&state, &site_id, &subscription_id).await?;
+
+        let mut change = PlanChange {
+            item_id: request.item_id,
+            plan: None,
+            quantity: request.quantity,
+            proration: request.proration,
+        };
+        if let Some(product_id) = request.product_id {
+            let loaded = state.loaded();
+            let product = loaded
+                .catalog
+                .get_for_site(&product_id, &site_id)
+                .filter(|p| p.active)
+                .ok_or(PaymentError::ProductNotFound { product_id })?;
+            // Keep the currency the customer is billed in
+            let currency = current
+                .lines
+                .iter()
+                .find_map(|l| l.unit_price.as_ref().map(|p| p.currency))
+                .unwrap_or(product.price.currency);
+            change.plan = Some(match request.variant_id.as_deref() {
+                Some(variant_id) => Plan::from_variant(product, variant_id, currency)?,
+                None => Plan::from_product(product, currency)?,
+            });
+        }
+        state.subscriptions.change_plan(&subscription_id, &change).await
+    };
+    result.await.map(Json).map_err(payment_error_to_response)
+}
+
+/// Report usage of a subscription's metered item (admin)
+#[instrument(skip(state, request))]
+pub async fn report_subscription_usage(
+    State(state): State<AppState>,
+    Path((site_id, subscription_id)): Path<(String, String)>,
+    Json(request): Json<ReportUsageRequest>,
+) -> Result<Json<UsageReport>, (StatusCode, Json<ErrorResponse>)> {
+    let result = async {
+        let subscription = site_subscription(&state, &site_id, &subscription_id).await?;
+        let metered: Vec<&SubscriptionLine> = subscription
+            .lines
+            .iter()
+            .filter(|l| l.usage_type == UsageType::Metered)
+            .collect();
+        let line = match request.item_id {
+            Some(ref item_id) => metered.iter().find(|l| l.item_id == *item_id),
+            None if metered.len() == 1 => metered.first(),
+            None => {
+                return Err(PaymentError::InvalidRequest(format!(
+                    "subscription {} has {} metered items, choose one with item_id",
+                    subscription_id,
+                    metered.len()
+                )))
+            }
+        }
+        .ok_or_else(|| {
+            PaymentError::InvalidRequest(format!(
+                "subscription {} has no metered item {}",
+                subscription_id,
+                request.item_id.as_deref().unwrap_or_default()
+            ))
+        })?;
+
+        let mut record = UsageRecord::new(line.item_id.as_str(), request.quantity)
+            .with_action(request.action);
+        record.timestamp = request.timestamp;
+        record.idempotency_key = request.idempotency_key;
+        state.subscriptions.report_usage(&record).await
+    };
+    result.await.map(Json).map_err(payment_error_to_response)
+}
+
+/// Get a subscription, hiding those sold on other sites
+async fn site_subscription(
+    state: &AppState,
+    site_id: &str,
+    subscription_id: &str,
+) -> PaymentResult<Subscription> {
+    if !state.loaded().sites.has_site(site_id) {
+        return Err(PaymentError::SiteNotFound {
+            site_id: site_id.to_string(),
+        });
+    }
+    let subscription = state.subscriptions.retrieve_subscription(subscription_id).await?;
+    if subscription.site_id() != Some(site_id) {
+        return Err(PaymentError::SubscriptionNotFound {
+            subscription_id: subscription_id.to_string(),
+        });
+    }
+    Ok(subscription)
+}
+
+/// Open a billing portal session for a site's customer
+///
+/// The customer is found from the checkout session of one of their orders
+/// (only the buyer has it) or, for admin callers, by order ID or email. The
+/// return URL always comes from the site config.
+#[instrument(skip(state, headers, request))]
+pub async fn create_portal_session(
+    State(state): State<AppState>,
+    Path(site_id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<CreatePortalRequest>,
+) -> Result<Json<PortalSession>, (StatusCode, Json<ErrorResponse>)> {
+    let result = async {
+        let loaded = state.loaded();
+        let site = loaded
+            .sites
+            .get(&site_id)
+            .ok_or_else(|| PaymentError::SiteNotFound { site_id: site_id.clone() })?;
+        let portal = site.portal.as_ref().ok_or_else(|| {
+            PaymentError::InvalidRequest(format!("Site {} has no billing portal", site_id))
+        })?;
+
+        // Order IDs and emails are not secret, so only admins may use them
+        let admin = || auth::authorize(state.config.admin_api_key.as_deref(), &headers);
+        let stored = match (request.session_id, request.order_id) {
+            (Some(session_id), _) => Some(
+                state
+                    .orders
+                    .find_by_session(&session_id)
+                    .await?
+                    .ok_or(PaymentError::SessionNotFound { session_id })?,
+            ),
+            (None, Some(order_id)) => {
+                admin()?;
+                Some(
+                    state
+                        .orders
+                        .get(&order_id)
+                        .await?
+                        .ok_or(PaymentError::OrderNotFound { order_id })?,
+                )
+            }
+            (None, None) => None,
+        };
+        let customer_id = match (stored, request.email) {
+            (Some(stored), _) => {
+                if stored.site_id.as_deref() != Some(site_id.as_str()) {
+                    return Err(PaymentError::OrderNotFound {
+                        order_id: stored.order.id,
+                    });
+                }
+                stored.session.and_then(|s| s.customer_id).ok_or_else(|| {
+                    PaymentError::InvalidRequest(format!(
+                        "Order {} has no customer yet",
+                        stored.order.id
+                    ))
+                })?
+            }
+            (None, Some(email)) => {
+                admin()?;
+                state
+                    .portal
+                    .find_customer_by_email(&email)
+                    .await?
+                    .ok_or_else(|| PaymentError::InvalidRequest("No customer with that email".to_string()))?
+            }
+            (None, None) => {
+                return Err(PaymentError::InvalidRequest(
+                    "One of session_id, order_id or email is required".to_string(),
+                ))
+            }
+        };
+
+        let return_url = site.portal_return_url();
+        state
+            .portal
+            .create_portal_session(&customer_id, portal, &return_url)
+            .await
+    };
+    result.await.map(Json).map_err(payment_error_to_response)
+}
+
+/// Get the order behind a checkout session (order store, then provider)
+pub async fn get_checkout_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> Result<Json<OrderReceipt>, (StatusCode, Json<ErrorResponse>)> {
+    lookup_session(&state, &session_id)
+        .await
+        .map(Json)
+        .map_err(payment_error_to_response)
+}
+
+/// Look up a checkout session's receipt.
+///
+/// The order store is authoritative; sessions it does not know are retrieved
+/// from the provider. A stored order still awaiting payment is checked with
+/// the provider too, since customers reach the success page before the
+/// webhook does.
+async fn lookup_session(state: &AppState, session_id: &str) -> PaymentResult<OrderReceipt> {
+    let Some(stored) = state.orders.find_by_session(session_id).await? else {
+        let strategy = state.default_strategy().ok_or_else(|| {
+            PaymentError::Configuration("No payment provider configured".to_string())
+        })?;
+        return strategy.retrieve_checkout(session_id).await;
+    };
+
+    let mut receipt = OrderReceipt::from_stored(&stored)?;
+    if stored.status == OrderStatus::AwaitingPayment {
+        let provider = stored.session.as_ref().map(|s| s.provider.as_str());
+        if let Some(strategy) = state.strategies.get_or_default(provider) {
+            match strategy.retrieve_checkout(session_id).await {
+                Ok(remote) if remote.is_paid() => {
+                    receipt.status = remote.status;
+                    receipt.payment_status = remote.payment_status;
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to refresh session {}: {}", session_id, e),
+            }
+        }
+    }
+    Ok(receipt)
+}
+
+/// Pick the locale for formatting prices: explicit request, then site config,
+/// then the browser's Accept-Language, then en-US
+fn resolve_locale(
+    state: &AppState,
+    requested: Option<&str>,
+    site_id: Option<&str>,
+    headers: &HeaderMap,
+) -> Locale {
+    requested
+        .and_then(Locale::from_tag)
+        .or_else(|| {
+            let loaded = state.loaded();
+            site_id.and_then(|id| loaded.sites.get(id)).and_then(|s| s.price_locale())
+        })
+        .or_else(|| {
+            headers
+                .get(header::ACCEPT_LANGUAGE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(Locale::from_accept_language)
+        })
+        .unwrap_or_default()
+}
+
+/// Escape text for safe inclusion in HTML
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
 /// Checkout success page
 pub async fn checkout_success(
-    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<CheckoutSuccessQuery>,
 ) -> impl IntoResponse {
-    let session_id = params.get("session_id").map(|s| s.as_str()).unwrap_or("unknown");
+    let receipt = match params.session_id.as_deref() {
+        Some(session_id) => match lookup_session(&state, session_id).await {
+            Ok(receipt) => Some(receipt),
+            Err(e) => {
+                warn!("No receipt for session {}: {}", session_id, e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let session_id = html_escape(params.session_id.as_deref().unwrap_or("unknown"));
+    let site_id = params
+        .site_id
+        .as_deref()
+        .or_else(|| receipt.as_ref().and_then(|r| r.site_id.as_deref()));
+    let locale = resolve_locale(&state, params.locale.as_deref(), site_id, &headers);
+
+    let details = match &receipt {
+        Some(receipt) => receipt_html(receipt, &locale),
+        // Optional amount line, formatted for the customer's locale
+        None => match (params.amount, params.currency.as_deref().map(str::parse::<Currency>)) {
+            (Some(amount), Some(Ok(currency))) => format!(
+                r#"<p>Amount paid: <strong>{}</strong></p>"#,
+                html_escape(&Price::from_cents(amount, currency).format(&locale, CurrencyDisplay::Symbol))
+            ),
+            _ => String::new(),
+        },
+    };
+
+    let (icon, title, message) = match receipt.as_ref().map(|r| r.payment_status) {
+        Some(PaymentStatus::Unpaid) => (
+            "⏳",
+            "Payment Processing",
+            "We'll email you once your payment is confirmed.",
+        ),
+        _ => (
+            "✅",
+            "Payment Successful!",
+            "Your payment was processed successfully.",
+        ),
+    };
+
     axum::response::Html(format!(r#"
 <!DOCTYPE html>
 <html>
-<head><title>Payment Successful</title></head>
-<body style="font-family: system-ui; display: flex; justify-content: center; align-items: center; height: 100vh; margin: 0; background: linear-gradient(135deg, #1a1a2e 0%, #16213e 100%);">
+<head><title>{title}</title></head>
+<body style="font-family: system-ui; display: flex; justify-content: center; align-items: center; min-height: 100vh; margin: 0; background: linear-gradient(135deg, #1a1a2e 0%, #16213e 100%);">
     <div style="background: white; padding: 60px; border-radius: 16px; text-align: center;">
-        <div style="font-size: 60px;">✅</div>
-        <h1>Payment Successful!</h1>
-        <p>Session: <code>{}</code></p>
-        <p style="color: #666;">Your payment was processed successfully.</p>
+        <div style="font-size: 60px;">{icon}</div>
+        <h1>{title}</h1>
+        <p>Session: <code>{session_id}</code></p>
+        {details}
+        <p style="color: #666;">{message}</p>
     </div>
 </body>
 </html>
-"#, session_id))
+"#))
+}
+
+/// Render a receipt's line items and totals as an HTML table
+fn receipt_html(receipt: &OrderReceipt, locale: &Locale) -> String {
+    let money = |price: &Price| html_escape(&price.format(locale, CurrencyDisplay::Symbol));
+    let row = |label: &str, value: String| {
+        format!(r#"<tr><td style="text-align: left;">{}</td><td style="text-align: right;">{}</td></tr>"#, label, value)
+    };
+
+    let mut rows: Vec<String> = receipt
+        .line_items
+        .iter()
+        .map(|line| {
+            let label = format!("{} × {}", html_escape(&line.name), line.quantity);
+            row(&label, money(&line.amount))
+        })
+        .collect();
+
+    let totals = &receipt.totals;
+    if totals.discount.amount > 0 || totals.tax.amount > 0 {
+        rows.push(row("Subtotal", money(&totals.subtotal)));
+    }
+    if totals.discount.amount > 0 {
+        rows.push(row("Discount", format!("−{}", money(&totals.discount))));
+    }
+    if totals.tax.amount > 0 {
+        let label = if totals.tax_inclusive { "Tax (included)" } else { "Tax" };
+        rows.push(row(label, money(&totals.tax)));
+    }
+    rows.push(row("<strong>Total</strong>", format!("<strong>{}</strong>", money(&totals.total))));
+
+    let order_line = receipt
+        .order_id
+        .as_deref()
+        .map(|id| format!("<p>Order: <code>{}</code></p>", html_escape(id)))
+        .unwrap_or_default();
+
+    format!(
+        r#"{}<table style="margin: 20px auto; border-collapse: collapse; min-width: 280px;">{}</table>"#,
+        order_line,
+        rows.join("")
+    )
 }
 
 /// Checkout cancel page  
@@ -499,10 +1704,124 @@ mod tests {
         assert_eq!(err.code, 400);
     }
 
+    #[test]
+    fn test_html_escape() {
+        assert_eq!(
+            html_escape("<script>alert('x')</script>"),
+            "&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;"
+        );
+        assert_eq!(html_escape("cs_test_123"), "cs_test_123");
+    }
+
+    #[test]
+    fn test_receipt_html() {
+        use pay_core::{Product, StoredOrder};
+
+        let mut order = Order::new(Currency::USD);
+        let product = Product::one_time("kit", "Kit <Pro>", Price::from_cents(1999, Currency::USD));
+        order.add_product(&product, 2).unwrap();
+        let receipt = OrderReceipt::from_stored(&StoredOrder::new(order, None)).unwrap();
+
+        let html = receipt_html(&receipt, &Locale::default());
+        assert!(html.contains("Kit &lt;Pro&gt; × 2"));
+        assert!(html.contains("$39.98"));
+        assert!(!html.contains("Subtotal"));
+    }
+
     #[test]
     fn test_payment_error_conversion() {
         let err = PaymentError::InvalidRequest("Bad data".to_string());
         let (status, _json) = payment_error_to_response(err);
         assert_eq!(status, StatusCode::BAD_REQUEST);
     }
+
+    #[tokio::test]
+    async fn test_fulfill_order() {
+        use pay_core::FulfillmentStatus;
+
+        let state = AppState::for_tests();
+        let order = Order::new(Currency::USD);
+        state.orders.insert(&order, None).await.unwrap();
+
+        // Unpaid orders cannot be fulfilled
+        let (status, _) = fulfill_order(State(state.clone()), Path(order.id.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        for trigger in [OrderTrigger::CheckoutCreated, OrderTrigger::PaymentSucceeded] {
+            state.orders.transition(&order.id, trigger, None).await.unwrap();
+        }
+        let Json(receipt) = fulfill_order(State(state.clone()), Path(order.id.clone()))
+            .await
+            .unwrap();
+        assert_eq!(receipt.fulfillment_status, FulfillmentStatus::Fulfilled);
+        let stored = state.orders.get(&order.id).await.unwrap().unwrap();
+        assert_eq!(stored.status, OrderStatus::Fulfilled);
+        assert_eq!(stored.history.last().unwrap().reason.as_deref(), Some("admin"));
+
+        // Repeating it is a no-op
+        assert!(fulfill_order(State(state.clone()), Path(order.id.clone())).await.is_ok());
+        let (status, _) = fulfill_order(State(state), Path("missing".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_ignores_spoofed_order_id() {
+        let state = AppState::for_tests();
+        let mut orders = Vec::new();
+        for session_id in ["cs_victim", "cs_buyer"] {
+            let order = Order::new(Currency::USD);
+            state.orders.insert(&order, None).await.unwrap();
+            let session = CheckoutSession::new(session_id, &order.id, "stripe", "https://x");
+            state.orders.save_session(&order.id, &session).await.unwrap();
+            state.orders.transition(&order.id, OrderTrigger::CheckoutCreated, None).await.unwrap();
+            orders.push(order);
+        }
+        let (victim, buyer) = (&orders[0], &orders[1]);
+        let completed = |session_id: &str, payment_intent_id: &str| WebhookEvent {
+            event_id: format!("evt_{}", session_id),
+            event_type: WebhookEventType::CheckoutCompleted,
+            provider: "stripe".to_string(),
+            session_id: Some(session_id.to_string()),
+            payment_intent_id: Some(payment_intent_id.to_string()),
+            customer_email: None,
+            amount_paid: None,
+            currency: None,
+            raw_data: Some(serde_json::json!({
+                "id": session_id,
+                "payment_status": "paid",
+                "metadata": {"order_id": victim.id}
+            })),
+            timestamp: chrono::Utc::now(),
+        };
+
+        // The buyer's session pays the buyer's order, whatever its metadata says
+        update_order_from_webhook(&state, &completed("cs_buyer", "pi_buyer")).await.unwrap();
+        let paid = state.orders.get(&buyer.id).await.unwrap().unwrap();
+        assert_eq!(paid.status, OrderStatus::Paid);
+
+        // A session the victim's order does not know cannot claim it either
+        let unknown = completed("cs_other", "pi_other");
+        assert!(find_webhook_order(&state, &unknown).await.unwrap().is_none());
+        update_order_from_webhook(&state, &unknown).await.unwrap();
+        let victim = state.orders.get(&victim.id).await.unwrap().unwrap();
+        assert_eq!(victim.status, OrderStatus::AwaitingPayment);
+        assert!(victim.amount_paid.is_none());
+    }
+
+    #[test]
+    fn test_site_product_rejects_other_sites() {
+        let mut catalog = ProductCatalog::new();
+        catalog.add(Product::one_time("kit", "Kit", Price::from_cents(1999, Currency::USD)));
+
+        assert!(site_product(&catalog, "kit", Some("chargegun")).is_ok());
+        assert!(site_product(&catalog, "kit", None).is_ok());
+        let (status, json) =
+            payment_error_to_response(site_product(&catalog, "kit", Some("spokenhope")).unwrap_err());
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(json.error, "Product kit is not sold on site spokenhope");
+    }
 }
@@ -5,7 +5,11 @@
 //! This crate provides:
 //! - Axum-based HTTP server
 //! - REST endpoints for checkout and products
+//! - Customer records that reuse the Stripe customer across checkouts
 //! - Webhook handlers for payment events
+//! - Outbound webhooks forwarding events to per-site subscribers
+//! - Worker pool that processes queued webhooks with retries
+//! - Catalog and site registry reloaded on file changes or SIGHUP
 //!
 //! ## Endpoints
 //!
@@ -15,11 +19,22 @@
 //! | POST | `/api/v1/checkout` | Create checkout session |
 //! | GET | `/api/v1/products` | List products |
 //! | GET | `/api/v1/products/:id` | Get product |
+//! | POST | `/api/v1/admin/orders/:id/refunds` | Refund an order (admin) |
+//! | GET | `/api/v1/admin/config` | Loaded config version and last reload (admin) |
+//! | POST | `/api/v1/admin/config/reload` | Reload products and sites (admin) |
+//! | GET | `/api/v1/:site_id/subscriptions` | List subscriptions (admin) |
+//! | POST | `/api/v1/:site_id/subscriptions/:id/{cancel,pause,resume,plan}` | Manage a subscription (admin) |
+//! | POST | `/api/v1/:site_id/subscriptions/:id/usage` | Report metered usage (admin) |
+//! | POST | `/api/v1/:site_id/portal` | Open the billing portal |
 //! | POST | `/webhook/stripe` | Stripe webhook |
 
+pub mod auth;
 pub mod handlers;
+pub mod outbound;
+pub mod reload;
 pub mod routes;
 pub mod state;
+pub mod worker;
 
 pub use routes::create_router;
 pub use state::{AppConfig, AppState};
@@ -12,14 +12,26 @@
 //!
 //! # Run the server
 //! lightning-cart
+//!
+//! # Check config/products.toml and config/sites.toml without starting
+//! lightning-cart validate-config [--products PATH] [--sites PATH]
 //! ```
 
-use pay_api::{routes, state::AppState};
+use pay_api::reload::{ConfigSources, LoadedConfig};
+use pay_api::{routes, state::AppState, worker::WebhookWorkerPool};
+use pay_core::RetryPolicy;
+use std::path::PathBuf;
 use tracing::{info, Level};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("validate-config") {
+        let valid = validate_config(&args[1..])?;
+        std::process::exit(if valid { 0 } else { 1 });
+    }
+
     // Initialize logging
     tracing_subscriber::registry()
         .with(fmt::layer())
@@ -40,12 +52,28 @@ async fn main() -> anyhow::Result<()> {
     let is_prod = state.config.is_production();
 
     info!("Environment: {}", state.config.environment);
-    info!("Products loaded: {}", state.catalog.products.len());
+    info!(
+        "Products loaded: {} (config version {})",
+        state.loaded().catalog.products.len(),
+        state.config_handle.status().version
+    );
     info!(
         "Payment providers: {:?}",
         state.strategies.providers()
     );
 
+    // Reload the catalog and site registry when they change (or on SIGHUP)
+    state.spawn_config_watcher();
+
+    // Keep the webhook ledger and delivery log within their retention window
+    state.spawn_webhook_purge();
+
+    // Process queued webhooks in the background
+    let retry_policy = RetryPolicy::default().with_max_attempts(state.config.webhook_max_attempts);
+    WebhookWorkerPool::new(state.config.webhook_workers)
+        .with_retry_policy(retry_policy)
+        .spawn(state.clone());
+
     // Create router
     let app = routes::create_router(state);
 
@@ -64,6 +92,42 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Print every issue in the config files; false if there are errors
+fn validate_config(args: &[String]) -> anyhow::Result<bool> {
+    let mut sources = ConfigSources::discover();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let path = args.next().map(PathBuf::from);
+        match arg.as_str() {
+            "--products" if path.is_some() => sources.products = path,
+            "--sites" if path.is_some() => sources.sites = path,
+            _ => anyhow::bail!("usage: lightning-cart validate-config [--products PATH] [--sites PATH]"),
+        }
+    }
+
+    let loaded = match LoadedConfig::read(&sources) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            println!("error: {:#}", e);
+            return Ok(false);
+        }
+    };
+    let report = loaded.validate();
+    for issue in &report.issues {
+        println!("{}", issue);
+    }
+    let errors = report.errors().count();
+    println!(
+        "{} products, {} sites (version {}): {} errors, {} warnings",
+        loaded.catalog.products.len(),
+        loaded.sites.len(),
+        loaded.version,
+        errors,
+        report.issues.len() - errors
+    );
+    Ok(errors == 0)
+}
+
 fn print_banner() {
     println!(
         r#"
@@ -0,0 +1,342 @@
+//! # Outbound Webhook Delivery
+//!
+//! Sends processed events to the site's `[[sites.webhooks]]` subscriptions.
+//!
+//! `dispatch` renders the event for each matching subscription and puts the
+//! deliveries in the durable `DeliveryQueue`, once per event and subscription
+//! (a retried webhook job skips deliveries already queued or attempted); the
+//! webhook workers send them
+//! with `run_once`. A failed attempt is retried with the subscription's
+//! `RetryPolicy` (surviving restarts), and every attempt is written to the
+//! delivery log. The endpoint, headers and secret are read from the current
+//! configuration when a delivery is sent.
+//!
+//! Signed requests carry `Lightning-Cart-Signature: t=<unix time>,v1=<hex>`,
+//! the HMAC-SHA256 of `"<t>.<body>"` (the same scheme Stripe uses).
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use pay_core::outbound::event_context;
+use pay_core::{
+    BoxedDeliveryLog, BoxedDeliveryQueue, DeliveryAttempt, DeliveryJob, OutboundWebhook,
+    PaymentResult, Site, SiteRegistry, WebhookEvent,
+};
+use sha2::Sha256;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// Signature header (`t=<unix time>,v1=<hex HMAC-SHA256>`)
+pub const SIGNATURE_HEADER: &str = "Lightning-Cart-Signature";
+
+/// Event type header (see `WebhookEventType::as_str`)
+pub const EVENT_TYPE_HEADER: &str = "Lightning-Cart-Event";
+
+/// Event ID header (lets receivers skip duplicate deliveries)
+pub const EVENT_ID_HEADER: &str = "Lightning-Cart-Event-Id";
+
+/// Delivers events to outbound webhook subscriptions
+#[derive(Clone)]
+pub struct OutboundDispatcher {
+    client: reqwest::Client,
+    log: BoxedDeliveryLog,
+    queue: BoxedDeliveryQueue,
+}
+
+impl OutboundDispatcher {
+    /// Create a dispatcher queueing deliveries in `queue` and recording attempts in `log`
+    pub fn new(queue: BoxedDeliveryQueue, log: BoxedDeliveryLog) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            log,
+            queue,
+        }
+    }
+
+    /// Set the HTTP client
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Get the delivery log
+    pub fn delivery_log(&self) -> BoxedDeliveryLog {
+        self.log.clone()
+    }
+
+    /// Queue an event for the site's matching subscriptions, returning how many were queued
+    pub async fn dispatch(&self, site: &Site, event: &WebhookEvent) -> PaymentResult<usize> {
+        let context = event_context(event, &site.id);
+        let attempted = self.log.attempts(&event.event_id).await?;
+        let mut queued = 0;
+        for webhook in site.webhooks.iter().filter(|w| w.matches(event, &context)) {
+            if attempted
+                .iter()
+                .any(|a| a.site_id == site.id && a.webhook_id == webhook.id)
+            {
+                info!(
+                    "Outbound webhook {}/{} already sent {}, not queueing it again",
+                    site.id, webhook.id, event.event_id
+                );
+                continue;
+            }
+            if webhook.endpoint_url().is_none() {
+                warn!(
+                    "Outbound webhook {}/{} has no URL, not forwarding {}",
+                    site.id, webhook.id, event.event_id
+                );
+                continue;
+            }
+            let body = match webhook.render(&context) {
+                Ok(body) => body.to_string(),
+                Err(e) => {
+                    error!("Outbound webhook {}/{}: {}", site.id, webhook.id, e);
+                    continue;
+                }
+            };
+            let job = DeliveryJob::new(&site.id, &webhook.id, event, body);
+            if self.queue.enqueue(&job).await?.is_some() {
+                queued += 1;
+            }
+        }
+        Ok(queued)
+    }
+
+    /// Send one due delivery. Returns false if no delivery was due.
+    pub async fn run_once(&self, sites: &SiteRegistry) -> PaymentResult<bool> {
+        let Some(job) = self.queue.claim(Utc::now()).await? else {
+            return Ok(false);
+        };
+
+        let webhook = sites
+            .get(&job.site_id)
+            .and_then(|site| site.webhooks.iter().find(|w| w.id == job.webhook_id));
+        let Some((webhook, url)) = webhook.and_then(|w| Some((w, w.endpoint_url()?))) else {
+            warn!(
+                "Outbound webhook {}/{} is no longer configured, dropping {}",
+                job.site_id, job.webhook_id, job.event_id
+            );
+            self.queue.complete(job.id).await?;
+            return Ok(true);
+        };
+
+        let result = self.send(&job, webhook, &url).await;
+        if let Err(e) = self.log.record(&result).await {
+            error!("Failed to log outbound webhook attempt: {}", e);
+        }
+
+        if result.succeeded() {
+            info!(
+                "Outbound webhook {}/{} delivered {} (attempt {}, {})",
+                job.site_id,
+                job.webhook_id,
+                job.event_id,
+                job.attempts,
+                result.status_code.unwrap_or_default()
+            );
+            self.queue.complete(job.id).await?;
+            return Ok(true);
+        }
+
+        let reason = result
+            .error
+            .unwrap_or_else(|| format!("HTTP {}", result.status_code.unwrap_or_default()));
+        let retry_at = webhook.retry.next_attempt(job.attempts, Utc::now());
+        match retry_at {
+            Some(retry_at) => warn!(
+                "Outbound webhook {}/{} failed for {} (attempt {}), retrying at {}: {}",
+                job.site_id, job.webhook_id, job.event_id, job.attempts, retry_at, reason
+            ),
+            None => error!(
+                "Outbound webhook {}/{} gave up on {} after {} attempts: {}",
+                job.site_id, job.webhook_id, job.event_id, job.attempts, reason
+            ),
+        }
+        self.queue.fail(job.id, &reason, retry_at).await?;
+        Ok(true)
+    }
+
+    /// Make one HTTP attempt
+    async fn send(&self, job: &DeliveryJob, webhook: &OutboundWebhook, url: &str) -> DeliveryAttempt {
+        let attempted_at = Utc::now();
+        let mut request = self
+            .client
+            .post(url)
+            .timeout(Duration::from_secs(webhook.timeout_secs))
+            .header("Content-Type", "application/json")
+            .header(EVENT_TYPE_HEADER, &job.event_type)
+            .header(EVENT_ID_HEADER, &job.event_id);
+        for (name, value) in &webhook.headers {
+            request = request.header(name, value);
+        }
+        if let Some(secret) = webhook.signing_secret() {
+            let signature = sign_payload(&secret, attempted_at.timestamp(), &job.body);
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+
+        let started = Instant::now();
+        let (status_code, error) = match request.body(job.body.clone()).send().await {
+            Ok(response) => (Some(response.status().as_u16()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        DeliveryAttempt {
+            site_id: job.site_id.clone(),
+            webhook_id: job.webhook_id.clone(),
+            event_id: job.event_id.clone(),
+            url: url.to_string(),
+            attempt: job.attempts,
+            status_code,
+            error,
+            duration_ms: started.elapsed().as_millis() as u64,
+            attempted_at,
+        }
+    }
+}
+
+/// Build the signature header value for a body
+pub fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    format!("t={},v1={}", timestamp, hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pay_core::{
+        DeliveryLog, DeliveryQueue, FieldMapping, InMemoryDeliveryLog, InMemoryDeliveryQueue,
+        RetryPolicy, WebhookEventType,
+    };
+    use std::sync::Arc;
+    use wiremock::matchers::{header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_signed_delivery_with_retry() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .and(header(EVENT_TYPE_HEADER, "checkout_completed"))
+            .and(header("X-Site", "chargegun"))
+            .and(header_exists(SIGNATURE_HEADER))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let mut webhook = OutboundWebhook::new(
+            "consultation",
+            format!("{}/hook", server.uri()),
+            &["checkout_completed"],
+        )
+        .with_field("email", FieldMapping::Path("metadata.client_email".into()))
+        .with_header("X-Site", "chargegun")
+        .with_secret("whsec_outbound");
+        webhook.retry = RetryPolicy {
+            base_delay_secs: 0,
+            ..RetryPolicy::default()
+        };
+        let site = Site::new("chargegun", "ChargeGun", "chargegun.io").with_webhook(webhook);
+
+        let log = Arc::new(InMemoryDeliveryLog::new());
+        let queue = Arc::new(InMemoryDeliveryQueue::new());
+        let dispatcher = OutboundDispatcher::new(queue.clone(), log.clone());
+        let event = WebhookEvent {
+            event_id: "evt_1".into(),
+            event_type: WebhookEventType::CheckoutCompleted,
+            provider: "stripe".into(),
+            session_id: Some("cs_1".into()),
+            payment_intent_id: None,
+            customer_email: None,
+            amount_paid: None,
+            currency: None,
+            raw_data: Some(serde_json::json!({"metadata": {"client_email": "a@b.co"}})),
+            timestamp: Utc::now(),
+        };
+        assert_eq!(dispatcher.dispatch(&site, &event).await.unwrap(), 1);
+        assert_eq!(dispatcher.dispatch(&site, &event).await.unwrap(), 0);
+
+        // Nothing is sent until a worker runs the queue; the retry stays queued
+        let sites = SiteRegistry::new().with_site(site.clone());
+        assert!(dispatcher.run_once(&sites).await.unwrap());
+        assert_eq!(queue.pending().await.unwrap(), 1);
+        assert!(dispatcher.run_once(&sites).await.unwrap());
+        assert_eq!(queue.pending().await.unwrap(), 0);
+        assert!(!dispatcher.run_once(&sites).await.unwrap());
+
+        let attempts = log.attempts("evt_1").await.unwrap();
+        let statuses: Vec<_> = attempts.iter().map(|a| (a.attempt, a.status_code)).collect();
+        assert_eq!(statuses, vec![(1, Some(500)), (2, Some(200))]);
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests[1].body, br#"{"email":"a@b.co"}"#);
+
+        // A retried webhook job does not send the event again
+        assert_eq!(dispatcher.dispatch(&site, &event).await.unwrap(), 0);
+
+        // Other event types are not forwarded
+        let mut failed = event.clone();
+        failed.event_type = WebhookEventType::PaymentFailed;
+        assert_eq!(dispatcher.dispatch(&site, &failed).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_delivery_gives_up_and_drops_removed_subscriptions() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+
+        let mut webhook = OutboundWebhook::new("crm", server.uri(), &[pay_core::outbound::ALL_EVENTS]);
+        webhook.retry = RetryPolicy {
+            max_attempts: 2,
+            base_delay_secs: 0,
+            ..RetryPolicy::default()
+        };
+        let site = Site::new("luckydrone", "LuckyDrone", "luckydrone.io").with_webhook(webhook);
+        let sites = SiteRegistry::new().with_site(site.clone());
+
+        let log = Arc::new(InMemoryDeliveryLog::new());
+        let queue = Arc::new(InMemoryDeliveryQueue::new());
+        let dispatcher = OutboundDispatcher::new(queue.clone(), log.clone());
+        let mut event = WebhookEvent {
+            event_id: "evt_2".into(),
+            event_type: WebhookEventType::PaymentFailed,
+            provider: "stripe".into(),
+            session_id: None,
+            payment_intent_id: Some("pi_2".into()),
+            customer_email: None,
+            amount_paid: None,
+            currency: None,
+            raw_data: None,
+            timestamp: Utc::now(),
+        };
+        dispatcher.dispatch(&site, &event).await.unwrap();
+        while dispatcher.run_once(&sites).await.unwrap() {}
+        assert_eq!(log.attempts("evt_2").await.unwrap().len(), 2);
+        assert_eq!(queue.pending().await.unwrap(), 0);
+
+        // A delivery whose subscription was removed from the config is dropped unsent
+        event.event_id = "evt_3".into();
+        dispatcher.dispatch(&site, &event).await.unwrap();
+        let unconfigured = SiteRegistry::new().with_site(Site::new("luckydrone", "LuckyDrone", "luckydrone.io"));
+        assert!(dispatcher.run_once(&unconfigured).await.unwrap());
+        assert_eq!(queue.pending().await.unwrap(), 0);
+        assert!(log.attempts("evt_3").await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sign_payload() {
+        let signature = sign_payload("secret", 1700000000, "{}");
+        assert!(signature.starts_with("t=1700000000,v1="));
+        assert_eq!(signature.len(), "t=1700000000,v1=".len() + 64);
+        assert_ne!(signature, sign_payload("other", 1700000000, "{}"));
+    }
+}
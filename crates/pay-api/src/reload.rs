@@ -0,0 +1,410 @@
+//! # Config Reload
+//!
+//! The product catalog and site registry sit behind a `ConfigHandle` that is
+//! swapped while the server runs, so price and site changes need no redeploy.
+//!
+//! `ConfigHandle::spawn_watcher` re-reads `products.toml` and `sites.toml`
+//! when their modification time changes (checked every `CONFIG_POLL_SECS`)
+//! and on `SIGHUP`. A reload that fails to read, parse or validate (see
+//! `pay_core::validate_config`) is logged and the previous version stays in
+//! place.
+//!
+//! Handlers take one `Arc<LoadedConfig>` per request, so a swap never mixes
+//! two versions within a request.
+
+use chrono::{DateTime, Utc};
+use pay_core::{validate_config, ProductCatalog, Site, SiteRegistry, ValidationReport};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{error, info, warn};
+
+/// Product catalog search paths
+const PRODUCT_PATHS: [&str; 3] = [
+    "config/products.toml",
+    "../config/products.toml",
+    "../../config/products.toml",
+];
+
+/// Site registry search paths (overridden by `SITES_CONFIG`)
+const SITE_PATHS: [&str; 3] = [
+    "config/sites.toml",
+    "../config/sites.toml",
+    "../../config/sites.toml",
+];
+
+/// Files the catalog and site registry are read from (None = built-in default)
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSources {
+    /// Product catalog file
+    pub products: Option<PathBuf>,
+    /// Site registry file
+    pub sites: Option<PathBuf>,
+}
+
+impl ConfigSources {
+    /// Find the config files in the usual locations
+    pub fn discover() -> Self {
+        let sites = match std::env::var("SITES_CONFIG").ok().filter(|p| !p.is_empty()) {
+            Some(path) => {
+                // Keep the override even if missing, so the error names it
+                info!("Using SITES_CONFIG override: {}", path);
+                Some(PathBuf::from(path))
+            }
+            None => first_existing(&SITE_PATHS),
+        };
+        Self {
+            products: first_existing(&PRODUCT_PATHS),
+            sites,
+        }
+    }
+
+    /// Modification times, to detect edits
+    fn modified(&self) -> [Option<SystemTime>; 2] {
+        let modified = |path: &Option<PathBuf>| {
+            path.as_ref()
+                .and_then(|p| std::fs::metadata(p).ok())
+                .and_then(|m| m.modified().ok())
+        };
+        [modified(&self.products), modified(&self.sites)]
+    }
+
+    fn paths(&self) -> Vec<String> {
+        [&self.products, &self.sites]
+            .into_iter()
+            .flatten()
+            .map(|p| p.display().to_string())
+            .collect()
+    }
+}
+
+fn first_existing(paths: &[&str]) -> Option<PathBuf> {
+    paths.iter().map(PathBuf::from).find(|p| p.is_file())
+}
+
+/// One version of the catalog and site registry
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    /// Product catalog
+    pub catalog: ProductCatalog,
+    /// Site registry (multi-tenant)
+    pub sites: SiteRegistry,
+    /// Hash of the loaded files (first 12 hex digits of SHA-256)
+    pub version: String,
+    /// When this version was loaded
+    pub loaded_at: DateTime<Utc>,
+}
+
+impl LoadedConfig {
+    /// Read, parse and validate the config files.
+    ///
+    /// Warnings are logged; errors fail the load.
+    pub fn load(sources: &ConfigSources) -> anyhow::Result<Self> {
+        let loaded = Self::read(sources)?;
+        let report = loaded.validate();
+        for warning in report.warnings() {
+            warn!("Config {}", warning);
+        }
+        if report.has_errors() {
+            let errors: Vec<String> = report.errors().map(ToString::to_string).collect();
+            anyhow::bail!(
+                "Invalid config ({} errors):\n  {}",
+                errors.len(),
+                errors.join("\n  ")
+            );
+        }
+        Ok(loaded)
+    }
+
+    /// Check the catalog against the site registry
+    pub fn validate(&self) -> ValidationReport {
+        validate_config(&self.catalog, &self.sites)
+    }
+
+    /// Read and parse the config files, without validating them
+    pub fn read(sources: &ConfigSources) -> anyhow::Result<Self> {
+        let products = read_file(sources.products.as_deref())?;
+        let sites = read_file(sources.sites.as_deref())?;
+
+        let catalog = match (&sources.products, &products) {
+            (Some(path), Some(content)) => parse_product_catalog(path, content)?,
+            _ => {
+                warn!("No product catalog found, using empty catalog");
+                ProductCatalog::new()
+            }
+        };
+        let registry = match (&sources.sites, &sites) {
+            (Some(path), Some(content)) => parse_site_registry(path, content)?,
+            _ => {
+                warn!("No site registry found, using default chargegun site");
+                default_site_registry()
+            }
+        };
+
+        let mut hasher = Sha256::new();
+        hasher.update(products.unwrap_or_default());
+        hasher.update([0]);
+        hasher.update(sites.unwrap_or_default());
+        Ok(Self {
+            catalog,
+            sites: registry,
+            version: hex::encode(hasher.finalize())[..12].to_string(),
+            loaded_at: Utc::now(),
+        })
+    }
+
+    /// Wrap an in-memory catalog and registry (version "static")
+    pub fn from_parts(catalog: ProductCatalog, sites: SiteRegistry) -> Self {
+        Self {
+            catalog,
+            sites,
+            version: "static".to_string(),
+            loaded_at: Utc::now(),
+        }
+    }
+}
+
+fn read_file(path: Option<&Path>) -> anyhow::Result<Option<String>> {
+    path.map(|p| {
+        std::fs::read_to_string(p)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", p.display(), e))
+    })
+    .transpose()
+}
+
+/// Parse the product catalog
+fn parse_product_catalog(path: &Path, content: &str) -> anyhow::Result<ProductCatalog> {
+    let catalog = ProductCatalog::from_toml(content)
+        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))?;
+    info!("Loaded {} products from {}", catalog.products.len(), path.display());
+    Ok(catalog)
+}
+
+/// Parse the site registry
+fn parse_site_registry(path: &Path, content: &str) -> anyhow::Result<SiteRegistry> {
+    let mut registry: SiteRegistry = toml::from_str(content)
+        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))?;
+
+    // Set default site to chargegun
+    registry.set_default("chargegun");
+
+    info!("Loaded {} sites from {}", registry.len(), path.display());
+    Ok(registry)
+}
+
+/// Registry with just the chargegun site, used when there is no sites.toml
+fn default_site_registry() -> SiteRegistry {
+    let mut registry = SiteRegistry::with_default("chargegun");
+    registry.add(
+        Site::new("chargegun", "ChargeGun", "chargegun.io")
+            .with_statement_descriptor("CHARGEGUN")
+            .with_success_url("https://chargegun.io/checkout/success")
+            .with_cancel_url("https://chargegun.io/checkout/cancel")
+            .with_support_email("info@chargegun.io"),
+    );
+    registry
+}
+
+/// Loaded version and reload history, for the admin API
+#[derive(Debug, Clone, Serialize)]
+pub struct ReloadStatus {
+    /// Hash of the files in use
+    pub version: String,
+    /// When the version in use was loaded
+    pub loaded_at: DateTime<Utc>,
+    /// Files watched
+    pub sources: Vec<String>,
+    /// Successful reloads since startup
+    pub reloads: u64,
+    /// Last reload attempt (successful or not)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    /// Error of the last attempt, if it failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Shared, swappable catalog and site registry
+#[derive(Clone)]
+pub struct ConfigHandle {
+    sources: Arc<ConfigSources>,
+    current: Arc<RwLock<Arc<LoadedConfig>>>,
+    status: Arc<Mutex<ReloadStatus>>,
+}
+
+impl ConfigHandle {
+    /// Load the config files (fails if they do not parse)
+    pub fn load(sources: ConfigSources) -> anyhow::Result<Self> {
+        let loaded = LoadedConfig::load(&sources)?;
+        Ok(Self::with_sources(sources, loaded))
+    }
+
+    /// Serve a fixed catalog and registry (reloads re-read nothing)
+    pub fn fixed(loaded: LoadedConfig) -> Self {
+        Self::with_sources(ConfigSources::default(), loaded)
+    }
+
+    fn with_sources(sources: ConfigSources, loaded: LoadedConfig) -> Self {
+        let status = ReloadStatus {
+            version: loaded.version.clone(),
+            loaded_at: loaded.loaded_at,
+            sources: sources.paths(),
+            reloads: 0,
+            last_attempt_at: None,
+            last_error: None,
+        };
+        Self {
+            sources: Arc::new(sources),
+            current: Arc::new(RwLock::new(Arc::new(loaded))),
+            status: Arc::new(Mutex::new(status)),
+        }
+    }
+
+    /// Current version
+    pub fn current(&self) -> Arc<LoadedConfig> {
+        match self.current.read() {
+            Ok(current) => current.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Loaded version and reload history
+    pub fn status(&self) -> ReloadStatus {
+        match self.status.lock() {
+            Ok(status) => status.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Re-read the files and swap in the new version.
+    ///
+    /// On error the current version stays in place.
+    pub fn reload(&self) -> anyhow::Result<Arc<LoadedConfig>> {
+        let result = LoadedConfig::load(&self.sources).map(Arc::new);
+
+        let mut status = self.status.lock().unwrap_or_else(|p| p.into_inner());
+        status.last_attempt_at = Some(Utc::now());
+        match result {
+            Ok(loaded) => {
+                *self.current.write().unwrap_or_else(|p| p.into_inner()) = loaded.clone();
+                status.version = loaded.version.clone();
+                status.loaded_at = loaded.loaded_at;
+                status.reloads += 1;
+                status.last_error = None;
+                info!(
+                    "Reloaded config version {} ({} products, {} sites)",
+                    loaded.version,
+                    loaded.catalog.products.len(),
+                    loaded.sites.len()
+                );
+                Ok(loaded)
+            }
+            Err(e) => {
+                error!("Config reload failed, keeping version {}: {}", status.version, e);
+                status.last_error = Some(e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    /// Reload when a file changes (checked every `poll`, None = never) or on `SIGHUP`
+    pub fn spawn_watcher(&self, poll: Option<Duration>) -> tokio::task::JoinHandle<()> {
+        let handle = self.clone();
+        tokio::spawn(async move {
+            let mut seen = handle.sources.modified();
+            let mut interval = tokio::time::interval(poll.unwrap_or(Duration::from_secs(3600)));
+            let mut hangup = hangup_signal();
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        let modified = handle.sources.modified();
+                        if poll.is_none() || modified == seen {
+                            continue;
+                        }
+                        seen = modified;
+                        info!("Config files changed, reloading");
+                    }
+                    Some(()) = recv(&mut hangup) => info!("SIGHUP received, reloading config"),
+                }
+                // Errors are logged and recorded in the status
+                let _ = handle.reload();
+            }
+        })
+    }
+}
+
+#[cfg(unix)]
+type Hangup = Option<tokio::signal::unix::Signal>;
+#[cfg(not(unix))]
+type Hangup = Option<()>;
+
+#[cfg(unix)]
+fn hangup_signal() -> Hangup {
+    use tokio::signal::unix::{signal, SignalKind};
+    signal(SignalKind::hangup())
+        .map_err(|e| warn!("Cannot listen for SIGHUP: {}", e))
+        .ok()
+}
+
+#[cfg(not(unix))]
+fn hangup_signal() -> Hangup {
+    None
+}
+
+/// Wait for the next `SIGHUP` (never, where there are no signals)
+async fn recv(hangup: &mut Hangup) -> Option<()> {
+    #[cfg(unix)]
+    if let Some(signal) = hangup {
+        return signal.recv().await;
+    }
+    let _ = hangup;
+    std::future::pending().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_keeps_old_version_on_error() {
+        let dir = std::env::temp_dir().join(format!("lc-reload-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let products = dir.join("products.toml");
+        let product = |amount: i64| {
+            format!(
+                "[[products]]\nid = \"kit\"\nname = \"Kit\"\ndescription = \"\"\n\
+                 price = {{ amount = {}, currency = \"usd\" }}\n",
+                amount
+            )
+        };
+        std::fs::write(&products, product(1000)).unwrap();
+
+        let handle = ConfigHandle::load(ConfigSources {
+            products: Some(products.clone()),
+            sites: None,
+        })
+        .unwrap();
+        let first = handle.current();
+        assert_eq!(first.catalog.get("kit").unwrap().price.amount, 1000);
+        assert!(first.sites.has_site("chargegun"));
+
+        std::fs::write(&products, product(1500)).unwrap();
+        let second = handle.reload().unwrap();
+        assert_eq!(handle.current().catalog.get("kit").unwrap().price.amount, 1500);
+        assert_ne!(second.version, first.version);
+        // Requests that took the old version keep it
+        assert_eq!(first.catalog.get("kit").unwrap().price.amount, 1000);
+
+        std::fs::write(&products, "[[products]]\nid = ").unwrap();
+        assert!(handle.reload().is_err());
+        assert_eq!(handle.current().version, second.version);
+        let status = handle.status();
+        assert_eq!(status.version, second.version);
+        assert_eq!(status.reloads, 1);
+        assert!(status.last_error.unwrap().contains("Failed to parse"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
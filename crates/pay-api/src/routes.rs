@@ -3,9 +3,11 @@
 //! Axum router configuration for the payment API.
 //! Supports both legacy single-tenant and multi-tenant routes.
 
+use crate::auth;
 use crate::handlers;
 use crate::state::AppState;
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
@@ -20,14 +22,33 @@ use tower_http::{
 /// - Legacy (backwards compatible):
 ///   - POST /api/v1/checkout - Create checkout (uses default site)
 ///   - GET  /api/v1/products - List all products
-///   - GET  /api/v1/products/{id} - Get product by ID
+///   - GET  /api/v1/products/{id} - Get product by ID (default site, or `?site_id=`)
+///
+/// - Orders:
+///   - GET  /api/v1/orders/{order_id} - Get order status and receipt (admin)
+///   - GET  /api/v1/checkout/sessions/{session_id} - Get order by checkout session
 ///
 /// - Multi-tenant:
 ///   - POST /api/v1/{site_id}/checkout - Create checkout for site
 ///   - GET  /api/v1/{site_id}/products - List products for site
+///   - GET  /api/v1/{site_id}/products/{id} - Get a product sold on the site
+///   - POST /api/v1/{site_id}/portal - Open the billing portal (by checkout session, or by order or email for admins)
 ///   - GET  /api/v1/sites - List all sites
 ///   - GET  /api/v1/sites/{site_id} - Get site info
 ///
+/// - Admin (`Authorization: Bearer <ADMIN_API_KEY>`):
+///   - POST /api/v1/admin/orders/{order_id}/refunds - Refund all or part of an order
+///   - POST /api/v1/admin/orders/{order_id}/fulfill - Mark a paid order as delivered
+///   - GET  /api/v1/admin/config - Loaded catalog/site config version and last reload
+///   - POST /api/v1/admin/config/reload - Reload the catalog and site registry now
+///   - GET  /api/v1/{site_id}/subscriptions - List the site's subscriptions
+///   - GET  /api/v1/{site_id}/subscriptions/{id} - Get a subscription
+///   - POST /api/v1/{site_id}/subscriptions/{id}/cancel - Cancel (at period end by default)
+///   - POST /api/v1/{site_id}/subscriptions/{id}/pause - Pause payment collection
+///   - POST /api/v1/{site_id}/subscriptions/{id}/resume - Resume collection, undo cancel
+///   - POST /api/v1/{site_id}/subscriptions/{id}/plan - Change plan and/or quantity
+///   - POST /api/v1/{site_id}/subscriptions/{id}/usage - Report usage of a metered item
+///
 /// - Webhooks:
 ///   - POST /webhook/stripe - Stripe webhook handler
 ///
@@ -55,22 +76,78 @@ pub fn create_router(state: AppState) -> Router {
         .route("/products", get(handlers::list_products))
         .route("/products/{product_id}", get(handlers::get_product));
 
+    // Order lookup: order IDs are not secret, so only admins get orders by ID;
+    // buyers use the checkout session ID from their success URL
+    let order_api_routes = Router::new()
+        .route("/orders/{order_id}", get(handlers::get_order))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth::require_admin))
+        .route(
+            "/checkout/sessions/{session_id}",
+            get(handlers::get_checkout_session),
+        );
+
     // Multi-tenant site routes
     let site_api_routes = Router::new()
         // Site-specific checkout
         .route("/{site_id}/checkout", post(handlers::create_checkout_for_site))
         // Site-specific products
         .route("/{site_id}/products", get(handlers::list_products_for_site))
+        .route(
+            "/{site_id}/products/{product_id}",
+            get(handlers::get_product_for_site),
+        )
+        // Customer billing portal
+        .route("/{site_id}/portal", post(handlers::create_portal_session))
         // Site management
         .route("/sites", get(handlers::list_sites))
         .route("/sites/{site_id}", get(handlers::get_site));
 
+    // Admin routes (bearer token required)
+    let admin_api_routes = Router::new()
+        .route("/orders/{order_id}/refunds", post(handlers::create_refund))
+        .route("/orders/{order_id}/fulfill", post(handlers::fulfill_order))
+        .route("/config", get(handlers::get_config_status))
+        .route("/config/reload", post(handlers::reload_config))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth::require_admin));
+
+    // Site subscription management (bearer token required)
+    let subscription_api_routes = Router::new()
+        .route("/{site_id}/subscriptions", get(handlers::list_subscriptions))
+        .route(
+            "/{site_id}/subscriptions/{subscription_id}",
+            get(handlers::get_subscription),
+        )
+        .route(
+            "/{site_id}/subscriptions/{subscription_id}/cancel",
+            post(handlers::cancel_subscription),
+        )
+        .route(
+            "/{site_id}/subscriptions/{subscription_id}/pause",
+            post(handlers::pause_subscription),
+        )
+        .route(
+            "/{site_id}/subscriptions/{subscription_id}/resume",
+            post(handlers::resume_subscription),
+        )
+        .route(
+            "/{site_id}/subscriptions/{subscription_id}/plan",
+            post(handlers::change_subscription_plan),
+        )
+        .route(
+            "/{site_id}/subscriptions/{subscription_id}/usage",
+            post(handlers::report_subscription_usage),
+        )
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth::require_admin));
+
     // Combined API v1 routes
     let api_routes = Router::new()
         // Legacy routes first (more specific)
         .merge(legacy_api_routes)
+        .merge(order_api_routes)
         // Then multi-tenant routes
-        .merge(site_api_routes);
+        .merge(site_api_routes)
+        .merge(subscription_api_routes)
+        .nest("/admin", admin_api_routes);
 
     // Webhook routes (no CORS, must accept raw body)
     let webhook_routes = Router::new()
@@ -94,21 +171,364 @@ pub fn create_router(state: AppState) -> Router {
         .with_state(state)
 }
 
-/// Create a minimal router for testing
-#[cfg(test)]
-pub fn create_test_router(state: AppState) -> Router {
-    Router::new()
-        .route("/health", get(handlers::health))
-        .route("/api/v1/checkout", post(handlers::create_checkout))
-        .route("/api/v1/{site_id}/checkout", post(handlers::create_checkout_for_site))
-        .route("/api/v1/products", get(handlers::list_products))
-        .route("/api/v1/{site_id}/products", get(handlers::list_products_for_site))
-        .route("/api/v1/sites", get(handlers::list_sites))
-        .with_state(state)
-}
-
 #[cfg(test)]
 mod tests {
-    // Router tests would go here
-    // Using axum-test crate for integration tests
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header, Method, Request, StatusCode};
+    use pay_core::{CheckoutSession, Currency, Order, OrderTrigger, Price, Product};
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const ADMIN_KEY: &str = "test-admin-key";
+
+    /// Send a request through the full router, returning the status and JSON body
+    async fn call(
+        state: &AppState,
+        method: Method,
+        uri: &str,
+        admin_key: Option<&str>,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(key) = admin_key {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", key));
+        }
+        let request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+
+        let response = create_router(state.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    /// A paid $10 order placed on `site_id`, with its Stripe session
+    async fn paid_order(state: &AppState, site_id: &str) -> Order {
+        let mut order = Order::new(Currency::USD);
+        let product = Product::one_time("kit", "Kit", Price::from_cents(1000, Currency::USD));
+        order.add_product(&product, 1).unwrap();
+        state.orders.insert(&order, Some(site_id)).await.unwrap();
+
+        let mut session = CheckoutSession::new("cs_1", &order.id, "stripe", "https://x");
+        session.payment_intent_id = Some("pi_1".into());
+        session.customer_id = Some("cus_1".into());
+        state.orders.save_session(&order.id, &session).await.unwrap();
+        for trigger in [OrderTrigger::CheckoutCreated, OrderTrigger::PaymentSucceeded] {
+            state.orders.transition(&order.id, trigger, None).await.unwrap();
+        }
+        order
+    }
+
+    #[tokio::test]
+    async fn test_admin_config_requires_key() {
+        let state = AppState::for_tests();
+        let uri = "/api/v1/admin/config";
+
+        let (status, _) = call(&state, Method::GET, uri, None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = call(&state, Method::GET, uri, Some("wrong-key"), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = call(&state, Method::GET, uri, Some(ADMIN_KEY), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["version"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_get_product_checks_site() {
+        let state = AppState::for_tests();
+        let uri = "/api/v1/products/luckydrone-consult-1hr";
+
+        let on_site = format!("{}?site_id=luckydrone", uri);
+        let (status, body) = call(&state, Method::GET, &on_site, None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["id"], "luckydrone-consult-1hr");
+
+        // An unknown site is not found rather than falling back to the default
+        let unknown_site = format!("{}?site_id=nowhere", uri);
+        let (status, _) = call(&state, Method::GET, &unknown_site, None, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Without a site the default site's catalog is used
+        let (status, _) = call(&state, Method::GET, "/api/v1/products/rang-play-rs", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call(&state, Method::GET, uri, None, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_checkout_rejects_reserved_metadata() {
+        let state = AppState::for_tests();
+        for key in ["order_id", "site_id", "statement_descriptor_suffix", "promo_code"] {
+            let body = json!({"product_id": "rang-play-rs-cli", "metadata": {key: "spoofed"}});
+            let (status, body) =
+                call(&state, Method::POST, "/api/v1/chargegun/checkout", None, Some(body)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], format!("Metadata key is reserved: {}", key));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_checkout_releases_promotion() {
+        use crate::reload::{ConfigHandle, LoadedConfig};
+        use pay_core::{
+            DiscountKind, Money, ProductCatalog, Promotion, PromotionCatalog, Site, SiteRegistry,
+            TaxConfig, TaxProvider, TaxRate,
+        };
+
+        // 100% tax on a price near the limit takes the total past i64::MAX
+        let mut site = Site::new("chargegun", "ChargeGun", "chargegun.io");
+        site.tax = Some(TaxConfig {
+            provider: TaxProvider::Table,
+            default_country: Some("US".to_string()),
+            rates: vec![TaxRate::new("US", Money::from_major_units(100), "Sales tax")],
+            ..TaxConfig::default()
+        });
+        let mut catalog = ProductCatalog::new();
+        let price = Price::from_cents(6_000_000_000_000_000_000, Currency::USD);
+        catalog.add(Product::one_time("huge", "Huge", price));
+        let mut state = AppState::for_tests();
+        state.config_handle = ConfigHandle::fixed(LoadedConfig {
+            catalog,
+            sites: SiteRegistry::with_default("chargegun").with_site(site),
+            version: "test".to_string(),
+            loaded_at: chrono::Utc::now(),
+        });
+        let mut promotions = PromotionCatalog::new();
+        let amount = Price::from_cents(100, Currency::USD);
+        promotions.add(Promotion::new("ONEOFF", DiscountKind::AmountOff { amount }).with_max_redemptions(1));
+        state.promotions = promotions;
+
+        let body = json!({"product_id": "huge", "promo_code": "ONEOFF"});
+        let (status, body) = call(&state, Method::POST, "/api/v1/checkout", None, Some(body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "Invalid price: amount overflow in order total");
+        assert_eq!(state.promo_redemptions.count("ONEOFF").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_checkout_customer_needs_admin_key() {
+        use pay_core::Customer;
+
+        let stripe = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/checkout/sessions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "cs_1",
+                "url": "https://checkout.stripe.com/c/pay/cs_1"
+            })))
+            .expect(2)
+            .mount(&stripe)
+            .await;
+        let state = AppState::for_tests_with_stripe(&stripe.uri());
+        let mut victim = Customer::new("victim@example.com").with_provider_id("stripe", "cus_victim");
+        victim.name = Some("Victim".to_string());
+        state.customers.save(&victim).await.unwrap();
+        let body = json!({
+            "product_id": "rang-play-rs-cli",
+            "customer_email": "victim@example.com",
+            "customer_name": "Mallory"
+        });
+
+        // Anyone can type the email: it is prefilled, but the stored customer is left alone
+        let uri = "/api/v1/chargegun/checkout";
+        let (status, _) = call(&state, Method::POST, uri, None, Some(body.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        let requests = stripe.received_requests().await.unwrap();
+        let sent = String::from_utf8_lossy(&requests[0].body);
+        assert!(sent.contains("customer_email=victim%40example.com"));
+        assert!(!sent.contains("cus_victim"));
+        let stored = state.customers.find_by_email("victim@example.com").await.unwrap().unwrap();
+        assert_eq!(stored.name.as_deref(), Some("Victim"));
+
+        // A site backend with the admin key gets the stored customer
+        let (status, _) = call(&state, Method::POST, uri, Some(ADMIN_KEY), Some(body)).await;
+        assert_eq!(status, StatusCode::OK);
+        let requests = stripe.received_requests().await.unwrap();
+        assert!(String::from_utf8_lossy(&requests[1].body).contains("customer=cus_victim"));
+    }
+
+    #[tokio::test]
+    async fn test_get_order() {
+        let state = AppState::for_tests();
+        let order = paid_order(&state, "chargegun").await;
+
+        let uri = format!("/api/v1/orders/{}", order.id);
+        let (status, _) = call(&state, Method::GET, &uri, None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, body) = call(&state, Method::GET, &uri, Some(ADMIN_KEY), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["order_id"], json!(order.id));
+
+        let missing = "/api/v1/orders/missing";
+        let (status, _) = call(&state, Method::GET, missing, Some(ADMIN_KEY), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // The buyer looks it up by checkout session instead
+        let (status, body) =
+            call(&state, Method::GET, "/api/v1/checkout/sessions/cs_1", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["order_id"], json!(order.id));
+    }
+
+    #[tokio::test]
+    async fn test_refund_order() {
+        let stripe = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/refunds"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "re_1",
+                "amount": 1000,
+                "currency": "usd",
+                "status": "succeeded",
+                "payment_intent": "pi_1",
+                "created": 1700000100
+            })))
+            .expect(1)
+            .mount(&stripe)
+            .await;
+        let state = AppState::for_tests_with_stripe(&stripe.uri());
+        let order = paid_order(&state, "chargegun").await;
+        let uri = format!("/api/v1/admin/orders/{}/refunds", order.id);
+
+        let (status, _) = call(&state, Method::POST, &uri, None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = call(&state, Method::POST, &uri, Some(ADMIN_KEY), None).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["status"], "refunded");
+        assert_eq!(body["refundable"]["amount"], 0);
+
+        // Nothing is left to refund
+        let (status, _) = call(&state, Method::POST, &uri, Some(ADMIN_KEY), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_refunds_cannot_both_pass() {
+        let stripe = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/refunds"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({
+                        "id": "re_1",
+                        "amount": 1000,
+                        "currency": "usd",
+                        "status": "succeeded",
+                        "payment_intent": "pi_1",
+                        "created": 1700000100
+                    }))
+                    .set_delay(std::time::Duration::from_millis(100)),
+            )
+            .expect(1)
+            .mount(&stripe)
+            .await;
+        let state = AppState::for_tests_with_stripe(&stripe.uri());
+        let order = paid_order(&state, "chargegun").await;
+        let uri = format!("/api/v1/admin/orders/{}/refunds", order.id);
+
+        let (first, second) = tokio::join!(
+            call(&state, Method::POST, &uri, Some(ADMIN_KEY), None),
+            call(&state, Method::POST, &uri, Some(ADMIN_KEY), None),
+        );
+        let mut statuses = [first.0, second.0];
+        statuses.sort();
+        assert_eq!(statuses, [StatusCode::CREATED, StatusCode::BAD_REQUEST]);
+        let stored = state.orders.get(&order.id).await.unwrap().unwrap();
+        assert_eq!(stored.refunds.len(), 1);
+        assert_eq!(stored.refunds[0].id, "re_1");
+    }
+
+    #[tokio::test]
+    async fn test_portal_checks_session_site_and_admin_access() {
+        let stripe = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/billing_portal/configurations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "bpc_1"})))
+            .mount(&stripe)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/billing_portal/sessions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "bps_1",
+                "url": "https://billing.stripe.com/p/session/1"
+            })))
+            .expect(2)
+            .mount(&stripe)
+            .await;
+        let state = AppState::for_tests_with_stripe(&stripe.uri());
+        let order = paid_order(&state, "chargegun").await;
+        let uri = "/api/v1/chargegun/portal";
+
+        // The buyer holds the checkout session ID
+        let body = json!({"session_id": "cs_1"});
+        let (status, body) = call(&state, Method::POST, uri, None, Some(body)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["customer_id"], "cus_1");
+
+        // Order IDs and emails are for admins only
+        let by_order = json!({"order_id": order.id});
+        let (status, _) = call(&state, Method::POST, uri, None, Some(by_order.clone())).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = call(&state, Method::POST, uri, Some(ADMIN_KEY), Some(by_order)).await;
+        assert_eq!(status, StatusCode::OK);
+        let body = json!({"email": "buyer@example.com"});
+        let (status, _) = call(&state, Method::POST, uri, None, Some(body)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // An order placed on another site is not found
+        let state = AppState::for_tests_with_stripe(&stripe.uri());
+        paid_order(&state, "luckydrone").await;
+        let body = json!({"session_id": "cs_1"});
+        let (status, _) = call(&state, Method::POST, uri, None, Some(body)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_subscription_scoped_to_site() {
+        let stripe = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/subscriptions/sub_1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "sub_1",
+                "customer": "cus_1",
+                "status": "active",
+                "cancel_at_period_end": false,
+                "current_period_end": 1700000000,
+                "metadata": {"site_id": "chargegun"},
+                "items": {"data": []}
+            })))
+            .mount(&stripe)
+            .await;
+        let state = AppState::for_tests_with_stripe(&stripe.uri());
+        let uri = "/api/v1/chargegun/subscriptions/sub_1";
+
+        let (status, _) = call(&state, Method::GET, uri, None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = call(&state, Method::GET, uri, Some(ADMIN_KEY), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["id"], "sub_1");
+
+        // Another site's subscription is not found
+        let uri = "/api/v1/luckydrone/subscriptions/sub_1";
+        let (status, _) = call(&state, Method::GET, uri, Some(ADMIN_KEY), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Nor is anything on an unknown site
+        for uri in ["/api/v1/nowhere/subscriptions", "/api/v1/nowhere/subscriptions/sub_1"] {
+            let (status, body) = call(&state, Method::GET, uri, Some(ADMIN_KEY), None).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+            assert_eq!(body["error"], "Site not found: nowhere");
+        }
+    }
 }
@@ -2,11 +2,30 @@
 //!
 //! Shared state for the Axum application.
 //! Contains payment strategies, configuration, site registry, and product catalog.
-
-use pay_core::{BoxedPaymentStrategy, CheckoutUrls, PaymentStrategySelector, ProductCatalog, Site, SiteRegistry};
-use pay_stripe::StripeCheckoutStrategy;
-use std::collections::HashMap;
+//!
+//! The catalog and site registry are reloadable (see `reload`); take one
+//! `state.loaded()` per request and read both from it.
+
+use pay_core::{
+    AppliedDiscount, BoxedBillingPortal, BoxedCustomerRepository, BoxedDeliveryLog,
+    BoxedDeliveryQueue, BoxedOrderRepository, BoxedPaymentStrategy, BoxedRedemptionStore, BoxedSubscriptionStrategy,
+    BoxedTaxCalculator, BoxedWebhookLedger, BoxedWebhookQueue, CheckoutUrls,
+    InMemoryCustomerRepository, InMemoryDeliveryLog, InMemoryDeliveryQueue, InMemoryOrderRepository,
+    InMemoryRedemptionStore, InMemoryWebhookLedger, InMemoryWebhookQueue,
+    Order, OrderEventHandler, OrderTransition, PaymentError, PaymentResult,
+    PaymentStrategySelector, PromotionCatalog, Site, SiteRegistry, TableTaxCalculator, TaxProvider,
+};
+use crate::outbound::OutboundDispatcher;
+use crate::reload::{ConfigHandle, ConfigSources, LoadedConfig};
+use pay_sqlite::{
+    Database, SqliteCustomerRepository, SqliteDeliveryLog, SqliteDeliveryQueue, SqliteOrderRepository,
+    SqliteRedemptionStore, SqliteWebhookLedger, SqliteWebhookQueue,
+};
+use pay_stripe::{
+    LoggingWebhookHandler, StripeCheckoutStrategy, StripeTaxCalculator, WebhookHandler,
+};
 use std::sync::Arc;
+use tokio::sync::Notify;
 
 /// Application configuration
 #[derive(Debug, Clone)]
@@ -19,6 +38,20 @@ pub struct AppConfig {
     pub base_url: String,
     /// Environment (development, staging, production)
     pub environment: String,
+    /// SQLite database path (None = in-memory stores, see `allow_in_memory_queue`)
+    pub database_path: Option<String>,
+    /// Start without `database_path`, keeping queued webhooks and deliveries in memory
+    pub allow_in_memory_queue: bool,
+    /// Days to keep processed webhook events and outbound delivery attempts
+    pub webhook_retention_days: i64,
+    /// Number of webhook worker tasks
+    pub webhook_workers: usize,
+    /// Attempts before a webhook job is dead-lettered
+    pub webhook_max_attempts: u32,
+    /// Bearer token for the admin API (None = admin API disabled)
+    pub admin_api_key: Option<String>,
+    /// Seconds between checks for changed config files (0 = only reload on SIGHUP)
+    pub config_poll_secs: u64,
 }
 
 impl AppConfig {
@@ -35,6 +68,26 @@ impl AppConfig {
             base_url: std::env::var("BASE_URL")
                 .unwrap_or_else(|_| "http://localhost:8080".to_string()),
             environment: std::env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string()),
+            database_path: std::env::var("DATABASE_PATH").ok().filter(|p| !p.is_empty()),
+            allow_in_memory_queue: std::env::var("ALLOW_IN_MEMORY_QUEUE")
+                .is_ok_and(|v| matches!(v.as_str(), "1" | "true")),
+            webhook_retention_days: std::env::var("WEBHOOK_RETENTION_DAYS")
+                .ok()
+                .and_then(|d| d.parse().ok())
+                .unwrap_or(30),
+            webhook_workers: std::env::var("WEBHOOK_WORKERS")
+                .ok()
+                .and_then(|n| n.parse().ok())
+                .unwrap_or(4),
+            webhook_max_attempts: std::env::var("WEBHOOK_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|n| n.parse().ok())
+                .unwrap_or(8),
+            admin_api_key: std::env::var("ADMIN_API_KEY").ok().filter(|k| !k.is_empty()),
+            config_poll_secs: std::env::var("CONFIG_POLL_SECS")
+                .ok()
+                .and_then(|n| n.parse().ok())
+                .unwrap_or(5),
         }
     }
 
@@ -57,23 +110,56 @@ impl Default for AppConfig {
     }
 }
 
+/// Order event handler that logs every transition
+pub struct LoggingOrderEventHandler;
+
+impl OrderEventHandler for LoggingOrderEventHandler {
+    fn on_transition(&self, transition: &OrderTransition) {
+        tracing::info!(
+            "Order {}: {} -> {} ({})",
+            transition.order_id,
+            transition.from,
+            transition.to,
+            transition.reason.as_deref().unwrap_or("-")
+        );
+    }
+}
+
 /// Shared application state
 #[derive(Clone)]
 pub struct AppState {
     /// Payment strategy selector
     pub strategies: PaymentStrategySelector,
-    /// Product catalog
-    pub catalog: ProductCatalog,
-    /// Site registry (multi-tenant)
-    pub sites: SiteRegistry,
+    /// Subscription management
+    pub subscriptions: BoxedSubscriptionStrategy,
+    /// Customer billing portal
+    pub portal: BoxedBillingPortal,
+    /// Product catalog and site registry (reloadable)
+    pub config_handle: ConfigHandle,
+    /// Promotion codes
+    pub promotions: PromotionCatalog,
+    /// Promotion redemptions (taken when a checkout session is created)
+    pub promo_redemptions: BoxedRedemptionStore,
+    /// Order store
+    pub orders: BoxedOrderRepository,
+    /// Customer records (shared by all sites)
+    pub customers: BoxedCustomerRepository,
+    /// Processed webhook events
+    pub webhook_ledger: BoxedWebhookLedger,
+    /// Verified webhook events waiting for the worker pool
+    pub webhook_queue: BoxedWebhookQueue,
+    /// Handler run by the webhook workers
+    pub webhook_handler: Arc<dyn WebhookHandler>,
+    /// Wakes idle webhook workers when an event is enqueued
+    pub webhook_notify: Arc<Notify>,
+    /// Receivers of order status transitions
+    pub order_event_handlers: Vec<Arc<dyn OrderEventHandler>>,
     /// Checkout URLs (fallback for legacy routes)
     pub urls: CheckoutUrls,
     /// Application config
     pub config: AppConfig,
-    /// HTTP client for webhook forwarding
-    pub http_client: reqwest::Client,
-    /// Webhook forward URLs per site (site_id → Vercel webhook URL)
-    pub webhook_forward_urls: HashMap<String, String>,
+    /// Outbound webhook delivery (subscriptions are configured per site)
+    pub outbound: OutboundDispatcher,
 }
 
 impl AppState {
@@ -82,52 +168,74 @@ impl AppState {
         let config = AppConfig::from_env();
         let urls = CheckoutUrls::new(&config.base_url);
 
-        // Load product catalog
-        let catalog = load_product_catalog()?;
+        // Load product catalog and site registry
+        let config_handle = ConfigHandle::load(ConfigSources::discover())?;
+        let loaded = config_handle.current();
 
-        // Load site registry
-        let sites = load_site_registry()?;
+        // Load promotion codes
+        let promotions = load_promotion_catalog()?;
+
+        // Open order, customer and redemption stores, webhook ledger and queue, and outbound deliveries
+        let db = open_database(&config)?;
+        let orders = open_order_repository(db.as_ref())?;
+        let promo_redemptions = open_redemption_store(db.as_ref())?;
+        let customers = open_customer_repository(db.as_ref())?;
+        let webhook_ledger = open_webhook_ledger(db.as_ref())?;
+        let webhook_queue = open_webhook_queue(db.as_ref())?;
+        let delivery_queue = open_delivery_queue(db.as_ref())?;
+        let delivery_log = open_delivery_log(db.as_ref())?;
 
         // Initialize payment strategies
-        let stripe_strategy = StripeCheckoutStrategy::from_env()
-            .map_err(|e| anyhow::anyhow!("Failed to initialize Stripe: {}", e))?;
+        let stripe_strategy = Arc::new(
+            StripeCheckoutStrategy::from_env()
+                .map_err(|e| anyhow::anyhow!("Failed to initialize Stripe: {}", e))?,
+        );
 
         let mut strategies = PaymentStrategySelector::new("stripe");
-        strategies.register(Arc::new(stripe_strategy) as BoxedPaymentStrategy);
-
-        // HTTP client for webhook forwarding to Vercel
-        let http_client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(15))
-            .build()
-            .expect("Failed to create HTTP client");
-
-        // Load webhook forward URLs from environment
-        let mut webhook_forward_urls = HashMap::new();
-        if let Ok(url) = std::env::var("CHARGEGUN_WEBHOOK_URL") {
-            tracing::info!("Webhook forwarding enabled for chargegun → {}", url);
-            webhook_forward_urls.insert("chargegun".to_string(), url);
-        }
-        if let Ok(url) = std::env::var("LUCKYDRONE_WEBHOOK_URL") {
-            tracing::info!("Webhook forwarding enabled for luckydrone → {}", url);
-            webhook_forward_urls.insert("luckydrone".to_string(), url);
-        }
-        if let Ok(url) = std::env::var("DRONEGRID_WEBHOOK_URL") {
-            tracing::info!("Webhook forwarding enabled for dronegrid → {}", url);
-            webhook_forward_urls.insert("dronegrid".to_string(), url);
-        }
-        if let Ok(url) = std::env::var("SPOKENHOPE_WEBHOOK_URL") {
-            tracing::info!("Webhook forwarding enabled for spokenhope → {}", url);
-            webhook_forward_urls.insert("spokenhope".to_string(), url);
+        strategies.register(stripe_strategy.clone() as BoxedPaymentStrategy);
+        let subscriptions: BoxedSubscriptionStrategy = stripe_strategy.clone();
+        let portal: BoxedBillingPortal = stripe_strategy;
+
+        // Outbound webhooks (per-request timeouts come from each subscription)
+        for site in loaded.sites.active_sites() {
+            for webhook in &site.webhooks {
+                match webhook.endpoint_url() {
+                    Some(url) => tracing::info!(
+                        "Outbound webhook {}/{} → {} ({})",
+                        site.id,
+                        webhook.id,
+                        url,
+                        webhook.events.join(", ")
+                    ),
+                    None => tracing::warn!(
+                        "Outbound webhook {}/{} has no URL (set {}), events will not be forwarded",
+                        site.id,
+                        webhook.id,
+                        webhook.url_env.as_deref().unwrap_or("url")
+                    ),
+                }
+            }
         }
+        warn_unused_url_vars(&loaded.sites);
+        let outbound = OutboundDispatcher::new(delivery_queue, delivery_log);
 
         Ok(Self {
             strategies,
-            catalog,
-            sites,
+            subscriptions,
+            portal,
+            config_handle,
+            promotions,
+            promo_redemptions,
+            orders,
+            customers,
+            webhook_ledger,
+            webhook_queue,
+            webhook_handler: Arc::new(LoggingWebhookHandler),
+            webhook_notify: Arc::new(Notify::new()),
+            order_event_handlers: vec![Arc::new(LoggingOrderEventHandler)],
             urls,
             config,
-            http_client,
-            webhook_forward_urls,
+            outbound,
         })
     }
 
@@ -141,9 +249,93 @@ impl AppState {
         self.strategies.get(provider)
     }
 
+    /// Current catalog and site registry
+    pub fn loaded(&self) -> Arc<LoadedConfig> {
+        self.config_handle.current()
+    }
+
     /// Get a site by ID, or default if not found
-    pub fn get_site(&self, site_id: Option<&str>) -> Option<&Site> {
-        self.sites.get_or_default(site_id)
+    pub fn get_site(&self, site_id: Option<&str>) -> Option<Site> {
+        self.loaded().sites.get_or_default(site_id).cloned()
+    }
+
+    /// Evaluate a promotion code for an order and record the order's redemption.
+    ///
+    /// Call `release_promotion` if the checkout is not created afterwards,
+    /// or expires unpaid.
+    pub async fn redeem_promotion(
+        &self,
+        code: &str,
+        order: &Order,
+        site_id: Option<&str>,
+    ) -> PaymentResult<AppliedDiscount> {
+        let promotion = self.promotions.get(code).ok_or_else(|| {
+            PaymentError::PromotionNotApplicable {
+                code: code.to_string(),
+                reason: "unknown code".to_string(),
+            }
+        })?;
+
+        let used = self.promo_redemptions.count(&promotion.code).await?;
+        let discount = promotion.evaluate(order, site_id, chrono::Utc::now(), used)?;
+        // A concurrent checkout may have taken the last redemption since the count
+        let reserved = self
+            .promo_redemptions
+            .reserve(&promotion.code, &order.id, promotion.max_redemptions)
+            .await?;
+        if !reserved {
+            return Err(PaymentError::PromotionNotApplicable {
+                code: promotion.code.clone(),
+                reason: "code has reached its usage limit".to_string(),
+            });
+        }
+        Ok(discount)
+    }
+
+    /// Purge webhook events and delivery attempts older than the retention window, once an hour
+    pub fn spawn_webhook_purge(&self) -> tokio::task::JoinHandle<()> {
+        let ledger = self.webhook_ledger.clone();
+        let delivery_log = self.outbound.delivery_log();
+        let retention = chrono::Duration::days(self.config.webhook_retention_days);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(3600));
+            loop {
+                interval.tick().await;
+                let cutoff = chrono::Utc::now() - retention;
+                match ledger.purge_before(cutoff).await {
+                    Ok(0) => {}
+                    Ok(purged) => tracing::info!("Purged {} old webhook events", purged),
+                    Err(e) => tracing::error!("Failed to purge webhook events: {}", e),
+                }
+                match delivery_log.purge_before(cutoff).await {
+                    Ok(0) => {}
+                    Ok(purged) => tracing::info!("Purged {} old webhook deliveries", purged),
+                    Err(e) => tracing::error!("Failed to purge webhook deliveries: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Reload the catalog and site registry when their files change or on SIGHUP
+    pub fn spawn_config_watcher(&self) -> tokio::task::JoinHandle<()> {
+        let poll = Some(self.config.config_poll_secs)
+            .filter(|&secs| secs > 0)
+            .map(std::time::Duration::from_secs);
+        self.config_handle.spawn_watcher(poll)
+    }
+
+    /// Notify the order event handlers of a transition
+    pub fn emit_transition(&self, transition: &OrderTransition) {
+        for handler in &self.order_event_handlers {
+            handler.on_transition(transition);
+        }
+    }
+
+    /// Give back the redemption an order took in `redeem_promotion` (if any)
+    pub async fn release_promotion(&self, order_id: &str) {
+        if let Err(e) = self.promo_redemptions.release(order_id).await {
+            tracing::error!("Failed to release promotion for order {}: {}", order_id, e);
+        }
     }
 
     /// Get success URL for a site (with session ID placeholder)
@@ -159,16 +351,26 @@ impl AppState {
     /// Get cancel URL for a site
     pub fn cancel_url_for_site(&self, site_id: Option<&str>) -> String {
         if let Some(site) = self.get_site(site_id) {
-            site.cancel_url.clone()
+            site.cancel_url
         } else {
             self.urls.cancel_url()
         }
     }
 
+    /// Get the tax calculator configured for a site (None = no tax)
+    pub fn tax_calculator_for_site(&self, site_id: Option<&str>) -> Option<BoxedTaxCalculator> {
+        let tax = self.get_site(site_id)?.tax?;
+        match tax.provider {
+            TaxProvider::None => None,
+            TaxProvider::Table => Some(Box::new(TableTaxCalculator::from_config(&tax))),
+            TaxProvider::Stripe => Some(Box::new(StripeTaxCalculator::new(tax.mode))),
+        }
+    }
+
     /// Get statement descriptor suffix for a site
     pub fn statement_descriptor_for_site(&self, site_id: Option<&str>) -> Option<String> {
         self.get_site(site_id)
-            .map(|s| s.statement_descriptor_suffix.clone())
+            .map(|s| s.statement_descriptor_suffix)
             .filter(|s| !s.is_empty())
     }
 
@@ -183,71 +385,213 @@ impl AppState {
     }
 }
 
-/// Load product catalog from config file
-fn load_product_catalog() -> anyhow::Result<ProductCatalog> {
-    // Try to load from config/products.toml
+/// Open the SQLite database (None if `DATABASE_PATH` is not set and in-memory
+/// queues are explicitly allowed)
+fn open_database(config: &AppConfig) -> anyhow::Result<Option<Database>> {
+    match config.database_path {
+        Some(ref path) => {
+            let db = Database::open(path)
+                .map_err(|e| anyhow::anyhow!("Failed to open database {}: {}", path, e))?;
+            tracing::info!("Database: SQLite at {}", path);
+            Ok(Some(db))
+        }
+        None if config.allow_in_memory_queue => {
+            tracing::warn!("DATABASE_PATH not set, orders, customers, promotion redemptions and webhooks are kept in memory only");
+            Ok(None)
+        }
+        None => anyhow::bail!(
+            "DATABASE_PATH is not set: queued webhooks and outbound deliveries would be lost \
+             on restart (set ALLOW_IN_MEMORY_QUEUE=true to run without a database)"
+        ),
+    }
+}
+
+/// Open the order store (SQLite if a database is configured, otherwise in-memory)
+fn open_order_repository(db: Option<&Database>) -> anyhow::Result<BoxedOrderRepository> {
+    match db {
+        Some(db) => {
+            let repo = SqliteOrderRepository::new(db.clone())
+                .map_err(|e| anyhow::anyhow!("Failed to initialize order store: {}", e))?;
+            Ok(Arc::new(repo))
+        }
+        None => Ok(Arc::new(InMemoryOrderRepository::new())),
+    }
+}
+
+/// Open the customer store (SQLite if a database is configured, otherwise in-memory)
+fn open_customer_repository(db: Option<&Database>) -> anyhow::Result<BoxedCustomerRepository> {
+    match db {
+        Some(db) => {
+            let repo = SqliteCustomerRepository::new(db.clone())
+                .map_err(|e| anyhow::anyhow!("Failed to initialize customer store: {}", e))?;
+            Ok(Arc::new(repo))
+        }
+        None => Ok(Arc::new(InMemoryCustomerRepository::new())),
+    }
+}
+
+/// Open the redemption store (SQLite if a database is configured, otherwise in-memory)
+fn open_redemption_store(db: Option<&Database>) -> anyhow::Result<BoxedRedemptionStore> {
+    match db {
+        Some(db) => {
+            let store = SqliteRedemptionStore::new(db.clone())
+                .map_err(|e| anyhow::anyhow!("Failed to initialize redemption store: {}", e))?;
+            Ok(Arc::new(store))
+        }
+        None => Ok(Arc::new(InMemoryRedemptionStore::new())),
+    }
+}
+
+/// Open the webhook ledger (SQLite if a database is configured, otherwise in-memory)
+fn open_webhook_ledger(db: Option<&Database>) -> anyhow::Result<BoxedWebhookLedger> {
+    match db {
+        Some(db) => {
+            let ledger = SqliteWebhookLedger::new(db.clone())
+                .map_err(|e| anyhow::anyhow!("Failed to initialize webhook ledger: {}", e))?;
+            Ok(Arc::new(ledger))
+        }
+        None => Ok(Arc::new(InMemoryWebhookLedger::new())),
+    }
+}
+
+/// Open the webhook queue (SQLite if a database is configured, otherwise in-memory)
+fn open_webhook_queue(db: Option<&Database>) -> anyhow::Result<BoxedWebhookQueue> {
+    match db {
+        Some(db) => {
+            let queue = SqliteWebhookQueue::new(db.clone())
+                .map_err(|e| anyhow::anyhow!("Failed to initialize webhook queue: {}", e))?;
+            Ok(Arc::new(queue))
+        }
+        None => Ok(Arc::new(InMemoryWebhookQueue::new())),
+    }
+}
+
+/// Open the outbound delivery queue (SQLite if a database is configured, otherwise in-memory)
+fn open_delivery_queue(db: Option<&Database>) -> anyhow::Result<BoxedDeliveryQueue> {
+    match db {
+        Some(db) => {
+            let queue = SqliteDeliveryQueue::new(db.clone())
+                .map_err(|e| anyhow::anyhow!("Failed to initialize delivery queue: {}", e))?;
+            Ok(Arc::new(queue))
+        }
+        None => Ok(Arc::new(InMemoryDeliveryQueue::new())),
+    }
+}
+
+/// Forwarding URL variables read before outbound webhooks were configured in sites.toml
+const LEGACY_WEBHOOK_URL_VARS: &[&str] = &[
+    "CHARGEGUN_WEBHOOK_URL",
+    "LUCKYDRONE_WEBHOOK_URL",
+    "DRONEGRID_WEBHOOK_URL",
+    "SPOKENHOPE_WEBHOOK_URL",
+];
+
+/// Warn about legacy forwarding variables that no subscription reads with `url_env`
+fn warn_unused_url_vars(sites: &SiteRegistry) {
+    for var in LEGACY_WEBHOOK_URL_VARS {
+        if std::env::var(var).is_err() {
+            continue;
+        }
+        let used = sites
+            .active_sites()
+            .flat_map(|site| site.webhooks.iter())
+            .any(|webhook| webhook.url_env.as_deref() == Some(*var));
+        if !used {
+            tracing::warn!(
+                "{} is set but no [[sites.webhooks]] subscription reads it (url_env); \
+                 events are not forwarded to it",
+                var
+            );
+        }
+    }
+}
+
+/// Open the outbound delivery log (SQLite if a database is configured, otherwise in-memory)
+fn open_delivery_log(db: Option<&Database>) -> anyhow::Result<BoxedDeliveryLog> {
+    match db {
+        Some(db) => {
+            let log = SqliteDeliveryLog::new(db.clone())
+                .map_err(|e| anyhow::anyhow!("Failed to initialize delivery log: {}", e))?;
+            Ok(Arc::new(log))
+        }
+        None => Ok(Arc::new(InMemoryDeliveryLog::new())),
+    }
+}
+
+/// Load promotion codes from config file
+fn load_promotion_catalog() -> anyhow::Result<PromotionCatalog> {
     let config_paths = [
-        "config/products.toml",
-        "../config/products.toml",
-        "../../config/products.toml",
+        "config/promotions.toml",
+        "../config/promotions.toml",
+        "../../config/promotions.toml",
     ];
 
     for path in config_paths {
         if let Ok(content) = std::fs::read_to_string(path) {
-            let catalog: ProductCatalog = toml::from_str(&content)
+            let catalog = PromotionCatalog::from_toml(&content)
                 .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path, e))?;
-            tracing::info!("Loaded {} products from {}", catalog.products.len(), path);
+            tracing::info!("Loaded {} promotions from {}", catalog.promotions.len(), path);
             return Ok(catalog);
         }
     }
 
-    // Return empty catalog if no config found
-    tracing::warn!("No product catalog found, using empty catalog");
-    Ok(ProductCatalog::new())
+    tracing::info!("No promotions config found, promotion codes disabled");
+    Ok(PromotionCatalog::new())
 }
 
-/// Load site registry from config file
-fn load_site_registry() -> anyhow::Result<SiteRegistry> {
-    // Check for SITES_CONFIG env var override (e.g., config/sites-dev.toml for local testing)
-    let env_path = std::env::var("SITES_CONFIG").ok();
-    
-    let config_paths: Vec<&str> = if let Some(ref p) = env_path {
-        tracing::info!("Using SITES_CONFIG override: {}", p);
-        vec![p.as_str()]
-    } else {
-        vec![
-            "config/sites.toml",
-            "../config/sites.toml",
-            "../../config/sites.toml",
-        ]
-    };
+#[cfg(test)]
+impl AppState {
+    /// In-memory state with the configured catalog and sites, a Stripe test
+    /// strategy (no reachable API) and admin key `test-admin-key`
+    pub(crate) fn for_tests() -> Self {
+        Self::for_tests_with_stripe("http://127.0.0.1:9")
+    }
 
-    for path in config_paths {
-        if let Ok(content) = std::fs::read_to_string(path) {
-            let mut registry: SiteRegistry = toml::from_str(&content)
-                .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path, e))?;
-            
-            // Set default site to chargegun
-            registry.set_default("chargegun");
-            
-            tracing::info!("Loaded {} sites from {}", registry.len(), path);
-            return Ok(registry);
+    /// `for_tests` with the Stripe API at `api_base_url` (e.g. a mock server)
+    pub(crate) fn for_tests_with_stripe(api_base_url: &str) -> Self {
+        let config = AppConfig {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            base_url: "http://localhost:8080".to_string(),
+            environment: "test".to_string(),
+            database_path: None,
+            allow_in_memory_queue: true,
+            webhook_retention_days: 30,
+            webhook_workers: 1,
+            webhook_max_attempts: 3,
+            admin_api_key: Some("test-admin-key".to_string()),
+            config_poll_secs: 0,
+        };
+        let stripe = Arc::new(StripeCheckoutStrategy::new(
+            pay_stripe::StripeConfig::new("sk_test_abc", "pk_test_xyz", "whsec_123")
+                .with_api_base_url(api_base_url),
+        ));
+        let mut strategies = PaymentStrategySelector::new("stripe");
+        strategies.register(stripe.clone() as BoxedPaymentStrategy);
+        let loaded = LoadedConfig::read(&ConfigSources::discover()).expect("test config loads");
+
+        Self {
+            strategies,
+            subscriptions: stripe.clone(),
+            portal: stripe,
+            config_handle: ConfigHandle::fixed(loaded),
+            promotions: PromotionCatalog::new(),
+            promo_redemptions: Arc::new(InMemoryRedemptionStore::new()),
+            orders: Arc::new(InMemoryOrderRepository::new()),
+            customers: Arc::new(InMemoryCustomerRepository::new()),
+            webhook_ledger: Arc::new(InMemoryWebhookLedger::new()),
+            webhook_queue: Arc::new(InMemoryWebhookQueue::new()),
+            webhook_handler: Arc::new(LoggingWebhookHandler),
+            webhook_notify: Arc::new(Notify::new()),
+            order_event_handlers: Vec::new(),
+            urls: CheckoutUrls::new(&config.base_url),
+            config,
+            outbound: OutboundDispatcher::new(
+                Arc::new(InMemoryDeliveryQueue::new()),
+                Arc::new(InMemoryDeliveryLog::new()),
+            ),
         }
     }
-
-    // Return default registry with chargegun site if no config found
-    tracing::warn!("No site registry found, using default chargegun site");
-    
-    let mut registry = SiteRegistry::with_default("chargegun");
-    registry.add(
-        Site::new("chargegun", "ChargeGun", "chargegun.io")
-            .with_statement_descriptor("CHARGEGUN")
-            .with_success_url("https://chargegun.io/checkout/success")
-            .with_cancel_url("https://chargegun.io/checkout/cancel")
-            .with_support_email("info@chargegun.io")
-    );
-    
-    Ok(registry)
 }
 
 #[cfg(test)]
@@ -268,14 +612,26 @@ mod tests {
 
     #[test]
     fn test_socket_addr() {
-        let config = AppConfig {
+        let mut config = AppConfig {
             host: "0.0.0.0".to_string(),
             port: 3000,
             base_url: "http://localhost:3000".to_string(),
             environment: "test".to_string(),
+            database_path: None,
+            allow_in_memory_queue: false,
+            webhook_retention_days: 30,
+            webhook_workers: 4,
+            webhook_max_attempts: 8,
+            admin_api_key: None,
+            config_poll_secs: 5,
         };
 
         let addr = config.socket_addr();
         assert_eq!(addr.to_string(), "0.0.0.0:3000");
+
+        // Without a database, startup needs the explicit in-memory opt-in
+        assert!(open_database(&config).is_err());
+        config.allow_in_memory_queue = true;
+        assert!(open_database(&config).unwrap().is_none());
     }
 }
@@ -0,0 +1,242 @@
+//! # Webhook Workers
+//!
+//! Worker pool that drains the webhook queue and the outbound delivery queue.
+//!
+//! Each worker claims a due job, runs `process_stripe_webhook` and records
+//! the result: success completes the job, a retryable failure (see
+//! `PaymentError::is_retryable`) schedules a retry with the pool's
+//! `RetryPolicy`, and any other failure, or the last attempt, dead-letters
+//! the job.
+//! The webhook ledger follows the job, so a dead-lettered event is marked
+//! failed and a later redelivery from Stripe is processed again.
+//!
+//! Between webhook jobs, workers send due outbound deliveries (see
+//! `OutboundDispatcher::run_once`).
+
+use crate::handlers::process_stripe_webhook;
+use crate::state::AppState;
+use chrono::Utc;
+use pay_core::{EventOutcome, PaymentError, PaymentResult, RetryPolicy, WebhookJob};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+/// Pool of webhook workers
+#[derive(Debug, Clone)]
+pub struct WebhookWorkerPool {
+    workers: usize,
+    poll_interval: Duration,
+    retry_policy: RetryPolicy,
+}
+
+impl WebhookWorkerPool {
+    /// Create a pool with `workers` workers
+    pub fn new(workers: usize) -> Self {
+        Self {
+            workers: workers.max(1),
+            poll_interval: Duration::from_secs(5),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Set how often idle workers look for due retries
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Set the retry policy
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Start the workers
+    pub fn spawn(self, state: AppState) -> Vec<JoinHandle<()>> {
+        info!("Starting {} webhook workers", self.workers);
+        (0..self.workers)
+            .map(|_| {
+                let pool = self.clone();
+                let state = state.clone();
+                tokio::spawn(async move { pool.run(state).await })
+            })
+            .collect()
+    }
+
+    async fn run(&self, state: AppState) {
+        loop {
+            let processed = match self.run_once(&state).await {
+                Ok(processed) => processed,
+                Err(e) => {
+                    error!("Webhook queue error: {}", e);
+                    false
+                }
+            };
+            let delivered = match state.outbound.run_once(&state.loaded().sites).await {
+                Ok(delivered) => delivered,
+                Err(e) => {
+                    error!("Outbound delivery queue error: {}", e);
+                    false
+                }
+            };
+            if processed || delivered {
+                continue;
+            }
+            // Sleep until a new event arrives or a retry may be due
+            let _ = tokio::time::timeout(self.poll_interval, state.webhook_notify.notified()).await;
+        }
+    }
+
+    /// Process one due job. Returns false if no job was due.
+    pub async fn run_once(&self, state: &AppState) -> PaymentResult<bool> {
+        let Some(job) = state.webhook_queue.claim(Utc::now()).await? else {
+            return Ok(false);
+        };
+
+        let event_id = job.event.event_id.clone();
+        match process_stripe_webhook(state, job.event.clone()).await {
+            Ok(()) => {
+                state.webhook_queue.complete(job.id).await?;
+                record(state, &event_id, EventOutcome::Processed, None).await;
+            }
+            Err(e) => self.fail(state, &job, &e).await?,
+        }
+        Ok(true)
+    }
+
+    async fn fail(&self, state: &AppState, job: &WebhookJob, error: &PaymentError) -> PaymentResult<()> {
+        let event_id = &job.event.event_id;
+        let message = error.to_string();
+        if !error.is_retryable() {
+            error!(
+                "Webhook {} failed permanently, moving to dead letters: {}",
+                event_id, message
+            );
+        } else if let Some(retry_at) = self.retry_policy.next_attempt(job.attempts, Utc::now()) {
+            warn!(
+                "Webhook {} failed (attempt {}), retrying at {}: {}",
+                event_id, job.attempts, retry_at, message
+            );
+            return state.webhook_queue.fail(job.id, &message, Some(retry_at)).await;
+        } else {
+            error!(
+                "Webhook {} failed {} times, moving to dead letters: {}",
+                event_id, job.attempts, message
+            );
+        }
+        state.webhook_queue.fail(job.id, &message, None).await?;
+        record(state, event_id, EventOutcome::Failed, Some(&message)).await;
+        Ok(())
+    }
+}
+
+/// Record a ledger outcome, logging failures (the queue is authoritative)
+async fn record(state: &AppState, event_id: &str, outcome: EventOutcome, error: Option<&str>) {
+    if let Err(e) = state.webhook_ledger.record(event_id, outcome, error).await {
+        error!("Failed to record outcome of webhook {}: {}", event_id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use pay_core::{WebhookEvent, WebhookEventType};
+    use pay_stripe::{CheckoutExpiredData, WebhookHandler};
+    use std::sync::Arc;
+
+    /// Fails every expired checkout with the error it makes
+    struct FailingHandler(fn() -> PaymentError);
+
+    #[async_trait]
+    impl WebhookHandler for FailingHandler {
+        async fn on_checkout_expired(&self, _data: CheckoutExpiredData) -> PaymentResult<()> {
+            Err((self.0)())
+        }
+    }
+
+    fn expired_event() -> WebhookEvent {
+        WebhookEvent {
+            event_id: "evt_expired".to_string(),
+            event_type: WebhookEventType::CheckoutExpired,
+            provider: "stripe".to_string(),
+            session_id: Some("cs_expired".to_string()),
+            payment_intent_id: None,
+            customer_email: None,
+            amount_paid: None,
+            currency: None,
+            raw_data: Some(serde_json::json!({
+                "id": "cs_expired",
+                "metadata": {"site_id": "luckydrone"}
+            })),
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn network_error() -> PaymentError {
+        PaymentError::NetworkError("connection reset".to_string())
+    }
+
+    async fn state_failing_with(error: Option<fn() -> PaymentError>) -> AppState {
+        let mut state = AppState::for_tests();
+        if let Some(error) = error {
+            state.webhook_handler = Arc::new(FailingHandler(error));
+        }
+        state.webhook_queue.enqueue(&expired_event()).await.unwrap();
+        state
+    }
+
+    fn pool() -> WebhookWorkerPool {
+        WebhookWorkerPool::new(1).with_retry_policy(RetryPolicy::default().with_max_attempts(3))
+    }
+
+    #[tokio::test]
+    async fn test_success_completes_job() {
+        let state = state_failing_with(None).await;
+        assert!(pool().run_once(&state).await.unwrap());
+        assert!(!pool().run_once(&state).await.unwrap());
+        assert!(state.webhook_queue.dead_letters().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retryable_error_is_retried() {
+        let state = state_failing_with(Some(network_error)).await;
+        assert!(pool().run_once(&state).await.unwrap());
+
+        // Not due again until the backoff has passed
+        assert!(!pool().run_once(&state).await.unwrap());
+        assert!(state.webhook_queue.dead_letters().await.unwrap().is_empty());
+        let later = Utc::now() + chrono::Duration::hours(1);
+        let job = state.webhook_queue.claim(later).await.unwrap().unwrap();
+        assert_eq!(job.attempts, 2);
+        assert_eq!(job.last_error.as_deref(), Some("Network error: connection reset"));
+    }
+
+    #[tokio::test]
+    async fn test_permanent_error_is_dead_lettered() {
+        let state =
+            state_failing_with(Some(|| PaymentError::InvalidRequest("unknown plan".to_string())))
+                .await;
+        assert!(pool().run_once(&state).await.unwrap());
+
+        let dead = state.webhook_queue.dead_letters().await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].attempts, 1);
+        let later = Utc::now() + chrono::Duration::hours(1);
+        assert!(state.webhook_queue.claim(later).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_last_attempt_is_dead_lettered() {
+        let state = state_failing_with(Some(network_error)).await;
+        let mut now = Utc::now();
+        for _ in 0..3 {
+            let job = state.webhook_queue.claim(now).await.unwrap().unwrap();
+            pool().fail(&state, &job, &network_error()).await.unwrap();
+            now += chrono::Duration::hours(1);
+        }
+        let dead = state.webhook_queue.dead_letters().await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].attempts, 3);
+    }
+}
@@ -0,0 +1,451 @@
+//! # Currency
+//!
+//! ISO 4217 currency table for lightning-cart.
+//!
+//! Every active ISO 4217 code is listed with its numeric code, minor-unit
+//! exponent, display symbol and symbol position. `Currency` is a small `Copy`
+//! handle into this table; serde accepts any valid code in any case.
+//!
+//! Historic codes that still circulate during a changeover (ANG, replaced
+//! by XCG) stay in the table but are skipped by numeric lookup, since their
+//! numeric code now belongs to the successor.
+
+use crate::error::{PaymentError, PaymentResult};
+use crate::money::Money;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Where the currency symbol is placed relative to the amount
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolPosition {
+    /// Symbol before the amount (e.g., "$10.00")
+    Before,
+    /// Symbol after the amount (e.g., "10,00 kr")
+    After,
+}
+
+/// Static data for one ISO 4217 currency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrencyInfo {
+    /// Alphabetic code (uppercase, e.g., "USD")
+    pub code: &'static str,
+    /// Numeric code (e.g., 840)
+    pub numeric: u16,
+    /// Number of minor-unit decimal places (0 for JPY, 3 for KWD)
+    pub exponent: u8,
+    /// Display symbol (e.g., "$", "€", "kr")
+    pub symbol: &'static str,
+    /// Symbol placement
+    pub symbol_position: SymbolPosition,
+    /// English name
+    pub name: &'static str,
+}
+
+/// Withdrawn codes kept for existing prices and orders
+const HISTORIC: &[&str] = &["ANG"];
+
+impl CurrencyInfo {
+    /// Whether ISO 4217 has withdrawn this code
+    pub fn is_historic(&self) -> bool {
+        HISTORIC.contains(&self.code)
+    }
+}
+
+/// An ISO 4217 currency
+///
+/// Stored as the lowercase alphabetic code; always refers to an entry
+/// in the currency table.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Currency([u8; 3]);
+
+const fn lower_code(code: &str) -> [u8; 3] {
+    let b = code.as_bytes();
+    [
+        b[0].to_ascii_lowercase(),
+        b[1].to_ascii_lowercase(),
+        b[2].to_ascii_lowercase(),
+    ]
+}
+
+macro_rules! currency_table {
+    ($($code:ident, $numeric:literal, $exp:literal, $symbol:literal, $pos:ident, $name:literal;)*) => {
+        #[allow(missing_docs)]
+        impl Currency {
+            $(pub const $code: Currency = Currency(lower_code(stringify!($code)));)*
+        }
+
+        /// All ISO 4217 currencies, sorted by code
+        static CURRENCIES: &[CurrencyInfo] = &[
+            $(CurrencyInfo {
+                code: stringify!($code),
+                numeric: $numeric,
+                exponent: $exp,
+                symbol: $symbol,
+                symbol_position: SymbolPosition::$pos,
+                name: $name,
+            },)*
+        ];
+    };
+}
+
+// Codes without a minor unit in ISO 4217 (precious metals, testing and
+// settlement units) are listed with exponent 0.
+currency_table! {
+    AED, 784, 2, "د.إ", Before, "UAE Dirham";
+    AFN, 971, 2, "؋", Before, "Afghani";
+    ALL, 8, 2, "L", Before, "Lek";
+    AMD, 51, 2, "֏", Before, "Armenian Dram";
+    ANG, 532, 2, "ƒ", Before, "Netherlands Antillean Guilder";
+    AOA, 973, 2, "Kz", Before, "Kwanza";
+    ARS, 32, 2, "$", Before, "Argentine Peso";
+    AUD, 36, 2, "A$", Before, "Australian Dollar";
+    AWG, 533, 2, "ƒ", Before, "Aruban Florin";
+    AZN, 944, 2, "₼", Before, "Azerbaijan Manat";
+    BAM, 977, 2, "KM", Before, "Convertible Mark";
+    BBD, 52, 2, "Bds$", Before, "Barbados Dollar";
+    BDT, 50, 2, "৳", Before, "Taka";
+    BGN, 975, 2, "лв", After, "Bulgarian Lev";
+    BHD, 48, 3, "BD", Before, "Bahraini Dinar";
+    BIF, 108, 0, "FBu", Before, "Burundi Franc";
+    BMD, 60, 2, "$", Before, "Bermudian Dollar";
+    BND, 96, 2, "B$", Before, "Brunei Dollar";
+    BOB, 68, 2, "Bs.", Before, "Boliviano";
+    BOV, 984, 2, "BOV", Before, "Mvdol";
+    BRL, 986, 2, "R$", Before, "Brazilian Real";
+    BSD, 44, 2, "B$", Before, "Bahamian Dollar";
+    BTN, 64, 2, "Nu.", Before, "Ngultrum";
+    BWP, 72, 2, "P", Before, "Pula";
+    BYN, 933, 2, "Br", After, "Belarusian Ruble";
+    BZD, 84, 2, "BZ$", Before, "Belize Dollar";
+    CAD, 124, 2, "C$", Before, "Canadian Dollar";
+    CDF, 976, 2, "FC", Before, "Congolese Franc";
+    CHE, 947, 2, "CHE", Before, "WIR Euro";
+    CHF, 756, 2, "CHF", Before, "Swiss Franc";
+    CHW, 948, 2, "CHW", Before, "WIR Franc";
+    CLF, 990, 4, "UF", Before, "Unidad de Fomento";
+    CLP, 152, 0, "$", Before, "Chilean Peso";
+    CNY, 156, 2, "¥", Before, "Yuan Renminbi";
+    COP, 170, 2, "$", Before, "Colombian Peso";
+    COU, 970, 2, "COU", Before, "Unidad de Valor Real";
+    CRC, 188, 2, "₡", Before, "Costa Rican Colon";
+    CUP, 192, 2, "$", Before, "Cuban Peso";
+    CVE, 132, 2, "Esc", Before, "Cabo Verde Escudo";
+    CZK, 203, 2, "Kč", After, "Czech Koruna";
+    DJF, 262, 0, "Fdj", Before, "Djibouti Franc";
+    DKK, 208, 2, "kr", After, "Danish Krone";
+    DOP, 214, 2, "RD$", Before, "Dominican Peso";
+    DZD, 12, 2, "DA", Before, "Algerian Dinar";
+    EGP, 818, 2, "E£", Before, "Egyptian Pound";
+    ERN, 232, 2, "Nfk", Before, "Nakfa";
+    ETB, 230, 2, "Br", Before, "Ethiopian Birr";
+    EUR, 978, 2, "€", Before, "Euro";
+    FJD, 242, 2, "FJ$", Before, "Fiji Dollar";
+    FKP, 238, 2, "£", Before, "Falkland Islands Pound";
+    GBP, 826, 2, "£", Before, "Pound Sterling";
+    GEL, 981, 2, "₾", Before, "Lari";
+    GHS, 936, 2, "GH₵", Before, "Ghana Cedi";
+    GIP, 292, 2, "£", Before, "Gibraltar Pound";
+    GMD, 270, 2, "D", Before, "Dalasi";
+    GNF, 324, 0, "FG", Before, "Guinean Franc";
+    GTQ, 320, 2, "Q", Before, "Quetzal";
+    GYD, 328, 2, "GY$", Before, "Guyana Dollar";
+    HKD, 344, 2, "HK$", Before, "Hong Kong Dollar";
+    HNL, 340, 2, "L", Before, "Lempira";
+    HTG, 332, 2, "G", Before, "Gourde";
+    HUF, 348, 2, "Ft", After, "Forint";
+    IDR, 360, 2, "Rp", Before, "Rupiah";
+    ILS, 376, 2, "₪", Before, "New Israeli Sheqel";
+    INR, 356, 2, "₹", Before, "Indian Rupee";
+    IQD, 368, 3, "ع.د", Before, "Iraqi Dinar";
+    IRR, 364, 2, "﷼", Before, "Iranian Rial";
+    ISK, 352, 0, "kr", After, "Iceland Krona";
+    JMD, 388, 2, "J$", Before, "Jamaican Dollar";
+    JOD, 400, 3, "JD", Before, "Jordanian Dinar";
+    JPY, 392, 0, "¥", Before, "Yen";
+    KES, 404, 2, "KSh", Before, "Kenyan Shilling";
+    KGS, 417, 2, "сом", After, "Som";
+    KHR, 116, 2, "៛", Before, "Riel";
+    KMF, 174, 0, "CF", Before, "Comorian Franc";
+    KPW, 408, 2, "₩", Before, "North Korean Won";
+    KRW, 410, 0, "₩", Before, "Won";
+    KWD, 414, 3, "KD", Before, "Kuwaiti Dinar";
+    KYD, 136, 2, "CI$", Before, "Cayman Islands Dollar";
+    KZT, 398, 2, "₸", Before, "Tenge";
+    LAK, 418, 2, "₭", Before, "Lao Kip";
+    LBP, 422, 2, "L£", Before, "Lebanese Pound";
+    LKR, 144, 2, "Rs", Before, "Sri Lanka Rupee";
+    LRD, 430, 2, "L$", Before, "Liberian Dollar";
+    LSL, 426, 2, "L", Before, "Loti";
+    LYD, 434, 3, "LD", Before, "Libyan Dinar";
+    MAD, 504, 2, "MAD", Before, "Moroccan Dirham";
+    MDL, 498, 2, "L", After, "Moldovan Leu";
+    MGA, 969, 2, "Ar", Before, "Malagasy Ariary";
+    MKD, 807, 2, "ден", After, "Denar";
+    MMK, 104, 2, "K", Before, "Kyat";
+    MNT, 496, 2, "₮", Before, "Tugrik";
+    MOP, 446, 2, "MOP$", Before, "Pataca";
+    MRU, 929, 2, "UM", Before, "Ouguiya";
+    MUR, 480, 2, "₨", Before, "Mauritius Rupee";
+    MVR, 462, 2, "Rf", Before, "Rufiyaa";
+    MWK, 454, 2, "MK", Before, "Malawi Kwacha";
+    MXN, 484, 2, "MX$", Before, "Mexican Peso";
+    MXV, 979, 2, "MXV", Before, "Mexican Unidad de Inversion (UDI)";
+    MYR, 458, 2, "RM", Before, "Malaysian Ringgit";
+    MZN, 943, 2, "MT", Before, "Mozambique Metical";
+    NAD, 516, 2, "N$", Before, "Namibia Dollar";
+    NGN, 566, 2, "₦", Before, "Naira";
+    NIO, 558, 2, "C$", Before, "Cordoba Oro";
+    NOK, 578, 2, "kr", After, "Norwegian Krone";
+    NPR, 524, 2, "Rs", Before, "Nepalese Rupee";
+    NZD, 554, 2, "NZ$", Before, "New Zealand Dollar";
+    OMR, 512, 3, "OMR", Before, "Rial Omani";
+    PAB, 590, 2, "B/.", Before, "Balboa";
+    PEN, 604, 2, "S/", Before, "Sol";
+    PGK, 598, 2, "K", Before, "Kina";
+    PHP, 608, 2, "₱", Before, "Philippine Peso";
+    PKR, 586, 2, "₨", Before, "Pakistan Rupee";
+    PLN, 985, 2, "zł", After, "Zloty";
+    PYG, 600, 0, "₲", Before, "Guarani";
+    QAR, 634, 2, "QR", Before, "Qatari Rial";
+    RON, 946, 2, "lei", After, "Romanian Leu";
+    RSD, 941, 2, "дин.", After, "Serbian Dinar";
+    RUB, 643, 2, "₽", After, "Russian Ruble";
+    RWF, 646, 0, "FRw", Before, "Rwanda Franc";
+    SAR, 682, 2, "SR", Before, "Saudi Riyal";
+    SBD, 90, 2, "SI$", Before, "Solomon Islands Dollar";
+    SCR, 690, 2, "SR", Before, "Seychelles Rupee";
+    SDG, 938, 2, "SDG", Before, "Sudanese Pound";
+    SEK, 752, 2, "kr", After, "Swedish Krona";
+    SGD, 702, 2, "S$", Before, "Singapore Dollar";
+    SHP, 654, 2, "£", Before, "Saint Helena Pound";
+    SLE, 925, 2, "Le", Before, "Leone";
+    SOS, 706, 2, "Sh", Before, "Somali Shilling";
+    SRD, 968, 2, "$", Before, "Surinam Dollar";
+    SSP, 728, 2, "£", Before, "South Sudanese Pound";
+    STN, 930, 2, "Db", Before, "Dobra";
+    SVC, 222, 2, "₡", Before, "El Salvador Colon";
+    SYP, 760, 2, "£S", Before, "Syrian Pound";
+    SZL, 748, 2, "E", Before, "Lilangeni";
+    THB, 764, 2, "฿", Before, "Baht";
+    TJS, 972, 2, "SM", Before, "Somoni";
+    TMT, 934, 2, "m", After, "Turkmenistan New Manat";
+    TND, 788, 3, "DT", Before, "Tunisian Dinar";
+    TOP, 776, 2, "T$", Before, "Pa'anga";
+    TRY, 949, 2, "₺", Before, "Turkish Lira";
+    TTD, 780, 2, "TT$", Before, "Trinidad and Tobago Dollar";
+    TWD, 901, 2, "NT$", Before, "New Taiwan Dollar";
+    TZS, 834, 2, "TSh", Before, "Tanzanian Shilling";
+    UAH, 980, 2, "₴", Before, "Hryvnia";
+    UGX, 800, 0, "USh", Before, "Uganda Shilling";
+    USD, 840, 2, "$", Before, "US Dollar";
+    USN, 997, 2, "USN", Before, "US Dollar (Next day)";
+    UYI, 940, 0, "UYI", Before, "Uruguay Peso en Unidades Indexadas (UI)";
+    UYU, 858, 2, "$U", Before, "Peso Uruguayo";
+    UYW, 927, 4, "UYW", Before, "Unidad Previsional";
+    UZS, 860, 2, "soʻm", After, "Uzbekistan Sum";
+    VED, 926, 2, "Bs.D", Before, "Bolívar Soberano (digital)";
+    VES, 928, 2, "Bs.S", Before, "Bolívar Soberano";
+    VND, 704, 0, "₫", After, "Dong";
+    VUV, 548, 0, "VT", Before, "Vatu";
+    WST, 882, 2, "WS$", Before, "Tala";
+    XAF, 950, 0, "FCFA", Before, "CFA Franc BEAC";
+    XAG, 961, 0, "XAG", Before, "Silver";
+    XAU, 959, 0, "XAU", Before, "Gold";
+    XBA, 955, 0, "XBA", Before, "Bond Markets Unit European Composite Unit (EURCO)";
+    XBB, 956, 0, "XBB", Before, "Bond Markets Unit European Monetary Unit (E.M.U.-6)";
+    XBC, 957, 0, "XBC", Before, "Bond Markets Unit European Unit of Account 9 (E.U.A.-9)";
+    XBD, 958, 0, "XBD", Before, "Bond Markets Unit European Unit of Account 17 (E.U.A.-17)";
+    XCD, 951, 2, "EC$", Before, "East Caribbean Dollar";
+    XCG, 532, 2, "Cg", Before, "Caribbean Guilder";
+    XDR, 960, 0, "XDR", Before, "SDR (Special Drawing Right)";
+    XOF, 952, 0, "CFA", Before, "CFA Franc BCEAO";
+    XPD, 964, 0, "XPD", Before, "Palladium";
+    XPF, 953, 0, "CFP", After, "CFP Franc";
+    XPT, 962, 0, "XPT", Before, "Platinum";
+    XSU, 994, 0, "XSU", Before, "Sucre";
+    XTS, 963, 0, "XTS", Before, "Codes specifically reserved for testing purposes";
+    XUA, 965, 0, "XUA", Before, "ADB Unit of Account";
+    XXX, 999, 0, "XXX", Before, "No currency";
+    YER, 886, 2, "﷼", Before, "Yemeni Rial";
+    ZAR, 710, 2, "R", Before, "Rand";
+    ZMW, 967, 2, "ZK", Before, "Zambian Kwacha";
+    ZWG, 924, 2, "ZiG", Before, "Zimbabwe Gold";
+}
+
+impl Currency {
+    /// Look up a currency by alphabetic code (case-insensitive)
+    pub fn from_code(code: &str) -> Option<Currency> {
+        if code.len() != 3 || !code.is_ascii() {
+            return None;
+        }
+        Self::find(code.as_bytes()).map(|info| Currency(lower_code(info.code)))
+    }
+
+    /// Look up a current currency by ISO 4217 numeric code
+    pub fn from_numeric(numeric: u16) -> Option<Currency> {
+        CURRENCIES
+            .iter()
+            .find(|info| info.numeric == numeric && !info.is_historic())
+            .map(|info| Currency(lower_code(info.code)))
+    }
+
+    /// Iterate over every known currency
+    pub fn all() -> impl Iterator<Item = Currency> {
+        CURRENCIES.iter().map(|info| Currency(lower_code(info.code)))
+    }
+
+    /// Table entry for this currency
+    pub fn info(&self) -> &'static CurrencyInfo {
+        Self::find(&self.0).expect("Currency always refers to a table entry")
+    }
+
+    /// Returns the ISO 4217 currency code (lowercase, as Stripe expects)
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.0).expect("currency codes are ASCII")
+    }
+
+    /// Returns the uppercase ISO 4217 currency code
+    pub fn code(&self) -> &'static str {
+        self.info().code
+    }
+
+    /// Returns the display symbol
+    pub fn symbol(&self) -> &'static str {
+        self.info().symbol
+    }
+
+    /// Returns where the symbol is placed relative to the amount
+    pub fn symbol_position(&self) -> SymbolPosition {
+        self.info().symbol_position
+    }
+
+    /// Returns the number of decimal places for this currency
+    /// (JPY has 0 decimals, KWD has 3, most others have 2)
+    pub fn decimal_places(&self) -> u8 {
+        self.info().exponent
+    }
+
+    /// Convert a decimal amount to the smallest currency unit (cents, etc.)
+    ///
+    /// Fails if the amount has more decimal places than the currency allows.
+    pub fn to_smallest_unit(&self, amount: Money) -> PaymentResult<i64> {
+        amount.to_minor_units(self.decimal_places())
+    }
+
+    /// Convert from smallest unit back to decimal
+    pub fn from_smallest_unit(&self, amount: i64) -> Money {
+        Money::from_minor_units(amount, self.decimal_places())
+            .expect("currency decimal places within Money::SCALE")
+    }
+
+    /// Find a table entry by code, ignoring ASCII case
+    fn find(code: &[u8]) -> Option<&'static CurrencyInfo> {
+        CURRENCIES
+            .binary_search_by(|info| info.code.bytes().cmp(code.iter().map(u8::to_ascii_uppercase)))
+            .ok()
+            .map(|idx| &CURRENCIES[idx])
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Currency::USD
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl fmt::Debug for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = PaymentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Currency::from_code(s).ok_or_else(|| PaymentError::UnsupportedCurrency {
+            currency: s.to_string(),
+        })
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        code.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_sorted_and_unique() {
+        assert!(CURRENCIES.windows(2).all(|w| w[0].code < w[1].code));
+        assert!(CURRENCIES.iter().all(|info| info.exponent <= Money::SCALE));
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(Currency::from_code("usd"), Some(Currency::USD));
+        assert_eq!(Currency::from_code("KRW"), Some(Currency::KRW));
+        assert_eq!(Currency::from_code("xyz"), None);
+        assert_eq!(Currency::from_code("us"), None);
+        assert_eq!(Currency::from_numeric(978), Some(Currency::EUR));
+        assert_eq!(Currency::from_code("kWd"), Some(Currency::KWD));
+        assert_eq!(Currency::TWD.info().code, "TWD");
+    }
+
+    #[test]
+    fn test_historic_numeric_code() {
+        // ANG and XCG share 532; the current code wins
+        assert_eq!(Currency::ANG.info().numeric, 532);
+        assert!(Currency::ANG.info().is_historic());
+        assert_eq!(Currency::from_numeric(532), Some(Currency::XCG));
+        assert_eq!(Currency::from_code("ang"), Some(Currency::ANG));
+        let numerics: Vec<u16> = CURRENCIES
+            .iter()
+            .filter(|info| !info.is_historic() && info.numeric != 0)
+            .map(|info| info.numeric)
+            .collect();
+        let mut unique = numerics.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), numerics.len());
+        assert!("zzz".parse::<Currency>().is_err());
+    }
+
+    #[test]
+    fn test_exponents() {
+        assert_eq!(Currency::USD.decimal_places(), 2);
+        assert_eq!(Currency::JPY.decimal_places(), 0);
+        assert_eq!(Currency::KRW.decimal_places(), 0);
+        assert_eq!(Currency::KWD.decimal_places(), 3);
+        assert_eq!(Currency::BHD.decimal_places(), 3);
+        assert_eq!(Currency::CLF.decimal_places(), 4);
+        assert_eq!(Currency::SEK.symbol_position(), SymbolPosition::After);
+    }
+
+    #[test]
+    fn test_serde() {
+        assert_eq!(serde_json::to_string(&Currency::SEK).unwrap(), "\"sek\"");
+        assert_eq!(serde_json::from_str::<Currency>("\"INR\"").unwrap(), Currency::INR);
+        assert_eq!(serde_json::from_str::<Currency>("\"brl\"").unwrap(), Currency::BRL);
+        assert!(serde_json::from_str::<Currency>("\"abc\"").is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! # Customers
+//!
+//! One record per buyer, keyed by email, shared by all sites.
+//!
+//! A customer remembers their ID at each payment provider, so repeat
+//! checkouts reuse the provider's customer instead of creating a guest one
+//! every time. `CustomerRepository` is implemented in-memory here and on
+//! SQLite in `pay-sqlite`.
+
+use crate::error::{PaymentError, PaymentResult};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+/// A buyer, across sites and providers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Customer {
+    /// Unique customer ID (generated)
+    pub id: String,
+
+    /// Email address (lowercase)
+    pub email: String,
+
+    /// Name (if known)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Customer ID at each provider (e.g., "stripe" → "cus_...")
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub provider_ids: HashMap<String, String>,
+
+    /// Sites the customer has checked out on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sites: Vec<String>,
+
+    /// Created timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Last update
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Customer {
+    /// Create a new customer with generated ID
+    pub fn new(email: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            email: normalize_email(email),
+            name: None,
+            provider_ids: HashMap::new(),
+            sites: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Set the name
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Associate with a site
+    pub fn with_site(mut self, site_id: impl Into<String>) -> Self {
+        self.add_site(site_id);
+        self
+    }
+
+    /// Set the customer ID at a provider
+    pub fn with_provider_id(mut self, provider: &str, provider_customer_id: impl Into<String>) -> Self {
+        self.provider_ids
+            .insert(provider.to_string(), provider_customer_id.into());
+        self
+    }
+
+    /// Customer ID at a provider
+    pub fn provider_id(&self, provider: &str) -> Option<&str> {
+        self.provider_ids.get(provider).map(String::as_str)
+    }
+
+    /// Associate with a site (no-op if already associated)
+    pub fn add_site(&mut self, site_id: impl Into<String>) {
+        let site_id = site_id.into();
+        if !self.sites.contains(&site_id) {
+            self.sites.push(site_id);
+        }
+    }
+
+    /// Check if the customer has checked out on a site
+    pub fn has_site(&self, site_id: &str) -> bool {
+        self.sites.iter().any(|s| s == site_id)
+    }
+
+    /// Merge what another record of the same customer knows.
+    ///
+    /// Newer provider IDs and names win; sites are combined. Returns whether
+    /// anything changed.
+    pub fn merge(&mut self, other: &Customer) -> bool {
+        let before = self.clone();
+        if other.name.is_some() {
+            self.name = other.name.clone();
+        }
+        for (provider, id) in &other.provider_ids {
+            self.provider_ids.insert(provider.clone(), id.clone());
+        }
+        for site in &other.sites {
+            self.add_site(site.clone());
+        }
+        let changed = *self != before;
+        if changed {
+            self.updated_at = Utc::now();
+        }
+        changed
+    }
+}
+
+/// Normalize an email address for lookups
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Storage for customers
+#[async_trait]
+pub trait CustomerRepository: Send + Sync {
+    /// Store a customer, replacing the record with the same ID
+    async fn save(&self, customer: &Customer) -> PaymentResult<()>;
+
+    /// Get a customer by ID
+    async fn get(&self, customer_id: &str) -> PaymentResult<Option<Customer>>;
+
+    /// Get a customer by email (case-insensitive)
+    async fn find_by_email(&self, email: &str) -> PaymentResult<Option<Customer>>;
+
+    /// Get a customer by their ID at a provider
+    async fn find_by_provider_id(
+        &self,
+        provider: &str,
+        provider_customer_id: &str,
+    ) -> PaymentResult<Option<Customer>>;
+
+    /// Merge a customer into the record with the same email (creating it).
+    ///
+    /// Returns the stored record.
+    async fn upsert(&self, customer: &Customer) -> PaymentResult<Customer> {
+        match self.find_by_email(&customer.email).await? {
+            Some(mut existing) => {
+                if existing.merge(customer) {
+                    self.save(&existing).await?;
+                }
+                Ok(existing)
+            }
+            None => {
+                self.save(customer).await?;
+                Ok(customer.clone())
+            }
+        }
+    }
+}
+
+/// Shared customer repository
+pub type BoxedCustomerRepository = Arc<dyn CustomerRepository>;
+
+/// In-memory customer repository (contents are lost on restart)
+#[derive(Debug, Default)]
+pub struct InMemoryCustomerRepository {
+    customers: RwLock<HashMap<String, Customer>>,
+}
+
+impl InMemoryCustomerRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn find(&self, matches: impl Fn(&Customer) -> bool) -> PaymentResult<Option<Customer>> {
+        Ok(self
+            .customers
+            .read()
+            .map_err(|_| PaymentError::Storage("customer store lock poisoned".to_string()))?
+            .values()
+            .find(|c| matches(c))
+            .cloned())
+    }
+}
+
+#[async_trait]
+impl CustomerRepository for InMemoryCustomerRepository {
+    async fn save(&self, customer: &Customer) -> PaymentResult<()> {
+        self.customers
+            .write()
+            .map_err(|_| PaymentError::Storage("customer store lock poisoned".to_string()))?
+            .insert(customer.id.clone(), customer.clone());
+        Ok(())
+    }
+
+    async fn get(&self, customer_id: &str) -> PaymentResult<Option<Customer>> {
+        self.find(|c| c.id == customer_id)
+    }
+
+    async fn find_by_email(&self, email: &str) -> PaymentResult<Option<Customer>> {
+        let email = normalize_email(email);
+        self.find(|c| c.email == email)
+    }
+
+    async fn find_by_provider_id(
+        &self,
+        provider: &str,
+        provider_customer_id: &str,
+    ) -> PaymentResult<Option<Customer>> {
+        self.find(|c| c.provider_id(provider) == Some(provider_customer_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_upsert_merges_by_email() {
+        let repo = InMemoryCustomerRepository::new();
+        let first = repo
+            .upsert(&Customer::new("Buyer@Example.com ").with_site("chargegun"))
+            .await
+            .unwrap();
+        assert_eq!(first.email, "buyer@example.com");
+
+        let second = repo
+            .upsert(
+                &Customer::new("buyer@example.com")
+                    .with_name("Ada")
+                    .with_site("luckydrone")
+                    .with_provider_id("stripe", "cus_1"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(second.id, first.id);
+        assert_eq!(second.sites, vec!["chargegun", "luckydrone"]);
+
+        let found = repo.find_by_provider_id("stripe", "cus_1").await.unwrap().unwrap();
+        assert_eq!(found.name.as_deref(), Some("Ada"));
+        assert!(found.has_site("chargegun"));
+    }
+}
@@ -3,6 +3,7 @@
 //! Typed error handling for the lightning-cart payment engine.
 //! All payment operations return `Result<T, PaymentError>`.
 
+use crate::lifecycle::OrderStatus;
 use thiserror::Error;
 
 /// Core error type for all payment operations
@@ -20,6 +21,18 @@ pub enum PaymentError {
     #[error("Product not found: {product_id}")]
     ProductNotFound { product_id: String },
 
+    /// Product exists but is sold on other sites
+    #[error("Product {product_id} is not sold on site {site_id}")]
+    ProductNotOnSite { product_id: String, site_id: String },
+
+    /// No active site with this ID
+    #[error("Site not found: {site_id}")]
+    SiteNotFound { site_id: String },
+
+    /// Product has no variant with this ID
+    #[error("Variant {variant_id} not found for product {product_id}")]
+    VariantNotFound { product_id: String, variant_id: String },
+
     /// Price mismatch or invalid amount
     #[error("Invalid price: {message}")]
     InvalidPrice { message: String },
@@ -52,6 +65,22 @@ pub enum PaymentError {
     #[error("Session not found or expired: {session_id}")]
     SessionNotFound { session_id: String },
 
+    /// Order not found in the order store
+    #[error("Order not found: {order_id}")]
+    OrderNotFound { order_id: String },
+
+    /// Subscription not found at the provider (or not sold on the site)
+    #[error("Subscription not found: {subscription_id}")]
+    SubscriptionNotFound { subscription_id: String },
+
+    /// Order status change not allowed by the lifecycle state machine
+    #[error("Invalid order transition: {from} → {to}")]
+    InvalidTransition { from: OrderStatus, to: OrderStatus },
+
+    /// Order store error
+    #[error("Storage error: {0}")]
+    Storage(String),
+
     /// Payment was declined
     #[error("Payment declined: {reason}")]
     PaymentDeclined { reason: String },
@@ -60,6 +89,14 @@ pub enum PaymentError {
     #[error("Idempotency conflict: request with key {key} already exists with different parameters")]
     IdempotencyConflict { key: String },
 
+    /// Promotion code unknown or not valid for this order
+    #[error("Promotion {code} cannot be applied: {reason}")]
+    PromotionNotApplicable { code: String, reason: String },
+
+    /// Missing or invalid API credentials
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
     /// Rate limited by provider
     #[error("Rate limited by {provider}, retry after {retry_after_secs} seconds")]
     RateLimited {
@@ -78,12 +115,16 @@ pub enum PaymentError {
 
 impl PaymentError {
     /// Returns true if this error is retryable
+    ///
+    /// Storage errors count as retryable: they are usually a busy or
+    /// briefly unavailable database.
     pub fn is_retryable(&self) -> bool {
         matches!(
             self,
             PaymentError::NetworkError(_)
                 | PaymentError::RateLimited { .. }
                 | PaymentError::ProviderError { .. }
+                | PaymentError::Storage(_)
         )
     }
 
@@ -93,6 +134,9 @@ impl PaymentError {
             PaymentError::Configuration(_) => 500,
             PaymentError::InvalidRequest(_) => 400,
             PaymentError::ProductNotFound { .. } => 404,
+            PaymentError::ProductNotOnSite { .. } => 403,
+            PaymentError::SiteNotFound { .. } => 404,
+            PaymentError::VariantNotFound { .. } => 404,
             PaymentError::InvalidPrice { .. } => 400,
             PaymentError::UnsupportedCurrency { .. } => 400,
             PaymentError::ProviderError { .. } => 502,
@@ -101,8 +145,14 @@ impl PaymentError {
             PaymentError::WebhookParseError(_) => 400,
             PaymentError::CheckoutCreationFailed(_) => 500,
             PaymentError::SessionNotFound { .. } => 404,
+            PaymentError::OrderNotFound { .. } => 404,
+            PaymentError::SubscriptionNotFound { .. } => 404,
+            PaymentError::InvalidTransition { .. } => 409,
+            PaymentError::Storage(_) => 500,
             PaymentError::PaymentDeclined { .. } => 402,
             PaymentError::IdempotencyConflict { .. } => 409,
+            PaymentError::PromotionNotApplicable { .. } => 400,
+            PaymentError::Unauthorized(_) => 401,
             PaymentError::RateLimited { .. } => 429,
             PaymentError::Internal(_) => 500,
             PaymentError::Serialization(_) => 500,
@@ -125,6 +175,7 @@ mod tests {
             retry_after_secs: 60
         }
         .is_retryable());
+        assert!(PaymentError::Storage("database is locked".into()).is_retryable());
         assert!(!PaymentError::InvalidRequest("bad data".into()).is_retryable());
     }
 
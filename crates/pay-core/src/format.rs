@@ -0,0 +1,306 @@
+//! # Price Formatting
+//!
+//! Locale-aware formatting of prices for lightning-cart.
+//!
+//! A `Locale` describes digit grouping, the decimal separator and where the
+//! currency symbol goes. `CurrencyDisplay` picks between the narrow symbol
+//! ("€") and the ISO code ("EUR").
+//!
+//! ```rust
+//! use pay_core::{Currency, CurrencyDisplay, Locale, Price};
+//!
+//! let price = Price::from_cents(123456, Currency::EUR);
+//! let de = Locale::from_tag("de-DE").unwrap();
+//!
+//! assert_eq!(price.format(&de, CurrencyDisplay::Symbol), "1.234,56 €");
+//! assert_eq!(price.format(&Locale::default(), CurrencyDisplay::Code), "EUR 1,234.56");
+//! ```
+
+use crate::currency::SymbolPosition;
+use crate::product::Price;
+use serde::{Deserialize, Serialize};
+
+/// How digits in the integer part are grouped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Grouping {
+    /// No grouping ("1234567")
+    None,
+    /// Groups of three ("1,234,567")
+    Thousands,
+    /// Indian lakh/crore grouping ("12,34,567")
+    Indian,
+}
+
+/// How the currency is shown next to the amount
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CurrencyDisplay {
+    /// Narrow symbol (e.g., "$", "€", "kr")
+    #[default]
+    Symbol,
+    /// ISO 4217 code (e.g., "USD", "EUR")
+    Code,
+}
+
+/// Number formatting conventions for a locale
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Locale {
+    /// BCP 47 language tag (e.g., "de-DE")
+    pub tag: String,
+
+    /// Separator between digit groups (e.g., "," or ".")
+    pub grouping_separator: String,
+
+    /// Separator between integer and fractional part (e.g., "." or ",")
+    pub decimal_separator: String,
+
+    /// Digit grouping style
+    pub grouping: Grouping,
+
+    /// Symbol placement; `None` uses the currency's own convention
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol_position: Option<SymbolPosition>,
+
+    /// Always put a space between a leading symbol and the amount
+    /// (trailing symbols and alphabetic symbols are always spaced)
+    #[serde(default)]
+    pub symbol_spacing: bool,
+}
+
+/// (tag, grouping separator, decimal separator, grouping, symbol position, symbol spacing)
+type LocaleRow = (&'static str, &'static str, &'static str, Grouping, Option<SymbolPosition>, bool);
+
+/// Built-in locales
+const LOCALES: &[LocaleRow] = &[
+    ("en-US", ",", ".", Grouping::Thousands, None, false),
+    ("en-GB", ",", ".", Grouping::Thousands, None, false),
+    ("en-IN", ",", ".", Grouping::Indian, None, false),
+    ("de-DE", ".", ",", Grouping::Thousands, Some(SymbolPosition::After), false),
+    ("de-AT", "\u{a0}", ",", Grouping::Thousands, Some(SymbolPosition::Before), true),
+    ("de-CH", "’", ".", Grouping::Thousands, Some(SymbolPosition::Before), true),
+    ("fr-FR", "\u{202f}", ",", Grouping::Thousands, Some(SymbolPosition::After), false),
+    ("es-ES", ".", ",", Grouping::Thousands, Some(SymbolPosition::After), false),
+    ("it-IT", ".", ",", Grouping::Thousands, Some(SymbolPosition::After), false),
+    ("nl-NL", ".", ",", Grouping::Thousands, Some(SymbolPosition::Before), true),
+    ("pt-BR", ".", ",", Grouping::Thousands, Some(SymbolPosition::Before), true),
+    ("sv-SE", "\u{a0}", ",", Grouping::Thousands, Some(SymbolPosition::After), false),
+    ("nb-NO", "\u{a0}", ",", Grouping::Thousands, Some(SymbolPosition::After), false),
+    ("da-DK", ".", ",", Grouping::Thousands, Some(SymbolPosition::After), false),
+    ("ja-JP", ",", ".", Grouping::Thousands, Some(SymbolPosition::Before), false),
+    ("ko-KR", ",", ".", Grouping::Thousands, Some(SymbolPosition::Before), false),
+];
+
+impl Locale {
+    /// Create a custom locale
+    pub fn new(
+        tag: impl Into<String>,
+        grouping_separator: impl Into<String>,
+        decimal_separator: impl Into<String>,
+    ) -> Self {
+        Self {
+            tag: tag.into(),
+            grouping_separator: grouping_separator.into(),
+            decimal_separator: decimal_separator.into(),
+            grouping: Grouping::Thousands,
+            symbol_position: None,
+            symbol_spacing: false,
+        }
+    }
+
+    /// Look up a built-in locale by tag (case-insensitive, "_" or "-").
+    ///
+    /// A bare language ("de") matches the first built-in locale for it.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let normalized = tag.trim().replace('_', "-");
+        let language = normalized.split('-').next().unwrap_or_default();
+
+        LOCALES
+            .iter()
+            .find(|l| l.0.eq_ignore_ascii_case(&normalized))
+            .or_else(|| {
+                LOCALES.iter().find(|l| {
+                    l.0.split('-')
+                        .next()
+                        .is_some_and(|lang| lang.eq_ignore_ascii_case(language))
+                })
+            })
+            .map(|&(tag, group, decimal, grouping, position, spacing)| Self {
+                tag: tag.to_string(),
+                grouping_separator: group.to_string(),
+                decimal_separator: decimal.to_string(),
+                grouping,
+                symbol_position: position,
+                symbol_spacing: spacing,
+            })
+    }
+
+    /// Pick the first supported locale from an `Accept-Language` header value
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        header
+            .split(',')
+            .filter_map(|part| part.split(';').next())
+            .find_map(Self::from_tag)
+    }
+
+    /// Builder: set digit grouping
+    pub fn with_grouping(mut self, grouping: Grouping) -> Self {
+        self.grouping = grouping;
+        self
+    }
+
+    /// Builder: force symbol placement
+    pub fn with_symbol_position(mut self, position: SymbolPosition) -> Self {
+        self.symbol_position = Some(position);
+        self
+    }
+
+    /// Builder: always space a leading symbol from the amount
+    pub fn with_symbol_spacing(mut self, spacing: bool) -> Self {
+        self.symbol_spacing = spacing;
+        self
+    }
+
+    /// Format a price in this locale
+    pub fn format_price(&self, price: &Price, display: CurrencyDisplay) -> String {
+        let currency = price.currency;
+        let digits = price.as_decimal().format_places(currency.decimal_places());
+        let digits = digits.trim_start_matches('-');
+        let (whole, frac) = match digits.split_once('.') {
+            Some((w, f)) => (w, Some(f)),
+            None => (digits, None),
+        };
+
+        let mut number = self.group(whole);
+        if let Some(frac) = frac {
+            number.push_str(&self.decimal_separator);
+            number.push_str(frac);
+        }
+
+        let (marker, position, spaced) = match display {
+            CurrencyDisplay::Symbol => {
+                let symbol = currency.symbol();
+                let position = self.symbol_position.unwrap_or(currency.symbol_position());
+                let spaced = self.symbol_spacing || symbol.ends_with(char::is_alphabetic);
+                (symbol, position, spaced)
+            }
+            CurrencyDisplay::Code => {
+                let position = self.symbol_position.unwrap_or(SymbolPosition::Before);
+                (currency.code(), position, true)
+            }
+        };
+
+        let sign = if price.amount < 0 { "-" } else { "" };
+        match position {
+            SymbolPosition::Before if spaced => format!("{}{} {}", sign, marker, number),
+            SymbolPosition::Before => format!("{}{}{}", sign, marker, number),
+            SymbolPosition::After => format!("{}{} {}", sign, number, marker),
+        }
+    }
+
+    fn group(&self, whole: &str) -> String {
+        let sizes: &[usize] = match self.grouping {
+            Grouping::None => return whole.to_string(),
+            Grouping::Thousands => &[3],
+            Grouping::Indian => &[3, 2],
+        };
+
+        // Split from the right: first group uses sizes[0], the rest use the last size
+        let mut groups = Vec::new();
+        let mut end = whole.len();
+        let mut idx = 0;
+        while end > 0 {
+            let size = sizes[idx.min(sizes.len() - 1)];
+            let start = end.saturating_sub(size);
+            groups.push(&whole[start..end]);
+            end = start;
+            idx += 1;
+        }
+        groups.reverse();
+        groups.join(&self.grouping_separator)
+    }
+}
+
+impl Default for Locale {
+    /// en-US
+    fn default() -> Self {
+        Self::from_tag("en-US").expect("en-US is a built-in locale")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::Currency;
+
+    #[test]
+    fn test_en_us() {
+        let en = Locale::default();
+        let price = Price::from_cents(123456789, Currency::USD);
+        assert_eq!(en.format_price(&price, CurrencyDisplay::Symbol), "$1,234,567.89");
+        assert_eq!(en.format_price(&price, CurrencyDisplay::Code), "USD 1,234,567.89");
+        assert_eq!(
+            en.format_price(&Price::from_cents(-500, Currency::USD), CurrencyDisplay::Symbol),
+            "-$5.00"
+        );
+        assert_eq!(
+            en.format_price(&Price::from_cents(1999, Currency::SEK), CurrencyDisplay::Symbol),
+            "19.99 kr"
+        );
+    }
+
+    #[test]
+    fn test_european_locales() {
+        let price = Price::from_cents(123456, Currency::EUR);
+
+        let de = Locale::from_tag("de-DE").unwrap();
+        assert_eq!(de.format_price(&price, CurrencyDisplay::Symbol), "1.234,56 €");
+        assert_eq!(de.format_price(&price, CurrencyDisplay::Code), "1.234,56 EUR");
+
+        let nl = Locale::from_tag("nl_nl").unwrap();
+        assert_eq!(nl.format_price(&price, CurrencyDisplay::Symbol), "€ 1.234,56");
+
+        let ch = Locale::from_tag("de-CH").unwrap();
+        let chf = Price::from_cents(123456, Currency::CHF);
+        assert_eq!(ch.format_price(&chf, CurrencyDisplay::Symbol), "CHF 1’234.56");
+    }
+
+    #[test]
+    fn test_zero_and_three_decimal_currencies() {
+        let en = Locale::default();
+        assert_eq!(
+            en.format_price(&Price::from_cents(1234567, Currency::JPY), CurrencyDisplay::Symbol),
+            "¥1,234,567"
+        );
+        assert_eq!(
+            en.format_price(&Price::from_cents(1234567, Currency::KWD), CurrencyDisplay::Symbol),
+            "KD 1,234.567"
+        );
+    }
+
+    #[test]
+    fn test_indian_grouping() {
+        let en_in = Locale::from_tag("en-IN").unwrap();
+        let price = Price::from_cents(123456789, Currency::INR);
+        assert_eq!(en_in.format_price(&price, CurrencyDisplay::Symbol), "₹12,34,567.89");
+    }
+
+    #[test]
+    fn test_locale_lookup() {
+        assert_eq!(Locale::from_tag("de").unwrap().tag, "de-DE");
+        assert!(Locale::from_tag("xx-YY").is_none());
+        assert_eq!(
+            Locale::from_accept_language("xx-YY, fr-CA;q=0.9, en;q=0.8").unwrap().tag,
+            "fr-FR"
+        );
+    }
+
+    #[test]
+    fn test_custom_locale() {
+        let custom = Locale::new("x-test", " ", ",")
+            .with_grouping(Grouping::None)
+            .with_symbol_position(SymbolPosition::After);
+        let price = Price::from_cents(123456, Currency::USD);
+        assert_eq!(custom.format_price(&price, CurrencyDisplay::Symbol), "1234,56 $");
+    }
+}
@@ -0,0 +1,324 @@
+//! # Billing Intervals
+//!
+//! How often a subscription bills: a unit (day, week, month, year) and a
+//! count, e.g. every 3 months.
+//!
+//! In TOML/JSON an interval is a name or a table:
+//!
+//! ```toml
+//! billing_interval = "monthly"                      # also "onetime", "daily", "weekly",
+//!                                                   # "quarterly", "semiannual", "yearly"
+//! billing_interval = "every 2 weeks"
+//! billing_interval = { unit = "month", count = 3 }
+//! ```
+//!
+//! Intervals are limited to three years (1095 days, 156 weeks, 36 months or
+//! 3 years), the longest Stripe accepts.
+
+use crate::error::{PaymentError, PaymentResult};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Unit of a billing interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntervalUnit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl IntervalUnit {
+    /// Get the unit name (as used by Stripe)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IntervalUnit::Day => "day",
+            IntervalUnit::Week => "week",
+            IntervalUnit::Month => "month",
+            IntervalUnit::Year => "year",
+        }
+    }
+
+    /// Largest count allowed for this unit (three years)
+    pub fn max_count(&self) -> u32 {
+        match self {
+            IntervalUnit::Day => 1095,
+            IntervalUnit::Week => 156,
+            IntervalUnit::Month => 36,
+            IntervalUnit::Year => 3,
+        }
+    }
+}
+
+impl FromStr for IntervalUnit {
+    type Err = PaymentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().trim_end_matches('s') {
+            "day" => Ok(IntervalUnit::Day),
+            "week" => Ok(IntervalUnit::Week),
+            "month" => Ok(IntervalUnit::Month),
+            "year" => Ok(IntervalUnit::Year),
+            _ => Err(PaymentError::InvalidRequest(format!(
+                "unknown interval unit: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Billing interval for subscriptions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(try_from = "RawInterval", into = "RawInterval")]
+pub enum BillingInterval {
+    /// One-time payment (not a subscription)
+    #[default]
+    OneTime,
+    /// Bill every `count` units
+    Every { unit: IntervalUnit, count: u32 },
+}
+
+impl BillingInterval {
+    /// Every day
+    pub const DAILY: Self = Self::Every {
+        unit: IntervalUnit::Day,
+        count: 1,
+    };
+    /// Every week
+    pub const WEEKLY: Self = Self::Every {
+        unit: IntervalUnit::Week,
+        count: 1,
+    };
+    /// Every month
+    pub const MONTHLY: Self = Self::Every {
+        unit: IntervalUnit::Month,
+        count: 1,
+    };
+    /// Every 3 months
+    pub const QUARTERLY: Self = Self::Every {
+        unit: IntervalUnit::Month,
+        count: 3,
+    };
+    /// Every 6 months
+    pub const SEMIANNUAL: Self = Self::Every {
+        unit: IntervalUnit::Month,
+        count: 6,
+    };
+    /// Every year
+    pub const YEARLY: Self = Self::Every {
+        unit: IntervalUnit::Year,
+        count: 1,
+    };
+
+    /// Bill every `count` units, checked against the limits
+    pub fn every(count: u32, unit: IntervalUnit) -> PaymentResult<Self> {
+        let interval = Self::Every { unit, count };
+        interval.validate()?;
+        Ok(interval)
+    }
+
+    /// Check the count is between 1 and the unit's maximum
+    pub fn validate(&self) -> PaymentResult<()> {
+        match *self {
+            BillingInterval::Every { unit, count } if count == 0 || count > unit.max_count() => {
+                Err(PaymentError::InvalidRequest(format!(
+                    "billing interval must be 1 to {} {}s, got {}",
+                    unit.max_count(),
+                    unit.as_str(),
+                    count
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check if this is a recurring interval
+    pub fn is_recurring(&self) -> bool {
+        matches!(self, BillingInterval::Every { .. })
+    }
+
+    /// Unit (None for one-time)
+    pub fn unit(&self) -> Option<IntervalUnit> {
+        match self {
+            BillingInterval::Every { unit, .. } => Some(*unit),
+            BillingInterval::OneTime => None,
+        }
+    }
+
+    /// Number of units between bills (0 for one-time)
+    pub fn count(&self) -> u32 {
+        match self {
+            BillingInterval::Every { count, .. } => *count,
+            BillingInterval::OneTime => 0,
+        }
+    }
+
+    /// Length in whole months (None for one-time, daily and weekly)
+    pub fn months(&self) -> Option<u32> {
+        match *self {
+            BillingInterval::Every {
+                unit: IntervalUnit::Month,
+                count,
+            } => Some(count),
+            BillingInterval::Every {
+                unit: IntervalUnit::Year,
+                count,
+            } => Some(count * 12),
+            _ => None,
+        }
+    }
+
+    /// Single-word name, if there is one (e.g., "monthly", "quarterly")
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match *self {
+            BillingInterval::OneTime => "onetime",
+            Self::DAILY => "daily",
+            Self::WEEKLY => "weekly",
+            Self::MONTHLY => "monthly",
+            Self::QUARTERLY => "quarterly",
+            Self::SEMIANNUAL => "semiannual",
+            Self::YEARLY => "yearly",
+            _ => return None,
+        })
+    }
+}
+
+/// "one-time", "every month", "every 3 months"
+impl fmt::Display for BillingInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            BillingInterval::OneTime => f.write_str("one-time"),
+            BillingInterval::Every { unit, count: 1 } => write!(f, "every {}", unit.as_str()),
+            BillingInterval::Every { unit, count } => {
+                write!(f, "every {} {}s", count, unit.as_str())
+            }
+        }
+    }
+}
+
+/// Parses names ("monthly", "one_time", "annual") and "every N units"
+impl FromStr for BillingInterval {
+    type Err = PaymentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.trim().to_ascii_lowercase();
+        let named = match normalized.as_str() {
+            "onetime" | "one_time" | "one-time" => Some(BillingInterval::OneTime),
+            "daily" => Some(Self::DAILY),
+            "weekly" => Some(Self::WEEKLY),
+            "monthly" => Some(Self::MONTHLY),
+            "quarterly" => Some(Self::QUARTERLY),
+            "semiannual" | "semi_annual" | "semi-annual" => Some(Self::SEMIANNUAL),
+            "yearly" | "annual" | "annually" => Some(Self::YEARLY),
+            _ => None,
+        };
+        if let Some(interval) = named {
+            return Ok(interval);
+        }
+
+        let invalid = || PaymentError::InvalidRequest(format!("invalid billing interval: {}", s));
+        let words: Vec<&str> = normalized
+            .strip_prefix("every ")
+            .ok_or_else(invalid)?
+            .split_whitespace()
+            .collect();
+        match words.as_slice() {
+            [unit] => Self::every(1, unit.parse()?),
+            [count, unit] => Self::every(count.parse().map_err(|_| invalid())?, unit.parse()?),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Wire format for `BillingInterval`: a name or `{ unit, count }`
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawInterval {
+    Name(String),
+    Every {
+        unit: IntervalUnit,
+        #[serde(default = "default_count")]
+        count: u32,
+    },
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+impl TryFrom<RawInterval> for BillingInterval {
+    type Error = PaymentError;
+
+    fn try_from(raw: RawInterval) -> Result<Self, Self::Error> {
+        match raw {
+            RawInterval::Name(name) => name.parse(),
+            RawInterval::Every { unit, count } => Self::every(count, unit),
+        }
+    }
+}
+
+impl From<BillingInterval> for RawInterval {
+    fn from(interval: BillingInterval) -> Self {
+        match (interval.name(), interval) {
+            (Some(name), _) => RawInterval::Name(name.to_string()),
+            (None, BillingInterval::Every { unit, count }) => RawInterval::Every { unit, count },
+            (None, BillingInterval::OneTime) => RawInterval::Name("onetime".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_parse_and_display() {
+        assert_eq!("monthly".parse::<BillingInterval>().unwrap(), BillingInterval::MONTHLY);
+        assert_eq!("onetime".parse::<BillingInterval>().unwrap(), BillingInterval::OneTime);
+        assert_eq!(
+            "every 2 weeks".parse::<BillingInterval>().unwrap(),
+            BillingInterval::every(2, IntervalUnit::Week).unwrap()
+        );
+        assert_eq!(BillingInterval::QUARTERLY.to_string(), "every 3 months");
+        assert_eq!(BillingInterval::YEARLY.to_string(), "every year");
+        assert_eq!(BillingInterval::OneTime.to_string(), "one-time");
+        assert_eq!(BillingInterval::SEMIANNUAL.months(), Some(6));
+
+        // Longer than three years is rejected
+        assert!(BillingInterval::every(37, IntervalUnit::Month).is_err());
+        assert!(BillingInterval::every(0, IntervalUnit::Day).is_err());
+        assert!("every 4 years".parse::<BillingInterval>().is_err());
+        assert!("fortnightly".parse::<BillingInterval>().is_err());
+    }
+
+    #[test]
+    fn test_interval_serde_round_trip() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Plan {
+            billing_interval: BillingInterval,
+        }
+
+        let plans: Vec<Plan> = [
+            r#"billing_interval = "monthly""#,
+            r#"billing_interval = "quarterly""#,
+            r#"billing_interval = { unit = "day", count = 7 }"#,
+            r#"billing_interval = { unit = "week", count = 2 }"#,
+        ]
+        .iter()
+        .map(|s| toml::from_str(s).unwrap())
+        .collect();
+        assert_eq!(plans[2].billing_interval, BillingInterval::every(7, IntervalUnit::Day).unwrap());
+
+        // Named intervals keep their old JSON form
+        let json = serde_json::to_string(&plans[0]).unwrap();
+        assert_eq!(json, r#"{"billing_interval":"monthly"}"#);
+        for plan in plans {
+            let json = serde_json::to_string(&plan).unwrap();
+            assert_eq!(serde_json::from_str::<Plan>(&json).unwrap(), plan);
+        }
+
+        assert!(toml::from_str::<Plan>(r#"billing_interval = { unit = "month", count = 40 }"#).is_err());
+    }
+}
@@ -0,0 +1,323 @@
+//! # Webhook Event Ledger
+//!
+//! Record of received webhook events, keyed by `WebhookEvent::event_id`.
+//!
+//! Providers retry deliveries, so the same event can arrive several times.
+//! Before processing, the handler claims the event with `WebhookLedger::begin`:
+//! events that were already queued or processed are acknowledged without
+//! running their side effects again. Failed events can be claimed again, so
+//! retries still work. Entries older than the retention window are purged.
+
+use crate::error::{PaymentError, PaymentResult};
+use crate::order::WebhookEvent;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+/// How long an unfinished claim blocks other deliveries of the same event
+pub const PROCESSING_TIMEOUT_SECS: i64 = 300;
+
+/// Processing outcome of a webhook event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventOutcome {
+    /// Claimed, processing not finished
+    Processing,
+    /// Handed to the webhook queue, which owns retries from here
+    Queued,
+    /// Processed successfully
+    Processed,
+    /// Processing failed (the event may be claimed again)
+    Failed,
+}
+
+impl EventOutcome {
+    /// Get the outcome as a lowercase string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventOutcome::Processing => "processing",
+            EventOutcome::Queued => "queued",
+            EventOutcome::Processed => "processed",
+            EventOutcome::Failed => "failed",
+        }
+    }
+}
+
+impl fmt::Display for EventOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EventOutcome {
+    type Err = PaymentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "processing" => Ok(EventOutcome::Processing),
+            "queued" => Ok(EventOutcome::Queued),
+            "processed" => Ok(EventOutcome::Processed),
+            "failed" => Ok(EventOutcome::Failed),
+            _ => Err(PaymentError::Serialization(format!(
+                "unknown event outcome: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// A webhook event in the ledger
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    /// Provider's event ID
+    pub event_id: String,
+
+    /// Provider name
+    pub provider: String,
+
+    /// Event type (see `WebhookEventType::as_str`)
+    pub event_type: String,
+
+    /// Processing outcome
+    pub outcome: EventOutcome,
+
+    /// Error message of the last failed attempt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Number of deliveries processed (duplicates not counted)
+    pub attempts: u32,
+
+    /// First delivery
+    pub received_at: DateTime<Utc>,
+
+    /// Last claim or outcome change
+    pub updated_at: DateTime<Utc>,
+}
+
+impl LedgerEntry {
+    /// Create a new entry claimed for processing
+    pub fn new(event: &WebhookEvent) -> Self {
+        let now = Utc::now();
+        Self {
+            event_id: event.event_id.clone(),
+            provider: event.provider.clone(),
+            event_type: event.event_type.as_str().to_string(),
+            outcome: EventOutcome::Processing,
+            error: None,
+            attempts: 1,
+            received_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Check if another delivery of this event may be processed at `now`
+    pub fn can_retry(&self, now: DateTime<Utc>) -> bool {
+        match self.outcome {
+            EventOutcome::Queued | EventOutcome::Processed => false,
+            EventOutcome::Failed => true,
+            // A crashed worker must not block the event forever
+            EventOutcome::Processing => {
+                now - self.updated_at > Duration::seconds(PROCESSING_TIMEOUT_SECS)
+            }
+        }
+    }
+
+    /// Claim the entry for another attempt
+    pub fn retry(&mut self, now: DateTime<Utc>) {
+        self.outcome = EventOutcome::Processing;
+        self.attempts += 1;
+        self.updated_at = now;
+    }
+}
+
+/// Result of claiming an event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventClaim {
+    /// First delivery (or a retry after failure): process it
+    New,
+    /// Already queued or processed: acknowledge without processing
+    Duplicate(LedgerEntry),
+    /// Another delivery is being processed right now
+    InProgress(LedgerEntry),
+}
+
+/// Storage for the webhook event ledger
+#[async_trait]
+pub trait WebhookLedger: Send + Sync {
+    /// Claim an event for processing (see `EventClaim`)
+    async fn begin(&self, event: &WebhookEvent) -> PaymentResult<EventClaim>;
+
+    /// Record the outcome of a claimed event (`error` explains a failure)
+    async fn record(
+        &self,
+        event_id: &str,
+        outcome: EventOutcome,
+        error: Option<&str>,
+    ) -> PaymentResult<()>;
+
+    /// Get an event by ID
+    async fn get(&self, event_id: &str) -> PaymentResult<Option<LedgerEntry>>;
+
+    /// Delete events received before `cutoff`, returning how many were removed
+    async fn purge_before(&self, cutoff: DateTime<Utc>) -> PaymentResult<usize>;
+}
+
+/// Shared webhook ledger
+pub type BoxedWebhookLedger = Arc<dyn WebhookLedger>;
+
+/// Decide how to handle a delivery of an event with an existing entry
+pub fn claim_existing(entry: &mut LedgerEntry, now: DateTime<Utc>) -> EventClaim {
+    if entry.can_retry(now) {
+        entry.retry(now);
+        EventClaim::New
+    } else if entry.outcome != EventOutcome::Processing {
+        EventClaim::Duplicate(entry.clone())
+    } else {
+        EventClaim::InProgress(entry.clone())
+    }
+}
+
+/// Apply an outcome to an entry
+pub fn record_outcome(
+    entry: &mut LedgerEntry,
+    outcome: EventOutcome,
+    error: Option<&str>,
+    now: DateTime<Utc>,
+) {
+    entry.outcome = outcome;
+    entry.error = error.map(String::from);
+    entry.updated_at = now;
+}
+
+/// In-memory webhook ledger (contents are lost on restart)
+#[derive(Debug, Default)]
+pub struct InMemoryWebhookLedger {
+    entries: RwLock<HashMap<String, LedgerEntry>>,
+}
+
+impl InMemoryWebhookLedger {
+    /// Create an empty ledger
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn write(&self) -> PaymentResult<std::sync::RwLockWriteGuard<'_, HashMap<String, LedgerEntry>>> {
+        self.entries
+            .write()
+            .map_err(|_| PaymentError::Storage("webhook ledger lock poisoned".to_string()))
+    }
+}
+
+#[async_trait]
+impl WebhookLedger for InMemoryWebhookLedger {
+    async fn begin(&self, event: &WebhookEvent) -> PaymentResult<EventClaim> {
+        let mut entries = self.write()?;
+        match entries.get_mut(&event.event_id) {
+            Some(entry) => Ok(claim_existing(entry, Utc::now())),
+            None => {
+                entries.insert(event.event_id.clone(), LedgerEntry::new(event));
+                Ok(EventClaim::New)
+            }
+        }
+    }
+
+    async fn record(
+        &self,
+        event_id: &str,
+        outcome: EventOutcome,
+        error: Option<&str>,
+    ) -> PaymentResult<()> {
+        let mut entries = self.write()?;
+        let entry = entries.get_mut(event_id).ok_or_else(|| {
+            PaymentError::Storage(format!("webhook event {} was not claimed", event_id))
+        })?;
+        record_outcome(entry, outcome, error, Utc::now());
+        Ok(())
+    }
+
+    async fn get(&self, event_id: &str) -> PaymentResult<Option<LedgerEntry>> {
+        let entries = self
+            .entries
+            .read()
+            .map_err(|_| PaymentError::Storage("webhook ledger lock poisoned".to_string()))?;
+        Ok(entries.get(event_id).cloned())
+    }
+
+    async fn purge_before(&self, cutoff: DateTime<Utc>) -> PaymentResult<usize> {
+        let mut entries = self.write()?;
+        let before = entries.len();
+        entries.retain(|_, entry| entry.received_at >= cutoff);
+        Ok(before - entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::WebhookEventType;
+
+    fn event(id: &str) -> WebhookEvent {
+        WebhookEvent {
+            event_id: id.into(),
+            event_type: WebhookEventType::CheckoutCompleted,
+            provider: "stripe".into(),
+            session_id: None,
+            payment_intent_id: None,
+            customer_email: None,
+            amount_paid: None,
+            currency: None,
+            raw_data: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_and_retry() {
+        let ledger = InMemoryWebhookLedger::new();
+
+        assert_eq!(ledger.begin(&event("evt_1")).await.unwrap(), EventClaim::New);
+        assert!(matches!(
+            ledger.begin(&event("evt_1")).await.unwrap(),
+            EventClaim::InProgress(_)
+        ));
+
+        // Failed events can be retried
+        ledger.record("evt_1", EventOutcome::Failed, Some("boom")).await.unwrap();
+        assert_eq!(ledger.begin(&event("evt_1")).await.unwrap(), EventClaim::New);
+
+        // Queued events are owned by the webhook queue
+        ledger.record("evt_1", EventOutcome::Queued, None).await.unwrap();
+        assert!(matches!(
+            ledger.begin(&event("evt_1")).await.unwrap(),
+            EventClaim::Duplicate(_)
+        ));
+
+        ledger.record("evt_1", EventOutcome::Processed, None).await.unwrap();
+        let EventClaim::Duplicate(entry) = ledger.begin(&event("evt_1")).await.unwrap() else {
+            panic!("expected duplicate");
+        };
+        assert_eq!(entry.outcome, EventOutcome::Processed);
+        assert_eq!(entry.attempts, 2);
+        assert_eq!(entry.error, None);
+        assert_eq!(entry.event_type, "checkout_completed");
+    }
+
+    #[tokio::test]
+    async fn test_purge_and_stale_claims() {
+        let ledger = InMemoryWebhookLedger::new();
+        ledger.begin(&event("evt_1")).await.unwrap();
+
+        let mut entry = ledger.get("evt_1").await.unwrap().unwrap();
+        let later = entry.updated_at + Duration::seconds(PROCESSING_TIMEOUT_SECS + 1);
+        assert_eq!(claim_existing(&mut entry, later), EventClaim::New);
+
+        assert_eq!(ledger.purge_before(Utc::now() - Duration::days(1)).await.unwrap(), 0);
+        assert_eq!(ledger.purge_before(Utc::now() + Duration::seconds(1)).await.unwrap(), 1);
+        assert!(ledger.get("evt_1").await.unwrap().is_none());
+    }
+}
@@ -4,9 +4,28 @@
 //!
 //! This crate provides:
 //! - `PaymentStrategy` trait for implementing payment providers
+//! - `Money` for exact fixed-point decimal amounts
+//! - `Currency` backed by the full ISO 4217 table
+//! - `Locale` for locale-aware price formatting
 //! - `Product` and `ProductCatalog` for the product catalog
+//! - `ProductOption` and `ProductVariant` for products sold in several variants
+//! - `BillingInterval` for day/week/month/year billing with a count
 //! - `Order`, `LineItem`, and `CheckoutSession` for checkout flow
+//! - `Promotion` and `PromotionCatalog` for discount codes
+//! - `OrderStatus` lifecycle state machine with validated transitions
+//! - `OrderRepository` for persisting orders and their status history
+//! - `Customer` and `CustomerRepository` for reusing provider customers
+//! - `RefundRequest` and `Refund` for full and partial refunds
+//! - `OrderReceipt` for showing an order's items, totals and status
+//! - `WebhookLedger` for webhook event idempotency
+//! - `WebhookQueue` for durable, retried webhook processing
+//! - `OutboundWebhook` for forwarding events to per-site subscribers
+//! - `SubscriptionStrategy` for cancelling, pausing and changing subscriptions
+//! - `BillingPortal` and `PortalConfig` for the per-site customer portal
+//! - `UsageModel` and `UsageRecord` for seat-based, tiered and metered billing
+//! - `TaxCalculator` for pluggable tax calculation
 //! - `Site` and `SiteRegistry` for multi-tenant support
+//! - `validate_config` for checking the catalog and site registry together
 //! - `PaymentError` for typed error handling
 //!
 //! ## Example
@@ -18,8 +37,8 @@
 //! let mut order = Order::new(Currency::USD);
 //!
 //! // Add products
-//! let product = Product::one_time("rang-play-rs", "Rang Play RS", Price::new(29.99, Currency::USD));
-//! order.add_product(&product, 1);
+//! let product = Product::one_time("rang-play-rs", "Rang Play RS", Price::parse("29.99", Currency::USD)?);
+//! order.add_product(&product, 1)?;
 //!
 //! // Get site configuration
 //! let site = registry.get("spokenhope").unwrap();
@@ -30,22 +49,85 @@
 //! // Redirect user to session.checkout_url
 //! ```
 
+pub mod currency;
+pub mod customer;
 pub mod error;
+pub mod format;
+pub mod interval;
+pub mod ledger;
+pub mod lifecycle;
+pub mod money;
 pub mod order;
+pub mod outbound;
+pub mod portal;
 pub mod product;
+pub mod promotion;
+pub mod queue;
+pub mod receipt;
+pub mod refund;
+pub mod repository;
 pub mod site;
 pub mod strategy;
+pub mod subscription;
+pub mod tax;
+pub mod usage;
+pub mod validation;
+pub mod variant;
 
 // Re-exports for convenience
+pub use currency::{CurrencyInfo, SymbolPosition};
+pub use customer::{
+    BoxedCustomerRepository, Customer, CustomerRepository, InMemoryCustomerRepository,
+};
 pub use error::{PaymentError, PaymentResult};
+pub use format::{CurrencyDisplay, Grouping, Locale};
+pub use interval::IntervalUnit;
+pub use ledger::{
+    BoxedWebhookLedger, EventClaim, EventOutcome, InMemoryWebhookLedger, LedgerEntry,
+    WebhookLedger,
+};
+pub use lifecycle::{OrderEventHandler, OrderStatus, OrderTransition, OrderTrigger};
+pub use money::Money;
 pub use order::{
-    CheckoutMode, CheckoutSession, CheckoutStatus, LineItem, Order, WebhookEvent,
-    WebhookEventType,
+    CheckoutMode, CheckoutSession, CheckoutStatus, LineItem, Order, OrderTotals, WebhookEvent,
+    WebhookEventType, RESERVED_METADATA_KEYS,
 };
+pub use outbound::{
+    BoxedDeliveryLog, BoxedDeliveryQueue, DeliveryAttempt, DeliveryJob, DeliveryLog,
+    DeliveryQueue, FieldKind, FieldMapping, InMemoryDeliveryLog, InMemoryDeliveryQueue,
+    OutboundWebhook,
+};
+pub use portal::{BillingPortal, BoxedBillingPortal, PortalConfig, PortalFeatures, PortalSession};
 pub use product::{
-    BillingInterval, Currency, Price, Product, ProductCatalog, ProductType,
+    BillingInterval, Currency, IntroPrice, Price, Product, ProductCatalog, ProductType,
+    TrialPaymentMethod,
+};
+pub use promotion::{
+    AppliedDiscount, BoxedRedemptionStore, DiscountKind, InMemoryRedemptionStore, Promotion,
+    PromotionCatalog, RedemptionStore,
+};
+pub use queue::{BoxedWebhookQueue, InMemoryWebhookQueue, RetryPolicy, WebhookJob, WebhookQueue};
+pub use receipt::{FulfillmentStatus, OrderReceipt, PaymentStatus, ReceiptLine};
+pub use refund::{Refund, RefundReason, RefundRequest, RefundStatus, RefundTarget};
+pub use repository::{
+    BoxedOrderRepository, InMemoryOrderRepository, OrderRepository, StatusChange, StoredOrder,
 };
 pub use site::{Site, SiteRegistry};
 pub use strategy::{
     BoxedPaymentStrategy, CheckoutUrls, PaymentStrategy, PaymentStrategySelector,
 };
+pub use subscription::{
+    BoxedSubscriptionStrategy, PauseBehavior, PauseCollection, Plan, PlanChange,
+    ProrationBehavior, Subscription, SubscriptionLine, SubscriptionQuery, SubscriptionStatus,
+    SubscriptionStrategy,
+};
+pub use tax::{
+    BoxedTaxCalculator, TableTaxCalculator, TaxBreakdown, TaxCalculator, TaxConfig, TaxLine,
+    TaxLocation, TaxMode, TaxProvider, TaxRate,
+};
+pub use usage::{
+    PriceTier, TiersMode, UsageAction, UsageAggregation, UsageModel, UsageRecord, UsageReport,
+    UsageType,
+};
+pub use validation::{validate_config, ConfigIssue, Severity, ValidationReport};
+pub use variant::{ProductOption, ProductVariant};
@@ -0,0 +1,323 @@
+//! # Order Lifecycle
+//!
+//! Order state machine for lightning-cart.
+//!
+//! - Pending → AwaitingPayment → Paid → Fulfilled
+//! - Paid / Fulfilled → PartiallyRefunded → Refunded
+//! - AwaitingPayment → Failed (can still be paid) or Expired
+//! - Paid / Fulfilled → Disputed → previous status (won) or Refunded (lost)
+//!
+//! Webhook events map to an `OrderTrigger`; the trigger picks the target
+//! status and the state machine rejects illegal jumps with
+//! `PaymentError::InvalidTransition`. Every accepted transition produces an
+//! `OrderTransition` event for `OrderEventHandler`s.
+
+use crate::error::{PaymentError, PaymentResult};
+use crate::order::{WebhookEvent, WebhookEventType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Lifecycle status of an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    /// Order created, no checkout session yet
+    #[default]
+    Pending,
+    /// Checkout session created, waiting for the customer to pay
+    AwaitingPayment,
+    /// Payment received
+    Paid,
+    /// Order delivered to the customer
+    Fulfilled,
+    /// Part of the payment refunded
+    PartiallyRefunded,
+    /// Payment fully refunded
+    Refunded,
+    /// Checkout session expired without payment
+    Expired,
+    /// Payment attempt failed (the customer may still retry)
+    Failed,
+    /// Customer disputed the charge
+    Disputed,
+}
+
+impl OrderStatus {
+    /// All statuses
+    pub const ALL: [OrderStatus; 9] = [
+        OrderStatus::Pending,
+        OrderStatus::AwaitingPayment,
+        OrderStatus::Paid,
+        OrderStatus::Fulfilled,
+        OrderStatus::PartiallyRefunded,
+        OrderStatus::Refunded,
+        OrderStatus::Expired,
+        OrderStatus::Failed,
+        OrderStatus::Disputed,
+    ];
+
+    /// Get the status as a lowercase string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Pending => "pending",
+            OrderStatus::AwaitingPayment => "awaiting_payment",
+            OrderStatus::Paid => "paid",
+            OrderStatus::Fulfilled => "fulfilled",
+            OrderStatus::PartiallyRefunded => "partially_refunded",
+            OrderStatus::Refunded => "refunded",
+            OrderStatus::Expired => "expired",
+            OrderStatus::Failed => "failed",
+            OrderStatus::Disputed => "disputed",
+        }
+    }
+
+    /// Statuses this status may move to
+    pub fn allowed_transitions(&self) -> &'static [OrderStatus] {
+        use OrderStatus::*;
+        match self {
+            Pending => &[AwaitingPayment, Failed, Expired],
+            AwaitingPayment => &[Paid, Failed, Expired],
+            // A failed attempt can be retried on the same checkout session
+            Failed => &[Paid, Expired],
+            Paid => &[Fulfilled, PartiallyRefunded, Refunded, Disputed],
+            Fulfilled => &[PartiallyRefunded, Refunded, Disputed],
+            PartiallyRefunded => &[Refunded, Disputed],
+            Disputed => &[Paid, Fulfilled, PartiallyRefunded, Refunded],
+            Expired | Refunded => &[],
+        }
+    }
+
+    /// Check if a transition to `to` is allowed
+    pub fn can_transition_to(&self, to: OrderStatus) -> bool {
+        self.allowed_transitions().contains(&to)
+    }
+
+    /// Check if no further transitions are possible
+    pub fn is_terminal(&self) -> bool {
+        self.allowed_transitions().is_empty()
+    }
+
+    /// Validate a transition to `to`
+    pub fn transition_to(&self, to: OrderStatus) -> PaymentResult<OrderStatus> {
+        if self.can_transition_to(to) {
+            Ok(to)
+        } else {
+            Err(PaymentError::InvalidTransition { from: *self, to })
+        }
+    }
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for OrderStatus {
+    type Err = PaymentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OrderStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == s)
+            .ok_or_else(|| PaymentError::Serialization(format!("unknown order status: {}", s)))
+    }
+}
+
+/// Something that happened to an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderTrigger {
+    /// Checkout session created
+    CheckoutCreated,
+    /// Checkout session could not be created
+    CheckoutFailed,
+    /// Payment succeeded
+    PaymentSucceeded,
+    /// Payment attempt failed
+    PaymentFailed,
+    /// Checkout session expired
+    SessionExpired,
+    /// Order delivered (admin `fulfill` endpoint)
+    Fulfilled,
+    /// Part of the payment refunded
+    PartiallyRefunded,
+    /// Payment fully refunded
+    Refunded,
+    /// Dispute opened
+    DisputeOpened,
+    /// Dispute closed in our favour
+    DisputeWon,
+    /// Dispute closed in the customer's favour
+    DisputeLost,
+}
+
+impl OrderTrigger {
+    /// Map a verified webhook event to a trigger (None = event does not affect orders)
+    pub fn from_webhook(event: &WebhookEvent) -> Option<Self> {
+        let data = event.raw_data.as_ref();
+        let field = |name: &str| data.and_then(|d| d.get(name));
+
+        match &event.event_type {
+            WebhookEventType::CheckoutCompleted => {
+                // Delayed payment methods complete the session before the money arrives
+                match field("payment_status").and_then(|v| v.as_str()) {
+                    Some("unpaid") => None,
+                    _ => Some(OrderTrigger::PaymentSucceeded),
+                }
+            }
+            WebhookEventType::CheckoutExpired => Some(OrderTrigger::SessionExpired),
+            WebhookEventType::PaymentSucceeded => Some(OrderTrigger::PaymentSucceeded),
+            WebhookEventType::PaymentFailed => Some(OrderTrigger::PaymentFailed),
+            WebhookEventType::RefundIssued => {
+                // charge.refunded: `refunded` is only true once the full amount is returned
+                match field("refunded").and_then(|v| v.as_bool()) {
+                    Some(false) => Some(OrderTrigger::PartiallyRefunded),
+                    _ => Some(OrderTrigger::Refunded),
+                }
+            }
+            WebhookEventType::Unknown(event_type) => match event_type.as_str() {
+                "checkout.session.async_payment_succeeded" => Some(OrderTrigger::PaymentSucceeded),
+                "checkout.session.async_payment_failed" => Some(OrderTrigger::PaymentFailed),
+                "charge.dispute.created" => Some(OrderTrigger::DisputeOpened),
+                "charge.dispute.closed" => match field("status").and_then(|v| v.as_str()) {
+                    Some("won") | Some("warning_closed") => Some(OrderTrigger::DisputeWon),
+                    Some("lost") => Some(OrderTrigger::DisputeLost),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Target status for this trigger.
+    ///
+    /// `before_dispute` is the status the order had before it was disputed
+    /// (a won dispute returns there).
+    pub fn target(&self, before_dispute: Option<OrderStatus>) -> OrderStatus {
+        match self {
+            OrderTrigger::CheckoutCreated => OrderStatus::AwaitingPayment,
+            OrderTrigger::CheckoutFailed | OrderTrigger::PaymentFailed => OrderStatus::Failed,
+            OrderTrigger::PaymentSucceeded => OrderStatus::Paid,
+            OrderTrigger::SessionExpired => OrderStatus::Expired,
+            OrderTrigger::Fulfilled => OrderStatus::Fulfilled,
+            OrderTrigger::PartiallyRefunded => OrderStatus::PartiallyRefunded,
+            OrderTrigger::Refunded | OrderTrigger::DisputeLost => OrderStatus::Refunded,
+            OrderTrigger::DisputeOpened => OrderStatus::Disputed,
+            OrderTrigger::DisputeWon => before_dispute.unwrap_or(OrderStatus::Paid),
+        }
+    }
+}
+
+/// Event emitted for every accepted status change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderTransition {
+    /// Order ID
+    pub order_id: String,
+
+    /// Previous status
+    pub from: OrderStatus,
+
+    /// New status
+    pub to: OrderStatus,
+
+    /// Why the status changed (e.g., webhook event ID)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// When the status changed
+    pub at: DateTime<Utc>,
+}
+
+/// Receives order transition events
+pub trait OrderEventHandler: Send + Sync {
+    /// Called after a transition is stored
+    fn on_transition(&self, transition: &OrderTransition);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_happy_path() {
+        let mut status = OrderStatus::Pending;
+        for next in [
+            OrderStatus::AwaitingPayment,
+            OrderStatus::Paid,
+            OrderStatus::Fulfilled,
+            OrderStatus::PartiallyRefunded,
+            OrderStatus::Refunded,
+        ] {
+            status = status.transition_to(next).unwrap();
+        }
+        assert!(status.is_terminal());
+    }
+
+    #[test]
+    fn test_illegal_transitions() {
+        assert!(matches!(
+            OrderStatus::Pending.transition_to(OrderStatus::Paid),
+            Err(PaymentError::InvalidTransition {
+                from: OrderStatus::Pending,
+                to: OrderStatus::Paid
+            })
+        ));
+        assert!(OrderStatus::Expired.transition_to(OrderStatus::Paid).is_err());
+        assert!(OrderStatus::Refunded.transition_to(OrderStatus::Paid).is_err());
+        assert!(OrderStatus::Paid.transition_to(OrderStatus::AwaitingPayment).is_err());
+        assert!(OrderStatus::Fulfilled.transition_to(OrderStatus::Failed).is_err());
+    }
+
+    #[test]
+    fn test_status_round_trip() {
+        for status in OrderStatus::ALL {
+            assert_eq!(status.as_str().parse::<OrderStatus>().unwrap(), status);
+        }
+        assert!("bogus".parse::<OrderStatus>().is_err());
+    }
+
+    #[test]
+    fn test_webhook_triggers() {
+        let event = |event_type, data: serde_json::Value| WebhookEvent {
+            event_id: "evt_1".into(),
+            event_type,
+            provider: "stripe".into(),
+            session_id: None,
+            payment_intent_id: None,
+            customer_email: None,
+            amount_paid: None,
+            currency: None,
+            raw_data: Some(data),
+            timestamp: Utc::now(),
+        };
+
+        let paid = event(WebhookEventType::CheckoutCompleted, serde_json::json!({"payment_status": "paid"}));
+        assert_eq!(OrderTrigger::from_webhook(&paid), Some(OrderTrigger::PaymentSucceeded));
+
+        let unpaid = event(WebhookEventType::CheckoutCompleted, serde_json::json!({"payment_status": "unpaid"}));
+        assert_eq!(OrderTrigger::from_webhook(&unpaid), None);
+
+        let partial = event(WebhookEventType::RefundIssued, serde_json::json!({"refunded": false}));
+        assert_eq!(OrderTrigger::from_webhook(&partial), Some(OrderTrigger::PartiallyRefunded));
+
+        let full = event(WebhookEventType::RefundIssued, serde_json::json!({"refunded": true}));
+        assert_eq!(OrderTrigger::from_webhook(&full), Some(OrderTrigger::Refunded));
+
+        let expired = event(WebhookEventType::CheckoutExpired, serde_json::json!({}));
+        assert_eq!(OrderTrigger::from_webhook(&expired), Some(OrderTrigger::SessionExpired));
+
+        let won = event(
+            WebhookEventType::Unknown("charge.dispute.closed".into()),
+            serde_json::json!({"status": "won"}),
+        );
+        assert_eq!(OrderTrigger::from_webhook(&won), Some(OrderTrigger::DisputeWon));
+        assert_eq!(
+            OrderTrigger::DisputeWon.target(Some(OrderStatus::Fulfilled)),
+            OrderStatus::Fulfilled
+        );
+    }
+}
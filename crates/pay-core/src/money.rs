@@ -0,0 +1,310 @@
+//! # Money
+//!
+//! Exact fixed-point decimal amounts for lightning-cart.
+//!
+//! `Money` stores amounts as an integer number of ten-thousandths, so every
+//! ISO 4217 minor unit (up to four decimal places) converts without loss.
+//! All arithmetic is checked: overflow returns a `PaymentError` instead of
+//! wrapping or losing precision through `f64`.
+//!
+//! `Money` has no currency, so it (de)serializes only as a decimal string
+//! (`"19.99"`); integer amounts in minor units belong in a `Price`.
+//!
+//! ```rust
+//! use pay_core::Money;
+//!
+//! let price: Money = "19.99".parse().unwrap();
+//! let total = price.checked_mul(3).unwrap();
+//! assert_eq!(total.to_string(), "59.97");
+//! assert_eq!(total.to_minor_units(2).unwrap(), 5997);
+//! ```
+
+use crate::error::{PaymentError, PaymentResult};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Exact decimal amount with a fixed scale of `Money::SCALE` decimal places
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Money {
+    /// Amount in units of 10^-SCALE
+    units: i128,
+}
+
+impl Money {
+    /// Number of decimal places stored (covers every ISO 4217 exponent)
+    pub const SCALE: u8 = 4;
+
+    /// Zero amount
+    pub const ZERO: Money = Money { units: 0 };
+
+    const FACTOR: i128 = 10_i128.pow(Self::SCALE as u32);
+
+    /// Create from an amount in minor units with the given number of decimal places
+    /// (e.g. `from_minor_units(1999, 2)` is 19.99)
+    pub fn from_minor_units(amount: i64, decimal_places: u8) -> PaymentResult<Self> {
+        let multiplier = Self::scale_factor(decimal_places)?;
+        Ok(Self {
+            units: amount as i128 * multiplier,
+        })
+    }
+
+    /// Create from a whole number of major units (e.g. dollars)
+    pub fn from_major_units(amount: i64) -> Self {
+        Self {
+            units: amount as i128 * Self::FACTOR,
+        }
+    }
+
+    /// Convert to minor units with the given number of decimal places.
+    ///
+    /// Fails if the amount has more precision than `decimal_places` allows
+    /// or does not fit in an `i64`.
+    pub fn to_minor_units(&self, decimal_places: u8) -> PaymentResult<i64> {
+        let divisor = Self::scale_factor(decimal_places)?;
+        if self.units % divisor != 0 {
+            return Err(PaymentError::InvalidPrice {
+                message: format!(
+                    "{} has more than {} decimal places",
+                    self, decimal_places
+                ),
+            });
+        }
+        i64::try_from(self.units / divisor).map_err(|_| Self::overflow("conversion"))
+    }
+
+    /// Checked addition
+    pub fn checked_add(self, other: Money) -> PaymentResult<Money> {
+        self.units
+            .checked_add(other.units)
+            .map(|units| Money { units })
+            .ok_or_else(|| Self::overflow("addition"))
+    }
+
+    /// Checked subtraction
+    pub fn checked_sub(self, other: Money) -> PaymentResult<Money> {
+        self.units
+            .checked_sub(other.units)
+            .map(|units| Money { units })
+            .ok_or_else(|| Self::overflow("subtraction"))
+    }
+
+    /// Checked multiplication by an integer factor (e.g. a quantity)
+    pub fn checked_mul(self, factor: i64) -> PaymentResult<Money> {
+        self.units
+            .checked_mul(factor as i128)
+            .map(|units| Money { units })
+            .ok_or_else(|| Self::overflow("multiplication"))
+    }
+
+    /// Check if the amount is zero
+    pub fn is_zero(&self) -> bool {
+        self.units == 0
+    }
+
+    /// Check if the amount is negative
+    pub fn is_negative(&self) -> bool {
+        self.units < 0
+    }
+
+    /// Format with exactly `decimal_places` digits after the point (e.g. "19.90").
+    ///
+    /// Extra precision beyond `decimal_places` is truncated.
+    pub fn format_places(&self, decimal_places: u8) -> String {
+        let places = decimal_places.min(Self::SCALE);
+        let sign = if self.units < 0 { "-" } else { "" };
+        let abs = self.units.unsigned_abs();
+        let whole = abs / Self::FACTOR as u128;
+        if places == 0 {
+            return format!("{}{}", sign, whole);
+        }
+        let frac = (abs % Self::FACTOR as u128) / 10_u128.pow((Self::SCALE - places) as u32);
+        format!("{}{}.{:0width$}", sign, whole, frac, width = places as usize)
+    }
+
+    fn scale_factor(decimal_places: u8) -> PaymentResult<i128> {
+        if decimal_places > Self::SCALE {
+            return Err(PaymentError::InvalidPrice {
+                message: format!(
+                    "{} decimal places exceeds supported precision of {}",
+                    decimal_places,
+                    Self::SCALE
+                ),
+            });
+        }
+        Ok(10_i128.pow((Self::SCALE - decimal_places) as u32))
+    }
+
+    fn overflow(operation: &str) -> PaymentError {
+        PaymentError::InvalidPrice {
+            message: format!("amount overflow in {}", operation),
+        }
+    }
+}
+
+impl FromStr for Money {
+    type Err = PaymentError;
+
+    /// Parse a decimal string such as "19.99", "-5" or "0.0001"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || PaymentError::InvalidPrice {
+            message: format!("invalid decimal amount: {:?}", s),
+        };
+
+        let trimmed = s.trim();
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+
+        let (whole, frac) = match digits.split_once('.') {
+            Some((w, f)) => (w, f),
+            None => (digits, ""),
+        };
+
+        if whole.is_empty() && frac.is_empty() {
+            return Err(invalid());
+        }
+        if !whole.bytes().all(|b| b.is_ascii_digit()) || !frac.bytes().all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        if frac.len() > Self::SCALE as usize {
+            return Err(PaymentError::InvalidPrice {
+                message: format!(
+                    "{:?} has more than {} decimal places",
+                    s,
+                    Self::SCALE
+                ),
+            });
+        }
+
+        let whole_units: i128 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().map_err(|_| Self::overflow("parsing"))?
+        };
+        let frac_units: i128 = if frac.is_empty() {
+            0
+        } else {
+            let padded = format!("{:0<width$}", frac, width = Self::SCALE as usize);
+            padded.parse().map_err(|_| invalid())?
+        };
+
+        let units = whole_units
+            .checked_mul(Self::FACTOR)
+            .and_then(|u| u.checked_add(frac_units))
+            .ok_or_else(|| Self::overflow("parsing"))?;
+
+        Ok(Money {
+            units: if negative { -units } else { units },
+        })
+    }
+}
+
+impl fmt::Display for Money {
+    /// Shortest exact representation (e.g. "19.99", "10", "0.005")
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let full = self.format_places(Self::SCALE);
+        let trimmed = full.trim_end_matches('0').trim_end_matches('.');
+        write!(f, "{}", trimmed)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Integer(i64),
+            Decimal(String),
+        }
+
+        // An integer could mean major or minor units; only a decimal string is unambiguous
+        match Repr::deserialize(deserializer)? {
+            Repr::Integer(n) => Err(serde::de::Error::custom(format!(
+                "amount must be a decimal string (e.g. \"{}\"), not the integer {}",
+                n, n
+            ))),
+            Repr::Decimal(s) => s.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let m: Money = "19.99".parse().unwrap();
+        assert_eq!(m.to_string(), "19.99");
+        assert_eq!("10".parse::<Money>().unwrap().to_string(), "10");
+        assert_eq!(".5".parse::<Money>().unwrap().to_string(), "0.5");
+        assert_eq!("-0.29".parse::<Money>().unwrap().to_string(), "-0.29");
+        assert_eq!("1.0050".parse::<Money>().unwrap().to_string(), "1.005");
+
+        assert!("".parse::<Money>().is_err());
+        assert!("abc".parse::<Money>().is_err());
+        assert!("1.2.3".parse::<Money>().is_err());
+        assert!("1.00001".parse::<Money>().is_err());
+    }
+
+    #[test]
+    fn test_minor_unit_conversion() {
+        let m: Money = "0.29".parse().unwrap();
+        assert_eq!(m.to_minor_units(2).unwrap(), 29);
+
+        let large: Money = "92233720368547.75".parse().unwrap();
+        assert_eq!(large.to_minor_units(2).unwrap(), 9_223_372_036_854_775);
+
+        let kwd: Money = "1.234".parse().unwrap();
+        assert_eq!(kwd.to_minor_units(3).unwrap(), 1234);
+        assert!(kwd.to_minor_units(2).is_err());
+
+        assert_eq!(Money::from_minor_units(1099, 2).unwrap().to_string(), "10.99");
+        assert_eq!(Money::from_minor_units(1000, 0).unwrap().to_string(), "1000");
+        assert!(Money::from_minor_units(1, 5).is_err());
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let a: Money = "0.1".parse().unwrap();
+        let b: Money = "0.2".parse().unwrap();
+        assert_eq!(a.checked_add(b).unwrap(), "0.3".parse().unwrap());
+        assert_eq!(a.checked_sub(b).unwrap(), "-0.1".parse().unwrap());
+        assert_eq!(a.checked_mul(3).unwrap(), "0.3".parse().unwrap());
+
+        let max = Money { units: i128::MAX };
+        assert!(max.checked_add(a).is_err());
+        assert!(max.checked_mul(2).is_err());
+        assert!(Money::from_major_units(i64::MAX).to_minor_units(2).is_err());
+    }
+
+    #[test]
+    fn test_format_places() {
+        let m: Money = "19.9".parse().unwrap();
+        assert_eq!(m.format_places(2), "19.90");
+        assert_eq!(m.format_places(0), "19");
+        assert_eq!("-3.5".parse::<Money>().unwrap().format_places(3), "-3.500");
+    }
+
+    #[test]
+    fn test_serde() {
+        let m: Money = "19.99".parse().unwrap();
+        assert_eq!(serde_json::to_string(&m).unwrap(), "\"19.99\"");
+        assert_eq!(serde_json::from_str::<Money>("\"19.99\"").unwrap(), m);
+        assert_eq!(
+            serde_json::from_str::<Money>("\"20\"").unwrap(),
+            Money::from_major_units(20)
+        );
+        // Integers are ambiguous without a currency
+        assert!(serde_json::from_str::<Money>("20").is_err());
+    }
+}
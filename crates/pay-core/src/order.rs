@@ -2,7 +2,13 @@
 //!
 //! Order and checkout session types for lightning-cart.
 
-use crate::product::{BillingInterval, Currency, Price, Product};
+use crate::error::{PaymentError, PaymentResult};
+use crate::product::{
+    BillingInterval, Currency, IntroPrice, Price, Product, ProductType, TrialPaymentMethod,
+};
+use crate::promotion::AppliedDiscount;
+use crate::tax::{TaxBreakdown, TaxMode};
+use crate::usage::UsageModel;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -16,6 +22,18 @@ pub struct LineItem {
     /// Product name (denormalized for display)
     pub name: String,
 
+    /// Variant ID (None = the product itself)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant_id: Option<String>,
+
+    /// Variant name (e.g., "Team, Docker")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant_name: Option<String>,
+
+    /// Stock keeping unit of the variant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sku: Option<String>,
+
     /// Description
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
@@ -30,6 +48,26 @@ pub struct LineItem {
     #[serde(default)]
     pub billing_interval: BillingInterval,
 
+    /// Product type (used for tax rates)
+    #[serde(default)]
+    pub product_type: ProductType,
+
+    /// Free trial length in days
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trial_days: Option<u32>,
+
+    /// Whether the trial needs a payment method up front
+    #[serde(default)]
+    pub trial_payment_method: TrialPaymentMethod,
+
+    /// Introductory price in the item's currency
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intro_price: Option<IntroPrice>,
+
+    /// Seat, tiered or metered billing (None = licensed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<UsageModel>,
+
     /// Optional image URL
     #[serde(skip_serializing_if = "Option::is_none")]
     pub image_url: Option<String>,
@@ -41,28 +79,126 @@ impl LineItem {
         Self {
             product_id: product.id.clone(),
             name: product.name.clone(),
+            variant_id: None,
+            variant_name: None,
+            sku: None,
             description: Some(product.description.clone()),
             unit_price: product.price.clone(),
             quantity,
             billing_interval: product.billing_interval,
+            product_type: product.product_type,
+            trial_days: product.trial_days,
+            trial_payment_method: product.trial_payment_method,
+            intro_price: product.intro_price.clone(),
+            usage: product.usage.clone(),
             image_url: product.image_url.clone(),
         }
     }
 
+    /// Create a line item from a product using its price in `currency`
+    ///
+    /// Products with variants need `from_variant_in`.
+    pub fn from_product_in(
+        product: &Product,
+        quantity: u32,
+        currency: Currency,
+    ) -> PaymentResult<Self> {
+        if product.has_variants() {
+            return Err(PaymentError::InvalidRequest(format!(
+                "choose a variant of {}",
+                product.id
+            )));
+        }
+        Self::priced_in(product, product.price_in(currency), quantity, currency)
+    }
+
+    /// Create a line item for a variant of a product using its price in `currency`
+    pub fn from_variant_in(
+        product: &Product,
+        variant_id: &str,
+        quantity: u32,
+        currency: Currency,
+    ) -> PaymentResult<Self> {
+        let variant = product
+            .variant(variant_id)
+            .ok_or_else(|| PaymentError::VariantNotFound {
+                product_id: product.id.clone(),
+                variant_id: variant_id.to_string(),
+            })?;
+        if !variant.active {
+            return Err(PaymentError::InvalidRequest(format!(
+                "variant is not available: {} {}",
+                product.id, variant.id
+            )));
+        }
+        Ok(Self {
+            variant_id: Some(variant.id.clone()),
+            variant_name: Some(variant.name.clone()),
+            sku: Some(variant.sku.clone()),
+            ..Self::priced_in(product, variant.price_in(currency), quantity, currency)?
+        })
+    }
+
+    fn priced_in(
+        product: &Product,
+        price: Option<&Price>,
+        quantity: u32,
+        currency: Currency,
+    ) -> PaymentResult<Self> {
+        let price = price.ok_or_else(|| PaymentError::UnsupportedCurrency {
+            currency: currency.to_string(),
+        })?;
+        // Tier amounts are only defined in the primary currency
+        let tiered = product.usage.as_ref().is_some_and(UsageModel::is_tiered);
+        if tiered && currency != product.price.currency {
+            return Err(PaymentError::UnsupportedCurrency {
+                currency: currency.to_string(),
+            });
+        }
+        // The intro offer only applies where it has a price in this currency,
+        // and only as a discount (a variant can cost less than the offer)
+        let intro_price = product.intro_price.as_ref().and_then(|intro| {
+            intro
+                .price_in(currency)
+                .filter(|p| p.amount <= price.amount)
+                .map(|p| IntroPrice::new(p.clone(), intro.periods))
+        });
+        Ok(Self {
+            unit_price: price.clone(),
+            intro_price,
+            ..Self::from_product(product, quantity)
+        })
+    }
+
+    /// Name shown to the customer: "Product – Variant", or the product name
+    pub fn display_name(&self) -> String {
+        match self.variant_name {
+            Some(ref variant) => format!("{} – {}", self.name, variant),
+            None => self.name.clone(),
+        }
+    }
+
     /// Calculate the total price for this line item
-    pub fn total(&self) -> Price {
-        Price {
-            amount: self.unit_price.amount * self.quantity as i64,
-            currency: self.unit_price.currency,
+    ///
+    /// Tiered items are priced by their table; metered items are billed
+    /// after the period and count as zero here.
+    pub fn total(&self) -> PaymentResult<Price> {
+        match self.usage {
+            Some(ref usage) => Ok(Price::from_cents(
+                usage.amount_for(self.quantity, self.unit_price.amount)?,
+                self.unit_price.currency,
+            )),
+            None => self.unit_price.checked_mul(self.quantity),
         }
     }
 }
 
 /// Checkout mode
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum CheckoutMode {
     /// One-time payment
+    #[default]
     Payment,
     /// Subscription
     Subscription,
@@ -70,11 +206,10 @@ pub enum CheckoutMode {
     Setup,
 }
 
-impl Default for CheckoutMode {
-    fn default() -> Self {
-        CheckoutMode::Payment
-    }
-}
+/// Order metadata keys set by the checkout itself, which clients cannot supply
+/// (webhooks and the provider trust them)
+pub const RESERVED_METADATA_KEYS: &[&str] =
+    &["order_id", "site_id", "statement_descriptor_suffix", "promo_code"];
 
 /// An order to be checked out
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,10 +227,22 @@ pub struct Order {
     #[serde(default)]
     pub mode: CheckoutMode,
 
+    /// Discounts applied to the first payment
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub discounts: Vec<AppliedDiscount>,
+
+    /// Calculated tax (None = no tax)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tax: Option<TaxBreakdown>,
+
     /// Customer email (optional, for prefill)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub customer_email: Option<String>,
 
+    /// Provider customer ID (reuses the provider's customer instead of the email)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub customer_id: Option<String>,
+
     /// Idempotency key (prevents duplicate charges)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub idempotency_key: Option<String>,
@@ -116,7 +263,10 @@ impl Order {
             line_items: Vec::new(),
             currency,
             mode: CheckoutMode::Payment,
+            discounts: Vec::new(),
+            tax: None,
             customer_email: None,
+            customer_id: None,
             idempotency_key: Some(Uuid::new_v4().to_string()),
             metadata: std::collections::HashMap::new(),
             created_at: Utc::now(),
@@ -124,26 +274,131 @@ impl Order {
     }
 
     /// Add a line item
-    pub fn add_item(&mut self, item: LineItem) {
+    ///
+    /// Fails with `UnsupportedCurrency` if the item is priced in a
+    /// different currency than the order.
+    pub fn add_item(&mut self, item: LineItem) -> PaymentResult<()> {
+        if item.unit_price.currency != self.currency {
+            return Err(PaymentError::UnsupportedCurrency {
+                currency: item.unit_price.currency.to_string(),
+            });
+        }
+        if let Some(ref usage) = item.usage {
+            if !item.billing_interval.is_recurring() {
+                return Err(PaymentError::InvalidRequest(format!(
+                    "usage billing needs a subscription: {}",
+                    item.product_id
+                )));
+            }
+            usage.validate()?;
+        }
         // Auto-detect subscription mode
-        if !matches!(item.billing_interval, BillingInterval::OneTime) {
+        if item.billing_interval.is_recurring() {
             self.mode = CheckoutMode::Subscription;
         }
         self.line_items.push(item);
+        Ok(())
+    }
+
+    /// Add a product with quantity, priced in the order's currency
+    pub fn add_product(&mut self, product: &Product, quantity: u32) -> PaymentResult<()> {
+        self.add_item(LineItem::from_product_in(product, quantity, self.currency)?)
     }
 
-    /// Add a product with quantity
-    pub fn add_product(&mut self, product: &Product, quantity: u32) {
-        self.add_item(LineItem::from_product(product, quantity));
+    /// Add a variant of a product with quantity, priced in the order's currency
+    pub fn add_variant(
+        &mut self,
+        product: &Product,
+        variant_id: &str,
+        quantity: u32,
+    ) -> PaymentResult<()> {
+        self.add_item(LineItem::from_variant_in(
+            product,
+            variant_id,
+            quantity,
+            self.currency,
+        )?)
     }
 
-    /// Calculate order total
-    pub fn total(&self) -> Price {
-        let total_amount: i64 = self.line_items.iter().map(|item| item.total().amount).sum();
-        Price {
-            amount: total_amount,
-            currency: self.currency,
+    /// Apply a discount
+    ///
+    /// Fails if the discount is in a different currency than the order or
+    /// would take the total below zero.
+    pub fn apply_discount(&mut self, discount: AppliedDiscount) -> PaymentResult<()> {
+        if discount.amount.currency != self.currency {
+            return Err(PaymentError::UnsupportedCurrency {
+                currency: discount.amount.currency.to_string(),
+            });
+        }
+        let discounted = self.discount_total()?.checked_add(&discount.amount)?;
+        if discount.amount.amount <= 0 || discounted.amount > self.subtotal()?.amount {
+            return Err(PaymentError::InvalidPrice {
+                message: format!("invalid discount for code {}", discount.code),
+            });
         }
+        self.discounts.push(discount);
+        Ok(())
+    }
+
+    /// Calculate the total of all line items before discounts
+    pub fn subtotal(&self) -> PaymentResult<Price> {
+        self.line_items
+            .iter()
+            .try_fold(Price::zero(self.currency), |acc, item| {
+                acc.checked_add(&item.total()?)
+            })
+    }
+
+    /// Calculate the sum of applied discounts
+    pub fn discount_total(&self) -> PaymentResult<Price> {
+        self.discounts
+            .iter()
+            .try_fold(Price::zero(self.currency), |acc, d| acc.checked_add(&d.amount))
+    }
+
+    /// Set the calculated tax
+    pub fn apply_tax(&mut self, tax: TaxBreakdown) -> PaymentResult<()> {
+        if tax.amount.currency != self.currency {
+            return Err(PaymentError::UnsupportedCurrency {
+                currency: tax.amount.currency.to_string(),
+            });
+        }
+        self.tax = Some(tax);
+        Ok(())
+    }
+
+    /// Calculate subtotal, discount, tax and grand total
+    pub fn totals(&self) -> PaymentResult<OrderTotals> {
+        let overflow = || PaymentError::InvalidPrice {
+            message: "amount overflow in order total".to_string(),
+        };
+
+        let subtotal = self.subtotal()?;
+        let discount = self.discount_total()?;
+        let tax = self
+            .tax
+            .as_ref()
+            .map(|t| t.amount.clone())
+            .unwrap_or_else(|| Price::zero(self.currency));
+
+        let mut total = subtotal.amount.checked_sub(discount.amount).ok_or_else(overflow)?;
+        let inclusive = self.tax.as_ref().is_some_and(|t| t.mode == TaxMode::Inclusive);
+        if !inclusive {
+            total = total.checked_add(tax.amount).ok_or_else(overflow)?;
+        }
+
+        Ok(OrderTotals {
+            subtotal,
+            discount,
+            tax,
+            tax_inclusive: inclusive,
+            total: Price::from_cents(total, self.currency),
+        })
+    }
+
+    /// Calculate order total (subtotal less discounts, plus exclusive tax)
+    pub fn total(&self) -> PaymentResult<Price> {
+        Ok(self.totals()?.total)
     }
 
     /// Set customer email
@@ -173,13 +428,84 @@ impl Order {
     pub fn item_count(&self) -> u32 {
         self.line_items.iter().map(|i| i.quantity).sum()
     }
+
+    /// Free trial of the subscription (the longest of its items)
+    pub fn trial_days(&self) -> Option<u32> {
+        self.line_items.iter().filter_map(|i| i.trial_days).max()
+    }
+
+    /// Trial payment method requirement (`Always` if any trial item needs one)
+    pub fn trial_payment_method(&self) -> TrialPaymentMethod {
+        let if_required = self
+            .line_items
+            .iter()
+            .filter(|i| i.trial_days.is_some())
+            .all(|i| i.trial_payment_method == TrialPaymentMethod::IfRequired);
+        if if_required && self.trial_days().is_some() {
+            TrialPaymentMethod::IfRequired
+        } else {
+            TrialPaymentMethod::Always
+        }
+    }
+
+    /// Discount per period from introductory prices, and for how many periods.
+    ///
+    /// Fails if the items' offers last different numbers of periods or an
+    /// intro price is above the regular price.
+    pub fn intro_discount(&self) -> PaymentResult<Option<(Price, u32)>> {
+        let mut discount = Price::zero(self.currency);
+        let mut periods = None;
+        for item in &self.line_items {
+            let Some(ref intro) = item.intro_price else {
+                continue;
+            };
+            if periods.is_some_and(|p| p != intro.periods) {
+                return Err(PaymentError::InvalidRequest(
+                    "introductory prices in one order must last the same number of periods"
+                        .to_string(),
+                ));
+            }
+            let off = item
+                .unit_price
+                .amount
+                .checked_sub(intro.price.amount)
+                .filter(|off| *off >= 0)
+                .ok_or_else(|| PaymentError::InvalidPrice {
+                    message: format!("introductory price of {} is above its price", item.product_id),
+                })?;
+            let off = Price::from_cents(off, self.currency).checked_mul(item.quantity)?;
+            discount = discount.checked_add(&off)?;
+            periods = Some(intro.periods);
+        }
+        Ok(periods.filter(|p| *p > 0).map(|p| (discount, p)))
+    }
+}
+
+/// Breakdown of an order's total
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderTotals {
+    /// Sum of line items
+    pub subtotal: Price,
+
+    /// Sum of discounts
+    pub discount: Price,
+
+    /// Tax (included in `subtotal` when `tax_inclusive`)
+    pub tax: Price,
+
+    /// Whether `tax` is already part of the prices
+    pub tax_inclusive: bool,
+
+    /// Amount to pay
+    pub total: Price,
 }
 
 /// Status of a checkout session
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum CheckoutStatus {
     /// Session created, awaiting payment
+    #[default]
     Open,
     /// Payment completed successfully
     Complete,
@@ -191,12 +517,6 @@ pub enum CheckoutStatus {
     Cancelled,
 }
 
-impl Default for CheckoutStatus {
-    fn default() -> Self {
-        CheckoutStatus::Open
-    }
-}
-
 /// A checkout session created by a payment provider
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CheckoutSession {
@@ -269,22 +589,47 @@ impl CheckoutSession {
 pub enum WebhookEventType {
     /// Checkout session completed
     CheckoutCompleted,
+    /// Checkout session expired without payment
+    CheckoutExpired,
     /// Payment succeeded
     PaymentSucceeded,
     /// Payment failed
     PaymentFailed,
     /// Subscription created
     SubscriptionCreated,
+    /// Subscription changed (plan, status, cancellation schedule, ...)
+    SubscriptionUpdated,
     /// Subscription cancelled
     SubscriptionCancelled,
     /// Subscription renewed
     SubscriptionRenewed,
+    /// Invoice payment failed
+    InvoicePaymentFailed,
     /// Refund issued
     RefundIssued,
     /// Unknown event (passthrough)
     Unknown(String),
 }
 
+impl WebhookEventType {
+    /// Get the event type name (the provider's own name for unknown events)
+    pub fn as_str(&self) -> &str {
+        match self {
+            WebhookEventType::CheckoutCompleted => "checkout_completed",
+            WebhookEventType::CheckoutExpired => "checkout_expired",
+            WebhookEventType::PaymentSucceeded => "payment_succeeded",
+            WebhookEventType::PaymentFailed => "payment_failed",
+            WebhookEventType::SubscriptionCreated => "subscription_created",
+            WebhookEventType::SubscriptionUpdated => "subscription_updated",
+            WebhookEventType::SubscriptionCancelled => "subscription_cancelled",
+            WebhookEventType::SubscriptionRenewed => "subscription_renewed",
+            WebhookEventType::InvoicePaymentFailed => "invoice_payment_failed",
+            WebhookEventType::RefundIssued => "refund_issued",
+            WebhookEventType::Unknown(event_type) => event_type,
+        }
+    }
+}
+
 /// A parsed webhook event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebhookEvent {
@@ -332,26 +677,147 @@ mod tests {
 
     #[test]
     fn test_line_item_total() {
-        let product = Product::one_time("test", "Test", Price::new(10.0, Currency::USD));
+        let product = Product::one_time("test", "Test", Price::parse("10.0", Currency::USD).unwrap());
         let item = LineItem::from_product(&product, 3);
 
-        assert_eq!(item.total().amount, 3000); // $30.00 in cents
+        assert_eq!(item.total().unwrap().amount, 3000); // $30.00 in cents
     }
 
     #[test]
     fn test_order_total() {
         let mut order = Order::new(Currency::USD);
 
-        let product1 = Product::one_time("p1", "Product 1", Price::new(10.0, Currency::USD));
-        let product2 = Product::one_time("p2", "Product 2", Price::new(25.0, Currency::USD));
+        let product1 = Product::one_time("p1", "Product 1", Price::parse("10.0", Currency::USD).unwrap());
+        let product2 = Product::one_time("p2", "Product 2", Price::parse("25.0", Currency::USD).unwrap());
 
-        order.add_product(&product1, 2); // $20
-        order.add_product(&product2, 1); // $25
+        order.add_product(&product1, 2).unwrap(); // $20
+        order.add_product(&product2, 1).unwrap(); // $25
 
-        assert_eq!(order.total().amount, 4500); // $45.00
+        assert_eq!(order.total().unwrap().amount, 4500); // $45.00
         assert_eq!(order.item_count(), 3);
     }
 
+    #[test]
+    fn test_trial_and_intro_terms() {
+        let monthly = Product::subscription(
+            "saas",
+            "SaaS",
+            Price::from_cents(2000, Currency::USD),
+            BillingInterval::MONTHLY,
+        )
+        .with_trial(14, TrialPaymentMethod::IfRequired)
+        .with_intro_price(
+            IntroPrice::new(Price::from_cents(500, Currency::USD), 3)
+                .with_price(Price::from_cents(450, Currency::EUR)),
+        )
+        .with_price(Price::from_cents(1800, Currency::EUR));
+
+        let mut order = Order::new(Currency::EUR);
+        order.add_product(&monthly, 2).unwrap();
+        assert_eq!(order.trial_days(), Some(14));
+        assert_eq!(order.trial_payment_method(), TrialPaymentMethod::IfRequired);
+        let (discount, periods) = order.intro_discount().unwrap().unwrap();
+        assert_eq!(discount, Price::from_cents(2700, Currency::EUR));
+        assert_eq!(periods, 3);
+
+        // An offer without a price in the order currency does not apply
+        let mut order = Order::new(Currency::GBP);
+        order
+            .add_product(&monthly.clone().with_price(Price::from_cents(1600, Currency::GBP)), 1)
+            .unwrap();
+        assert!(order.intro_discount().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_order_total_overflow() {
+        let mut order = Order::new(Currency::USD);
+        let product = Product::one_time("big", "Big", Price::from_cents(i64::MAX / 2, Currency::USD));
+
+        order.add_product(&product, 1).unwrap();
+        assert!(order.total().is_ok());
+
+        order.add_product(&product, 2).unwrap();
+        assert!(order.total().is_err());
+    }
+
+    #[test]
+    fn test_order_currency_enforced() {
+        let usd_only = Product::one_time("usd", "USD Only", Price::from_cents(1000, Currency::USD));
+        let multi = Product::one_time("multi", "Multi", Price::from_cents(1000, Currency::USD))
+            .with_price(Price::from_cents(900, Currency::EUR));
+
+        let mut order = Order::new(Currency::EUR);
+        order.add_product(&multi, 2).unwrap();
+        assert_eq!(order.total().unwrap(), Price::from_cents(1800, Currency::EUR));
+
+        assert!(matches!(
+            order.add_product(&usd_only, 1),
+            Err(PaymentError::UnsupportedCurrency { .. })
+        ));
+        assert!(matches!(
+            order.add_item(LineItem::from_product(&usd_only, 1)),
+            Err(PaymentError::UnsupportedCurrency { .. })
+        ));
+        assert_eq!(order.line_items.len(), 1);
+    }
+
+    #[test]
+    fn test_order_variants() {
+        use crate::variant::ProductVariant;
+
+        let product = Product::one_time("rang", "Rang Play RS", Price::from_cents(1999, Currency::USD))
+            .with_variant(ProductVariant::new(
+                "team",
+                "Team",
+                "RPR-TEAM",
+                Price::from_cents(9900, Currency::USD),
+            ))
+            .with_variant(
+                ProductVariant::new("old", "Old", "RPR-OLD", Price::from_cents(500, Currency::USD))
+                    .inactive(),
+            );
+
+        let mut order = Order::new(Currency::USD);
+        order.add_variant(&product, "team", 2).unwrap();
+        let item = &order.line_items[0];
+        assert_eq!(item.display_name(), "Rang Play RS – Team");
+        assert_eq!(item.variant_id.as_deref(), Some("team"));
+        assert_eq!(item.sku.as_deref(), Some("RPR-TEAM"));
+        assert_eq!(order.total().unwrap().amount, 19800);
+
+        assert!(matches!(
+            order.add_variant(&product, "nope", 1),
+            Err(PaymentError::VariantNotFound { ref variant_id, .. }) if variant_id == "nope"
+        ));
+        assert!(matches!(
+            order.add_variant(&product, "old", 1),
+            Err(PaymentError::InvalidRequest(_))
+        ));
+        // The parent product itself cannot be bought
+        assert!(order.add_product(&product, 1).is_err());
+        assert_eq!(order.line_items.len(), 1);
+    }
+
+    #[test]
+    fn test_order_discount() {
+        let mut order = Order::new(Currency::USD);
+        let product = Product::one_time("p1", "Product 1", Price::from_cents(5000, Currency::USD));
+        order.add_product(&product, 1).unwrap();
+
+        let discount = |cents| AppliedDiscount {
+            code: "SAVE".into(),
+            description: String::new(),
+            amount: Price::from_cents(cents, Currency::USD),
+        };
+
+        order.apply_discount(discount(1500)).unwrap();
+        assert_eq!(order.subtotal().unwrap().amount, 5000);
+        assert_eq!(order.total().unwrap().amount, 3500);
+
+        assert!(order.apply_discount(discount(4000)).is_err());
+        assert_eq!(order.discounts.len(), 1);
+    }
+
     #[test]
     fn test_subscription_mode_detection() {
         let mut order = Order::new(Currency::USD);
@@ -359,11 +825,11 @@ mod tests {
         let subscription = Product::subscription(
             "sub",
             "Monthly Sub",
-            Price::new(29.0, Currency::USD),
-            BillingInterval::Monthly,
+            Price::parse("29.0", Currency::USD).unwrap(),
+            BillingInterval::MONTHLY,
         );
 
-        order.add_product(&subscription, 1);
+        order.add_product(&subscription, 1).unwrap();
 
         assert_eq!(order.mode, CheckoutMode::Subscription);
     }
@@ -0,0 +1,708 @@
+//! # Outbound Webhooks
+//!
+//! Per-site subscriptions that forward payment events to other services.
+//!
+//! Subscriptions are configured in `sites.toml` under `[[sites.webhooks]]`.
+//! Each one selects event types, shapes the request body with a field
+//! mapping or a JSON template, and may sign the body with an HMAC secret.
+//! Values are looked up by dotted path in the event context (see
+//! `event_context`), e.g. `metadata.client_email` or `data.amount_total`.
+//!
+//! Deliveries wait in a durable `DeliveryQueue` until they succeed or the
+//! subscription's `RetryPolicy` gives up, and every attempt is recorded in a
+//! `DeliveryLog`.
+
+use crate::error::{PaymentError, PaymentResult};
+use crate::order::WebhookEvent;
+use crate::queue::{RetryPolicy, JOB_LEASE_SECS};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Event type that matches every event
+pub const ALL_EVENTS: &str = "*";
+
+/// An outbound webhook subscription
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboundWebhook {
+    /// Subscription name (used in logs and the delivery log)
+    pub id: String,
+
+    /// Endpoint URL
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub url: String,
+
+    /// Environment variable holding the endpoint URL (preferred over `url`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url_env: Option<String>,
+
+    /// Event types to forward (see `WebhookEventType::as_str`, "*" = all)
+    pub events: Vec<String>,
+
+    /// Only forward events whose metadata has all of these keys
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub require_metadata: Vec<String>,
+
+    /// Body fields (field name → context path); takes precedence over `template`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, FieldMapping>,
+
+    /// JSON body with `{{path}}` placeholders, replaced by JSON values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+
+    /// HMAC-SHA256 signing secret
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+
+    /// Environment variable holding the signing secret (preferred over `secret`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_env: Option<String>,
+
+    /// Extra request headers
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+
+    /// Request timeout (seconds)
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Retry policy for failed deliveries
+    #[serde(default)]
+    pub retry: RetryPolicy,
+}
+
+fn default_timeout_secs() -> u64 {
+    15
+}
+
+/// How a body field is filled from the event context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FieldMapping {
+    /// Context path, e.g. `"metadata.client_email"`
+    Path(String),
+    /// Context path with a type conversion and a fallback value
+    Spec {
+        /// Context path
+        from: String,
+        /// Conversion applied to the value
+        #[serde(rename = "type", default)]
+        kind: FieldKind,
+        /// Value used when the path is missing or cannot be converted
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        default: Option<Value>,
+    },
+}
+
+/// Type conversion for a mapped field
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldKind {
+    /// Keep the value as found
+    #[default]
+    Raw,
+    /// Convert to a string
+    String,
+    /// Convert to an integer (numeric strings are parsed)
+    Integer,
+    /// Convert to a boolean ("true"/"false" strings are parsed)
+    Boolean,
+}
+
+impl FieldMapping {
+    /// Resolve the field against an event context
+    pub fn resolve(&self, context: &Value) -> Value {
+        match self {
+            FieldMapping::Path(path) => lookup(context, path).cloned().unwrap_or(Value::Null),
+            FieldMapping::Spec {
+                from,
+                kind,
+                default,
+            } => lookup(context, from)
+                .and_then(|value| kind.convert(value))
+                .or_else(|| default.clone())
+                .unwrap_or(Value::Null),
+        }
+    }
+}
+
+impl FieldKind {
+    fn convert(&self, value: &Value) -> Option<Value> {
+        match (self, value) {
+            (_, Value::Null) => None,
+            (FieldKind::Raw, _) => Some(value.clone()),
+            (FieldKind::String, Value::String(_)) => Some(value.clone()),
+            (FieldKind::String, _) => Some(Value::String(value.to_string())),
+            (FieldKind::Integer, Value::Number(n)) => n.as_i64().map(Value::from),
+            (FieldKind::Integer, Value::String(s)) => s.trim().parse::<i64>().ok().map(Value::from),
+            (FieldKind::Boolean, Value::Bool(_)) => Some(value.clone()),
+            (FieldKind::Boolean, Value::String(s)) => s.trim().parse::<bool>().ok().map(Value::from),
+            _ => None,
+        }
+    }
+}
+
+impl OutboundWebhook {
+    /// Create a subscription forwarding `events` to `url`
+    pub fn new(id: impl Into<String>, url: impl Into<String>, events: &[&str]) -> Self {
+        Self {
+            id: id.into(),
+            url: url.into(),
+            url_env: None,
+            events: events.iter().map(|e| e.to_string()).collect(),
+            require_metadata: Vec::new(),
+            fields: BTreeMap::new(),
+            template: None,
+            secret: None,
+            secret_env: None,
+            headers: BTreeMap::new(),
+            timeout_secs: default_timeout_secs(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Builder: only forward events with this metadata key
+    pub fn with_required_metadata(mut self, key: impl Into<String>) -> Self {
+        self.require_metadata.push(key.into());
+        self
+    }
+
+    /// Builder: add a body field
+    pub fn with_field(mut self, name: impl Into<String>, mapping: FieldMapping) -> Self {
+        self.fields.insert(name.into(), mapping);
+        self
+    }
+
+    /// Builder: set the body template
+    pub fn with_template(mut self, template: impl Into<String>) -> Self {
+        self.template = Some(template.into());
+        self
+    }
+
+    /// Builder: set the signing secret
+    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Builder: add a request header
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Check if an event (with its context) should be forwarded
+    pub fn matches(&self, event: &WebhookEvent, context: &Value) -> bool {
+        let event_type = event.event_type.as_str();
+        let wanted = self
+            .events
+            .iter()
+            .any(|e| e == ALL_EVENTS || e == event_type);
+        wanted
+            && self
+                .require_metadata
+                .iter()
+                .all(|key| context["metadata"].get(key).is_some())
+    }
+
+    /// Get the endpoint URL, reading `url_env` if set (None if neither is set)
+    pub fn endpoint_url(&self) -> Option<String> {
+        self.url_env
+            .as_ref()
+            .and_then(|name| std::env::var(name).ok())
+            .filter(|url| !url.is_empty())
+            .or_else(|| Some(self.url.clone()))
+            .filter(|url| !url.is_empty())
+    }
+
+    /// Get the signing secret, reading `secret_env` if set
+    pub fn signing_secret(&self) -> Option<String> {
+        self.secret_env
+            .as_ref()
+            .and_then(|name| std::env::var(name).ok())
+            .or_else(|| self.secret.clone())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Build the request body: mapped fields, the template, or the whole context
+    pub fn render(&self, context: &Value) -> PaymentResult<Value> {
+        if !self.fields.is_empty() {
+            let body = self
+                .fields
+                .iter()
+                .map(|(name, mapping)| (name.clone(), mapping.resolve(context)))
+                .collect();
+            return Ok(Value::Object(body));
+        }
+        match self.template {
+            Some(ref template) => render_template(template, context).map_err(|e| {
+                PaymentError::Configuration(format!("webhook {} template: {}", self.id, e))
+            }),
+            None => Ok(context.clone()),
+        }
+    }
+}
+
+/// Build the context that subscription paths are resolved against
+///
+/// Top-level event fields plus `site_id`, `data` (the provider's object) and
+/// `metadata` (its metadata, or an empty object).
+pub fn event_context(event: &WebhookEvent, site_id: &str) -> Value {
+    let data = event.raw_data.clone().unwrap_or(Value::Null);
+    let metadata = data
+        .get("metadata")
+        .filter(|m| m.is_object())
+        .cloned()
+        .unwrap_or_else(|| Value::Object(Default::default()));
+    serde_json::json!({
+        "event_id": event.event_id,
+        "event_type": event.event_type.as_str(),
+        "provider": event.provider,
+        "site_id": site_id,
+        "session_id": event.session_id,
+        "payment_intent_id": event.payment_intent_id,
+        "customer_email": event.customer_email,
+        "amount_paid": event.amount_paid,
+        "currency": event.currency,
+        "timestamp": event.timestamp,
+        "data": data,
+        "metadata": metadata,
+    })
+}
+
+/// Look up a dotted path (`data.customer_details.email`) in a JSON value
+pub fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(value, |current, key| match current {
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => current.get(key),
+        })
+        .filter(|v| !v.is_null())
+}
+
+fn render_template(template: &str, context: &Value) -> Result<Value, String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| "unclosed {{ placeholder".to_string())?;
+        let path = rest[start + 2..start + end].trim();
+        let value = lookup(context, path).cloned().unwrap_or(Value::Null);
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(&value.to_string());
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+    serde_json::from_str(&rendered).map_err(|e| format!("not valid JSON: {}", e))
+}
+
+/// One attempt to deliver an event to a subscription
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryAttempt {
+    /// Site the subscription belongs to
+    pub site_id: String,
+
+    /// Subscription ID
+    pub webhook_id: String,
+
+    /// Forwarded event ID
+    pub event_id: String,
+
+    /// Endpoint URL
+    pub url: String,
+
+    /// Attempt number (1 = first)
+    pub attempt: u32,
+
+    /// HTTP status returned by the endpoint (None if the request failed)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<u16>,
+
+    /// Error of a failed attempt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Request duration (milliseconds)
+    pub duration_ms: u64,
+
+    /// When the attempt was made
+    pub attempted_at: DateTime<Utc>,
+}
+
+impl DeliveryAttempt {
+    /// Check if the endpoint accepted the delivery
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none() && matches!(self.status_code, Some(200..=299))
+    }
+}
+
+/// Storage for outbound delivery attempts
+#[async_trait]
+pub trait DeliveryLog: Send + Sync {
+    /// Record an attempt
+    async fn record(&self, attempt: &DeliveryAttempt) -> PaymentResult<()>;
+
+    /// Attempts for an event, oldest first
+    async fn attempts(&self, event_id: &str) -> PaymentResult<Vec<DeliveryAttempt>>;
+
+    /// Delete attempts made before `cutoff`, returning how many were removed
+    async fn purge_before(&self, cutoff: DateTime<Utc>) -> PaymentResult<usize>;
+}
+
+/// Shared delivery log
+pub type BoxedDeliveryLog = Arc<dyn DeliveryLog>;
+
+/// In-memory delivery log (contents are lost on restart)
+#[derive(Debug, Default)]
+pub struct InMemoryDeliveryLog {
+    attempts: RwLock<Vec<DeliveryAttempt>>,
+}
+
+impl InMemoryDeliveryLog {
+    /// Create an empty log
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn poisoned<T>(_: T) -> PaymentError {
+    PaymentError::Storage("outbound delivery lock poisoned".to_string())
+}
+
+#[async_trait]
+impl DeliveryLog for InMemoryDeliveryLog {
+    async fn record(&self, attempt: &DeliveryAttempt) -> PaymentResult<()> {
+        self.attempts.write().map_err(poisoned)?.push(attempt.clone());
+        Ok(())
+    }
+
+    async fn attempts(&self, event_id: &str) -> PaymentResult<Vec<DeliveryAttempt>> {
+        let attempts = self.attempts.read().map_err(poisoned)?;
+        Ok(attempts
+            .iter()
+            .filter(|a| a.event_id == event_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn purge_before(&self, cutoff: DateTime<Utc>) -> PaymentResult<usize> {
+        let mut attempts = self.attempts.write().map_err(poisoned)?;
+        let before = attempts.len();
+        attempts.retain(|a| a.attempted_at >= cutoff);
+        Ok(before - attempts.len())
+    }
+}
+
+/// A delivery waiting to be sent to one subscription
+///
+/// The body is rendered when the event is processed; the endpoint, headers,
+/// secret and retry policy are read from the subscription when it is sent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryJob {
+    /// Queue-assigned ID
+    pub id: i64,
+
+    /// Site the subscription belongs to
+    pub site_id: String,
+
+    /// Subscription ID
+    pub webhook_id: String,
+
+    /// Forwarded event ID
+    pub event_id: String,
+
+    /// Forwarded event type (see `WebhookEventType::as_str`)
+    pub event_type: String,
+
+    /// Rendered request body
+    pub body: String,
+
+    /// Attempts made so far (counted when the job is claimed)
+    pub attempts: u32,
+
+    /// When the job is next due
+    pub run_at: DateTime<Utc>,
+
+    /// Error of the last failed attempt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+
+    /// When the job was enqueued
+    pub created_at: DateTime<Utc>,
+}
+
+impl DeliveryJob {
+    /// Create a job for an event rendered for a subscription, due immediately
+    pub fn new(
+        site_id: impl Into<String>,
+        webhook_id: impl Into<String>,
+        event: &WebhookEvent,
+        body: impl Into<String>,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id: 0,
+            site_id: site_id.into(),
+            webhook_id: webhook_id.into(),
+            event_id: event.event_id.clone(),
+            event_type: event.event_type.as_str().to_string(),
+            body: body.into(),
+            attempts: 0,
+            run_at: now,
+            last_error: None,
+            created_at: now,
+        }
+    }
+}
+
+/// Durable queue of outbound deliveries
+///
+/// Claiming works like `WebhookQueue::claim`: the job's attempt count goes
+/// up and it is leased for `JOB_LEASE_SECS`.
+#[async_trait]
+pub trait DeliveryQueue: Send + Sync {
+    /// Add a delivery to the queue, returning it with its assigned ID
+    /// (None if the event is already queued for the subscription)
+    async fn enqueue(&self, job: &DeliveryJob) -> PaymentResult<Option<DeliveryJob>>;
+
+    /// Claim the oldest due delivery
+    async fn claim(&self, now: DateTime<Utc>) -> PaymentResult<Option<DeliveryJob>>;
+
+    /// Remove a delivered job
+    async fn complete(&self, job_id: i64) -> PaymentResult<()>;
+
+    /// Record a failed attempt: retry at `retry_at`, or drop the job if None
+    /// (its attempts stay in the delivery log)
+    async fn fail(
+        &self,
+        job_id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> PaymentResult<()>;
+
+    /// Number of queued deliveries
+    async fn pending(&self) -> PaymentResult<usize>;
+}
+
+/// Shared delivery queue
+pub type BoxedDeliveryQueue = Arc<dyn DeliveryQueue>;
+
+#[derive(Debug, Default)]
+struct MemoryDeliveries {
+    next_id: i64,
+    jobs: BTreeMap<i64, (DeliveryJob, Option<DateTime<Utc>>)>,
+}
+
+/// In-memory delivery queue (contents are lost on restart)
+#[derive(Debug, Default)]
+pub struct InMemoryDeliveryQueue {
+    inner: Mutex<MemoryDeliveries>,
+}
+
+impl InMemoryDeliveryQueue {
+    /// Create an empty queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DeliveryQueue for InMemoryDeliveryQueue {
+    async fn enqueue(&self, job: &DeliveryJob) -> PaymentResult<Option<DeliveryJob>> {
+        let mut queue = self.inner.lock().map_err(poisoned)?;
+        let queued = queue.jobs.values().any(|(queued, _)| {
+            (&queued.site_id, &queued.webhook_id, &queued.event_id)
+                == (&job.site_id, &job.webhook_id, &job.event_id)
+        });
+        if queued {
+            return Ok(None);
+        }
+        queue.next_id += 1;
+        let job = DeliveryJob {
+            id: queue.next_id,
+            ..job.clone()
+        };
+        queue.jobs.insert(job.id, (job.clone(), None));
+        Ok(Some(job))
+    }
+
+    async fn claim(&self, now: DateTime<Utc>) -> PaymentResult<Option<DeliveryJob>> {
+        let mut queue = self.inner.lock().map_err(poisoned)?;
+        let due = queue
+            .jobs
+            .values_mut()
+            .filter(|(job, leased)| {
+                job.run_at <= now && leased.map_or(true, |until| until <= now)
+            })
+            .min_by_key(|(job, _)| (job.run_at, job.id));
+
+        Ok(due.map(|(job, leased)| {
+            job.attempts += 1;
+            *leased = Some(now + Duration::seconds(JOB_LEASE_SECS));
+            job.clone()
+        }))
+    }
+
+    async fn complete(&self, job_id: i64) -> PaymentResult<()> {
+        self.inner.lock().map_err(poisoned)?.jobs.remove(&job_id);
+        Ok(())
+    }
+
+    async fn fail(
+        &self,
+        job_id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> PaymentResult<()> {
+        let mut queue = self.inner.lock().map_err(poisoned)?;
+        let (mut job, _) = queue.jobs.remove(&job_id).ok_or_else(|| {
+            PaymentError::Storage(format!("delivery job {} not found", job_id))
+        })?;
+        if let Some(run_at) = retry_at {
+            job.run_at = run_at;
+            job.last_error = Some(error.to_string());
+            queue.jobs.insert(job_id, (job, None));
+        }
+        Ok(())
+    }
+
+    async fn pending(&self) -> PaymentResult<usize> {
+        Ok(self.inner.lock().map_err(poisoned)?.jobs.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::WebhookEventType;
+
+    fn event() -> WebhookEvent {
+        WebhookEvent {
+            event_id: "evt_1".into(),
+            event_type: WebhookEventType::CheckoutCompleted,
+            provider: "stripe".into(),
+            session_id: Some("cs_1".into()),
+            payment_intent_id: Some("pi_1".into()),
+            customer_email: None,
+            amount_paid: Some(15000),
+            currency: None,
+            raw_data: Some(serde_json::json!({
+                "id": "cs_1",
+                "amount_total": 15000,
+                "metadata": {"appointment_date": "2025-03-01", "duration": "2"}
+            })),
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_field_mapping_from_toml() {
+        let webhook: OutboundWebhook = toml::from_str(
+            r#"
+            id = "consultation"
+            url = "https://example.com/hook"
+            events = ["checkout_completed"]
+            require_metadata = ["appointment_date"]
+
+            [fields]
+            date = "metadata.appointment_date"
+            amountCents = "data.amount_total"
+            duration = { from = "metadata.duration", type = "integer", default = 1 }
+            name = { from = "metadata.client_name", default = "" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(webhook.timeout_secs, 15);
+        assert_eq!(webhook.retry, RetryPolicy::default());
+
+        let event = event();
+        let context = event_context(&event, "chargegun");
+        assert!(webhook.matches(&event, &context));
+        assert_eq!(
+            webhook.render(&context).unwrap(),
+            serde_json::json!({
+                "date": "2025-03-01",
+                "amountCents": 15000,
+                "duration": 2,
+                "name": "",
+            })
+        );
+
+        let mut other = event.clone();
+        other.raw_data = Some(serde_json::json!({"id": "cs_2"}));
+        assert!(!webhook.matches(&other, &event_context(&other, "chargegun")));
+        other.event_type = WebhookEventType::PaymentFailed;
+        assert!(!webhook.matches(&other, &event_context(&other, "chargegun")));
+    }
+
+    #[test]
+    fn test_template_and_default_body() {
+        let event = event();
+        let context = event_context(&event, "chargegun");
+
+        let webhook = OutboundWebhook::new("crm", "https://example.com", &[ALL_EVENTS])
+            .with_template(r#"{"site": {{ site_id }}, "paid": {{amount_paid}}, "x": {{missing}}}"#);
+        assert_eq!(
+            webhook.render(&context).unwrap(),
+            serde_json::json!({"site": "chargegun", "paid": 15000, "x": null})
+        );
+
+        let broken = webhook.clone().with_template("{\"a\": {{site_id}");
+        assert!(matches!(broken.render(&context), Err(PaymentError::Configuration(_))));
+
+        let raw = OutboundWebhook::new("raw", "https://example.com", &[ALL_EVENTS]);
+        let body = raw.render(&context).unwrap();
+        assert_eq!(body["event_type"], "checkout_completed");
+        assert_eq!(body["data"]["id"], "cs_1");
+    }
+
+    #[test]
+    fn test_endpoint_url_from_env() {
+        let mut webhook: OutboundWebhook = toml::from_str(
+            r#"
+            id = "consultation"
+            url_env = "LIGHTNING_CART_TEST_OUTBOUND_URL"
+            events = ["checkout_completed"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(webhook.endpoint_url(), None);
+
+        webhook.url = "https://example.com/fallback".into();
+        assert_eq!(webhook.endpoint_url().as_deref(), Some("https://example.com/fallback"));
+
+        std::env::set_var("LIGHTNING_CART_TEST_OUTBOUND_URL", "https://example.com/env");
+        assert_eq!(webhook.endpoint_url().as_deref(), Some("https://example.com/env"));
+        std::env::remove_var("LIGHTNING_CART_TEST_OUTBOUND_URL");
+    }
+
+    #[tokio::test]
+    async fn test_delivery_queue_retry_and_drop() {
+        let queue = InMemoryDeliveryQueue::new();
+        let job = DeliveryJob::new("chargegun", "consultation", &event(), "{}");
+        let job = queue.enqueue(&job).await.unwrap().unwrap();
+        assert!(queue.enqueue(&job).await.unwrap().is_none());
+        let now = Utc::now();
+
+        let claimed = queue.claim(now).await.unwrap().unwrap();
+        assert_eq!((claimed.id, claimed.attempts), (job.id, 1));
+        assert_eq!(claimed.event_type, "checkout_completed");
+        assert!(queue.claim(now).await.unwrap().is_none());
+
+        let retry_at = now + Duration::seconds(10);
+        queue.fail(job.id, "HTTP 500", Some(retry_at)).await.unwrap();
+        assert!(queue.claim(now).await.unwrap().is_none());
+        let retried = queue.claim(retry_at).await.unwrap().unwrap();
+        assert_eq!(retried.attempts, 2);
+        assert_eq!(retried.last_error.as_deref(), Some("HTTP 500"));
+
+        queue.fail(job.id, "HTTP 500", None).await.unwrap();
+        assert_eq!(queue.pending().await.unwrap(), 0);
+        assert!(queue.fail(job.id, "HTTP 500", None).await.is_err());
+    }
+}
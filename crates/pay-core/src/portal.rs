@@ -0,0 +1,165 @@
+//! # Billing Portal
+//!
+//! Self-service pages where customers update their payment method, see
+//! invoices and cancel subscriptions.
+//!
+//! Each site configures its portal in `config/sites.toml`:
+//!
+//! ```toml
+//! [sites.portal]
+//! return_url = "https://chargegun.io/account"
+//!
+//! [sites.portal.features]
+//! update_payment_method = true
+//! cancel_subscription = true
+//! ```
+//!
+//! Sites without a `[sites.portal]` table have no portal.
+
+use crate::error::PaymentResult;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// What customers may do in the portal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortalFeatures {
+    /// Update the card or other payment method
+    pub update_payment_method: bool,
+    /// See and download past invoices
+    pub invoice_history: bool,
+    /// Edit email, name and billing address
+    pub update_customer: bool,
+    /// Cancel subscriptions (at the end of the period)
+    pub cancel_subscription: bool,
+    /// Change the quantity of subscriptions
+    pub update_quantity: bool,
+}
+
+impl Default for PortalFeatures {
+    fn default() -> Self {
+        Self {
+            update_payment_method: true,
+            invoice_history: true,
+            update_customer: false,
+            cancel_subscription: true,
+            update_quantity: false,
+        }
+    }
+}
+
+/// Per-site portal configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortalConfig {
+    /// Where the portal's "back" link goes (default: the site's home page)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub return_url: Option<String>,
+
+    /// Existing provider configuration to use instead of `features` (bpc_...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub configuration_id: Option<String>,
+
+    /// Allowed features
+    #[serde(default)]
+    pub features: PortalFeatures,
+}
+
+impl PortalConfig {
+    /// Portal with the default features
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the return URL
+    pub fn with_return_url(mut self, url: impl Into<String>) -> Self {
+        self.return_url = Some(url.into());
+        self
+    }
+
+    /// Use an existing provider configuration
+    pub fn with_configuration(mut self, configuration_id: impl Into<String>) -> Self {
+        self.configuration_id = Some(configuration_id.into());
+        self
+    }
+
+    /// Set the allowed features
+    pub fn with_features(mut self, features: PortalFeatures) -> Self {
+        self.features = features;
+        self
+    }
+}
+
+/// A portal session to redirect the customer to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortalSession {
+    /// Provider's session ID
+    pub id: String,
+
+    /// Provider's customer ID
+    pub customer_id: String,
+
+    /// Portal URL (short-lived)
+    pub url: String,
+
+    /// Where the portal returns to
+    pub return_url: String,
+}
+
+/// Billing portal at a payment provider
+#[async_trait]
+pub trait BillingPortal: Send + Sync {
+    /// Find the provider customer with an email address
+    async fn find_customer_by_email(&self, email: &str) -> PaymentResult<Option<String>>;
+
+    /// Open a portal session for a customer
+    async fn create_portal_session(
+        &self,
+        customer_id: &str,
+        config: &PortalConfig,
+        return_url: &str,
+    ) -> PaymentResult<PortalSession>;
+
+    /// Get the provider name
+    fn provider_name(&self) -> &'static str;
+}
+
+/// Shared billing portal
+pub type BoxedBillingPortal = Arc<dyn BillingPortal>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_portal_config_from_toml() {
+        let config: PortalConfig = toml::from_str(
+            r#"
+            return_url = "https://chargegun.io/account"
+            configuration_id = "bpc_1"
+
+            [features]
+            update_quantity = true
+            cancel_subscription = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.return_url.as_deref(), Some("https://chargegun.io/account"));
+        assert_eq!(config.configuration_id.as_deref(), Some("bpc_1"));
+        assert!(config.features.update_quantity);
+        assert!(!config.features.cancel_subscription);
+        // Unlisted features keep their defaults
+        assert!(config.features.update_payment_method);
+        assert!(config.features.invoice_history);
+        assert!(!config.features.update_customer);
+
+        // An empty table is the default config, as are the builders' starting point
+        let empty: PortalConfig = toml::from_str("").unwrap();
+        assert_eq!(empty, PortalConfig::new());
+        let built = PortalConfig::new()
+            .with_return_url("https://chargegun.io/account")
+            .with_configuration("bpc_1")
+            .with_features(config.features);
+        assert_eq!(built, config);
+    }
+}
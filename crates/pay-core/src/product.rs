@@ -3,73 +3,22 @@
 //! Product catalog types for lightning-cart.
 //! Products are loaded from `config/products.toml`.
 
+use crate::error::{PaymentError, PaymentResult};
+use crate::format::{CurrencyDisplay, Locale};
+use crate::money::Money;
+use crate::usage::UsageModel;
+use crate::variant::{ProductOption, ProductVariant};
 use serde::{Deserialize, Serialize};
 
-/// Supported currencies (ISO 4217)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum Currency {
-    USD,
-    EUR,
-    GBP,
-    JPY,
-    CAD,
-    AUD,
-    CHF,
-    MXN,
-}
-
-impl Currency {
-    /// Returns the ISO 4217 currency code
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            Currency::USD => "usd",
-            Currency::EUR => "eur",
-            Currency::GBP => "gbp",
-            Currency::JPY => "jpy",
-            Currency::CAD => "cad",
-            Currency::AUD => "aud",
-            Currency::CHF => "chf",
-            Currency::MXN => "mxn",
-        }
-    }
-
-    /// Returns the number of decimal places for this currency
-    /// (JPY has 0 decimals, most others have 2)
-    pub fn decimal_places(&self) -> u8 {
-        match self {
-            Currency::JPY => 0,
-            _ => 2,
-        }
-    }
-
-    /// Convert a decimal amount to the smallest currency unit (cents, etc.)
-    pub fn to_smallest_unit(&self, amount: f64) -> i64 {
-        let multiplier = 10_f64.powi(self.decimal_places() as i32);
-        (amount * multiplier).round() as i64
-    }
-
-    /// Convert from smallest unit back to decimal
-    pub fn from_smallest_unit(&self, amount: i64) -> f64 {
-        let divisor = 10_f64.powi(self.decimal_places() as i32);
-        amount as f64 / divisor
-    }
-}
-
-impl Default for Currency {
-    fn default() -> Self {
-        Currency::USD
-    }
-}
-
-impl std::fmt::Display for Currency {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.as_str().to_uppercase())
-    }
-}
+pub use crate::currency::{Currency, CurrencyInfo, SymbolPosition};
+pub use crate::interval::{BillingInterval, IntervalUnit};
 
 /// Price with amount in smallest currency unit
+///
+/// In TOML/JSON, `amount` is either an integer in the smallest unit
+/// (`amount = 1999`) or an exact decimal string (`amount = "19.99"`).
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawPrice")]
 pub struct Price {
     /// Amount in smallest currency unit (cents for USD)
     pub amount: i64,
@@ -79,11 +28,16 @@ pub struct Price {
 
 impl Price {
     /// Create a new price from decimal amount
-    pub fn new(amount: f64, currency: Currency) -> Self {
-        Self {
-            amount: currency.to_smallest_unit(amount),
+    pub fn new(amount: Money, currency: Currency) -> PaymentResult<Self> {
+        Ok(Self {
+            amount: currency.to_smallest_unit(amount)?,
             currency,
-        }
+        })
+    }
+
+    /// Create a new price from a decimal string (e.g., "19.99")
+    pub fn parse(amount: &str, currency: Currency) -> PaymentResult<Self> {
+        Self::new(amount.parse()?, currency)
     }
 
     /// Create a price from smallest unit (cents)
@@ -91,56 +45,141 @@ impl Price {
         Self { amount, currency }
     }
 
+    /// Create a zero price
+    pub fn zero(currency: Currency) -> Self {
+        Self::from_cents(0, currency)
+    }
+
     /// Get the decimal amount
-    pub fn as_decimal(&self) -> f64 {
+    pub fn as_decimal(&self) -> Money {
         self.currency.from_smallest_unit(self.amount)
     }
 
-    /// Format for display (e.g., "$10.00")
+    /// Add another price in the same currency, failing on overflow
+    pub fn checked_add(&self, other: &Price) -> PaymentResult<Price> {
+        if self.currency != other.currency {
+            return Err(PaymentError::InvalidPrice {
+                message: format!(
+                    "cannot add {} to {}",
+                    other.currency, self.currency
+                ),
+            });
+        }
+        Self::new(self.as_decimal().checked_add(other.as_decimal())?, self.currency)
+    }
+
+    /// Multiply by a quantity, failing on overflow
+    pub fn checked_mul(&self, quantity: u32) -> PaymentResult<Price> {
+        Self::new(self.as_decimal().checked_mul(quantity as i64)?, self.currency)
+    }
+
+    /// Format for display in en-US (e.g., "$1,000.00", "CHF 10.00", "10.00 kr")
     pub fn display(&self) -> String {
-        let symbol = match self.currency {
-            Currency::USD => "$",
-            Currency::EUR => "€",
-            Currency::GBP => "£",
-            Currency::JPY => "¥",
-            Currency::CAD => "C$",
-            Currency::AUD => "A$",
-            Currency::CHF => "CHF ",
-            Currency::MXN => "MX$",
-        };
-        if self.currency.decimal_places() == 0 {
-            format!("{}{}", symbol, self.amount)
-        } else {
-            format!("{}{:.2}", symbol, self.as_decimal())
+        self.format(&Locale::default(), CurrencyDisplay::Symbol)
+    }
+
+    /// Format for display in the given locale (e.g., "1.234,56 €" for de-DE)
+    pub fn format(&self, locale: &Locale, display: CurrencyDisplay) -> String {
+        locale.format_price(self, display)
+    }
+}
+
+/// Wire format for `Price`: integer minor units or a decimal string
+#[derive(Deserialize)]
+struct RawPrice {
+    amount: RawAmount,
+    #[serde(default)]
+    currency: Currency,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawAmount {
+    Minor(i64),
+    Decimal(String),
+}
+
+impl TryFrom<RawPrice> for Price {
+    type Error = PaymentError;
+
+    fn try_from(raw: RawPrice) -> Result<Self, Self::Error> {
+        match raw.amount {
+            RawAmount::Minor(amount) => Ok(Price::from_cents(amount, raw.currency)),
+            RawAmount::Decimal(amount) => Price::parse(&amount, raw.currency),
         }
     }
 }
 
-/// Billing interval for subscriptions
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum BillingInterval {
-    /// One-time payment (not a subscription)
-    OneTime,
-    /// Weekly billing
-    Weekly,
-    /// Monthly billing
-    Monthly,
-    /// Yearly billing
-    Yearly,
+/// Whether a free trial needs a payment method up front
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrialPaymentMethod {
+    /// Collect a payment method before the trial starts
+    #[default]
+    Always,
+    /// Start the trial without one (the subscription cancels if none is added)
+    IfRequired,
 }
 
-impl Default for BillingInterval {
-    fn default() -> Self {
-        BillingInterval::OneTime
+impl TrialPaymentMethod {
+    /// Get the wire name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrialPaymentMethod::Always => "always",
+            TrialPaymentMethod::IfRequired => "if_required",
+        }
+    }
+}
+
+/// Discounted price for the first billing periods of a subscription
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntroPrice {
+    /// Price per period (primary currency)
+    pub price: Price,
+
+    /// Prices in other currencies (one per currency)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prices: Vec<Price>,
+
+    /// Number of billing periods charged at this price
+    pub periods: u32,
+}
+
+impl IntroPrice {
+    /// Create an introductory price for a number of periods
+    pub fn new(price: Price, periods: u32) -> Self {
+        Self {
+            price,
+            prices: Vec::new(),
+            periods,
+        }
+    }
+
+    /// Builder: add a price in another currency
+    pub fn with_price(mut self, price: Price) -> Self {
+        if price.currency == self.price.currency {
+            self.price = price;
+        } else {
+            self.prices.retain(|p| p.currency != price.currency);
+            self.prices.push(price);
+        }
+        self
+    }
+
+    /// Get the price in a specific currency
+    pub fn price_in(&self, currency: Currency) -> Option<&Price> {
+        std::iter::once(&self.price)
+            .chain(self.prices.iter())
+            .find(|p| p.currency == currency)
     }
 }
 
 /// Product type
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ProductType {
     /// Digital download (WASM, Docker image, etc.)
+    #[default]
     Digital,
     /// SaaS subscription
     Subscription,
@@ -152,12 +191,6 @@ pub enum ProductType {
     Service,
 }
 
-impl Default for ProductType {
-    fn default() -> Self {
-        ProductType::Digital
-    }
-}
-
 /// A product in the catalog
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Product {
@@ -169,6 +202,10 @@ pub struct Product {
     #[serde(default = "default_site_id")]
     pub site_id: String,
 
+    /// Other sites this product is also sold on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shared_with: Vec<String>,
+
     /// Display name
     pub name: String,
 
@@ -179,13 +216,41 @@ pub struct Product {
     #[serde(default)]
     pub product_type: ProductType,
 
-    /// Price
+    /// Price (primary currency)
     pub price: Price,
 
+    /// Additional prices in other currencies (one per currency)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prices: Vec<Price>,
+
     /// Billing interval (for subscriptions)
     #[serde(default)]
     pub billing_interval: BillingInterval,
 
+    /// Free trial length in days (subscriptions only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trial_days: Option<u32>,
+
+    /// Whether the trial needs a payment method up front
+    #[serde(default)]
+    pub trial_payment_method: TrialPaymentMethod,
+
+    /// Discounted price for the first periods (subscriptions only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intro_price: Option<IntroPrice>,
+
+    /// Seat, tiered or metered billing (subscriptions only; None = licensed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<UsageModel>,
+
+    /// Option dimensions of the variants (e.g., license, delivery)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<ProductOption>,
+
+    /// Purchasable variants (empty = the product itself is purchased)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<ProductVariant>,
+
     /// Whether this product is active and available for purchase
     #[serde(default = "default_true")]
     pub active: bool,
@@ -213,11 +278,19 @@ impl Product {
         Self {
             id: id.into(),
             site_id: default_site_id(),
+            shared_with: Vec::new(),
             name: name.into(),
             description: String::new(),
             product_type: ProductType::Digital,
             price,
+            prices: Vec::new(),
             billing_interval: BillingInterval::OneTime,
+            trial_days: None,
+            trial_payment_method: TrialPaymentMethod::Always,
+            intro_price: None,
+            usage: None,
+            options: Vec::new(),
+            variants: Vec::new(),
             active: true,
             image_url: None,
             metadata: std::collections::HashMap::new(),
@@ -234,11 +307,19 @@ impl Product {
         Self {
             id: id.into(),
             site_id: default_site_id(),
+            shared_with: Vec::new(),
             name: name.into(),
             description: String::new(),
             product_type: ProductType::Subscription,
             price,
+            prices: Vec::new(),
             billing_interval: interval,
+            trial_days: None,
+            trial_payment_method: TrialPaymentMethod::Always,
+            intro_price: None,
+            usage: None,
+            options: Vec::new(),
+            variants: Vec::new(),
             active: true,
             image_url: None,
             metadata: std::collections::HashMap::new(),
@@ -251,6 +332,17 @@ impl Product {
         self
     }
 
+    /// Builder: also sell the product on another site
+    pub fn shared_with(mut self, site_id: impl Into<String>) -> Self {
+        self.shared_with.push(site_id.into());
+        self
+    }
+
+    /// Check if the product can be bought on a site
+    pub fn is_sold_on(&self, site_id: &str) -> bool {
+        self.site_id == site_id || self.shared_with.iter().any(|s| s == site_id)
+    }
+
     /// Builder: set description
     pub fn with_description(mut self, desc: impl Into<String>) -> Self {
         self.description = desc.into();
@@ -269,9 +361,73 @@ impl Product {
         self
     }
 
+    /// Builder: set a free trial
+    pub fn with_trial(mut self, days: u32, payment_method: TrialPaymentMethod) -> Self {
+        self.trial_days = Some(days);
+        self.trial_payment_method = payment_method;
+        self
+    }
+
+    /// Builder: set an introductory price
+    pub fn with_intro_price(mut self, intro: IntroPrice) -> Self {
+        self.intro_price = Some(intro);
+        self
+    }
+
+    /// Builder: set how usage is billed
+    pub fn with_usage(mut self, usage: UsageModel) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Builder: add a price in another currency (replaces any existing price in that currency)
+    pub fn with_price(mut self, price: Price) -> Self {
+        if price.currency == self.price.currency {
+            self.price = price;
+        } else {
+            self.prices.retain(|p| p.currency != price.currency);
+            self.prices.push(price);
+        }
+        self
+    }
+
+    /// Get the price in a specific currency, if this product is sold in it
+    pub fn price_in(&self, currency: Currency) -> Option<&Price> {
+        std::iter::once(&self.price)
+            .chain(self.prices.iter())
+            .find(|p| p.currency == currency)
+    }
+
+    /// All currencies this product can be sold in (primary first)
+    pub fn currencies(&self) -> impl Iterator<Item = Currency> + '_ {
+        std::iter::once(self.price.currency).chain(self.prices.iter().map(|p| p.currency))
+    }
+
+    /// Builder: add an option dimension
+    pub fn with_option(mut self, option: ProductOption) -> Self {
+        self.options.push(option);
+        self
+    }
+
+    /// Builder: add a variant
+    pub fn with_variant(mut self, variant: ProductVariant) -> Self {
+        self.variants.push(variant);
+        self
+    }
+
+    /// Find a variant by ID
+    pub fn variant(&self, id: &str) -> Option<&ProductVariant> {
+        self.variants.iter().find(|v| v.id == id)
+    }
+
+    /// Check if checkout must pick a variant
+    pub fn has_variants(&self) -> bool {
+        !self.variants.is_empty()
+    }
+
     /// Check if this is a subscription product
     pub fn is_subscription(&self) -> bool {
-        !matches!(self.billing_interval, BillingInterval::OneTime)
+        self.billing_interval.is_recurring()
     }
 }
 
@@ -299,11 +455,26 @@ impl ProductCatalog {
         self.products.iter().find(|p| p.id == id)
     }
 
-    /// Find a product by ID for a specific site
+    /// Find a product by ID for a specific site (its own or one it is shared with)
     pub fn get_for_site(&self, id: &str, site_id: &str) -> Option<&Product> {
         self.products
             .iter()
-            .find(|p| p.id == id && p.site_id == site_id)
+            .find(|p| p.id == id && p.is_sold_on(site_id))
+    }
+
+    /// Find a product by ID for a site, telling unknown products apart from
+    /// products of other sites
+    pub fn resolve_for_site(&self, id: &str, site_id: &str) -> PaymentResult<&Product> {
+        match self.get_for_site(id, site_id) {
+            Some(product) => Ok(product),
+            None if self.get(id).is_some() => Err(PaymentError::ProductNotOnSite {
+                product_id: id.to_string(),
+                site_id: site_id.to_string(),
+            }),
+            None => Err(PaymentError::ProductNotFound {
+                product_id: id.to_string(),
+            }),
+        }
     }
 
     /// Get all active products
@@ -315,7 +486,7 @@ impl ProductCatalog {
     pub fn active_products_for_site<'a>(&'a self, site_id: &'a str) -> impl Iterator<Item = &'a Product> {
         self.products
             .iter()
-            .filter(move |p| p.active && p.site_id == site_id)
+            .filter(move |p| p.active && p.is_sold_on(site_id))
     }
 
     /// Load catalog from TOML string
@@ -331,26 +502,94 @@ mod tests {
     #[test]
     fn test_currency_conversion() {
         let usd = Currency::USD;
-        assert_eq!(usd.to_smallest_unit(10.99), 1099);
-        assert_eq!(usd.from_smallest_unit(1099), 10.99);
+        assert_eq!(usd.to_smallest_unit("10.99".parse().unwrap()).unwrap(), 1099);
+        assert_eq!(usd.to_smallest_unit("0.29".parse().unwrap()).unwrap(), 29);
+        assert_eq!(usd.from_smallest_unit(1099), "10.99".parse().unwrap());
+        assert!(usd.to_smallest_unit("10.999".parse().unwrap()).is_err());
 
         let jpy = Currency::JPY;
-        assert_eq!(jpy.to_smallest_unit(1000.0), 1000);
-        assert_eq!(jpy.from_smallest_unit(1000), 1000.0);
+        assert_eq!(jpy.to_smallest_unit("1000".parse().unwrap()).unwrap(), 1000);
+        assert_eq!(jpy.from_smallest_unit(1000), Money::from_major_units(1000));
+        assert!(jpy.to_smallest_unit("1000.5".parse().unwrap()).is_err());
+
+        let bhd = Currency::BHD;
+        assert_eq!(bhd.to_smallest_unit("12.345".parse().unwrap()).unwrap(), 12345);
+        assert_eq!(bhd.from_smallest_unit(12345), "12.345".parse().unwrap());
+    }
+
+    #[test]
+    fn test_price_checked_arithmetic() {
+        let price = Price::from_cents(1999, Currency::USD);
+        assert_eq!(price.checked_mul(3).unwrap().amount, 5997);
+        assert_eq!(
+            price.checked_add(&Price::from_cents(1, Currency::USD)).unwrap().amount,
+            2000
+        );
+
+        assert!(Price::from_cents(i64::MAX, Currency::USD).checked_mul(2).is_err());
+        assert!(price.checked_add(&Price::from_cents(i64::MAX, Currency::USD)).is_err());
+        assert!(price.checked_add(&Price::from_cents(100, Currency::EUR)).is_err());
+    }
+
+    #[test]
+    fn test_price_deserialize_decimal_or_minor() {
+        let minor: Price = toml::from_str("amount = 1999\ncurrency = \"usd\"").unwrap();
+        let decimal: Price = toml::from_str("amount = \"19.99\"\ncurrency = \"usd\"").unwrap();
+        assert_eq!(minor, decimal);
+
+        let too_precise = toml::from_str::<Price>("amount = \"19.999\"\ncurrency = \"usd\"");
+        assert!(too_precise.is_err());
     }
 
     #[test]
     fn test_price_display() {
-        let price = Price::new(29.99, Currency::USD);
+        let price = Price::parse("29.99", Currency::USD).unwrap();
         assert_eq!(price.display(), "$29.99");
 
-        let price_eur = Price::new(19.99, Currency::EUR);
+        let price_eur = Price::parse("19.99", Currency::EUR).unwrap();
         assert_eq!(price_eur.display(), "€19.99");
+
+        assert_eq!(Price::from_cents(1000, Currency::JPY).display(), "¥1,000");
+        assert_eq!(Price::from_cents(50000, Currency::KRW).display(), "₩50,000");
+        assert_eq!(Price::from_cents(1234, Currency::KWD).display(), "KD 1.234");
+        assert_eq!(Price::from_cents(1999, Currency::SEK).display(), "19.99 kr");
+        assert_eq!(Price::from_cents(1999, Currency::CHF).display(), "CHF 19.99");
+    }
+
+    #[test]
+    fn test_trial_and_intro_from_toml() {
+        let catalog = ProductCatalog::from_toml(
+            r#"
+            [[products]]
+            id = "saas"
+            name = "SaaS"
+            description = ""
+            billing_interval = "monthly"
+            trial_days = 14
+            trial_payment_method = "if_required"
+
+            [products.price]
+            amount = 2900
+            currency = "usd"
+
+            [products.intro_price]
+            periods = 3
+            price = { amount = "9.00", currency = "usd" }
+            "#,
+        )
+        .unwrap();
+        let product = catalog.get("saas").unwrap();
+        assert_eq!(product.trial_days, Some(14));
+        assert_eq!(product.trial_payment_method, TrialPaymentMethod::IfRequired);
+        let intro = product.intro_price.as_ref().unwrap();
+        assert_eq!(intro.periods, 3);
+        assert_eq!(intro.price_in(Currency::USD).unwrap().amount, 900);
+        assert!(intro.price_in(Currency::EUR).is_none());
     }
 
     #[test]
     fn test_product_builder() {
-        let product = Product::one_time("test-product", "Test Product", Price::new(9.99, Currency::USD))
+        let product = Product::one_time("test-product", "Test Product", Price::parse("9.99", Currency::USD).unwrap())
             .with_description("A test product")
             .with_site("spokenhope")
             .with_metadata("tier", "pro");
@@ -367,25 +606,71 @@ mod tests {
         let product = Product::subscription(
             "api-pro",
             "API Pro Plan",
-            Price::new(29.0, Currency::USD),
-            BillingInterval::Monthly,
+            Price::parse("29.0", Currency::USD).unwrap(),
+            BillingInterval::MONTHLY,
         );
 
         assert!(product.is_subscription());
-        assert_eq!(product.billing_interval, BillingInterval::Monthly);
+        assert_eq!(product.billing_interval, BillingInterval::MONTHLY);
         assert_eq!(product.site_id, "chargegun"); // default
     }
 
+    #[test]
+    fn test_multi_currency_prices() {
+        let product = Product::one_time("multi", "Multi", Price::from_cents(1999, Currency::USD))
+            .with_price(Price::from_cents(1899, Currency::EUR))
+            .with_price(Price::from_cents(1599, Currency::GBP))
+            .with_price(Price::from_cents(1799, Currency::EUR));
+
+        assert_eq!(product.price_in(Currency::USD).unwrap().amount, 1999);
+        assert_eq!(product.price_in(Currency::EUR).unwrap().amount, 1799);
+        assert_eq!(product.price_in(Currency::GBP).unwrap().amount, 1599);
+        assert!(product.price_in(Currency::JPY).is_none());
+        assert_eq!(
+            product.currencies().collect::<Vec<_>>(),
+            vec![Currency::USD, Currency::GBP, Currency::EUR]
+        );
+    }
+
+    #[test]
+    fn test_catalog_multi_currency_toml() {
+        let catalog = ProductCatalog::from_toml(
+            r#"
+            [[products]]
+            id = "multi"
+            name = "Multi"
+            description = "Sold in three currencies"
+
+            [products.price]
+            amount = 1999
+            currency = "usd"
+
+            [[products.prices]]
+            amount = "18.99"
+            currency = "eur"
+
+            [[products.prices]]
+            amount = 1599
+            currency = "gbp"
+            "#,
+        )
+        .unwrap();
+
+        let product = catalog.get("multi").unwrap();
+        assert_eq!(product.price_in(Currency::EUR).unwrap().amount, 1899);
+        assert_eq!(product.price_in(Currency::GBP).unwrap().amount, 1599);
+    }
+
     #[test]
     fn test_catalog_site_filtering() {
         let mut catalog = ProductCatalog::new();
         
         catalog.add(
-            Product::one_time("prod-a", "Product A", Price::new(10.0, Currency::USD))
+            Product::one_time("prod-a", "Product A", Price::parse("10.0", Currency::USD).unwrap())
                 .with_site("chargegun")
         );
         catalog.add(
-            Product::one_time("prod-b", "Product B", Price::new(20.0, Currency::USD))
+            Product::one_time("prod-b", "Product B", Price::parse("20.0", Currency::USD).unwrap())
                 .with_site("spokenhope")
         );
 
@@ -396,5 +681,21 @@ mod tests {
         let spokenhope_products: Vec<_> = catalog.active_products_for_site("spokenhope").collect();
         assert_eq!(spokenhope_products.len(), 1);
         assert_eq!(spokenhope_products[0].id, "prod-b");
+
+        catalog.add(
+            Product::one_time("bundle", "Bundle", Price::parse("30.0", Currency::USD).unwrap())
+                .with_site("chargegun")
+                .shared_with("spokenhope"),
+        );
+        assert_eq!(catalog.active_products_for_site("spokenhope").count(), 2);
+        assert_eq!(catalog.resolve_for_site("bundle", "spokenhope").unwrap().id, "bundle");
+        assert!(matches!(
+            catalog.resolve_for_site("prod-a", "spokenhope"),
+            Err(PaymentError::ProductNotOnSite { .. })
+        ));
+        assert!(matches!(
+            catalog.resolve_for_site("nope", "spokenhope"),
+            Err(PaymentError::ProductNotFound { .. })
+        ));
     }
 }
@@ -0,0 +1,504 @@
+//! # Promotions
+//!
+//! Discount codes for lightning-cart.
+//! Promotions are loaded from `config/promotions.toml`.
+//!
+//! A promotion computes a single discount amount for an order. Every kind of
+//! discount applies to the first payment only: for subscriptions the
+//! recurring price is unchanged after the first invoice.
+//!
+//! Redemptions are recorded per order in a `RedemptionStore` (in-memory here,
+//! SQLite in `pay-sqlite`): a redemption is taken when the checkout is created
+//! and released if the checkout fails or expires unpaid.
+
+use crate::error::{PaymentError, PaymentResult};
+use crate::order::{LineItem, Order};
+use crate::product::{BillingInterval, Price};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Kind of discount a promotion grants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DiscountKind {
+    /// Percentage off eligible items (1-100)
+    PercentOff { percent: u8 },
+    /// Fixed amount off eligible items (currency must match the order)
+    AmountOff { amount: Price },
+    /// Buy `buy` units of an item, get `get` more of the same item free
+    BuyXGetY { buy: u32, get: u32 },
+    /// First month of eligible monthly subscriptions free
+    FreeFirstMonth,
+}
+
+/// A discount code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Promotion {
+    /// Code customers enter (matched case-insensitively)
+    pub code: String,
+
+    /// Description shown to customers
+    #[serde(default)]
+    pub description: String,
+
+    /// Discount granted
+    #[serde(flatten)]
+    pub kind: DiscountKind,
+
+    /// Sites this code is valid on (empty = all sites)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub site_ids: Vec<String>,
+
+    /// Products the discount applies to (empty = all products)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub product_ids: Vec<String>,
+
+    /// Expiry (RFC 3339 string in TOML, e.g. "2026-12-31T23:59:59Z")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// Maximum number of redemptions (None = unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_redemptions: Option<u32>,
+
+    /// Minimum order subtotal (currency must match the order)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_order_amount: Option<Price>,
+
+    /// Whether this code can be redeemed
+    #[serde(default = "default_true")]
+    pub active: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A discount applied to an order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppliedDiscount {
+    /// Promotion code
+    pub code: String,
+
+    /// Description shown to customers
+    #[serde(default)]
+    pub description: String,
+
+    /// Discount amount (in the order currency)
+    pub amount: Price,
+}
+
+impl Promotion {
+    /// Create a new promotion
+    pub fn new(code: impl Into<String>, kind: DiscountKind) -> Self {
+        Self {
+            code: code.into(),
+            description: String::new(),
+            kind,
+            site_ids: Vec::new(),
+            product_ids: Vec::new(),
+            expires_at: None,
+            max_redemptions: None,
+            min_order_amount: None,
+            active: true,
+        }
+    }
+
+    /// Builder: set description
+    pub fn with_description(mut self, desc: impl Into<String>) -> Self {
+        self.description = desc.into();
+        self
+    }
+
+    /// Builder: restrict to a site
+    pub fn with_site(mut self, site_id: impl Into<String>) -> Self {
+        self.site_ids.push(site_id.into());
+        self
+    }
+
+    /// Builder: restrict to a product
+    pub fn with_product(mut self, product_id: impl Into<String>) -> Self {
+        self.product_ids.push(product_id.into());
+        self
+    }
+
+    /// Builder: set expiry
+    pub fn with_expiry(mut self, expires_at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Builder: set usage cap
+    pub fn with_max_redemptions(mut self, max: u32) -> Self {
+        self.max_redemptions = Some(max);
+        self
+    }
+
+    /// Builder: set minimum order amount
+    pub fn with_min_order_amount(mut self, amount: Price) -> Self {
+        self.min_order_amount = Some(amount);
+        self
+    }
+
+    /// Check if this promotion is valid on a site
+    pub fn applies_to_site(&self, site_id: Option<&str>) -> bool {
+        self.site_ids.is_empty()
+            || site_id.is_some_and(|id| self.site_ids.iter().any(|s| s == id))
+    }
+
+    /// Check if this promotion covers a product
+    pub fn applies_to_product(&self, product_id: &str) -> bool {
+        self.product_ids.is_empty() || self.product_ids.iter().any(|p| p == product_id)
+    }
+
+    /// Compute the discount for an order.
+    ///
+    /// `times_redeemed` is the number of times this code has been used so far.
+    pub fn evaluate(
+        &self,
+        order: &Order,
+        site_id: Option<&str>,
+        now: DateTime<Utc>,
+        times_redeemed: u32,
+    ) -> PaymentResult<AppliedDiscount> {
+        if !self.active {
+            return Err(self.rejected("code is not active"));
+        }
+        if self.expires_at.is_some_and(|exp| exp <= now) {
+            return Err(self.rejected("code has expired"));
+        }
+        if !self.applies_to_site(site_id) {
+            return Err(self.rejected("code is not valid on this site"));
+        }
+        if self.max_redemptions.is_some_and(|max| times_redeemed >= max) {
+            return Err(self.rejected("code has reached its usage limit"));
+        }
+
+        let subtotal = order.subtotal()?;
+        if let Some(ref min) = self.min_order_amount {
+            if min.currency != subtotal.currency || subtotal.amount < min.amount {
+                return Err(self.rejected(&format!(
+                    "order must be at least {}",
+                    min.display()
+                )));
+            }
+        }
+
+        let eligible: Vec<&LineItem> = order
+            .line_items
+            .iter()
+            .filter(|item| self.applies_to_product(&item.product_id))
+            .collect();
+        if eligible.is_empty() {
+            return Err(self.rejected("no items in the order qualify"));
+        }
+
+        let eligible_total = eligible
+            .iter()
+            .try_fold(Price::zero(order.currency), |acc, item| {
+                acc.checked_add(&item.total()?)
+            })?;
+
+        let amount = match &self.kind {
+            DiscountKind::PercentOff { percent } => {
+                if *percent == 0 || *percent > 100 {
+                    return Err(self.rejected("percentage must be between 1 and 100"));
+                }
+                // Round half up to the nearest minor unit
+                let raw = eligible_total.amount as i128 * *percent as i128;
+                ((raw + 50) / 100) as i64
+            }
+            DiscountKind::AmountOff { amount } => {
+                if amount.currency != order.currency {
+                    return Err(self.rejected(&format!(
+                        "code is only valid for {} orders",
+                        amount.currency
+                    )));
+                }
+                amount.amount.min(eligible_total.amount)
+            }
+            DiscountKind::BuyXGetY { buy, get } => {
+                let bundle = buy.saturating_add(*get);
+                if *get == 0 || bundle == 0 {
+                    return Err(self.rejected("invalid buy/get quantities"));
+                }
+                eligible.iter().try_fold(0_i64, |acc, item| {
+                    let free = (item.quantity / bundle) * get;
+                    let discount = item.unit_price.checked_mul(free)?;
+                    acc.checked_add(discount.amount).ok_or_else(|| {
+                        PaymentError::InvalidPrice {
+                            message: "amount overflow in discount".to_string(),
+                        }
+                    })
+                })?
+            }
+            DiscountKind::FreeFirstMonth => eligible
+                .iter()
+                .filter(|item| item.billing_interval == BillingInterval::MONTHLY)
+                .try_fold(Price::zero(order.currency), |acc, item| {
+                    acc.checked_add(&item.total()?)
+                })?
+                .amount,
+        };
+
+        if amount <= 0 {
+            return Err(self.rejected("no discount applies to this order"));
+        }
+
+        Ok(AppliedDiscount {
+            code: self.code.clone(),
+            description: self.description.clone(),
+            amount: Price::from_cents(amount, order.currency),
+        })
+    }
+
+    fn rejected(&self, reason: &str) -> PaymentError {
+        PaymentError::PromotionNotApplicable {
+            code: self.code.clone(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Promotion catalog (loaded from config)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromotionCatalog {
+    #[serde(default)]
+    pub promotions: Vec<Promotion>,
+}
+
+impl PromotionCatalog {
+    /// Create an empty catalog
+    pub fn new() -> Self {
+        Self {
+            promotions: Vec::new(),
+        }
+    }
+
+    /// Add a promotion
+    pub fn add(&mut self, promotion: Promotion) {
+        self.promotions.push(promotion);
+    }
+
+    /// Find a promotion by code (case-insensitive)
+    pub fn get(&self, code: &str) -> Option<&Promotion> {
+        let code = code.trim();
+        self.promotions
+            .iter()
+            .find(|p| p.code.eq_ignore_ascii_case(code))
+    }
+
+    /// Load catalog from TOML string
+    pub fn from_toml(toml_str: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(toml_str)
+    }
+}
+
+/// Storage for promotion redemptions, one per order
+#[async_trait]
+pub trait RedemptionStore: Send + Sync {
+    /// Number of orders currently holding a redemption of a code
+    async fn count(&self, code: &str) -> PaymentResult<u32>;
+
+    /// Record that an order redeems a code, unless `limit` orders already hold one.
+    ///
+    /// Returns `false` if the limit was reached. Reserving again for the same
+    /// order is a no-op.
+    async fn reserve(&self, code: &str, order_id: &str, limit: Option<u32>) -> PaymentResult<bool>;
+
+    /// Give back an order's redemption (no-op if it holds none)
+    async fn release(&self, order_id: &str) -> PaymentResult<()>;
+}
+
+/// Shared redemption store
+pub type BoxedRedemptionStore = Arc<dyn RedemptionStore>;
+
+/// In-memory redemption store (contents are lost on restart)
+#[derive(Debug, Default)]
+pub struct InMemoryRedemptionStore {
+    /// Code per order ID
+    redemptions: RwLock<HashMap<String, String>>,
+}
+
+impl InMemoryRedemptionStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn poisoned<T>(_: T) -> PaymentError {
+    PaymentError::Storage("redemption store lock poisoned".to_string())
+}
+
+#[async_trait]
+impl RedemptionStore for InMemoryRedemptionStore {
+    async fn count(&self, code: &str) -> PaymentResult<u32> {
+        let redemptions = self.redemptions.read().map_err(poisoned)?;
+        Ok(redemptions.values().filter(|c| *c == code).count() as u32)
+    }
+
+    async fn reserve(&self, code: &str, order_id: &str, limit: Option<u32>) -> PaymentResult<bool> {
+        let mut redemptions = self.redemptions.write().map_err(poisoned)?;
+        if redemptions.contains_key(order_id) {
+            return Ok(true);
+        }
+        let used = redemptions.values().filter(|c| *c == code).count() as u32;
+        if limit.is_some_and(|max| used >= max) {
+            return Ok(false);
+        }
+        redemptions.insert(order_id.to_string(), code.to_string());
+        Ok(true)
+    }
+
+    async fn release(&self, order_id: &str) -> PaymentResult<()> {
+        self.redemptions.write().map_err(poisoned)?.remove(order_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::product::{Currency, Product};
+    use chrono::Duration;
+
+    fn order_with(products: &[(&Product, u32)]) -> Order {
+        let mut order = Order::new(Currency::USD);
+        for (product, qty) in products {
+            order.add_product(product, *qty).unwrap();
+        }
+        order
+    }
+
+    fn usd(cents: i64) -> Price {
+        Price::from_cents(cents, Currency::USD)
+    }
+
+    #[test]
+    fn test_percent_off_scoped() {
+        let a = Product::one_time("a", "A", usd(1000));
+        let b = Product::one_time("b", "B", usd(2999));
+        let order = order_with(&[(&a, 1), (&b, 1)]);
+
+        let promo = Promotion::new("SAVE15", DiscountKind::PercentOff { percent: 15 }).with_product("b");
+        let discount = promo.evaluate(&order, None, Utc::now(), 0).unwrap();
+        assert_eq!(discount.amount, usd(450)); // 15% of 29.99 = 4.4985 → 4.50
+    }
+
+    #[test]
+    fn test_amount_off_capped_and_currency_checked() {
+        let a = Product::one_time("a", "A", usd(500));
+        let order = order_with(&[(&a, 1)]);
+
+        let promo = Promotion::new("TENOFF", DiscountKind::AmountOff { amount: usd(1000) });
+        assert_eq!(promo.evaluate(&order, None, Utc::now(), 0).unwrap().amount, usd(500));
+
+        let eur = Promotion::new("EUR10", DiscountKind::AmountOff {
+            amount: Price::from_cents(1000, Currency::EUR),
+        });
+        assert!(eur.evaluate(&order, None, Utc::now(), 0).is_err());
+    }
+
+    #[test]
+    fn test_buy_x_get_y() {
+        let a = Product::one_time("a", "A", usd(1000));
+        let promo = Promotion::new("B2G1", DiscountKind::BuyXGetY { buy: 2, get: 1 });
+
+        let order = order_with(&[(&a, 7)]);
+        assert_eq!(promo.evaluate(&order, None, Utc::now(), 0).unwrap().amount, usd(2000));
+
+        let order = order_with(&[(&a, 2)]);
+        assert!(promo.evaluate(&order, None, Utc::now(), 0).is_err());
+    }
+
+    #[test]
+    fn test_free_first_month() {
+        let monthly = Product::subscription("m", "Monthly", usd(2900), BillingInterval::MONTHLY);
+        let yearly = Product::subscription("y", "Yearly", usd(29000), BillingInterval::YEARLY);
+        let promo = Promotion::new("FIRSTFREE", DiscountKind::FreeFirstMonth);
+
+        let order = order_with(&[(&monthly, 1)]);
+        assert_eq!(promo.evaluate(&order, None, Utc::now(), 0).unwrap().amount, usd(2900));
+
+        let order = order_with(&[(&yearly, 1)]);
+        assert!(promo.evaluate(&order, None, Utc::now(), 0).is_err());
+    }
+
+    #[test]
+    fn test_restrictions() {
+        let a = Product::one_time("a", "A", usd(1000));
+        let order = order_with(&[(&a, 1)]);
+        let now = Utc::now();
+        let base = Promotion::new("X", DiscountKind::PercentOff { percent: 10 });
+
+        let expired = base.clone().with_expiry(now - Duration::days(1));
+        assert!(expired.evaluate(&order, None, now, 0).is_err());
+
+        let site_only = base.clone().with_site("spokenhope");
+        assert!(site_only.evaluate(&order, Some("chargegun"), now, 0).is_err());
+        assert!(site_only.evaluate(&order, Some("spokenhope"), now, 0).is_ok());
+
+        let capped = base.clone().with_max_redemptions(3);
+        assert!(capped.evaluate(&order, None, now, 2).is_ok());
+        assert!(matches!(
+            capped.evaluate(&order, None, now, 3),
+            Err(PaymentError::PromotionNotApplicable { .. })
+        ));
+
+        let minimum = base.clone().with_min_order_amount(usd(5000));
+        assert!(minimum.evaluate(&order, None, now, 0).is_err());
+
+        let other_product = base.with_product("b");
+        assert!(other_product.evaluate(&order, None, now, 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_redemption_limit() {
+        let store = InMemoryRedemptionStore::new();
+        assert!(store.reserve("LAUNCH20", "order_1", Some(2)).await.unwrap());
+        assert!(store.reserve("LAUNCH20", "order_1", Some(2)).await.unwrap());
+        assert!(store.reserve("LAUNCH20", "order_2", Some(2)).await.unwrap());
+        assert!(!store.reserve("LAUNCH20", "order_3", Some(2)).await.unwrap());
+        assert_eq!(store.count("LAUNCH20").await.unwrap(), 2);
+
+        // An expired checkout frees its redemption
+        store.release("order_1").await.unwrap();
+        store.release("order_1").await.unwrap();
+        assert!(store.reserve("LAUNCH20", "order_3", Some(2)).await.unwrap());
+        assert_eq!(store.count("LAUNCH20").await.unwrap(), 2);
+    }
+
+    #[test]
+    fn test_catalog_from_toml() {
+        let catalog = PromotionCatalog::from_toml(
+            r#"
+            [[promotions]]
+            code = "LAUNCH20"
+            type = "percent_off"
+            percent = 20
+            site_ids = ["chargegun"]
+            expires_at = "2030-01-01T00:00:00Z"
+            max_redemptions = 100
+
+            [[promotions]]
+            code = "FIVEOFF"
+            type = "amount_off"
+            amount = { amount = 500, currency = "usd" }
+            min_order_amount = { amount = "20.00", currency = "usd" }
+            "#,
+        )
+        .unwrap();
+
+        let launch = catalog.get("launch20").unwrap();
+        assert_eq!(launch.kind, DiscountKind::PercentOff { percent: 20 });
+        assert_eq!(launch.max_redemptions, Some(100));
+
+        let five = catalog.get("FIVEOFF").unwrap();
+        assert_eq!(five.min_order_amount, Some(usd(2000)));
+        assert!(catalog.get("nope").is_none());
+    }
+}
@@ -0,0 +1,281 @@
+//! # Webhook Queue
+//!
+//! Durable queue of verified webhook events.
+//!
+//! The webhook endpoint enqueues each event and answers the provider right
+//! away; workers claim due jobs, run the handlers and either complete the
+//! job, schedule a retry with exponential backoff (`RetryPolicy`), or move it
+//! to the dead-letter list once its attempts are used up.
+//!
+//! A claimed job is leased for `JOB_LEASE_SECS`; if the worker dies, the job
+//! becomes due again when the lease runs out.
+
+use crate::error::{PaymentError, PaymentResult};
+use crate::order::WebhookEvent;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// How long a claimed job stays invisible to other workers
+pub const JOB_LEASE_SECS: i64 = 300;
+
+/// When and how often failed jobs are retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts before a job is dead-lettered
+    pub max_attempts: u32,
+
+    /// Delay before the first retry (seconds)
+    pub base_delay_secs: i64,
+
+    /// Upper bound for the delay (seconds)
+    pub max_delay_secs: i64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            base_delay_secs: 10,
+            max_delay_secs: 3600,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Set the total number of attempts
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Delay before retrying a job that has failed `attempts` times
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(30);
+        let delay = self
+            .base_delay_secs
+            .saturating_mul(1i64 << exponent)
+            .min(self.max_delay_secs);
+        Duration::seconds(delay)
+    }
+
+    /// When to retry a job that has failed `attempts` times (None = give up)
+    pub fn next_attempt(&self, attempts: u32, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        (attempts < self.max_attempts).then(|| now + self.backoff(attempts))
+    }
+}
+
+/// A queued webhook event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookJob {
+    /// Job ID
+    pub id: i64,
+
+    /// The verified event
+    pub event: WebhookEvent,
+
+    /// Attempts started so far
+    pub attempts: u32,
+
+    /// When the job is next due
+    pub run_at: DateTime<Utc>,
+
+    /// Error of the last failed attempt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+
+    /// When the job was enqueued
+    pub created_at: DateTime<Utc>,
+}
+
+/// Storage for webhook jobs
+#[async_trait]
+pub trait WebhookQueue: Send + Sync {
+    /// Add an event to the queue, due immediately
+    async fn enqueue(&self, event: &WebhookEvent) -> PaymentResult<WebhookJob>;
+
+    /// Claim the oldest due job, counting an attempt and leasing it to the caller
+    async fn claim(&self, now: DateTime<Utc>) -> PaymentResult<Option<WebhookJob>>;
+
+    /// Remove a successfully processed job
+    async fn complete(&self, job_id: i64) -> PaymentResult<()>;
+
+    /// Record a failed attempt: retry at `retry_at`, or dead-letter the job if None
+    async fn fail(
+        &self,
+        job_id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> PaymentResult<()>;
+
+    /// Jobs that used up their attempts, oldest first
+    async fn dead_letters(&self) -> PaymentResult<Vec<WebhookJob>>;
+}
+
+/// Shared webhook queue
+pub type BoxedWebhookQueue = Arc<dyn WebhookQueue>;
+
+#[derive(Debug, Default)]
+struct MemoryQueue {
+    next_id: i64,
+    jobs: BTreeMap<i64, (WebhookJob, Option<DateTime<Utc>>)>,
+    dead: Vec<WebhookJob>,
+}
+
+/// In-memory webhook queue (contents are lost on restart)
+#[derive(Debug, Default)]
+pub struct InMemoryWebhookQueue {
+    inner: Mutex<MemoryQueue>,
+}
+
+impl InMemoryWebhookQueue {
+    /// Create an empty queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> PaymentResult<MutexGuard<'_, MemoryQueue>> {
+        self.inner
+            .lock()
+            .map_err(|_| PaymentError::Storage("webhook queue lock poisoned".to_string()))
+    }
+}
+
+#[async_trait]
+impl WebhookQueue for InMemoryWebhookQueue {
+    async fn enqueue(&self, event: &WebhookEvent) -> PaymentResult<WebhookJob> {
+        let mut queue = self.lock()?;
+        queue.next_id += 1;
+        let now = Utc::now();
+        let job = WebhookJob {
+            id: queue.next_id,
+            event: event.clone(),
+            attempts: 0,
+            run_at: now,
+            last_error: None,
+            created_at: now,
+        };
+        queue.jobs.insert(job.id, (job.clone(), None));
+        Ok(job)
+    }
+
+    async fn claim(&self, now: DateTime<Utc>) -> PaymentResult<Option<WebhookJob>> {
+        let mut queue = self.lock()?;
+        let due = queue
+            .jobs
+            .values_mut()
+            .filter(|(job, leased)| {
+                job.run_at <= now && leased.map_or(true, |until| until <= now)
+            })
+            .min_by_key(|(job, _)| (job.run_at, job.id));
+
+        Ok(due.map(|(job, leased)| {
+            job.attempts += 1;
+            *leased = Some(now + Duration::seconds(JOB_LEASE_SECS));
+            job.clone()
+        }))
+    }
+
+    async fn complete(&self, job_id: i64) -> PaymentResult<()> {
+        self.lock()?.jobs.remove(&job_id);
+        Ok(())
+    }
+
+    async fn fail(
+        &self,
+        job_id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> PaymentResult<()> {
+        let mut queue = self.lock()?;
+        let (mut job, _) = queue.jobs.remove(&job_id).ok_or_else(|| {
+            PaymentError::Storage(format!("webhook job {} not found", job_id))
+        })?;
+        job.last_error = Some(error.to_string());
+        match retry_at {
+            Some(run_at) => {
+                job.run_at = run_at;
+                queue.jobs.insert(job_id, (job, None));
+            }
+            None => queue.dead.push(job),
+        }
+        Ok(())
+    }
+
+    async fn dead_letters(&self) -> PaymentResult<Vec<WebhookJob>> {
+        Ok(self.lock()?.dead.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::WebhookEventType;
+
+    fn event(id: &str) -> WebhookEvent {
+        WebhookEvent {
+            event_id: id.into(),
+            event_type: WebhookEventType::PaymentSucceeded,
+            provider: "stripe".into(),
+            session_id: None,
+            payment_intent_id: None,
+            customer_email: None,
+            amount_paid: None,
+            currency: None,
+            raw_data: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let policy = RetryPolicy::default().with_max_attempts(3);
+        assert_eq!(policy.backoff(1), Duration::seconds(10));
+        assert_eq!(policy.backoff(2), Duration::seconds(20));
+        assert_eq!(policy.backoff(3), Duration::seconds(40));
+        assert_eq!(policy.backoff(40), Duration::seconds(3600));
+
+        let now = Utc::now();
+        assert_eq!(policy.next_attempt(2, now), Some(now + Duration::seconds(20)));
+        assert_eq!(policy.next_attempt(3, now), None);
+    }
+
+    #[tokio::test]
+    async fn test_claim_retry_and_dead_letter() {
+        let queue = InMemoryWebhookQueue::new();
+        let job = queue.enqueue(&event("evt_1")).await.unwrap();
+        let now = Utc::now();
+
+        let claimed = queue.claim(now).await.unwrap().unwrap();
+        assert_eq!((claimed.id, claimed.attempts), (job.id, 1));
+        // Leased jobs are not handed out twice
+        assert!(queue.claim(now).await.unwrap().is_none());
+
+        let retry_at = now + Duration::seconds(10);
+        queue.fail(job.id, "downstream timeout", Some(retry_at)).await.unwrap();
+        assert!(queue.claim(now).await.unwrap().is_none());
+        let retried = queue.claim(retry_at).await.unwrap().unwrap();
+        assert_eq!(retried.attempts, 2);
+        assert_eq!(retried.last_error.as_deref(), Some("downstream timeout"));
+
+        queue.fail(job.id, "still down", None).await.unwrap();
+        assert!(queue.claim(retry_at).await.unwrap().is_none());
+        let dead = queue.dead_letters().await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].event.event_id, "evt_1");
+    }
+
+    #[tokio::test]
+    async fn test_expired_lease_is_reclaimed() {
+        let queue = InMemoryWebhookQueue::new();
+        queue.enqueue(&event("evt_1")).await.unwrap();
+        let now = Utc::now();
+
+        queue.claim(now).await.unwrap().unwrap();
+        let later = now + Duration::seconds(JOB_LEASE_SECS);
+        assert_eq!(queue.claim(later).await.unwrap().unwrap().attempts, 2);
+    }
+}
@@ -0,0 +1,204 @@
+//! # Receipts
+//!
+//! Read-only view of an order for status pages and the lookup endpoints.
+//!
+//! A receipt is built from a `StoredOrder` when the order store has it, or by
+//! the payment provider from its own copy of the checkout session
+//! (`PaymentStrategy::retrieve_checkout`).
+
+use crate::error::PaymentResult;
+use crate::lifecycle::OrderStatus;
+use crate::order::{LineItem, OrderTotals};
+use crate::product::{Currency, Price};
+use crate::repository::StoredOrder;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Payment state of an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentStatus {
+    /// Not paid (yet)
+    Unpaid,
+    /// Last payment attempt failed
+    Failed,
+    /// Paid in full
+    Paid,
+    /// Part of the payment refunded
+    PartiallyRefunded,
+    /// Payment fully refunded
+    Refunded,
+    /// Payment disputed by the customer
+    Disputed,
+}
+
+impl From<OrderStatus> for PaymentStatus {
+    fn from(status: OrderStatus) -> Self {
+        match status {
+            OrderStatus::Pending | OrderStatus::AwaitingPayment | OrderStatus::Expired => {
+                PaymentStatus::Unpaid
+            }
+            OrderStatus::Failed => PaymentStatus::Failed,
+            OrderStatus::Paid | OrderStatus::Fulfilled => PaymentStatus::Paid,
+            OrderStatus::PartiallyRefunded => PaymentStatus::PartiallyRefunded,
+            OrderStatus::Refunded => PaymentStatus::Refunded,
+            OrderStatus::Disputed => PaymentStatus::Disputed,
+        }
+    }
+}
+
+/// Fulfillment state of an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FulfillmentStatus {
+    /// Not delivered yet
+    #[default]
+    Unfulfilled,
+    /// Delivered to the customer
+    Fulfilled,
+}
+
+/// A line on a receipt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptLine {
+    /// Product name ("Product – Variant" for variants)
+    pub name: String,
+
+    /// Quantity
+    pub quantity: u32,
+
+    /// Unit price
+    pub unit_price: Price,
+
+    /// Line total (unit price × quantity)
+    pub amount: Price,
+}
+
+impl ReceiptLine {
+    /// Create a receipt line from an order line item
+    pub fn from_line_item(item: &LineItem) -> PaymentResult<Self> {
+        Ok(Self {
+            name: item.display_name(),
+            quantity: item.quantity,
+            unit_price: item.unit_price.clone(),
+            amount: item.total()?,
+        })
+    }
+}
+
+/// An order as shown to the customer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderReceipt {
+    /// Our order ID (None if the provider session carries no order ID)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+
+    /// Site the order was placed on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_id: Option<String>,
+
+    /// Provider's checkout session ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+
+    /// Lifecycle status
+    pub status: OrderStatus,
+
+    /// Payment state
+    pub payment_status: PaymentStatus,
+
+    /// Fulfillment state
+    pub fulfillment_status: FulfillmentStatus,
+
+    /// Customer email
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer_email: Option<String>,
+
+    /// Order currency
+    pub currency: Currency,
+
+    /// Purchased items
+    pub line_items: Vec<ReceiptLine>,
+
+    /// Subtotal, discount, tax and total
+    pub totals: OrderTotals,
+
+    /// When the order was placed
+    pub created_at: DateTime<Utc>,
+}
+
+impl OrderReceipt {
+    /// Build a receipt from a stored order
+    pub fn from_stored(stored: &StoredOrder) -> PaymentResult<Self> {
+        let order = &stored.order;
+        let fulfilled = stored
+            .history
+            .iter()
+            .any(|change| change.to == OrderStatus::Fulfilled);
+
+        Ok(Self {
+            order_id: Some(order.id.clone()),
+            site_id: stored.site_id.clone(),
+            session_id: stored.session.as_ref().map(|s| s.session_id.clone()),
+            status: stored.status,
+            payment_status: stored.status.into(),
+            fulfillment_status: if fulfilled {
+                FulfillmentStatus::Fulfilled
+            } else {
+                FulfillmentStatus::Unfulfilled
+            },
+            customer_email: order.customer_email.clone(),
+            currency: order.currency,
+            line_items: order
+                .line_items
+                .iter()
+                .map(ReceiptLine::from_line_item)
+                .collect::<PaymentResult<_>>()?,
+            totals: order.totals()?,
+            created_at: order.created_at,
+        })
+    }
+
+    /// Check if the order has been paid (refunds and disputes included)
+    pub fn is_paid(&self) -> bool {
+        !matches!(
+            self.payment_status,
+            PaymentStatus::Unpaid | PaymentStatus::Failed
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifecycle::OrderTrigger;
+    use crate::order::Order;
+    use crate::product::Product;
+
+    #[test]
+    fn test_receipt_from_stored_order() {
+        let mut order = Order::new(Currency::USD).with_email("buyer@example.com");
+        let product = Product::one_time("kit", "Starter Kit", Price::from_cents(1250, Currency::USD));
+        order.add_product(&product, 2).unwrap();
+
+        let mut stored = StoredOrder::new(order, Some("chargegun"));
+        let receipt = OrderReceipt::from_stored(&stored).unwrap();
+        assert_eq!(receipt.payment_status, PaymentStatus::Unpaid);
+        assert!(!receipt.is_paid());
+        assert_eq!(receipt.line_items[0].amount.amount, 2500);
+        assert_eq!(receipt.totals.total.amount, 2500);
+
+        for trigger in [
+            OrderTrigger::CheckoutCreated,
+            OrderTrigger::PaymentSucceeded,
+            OrderTrigger::Fulfilled,
+            OrderTrigger::PartiallyRefunded,
+        ] {
+            stored.apply(trigger, None).unwrap();
+        }
+        let receipt = OrderReceipt::from_stored(&stored).unwrap();
+        assert_eq!(receipt.payment_status, PaymentStatus::PartiallyRefunded);
+        assert_eq!(receipt.fulfillment_status, FulfillmentStatus::Fulfilled);
+        assert!(receipt.is_paid());
+    }
+}
@@ -0,0 +1,185 @@
+//! # Refunds
+//!
+//! Types for refunding payments through a provider.
+//!
+//! A `RefundRequest` names the payment to refund (by order, checkout session
+//! or payment intent) and an optional amount; without one the provider
+//! refunds whatever is left. Providers implement `PaymentStrategy::refund`
+//! and return the resulting `Refund`, which the order store keeps so later
+//! refunds can be checked against what was already returned.
+
+use crate::product::Price;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Why a payment is refunded (the reasons Stripe accepts)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefundReason {
+    /// The customer was charged twice
+    Duplicate,
+    /// The payment was fraudulent
+    Fraudulent,
+    /// The customer asked for their money back
+    RequestedByCustomer,
+}
+
+impl RefundReason {
+    /// Get the reason as a string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RefundReason::Duplicate => "duplicate",
+            RefundReason::Fraudulent => "fraudulent",
+            RefundReason::RequestedByCustomer => "requested_by_customer",
+        }
+    }
+}
+
+/// The payment a refund applies to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "id", rename_all = "snake_case")]
+pub enum RefundTarget {
+    /// Our order ID (the provider looks up its payment)
+    Order(String),
+    /// Provider checkout session ID
+    Session(String),
+    /// Provider payment intent ID
+    PaymentIntent(String),
+}
+
+/// A request to refund a payment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefundRequest {
+    /// Payment to refund
+    pub target: RefundTarget,
+
+    /// Amount to refund, in the payment's currency (None = everything not yet refunded)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Price>,
+
+    /// Reason reported to the provider
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<RefundReason>,
+
+    /// Metadata stored with the refund
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+
+    /// Idempotency key (retrying with the same key returns the same refund)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+}
+
+impl RefundRequest {
+    /// Refund the full remaining amount of a payment
+    pub fn new(target: RefundTarget) -> Self {
+        Self {
+            target,
+            amount: None,
+            reason: None,
+            metadata: HashMap::new(),
+            idempotency_key: None,
+        }
+    }
+
+    /// Refund only part of the payment
+    pub fn with_amount(mut self, amount: Price) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    /// Set the reason
+    pub fn with_reason(mut self, reason: RefundReason) -> Self {
+        self.reason = Some(reason);
+        self
+    }
+
+    /// Add metadata
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set the idempotency key
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+}
+
+/// Refund status reported by the provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefundStatus {
+    /// Submitted, not yet settled
+    #[default]
+    Pending,
+    /// The customer must take an action before the refund completes
+    RequiresAction,
+    /// Money returned to the customer
+    Succeeded,
+    /// The refund failed
+    Failed,
+    /// The refund was cancelled
+    Canceled,
+}
+
+impl RefundStatus {
+    /// Check if the refund has returned (or will return) money to the customer
+    pub fn is_effective(&self) -> bool {
+        !matches!(self, RefundStatus::Failed | RefundStatus::Canceled)
+    }
+}
+
+/// A refund issued by a provider
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Refund {
+    /// Provider's refund ID (re_...)
+    pub id: String,
+
+    /// Provider name (e.g., "stripe")
+    pub provider: String,
+
+    /// Refunded payment intent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_intent_id: Option<String>,
+
+    /// Refunded amount
+    pub amount: Price,
+
+    /// Status
+    pub status: RefundStatus,
+
+    /// Reason
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<RefundReason>,
+
+    /// Metadata
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+
+    /// When the refund was created
+    pub created_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refund_request_serde() {
+        let request = RefundRequest::new(RefundTarget::PaymentIntent("pi_1".into()))
+            .with_amount(Price::from_cents(500, crate::product::Currency::USD))
+            .with_reason(RefundReason::RequestedByCustomer);
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["target"], serde_json::json!({"type": "payment_intent", "id": "pi_1"}));
+        assert_eq!(json["reason"], "requested_by_customer");
+        assert!(json.get("metadata").is_none());
+
+        let parsed: RefundRequest = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, request);
+        assert!(!RefundStatus::Failed.is_effective());
+        assert!(RefundStatus::Pending.is_effective());
+    }
+}
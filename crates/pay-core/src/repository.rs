@@ -0,0 +1,614 @@
+//! # Order Repository
+//!
+//! Persistence for orders, their checkout sessions, status history and refunds.
+//!
+//! `OrderRepository` is implemented in-memory here (tests, local development)
+//! and on SQLite in `pay-sqlite`.
+
+use crate::error::{PaymentError, PaymentResult};
+use crate::lifecycle::{OrderStatus, OrderTransition, OrderTrigger};
+use crate::order::{CheckoutSession, Order};
+use crate::product::Price;
+use crate::refund::{Refund, RefundStatus};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+/// A recorded status change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusChange {
+    /// Previous status (None for the initial status)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<OrderStatus>,
+
+    /// New status
+    pub to: OrderStatus,
+
+    /// Why the status changed (e.g., webhook event ID)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// When the status changed
+    pub at: DateTime<Utc>,
+}
+
+/// An order with its checkout session and status history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredOrder {
+    /// The order
+    pub order: Order,
+
+    /// Site the order was placed on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_id: Option<String>,
+
+    /// Current status
+    pub status: OrderStatus,
+
+    /// Checkout session (once created)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<CheckoutSession>,
+
+    /// Status changes, oldest first
+    pub history: Vec<StatusChange>,
+
+    /// Refunds issued against the payment, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub refunds: Vec<Refund>,
+
+    /// Amount the provider reports as paid (once the checkout completes)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount_paid: Option<Price>,
+
+    /// Last update
+    pub updated_at: DateTime<Utc>,
+}
+
+impl StoredOrder {
+    /// Create a new pending order record
+    pub fn new(order: Order, site_id: Option<&str>) -> Self {
+        let now = Utc::now();
+        Self {
+            order,
+            site_id: site_id.map(String::from),
+            status: OrderStatus::Pending,
+            session: None,
+            history: vec![StatusChange {
+                from: None,
+                to: OrderStatus::Pending,
+                reason: None,
+                at: now,
+            }],
+            refunds: Vec::new(),
+            amount_paid: None,
+            updated_at: now,
+        }
+    }
+
+    /// Apply a trigger, recording the change in the history.
+    ///
+    /// Returns `None` (and records nothing) if the order already has the
+    /// target status, or `InvalidTransition` if the state machine forbids it.
+    pub fn apply(
+        &mut self,
+        trigger: OrderTrigger,
+        reason: Option<&str>,
+    ) -> PaymentResult<Option<OrderTransition>> {
+        let to = trigger.target(self.status_before_dispute());
+        if self.status == to {
+            return Ok(None);
+        }
+        self.status.transition_to(to)?;
+
+        let now = Utc::now();
+        let transition = OrderTransition {
+            order_id: self.order.id.clone(),
+            from: self.status,
+            to,
+            reason: reason.map(String::from),
+            at: now,
+        };
+        self.history.push(StatusChange {
+            from: Some(self.status),
+            to,
+            reason: transition.reason.clone(),
+            at: now,
+        });
+        self.status = to;
+        self.updated_at = now;
+        Ok(Some(transition))
+    }
+
+    /// Record a refund, replacing an earlier record with the same ID
+    pub fn record_refund(&mut self, refund: Refund) {
+        match self.refunds.iter_mut().find(|r| r.id == refund.id) {
+            Some(existing) => *existing = refund,
+            None => self.refunds.push(refund),
+        }
+        self.updated_at = Utc::now();
+    }
+
+    /// Total of the refunds that have not failed or been cancelled
+    pub fn refunded_amount(&self) -> i64 {
+        self.refunds
+            .iter()
+            .filter(|r| r.status.is_effective())
+            .map(|r| r.amount.amount)
+            .sum()
+    }
+
+    /// Replace the order's tax with the amount the provider charged.
+    ///
+    /// Returns `false` (and changes nothing) if the order was placed without tax.
+    pub fn record_tax(&mut self, amount: Price) -> PaymentResult<bool> {
+        let Some(tax) = self.order.tax.as_mut() else {
+            return Ok(false);
+        };
+        if amount.currency != tax.amount.currency {
+            return Err(PaymentError::UnsupportedCurrency {
+                currency: amount.currency.to_string(),
+            });
+        }
+        tax.amount = amount;
+        tax.charged = true;
+        self.updated_at = Utc::now();
+        Ok(true)
+    }
+
+    /// Record the amount the provider charged
+    pub fn record_payment(&mut self, amount: Price) -> PaymentResult<()> {
+        if amount.currency != self.order.currency {
+            return Err(PaymentError::UnsupportedCurrency {
+                currency: amount.currency.to_string(),
+            });
+        }
+        self.amount_paid = Some(amount);
+        self.updated_at = Utc::now();
+        Ok(())
+    }
+
+    /// Amount paid: what the provider charged, or the order total before that is known
+    pub fn paid_amount(&self) -> PaymentResult<Price> {
+        match self.amount_paid {
+            Some(ref amount) => Ok(amount.clone()),
+            None => self.order.total(),
+        }
+    }
+
+    /// Check a refund against what was paid and what was already refunded.
+    ///
+    /// Returns the amount to refund (`None` = everything not yet refunded).
+    pub fn check_refund(&self, amount: Option<i64>) -> PaymentResult<i64> {
+        if !matches!(
+            self.status,
+            OrderStatus::Paid | OrderStatus::Fulfilled | OrderStatus::PartiallyRefunded
+        ) {
+            return Err(PaymentError::InvalidRequest(format!(
+                "order {} cannot be refunded while {}",
+                self.order.id, self.status
+            )));
+        }
+
+        let remaining = (self.paid_amount()?.amount - self.refunded_amount()).max(0);
+        let amount = amount.unwrap_or(remaining);
+        if amount <= 0 || remaining <= 0 {
+            return Err(PaymentError::InvalidRequest(format!(
+                "order {} has nothing left to refund",
+                self.order.id
+            )));
+        }
+        if amount > remaining {
+            return Err(PaymentError::InvalidRequest(format!(
+                "refund of {} exceeds the {} left to refund on order {}",
+                amount, remaining, self.order.id
+            )));
+        }
+        Ok(amount)
+    }
+
+    /// Check a refund and hold its amount with a pending placeholder, which counts
+    /// against later checks until `settle_refund` replaces or drops it
+    pub fn reserve_refund(&mut self, amount: Option<i64>) -> PaymentResult<Refund> {
+        let amount = self.check_refund(amount)?;
+        let reservation = Refund {
+            id: format!("reserved_{}", Uuid::new_v4()),
+            provider: "reservation".to_string(),
+            payment_intent_id: None,
+            amount: Price::from_cents(amount, self.order.currency),
+            status: RefundStatus::Pending,
+            reason: None,
+            metadata: HashMap::new(),
+            created_at: Utc::now(),
+        };
+        self.record_refund(reservation.clone());
+        Ok(reservation)
+    }
+
+    /// Replace a refund reservation with the issued refund (`None` drops it)
+    pub fn settle_refund(&mut self, reservation_id: &str, refund: Option<Refund>) {
+        self.refunds.retain(|r| r.id != reservation_id);
+        match refund {
+            Some(refund) => self.record_refund(refund),
+            None => self.updated_at = Utc::now(),
+        }
+    }
+
+    /// Status the order had before its most recent dispute
+    pub fn status_before_dispute(&self) -> Option<OrderStatus> {
+        self.history
+            .iter()
+            .rev()
+            .find(|change| change.to == OrderStatus::Disputed)
+            .and_then(|change| change.from)
+    }
+}
+
+/// Storage for orders
+#[async_trait]
+pub trait OrderRepository: Send + Sync {
+    /// Store a new order with status `Pending`
+    async fn insert(&self, order: &Order, site_id: Option<&str>) -> PaymentResult<()>;
+
+    /// Store or replace the order's checkout session
+    async fn save_session(&self, order_id: &str, session: &CheckoutSession) -> PaymentResult<()>;
+
+    /// Apply a lifecycle trigger to an order (see `StoredOrder::apply`).
+    ///
+    /// Returns the transition event, if the status changed.
+    async fn transition(
+        &self,
+        order_id: &str,
+        trigger: OrderTrigger,
+        reason: Option<&str>,
+    ) -> PaymentResult<Option<OrderTransition>>;
+
+    /// Store a refund of the order's payment, replacing one with the same ID
+    async fn save_refund(&self, order_id: &str, refund: &Refund) -> PaymentResult<()>;
+
+    /// Check a refund and reserve its amount in one step (see `StoredOrder::reserve_refund`),
+    /// so concurrent refunds cannot both pass the check
+    async fn reserve_refund(&self, order_id: &str, amount: Option<i64>) -> PaymentResult<Refund>;
+
+    /// Replace a refund reservation with the issued refund (`None` releases it)
+    async fn settle_refund(
+        &self,
+        order_id: &str,
+        reservation_id: &str,
+        refund: Option<&Refund>,
+    ) -> PaymentResult<()>;
+
+    /// Record the tax the provider charged (see `StoredOrder::record_tax`)
+    async fn save_tax(&self, order_id: &str, amount: &Price) -> PaymentResult<()>;
+
+    /// Record the amount the provider charged (see `StoredOrder::record_payment`)
+    async fn save_payment(&self, order_id: &str, amount: &Price) -> PaymentResult<()>;
+
+    /// Get an order by ID
+    async fn get(&self, order_id: &str) -> PaymentResult<Option<StoredOrder>>;
+
+    /// Get an order by its checkout session ID
+    async fn find_by_session(&self, session_id: &str) -> PaymentResult<Option<StoredOrder>>;
+
+    /// Get an order by its payment intent ID
+    async fn find_by_payment_intent(
+        &self,
+        payment_intent_id: &str,
+    ) -> PaymentResult<Option<StoredOrder>>;
+}
+
+/// Shared order repository
+pub type BoxedOrderRepository = Arc<dyn OrderRepository>;
+
+/// In-memory order repository (contents are lost on restart)
+#[derive(Debug, Default)]
+pub struct InMemoryOrderRepository {
+    orders: RwLock<HashMap<String, StoredOrder>>,
+}
+
+impl InMemoryOrderRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> PaymentResult<std::sync::RwLockReadGuard<'_, HashMap<String, StoredOrder>>> {
+        self.orders
+            .read()
+            .map_err(|_| PaymentError::Storage("order store lock poisoned".to_string()))
+    }
+
+    fn write(&self) -> PaymentResult<std::sync::RwLockWriteGuard<'_, HashMap<String, StoredOrder>>> {
+        self.orders
+            .write()
+            .map_err(|_| PaymentError::Storage("order store lock poisoned".to_string()))
+    }
+
+    fn find(&self, matches: impl Fn(&CheckoutSession) -> bool) -> PaymentResult<Option<StoredOrder>> {
+        Ok(self
+            .read()?
+            .values()
+            .find(|stored| stored.session.as_ref().is_some_and(&matches))
+            .cloned())
+    }
+}
+
+#[async_trait]
+impl OrderRepository for InMemoryOrderRepository {
+    async fn insert(&self, order: &Order, site_id: Option<&str>) -> PaymentResult<()> {
+        let mut orders = self.write()?;
+        if orders.contains_key(&order.id) {
+            return Err(PaymentError::Storage(format!(
+                "order {} already exists",
+                order.id
+            )));
+        }
+        orders.insert(order.id.clone(), StoredOrder::new(order.clone(), site_id));
+        Ok(())
+    }
+
+    async fn save_session(&self, order_id: &str, session: &CheckoutSession) -> PaymentResult<()> {
+        let mut orders = self.write()?;
+        let stored = orders.get_mut(order_id).ok_or_else(|| not_found(order_id))?;
+        stored.session = Some(session.clone());
+        stored.updated_at = Utc::now();
+        Ok(())
+    }
+
+    async fn transition(
+        &self,
+        order_id: &str,
+        trigger: OrderTrigger,
+        reason: Option<&str>,
+    ) -> PaymentResult<Option<OrderTransition>> {
+        let mut orders = self.write()?;
+        let stored = orders.get_mut(order_id).ok_or_else(|| not_found(order_id))?;
+        stored.apply(trigger, reason)
+    }
+
+    async fn save_refund(&self, order_id: &str, refund: &Refund) -> PaymentResult<()> {
+        let mut orders = self.write()?;
+        let stored = orders.get_mut(order_id).ok_or_else(|| not_found(order_id))?;
+        stored.record_refund(refund.clone());
+        Ok(())
+    }
+
+    async fn reserve_refund(&self, order_id: &str, amount: Option<i64>) -> PaymentResult<Refund> {
+        let mut orders = self.write()?;
+        let stored = orders.get_mut(order_id).ok_or_else(|| not_found(order_id))?;
+        stored.reserve_refund(amount)
+    }
+
+    async fn settle_refund(
+        &self,
+        order_id: &str,
+        reservation_id: &str,
+        refund: Option<&Refund>,
+    ) -> PaymentResult<()> {
+        let mut orders = self.write()?;
+        let stored = orders.get_mut(order_id).ok_or_else(|| not_found(order_id))?;
+        stored.settle_refund(reservation_id, refund.cloned());
+        Ok(())
+    }
+
+    async fn save_tax(&self, order_id: &str, amount: &Price) -> PaymentResult<()> {
+        let mut orders = self.write()?;
+        let stored = orders.get_mut(order_id).ok_or_else(|| not_found(order_id))?;
+        stored.record_tax(amount.clone())?;
+        Ok(())
+    }
+
+    async fn save_payment(&self, order_id: &str, amount: &Price) -> PaymentResult<()> {
+        let mut orders = self.write()?;
+        let stored = orders.get_mut(order_id).ok_or_else(|| not_found(order_id))?;
+        stored.record_payment(amount.clone())
+    }
+
+    async fn get(&self, order_id: &str) -> PaymentResult<Option<StoredOrder>> {
+        Ok(self.read()?.get(order_id).cloned())
+    }
+
+    async fn find_by_session(&self, session_id: &str) -> PaymentResult<Option<StoredOrder>> {
+        self.find(|s| s.session_id == session_id)
+    }
+
+    async fn find_by_payment_intent(
+        &self,
+        payment_intent_id: &str,
+    ) -> PaymentResult<Option<StoredOrder>> {
+        self.find(|s| s.payment_intent_id.as_deref() == Some(payment_intent_id))
+    }
+}
+
+fn not_found(order_id: &str) -> PaymentError {
+    PaymentError::OrderNotFound {
+        order_id: order_id.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::product::{Currency, Product};
+    use crate::tax::{TaxBreakdown, TaxMode};
+
+    #[tokio::test]
+    async fn test_in_memory_lifecycle() {
+        let repo = InMemoryOrderRepository::new();
+        let order = Order::new(Currency::USD);
+        repo.insert(&order, Some("chargegun")).await.unwrap();
+        assert!(repo.insert(&order, None).await.is_err());
+
+        let mut session = CheckoutSession::new("cs_1", &order.id, "stripe", "https://x");
+        repo.save_session(&order.id, &session).await.unwrap();
+        let created = repo
+            .transition(&order.id, OrderTrigger::CheckoutCreated, Some("cs_1"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((created.from, created.to), (OrderStatus::Pending, OrderStatus::AwaitingPayment));
+
+        session.payment_intent_id = Some("pi_1".into());
+        repo.save_session(&order.id, &session).await.unwrap();
+        repo.transition(&order.id, OrderTrigger::PaymentSucceeded, Some("evt_1"))
+            .await
+            .unwrap();
+        // Duplicate events are a no-op
+        assert!(repo
+            .transition(&order.id, OrderTrigger::PaymentSucceeded, Some("evt_2"))
+            .await
+            .unwrap()
+            .is_none());
+
+        let by_session = repo.find_by_session("cs_1").await.unwrap().unwrap();
+        assert_eq!(by_session.status, OrderStatus::Paid);
+        assert_eq!(by_session.site_id.as_deref(), Some("chargegun"));
+        let by_intent = repo.find_by_payment_intent("pi_1").await.unwrap().unwrap();
+        assert_eq!(by_intent.order.id, order.id);
+
+        let history: Vec<_> = by_session.history.iter().map(|c| c.to).collect();
+        assert_eq!(
+            history,
+            [OrderStatus::Pending, OrderStatus::AwaitingPayment, OrderStatus::Paid]
+        );
+        assert_eq!(by_session.history[2].reason.as_deref(), Some("evt_1"));
+
+        assert!(matches!(
+            repo.transition(&order.id, OrderTrigger::SessionExpired, None).await,
+            Err(PaymentError::InvalidTransition { .. })
+        ));
+        assert!(matches!(
+            repo.transition("missing", OrderTrigger::PaymentSucceeded, None).await,
+            Err(PaymentError::OrderNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_dispute_won_restores_status() {
+        let mut stored = StoredOrder::new(Order::new(Currency::USD), None);
+        for trigger in [
+            OrderTrigger::CheckoutCreated,
+            OrderTrigger::PaymentSucceeded,
+            OrderTrigger::Fulfilled,
+            OrderTrigger::DisputeOpened,
+        ] {
+            stored.apply(trigger, None).unwrap();
+        }
+        assert_eq!(stored.status, OrderStatus::Disputed);
+
+        stored.apply(OrderTrigger::DisputeWon, None).unwrap();
+        assert_eq!(stored.status, OrderStatus::Fulfilled);
+    }
+
+    #[tokio::test]
+    async fn test_save_tax_replaces_estimate() {
+        let repo = InMemoryOrderRepository::new();
+        let product = Product::one_time("p", "P", Price::from_cents(1000, Currency::USD));
+        let mut order = Order::new(Currency::USD);
+        order.add_product(&product, 1).unwrap();
+        order
+            .apply_tax(TaxBreakdown {
+                mode: TaxMode::Exclusive,
+                amount: Price::zero(Currency::USD),
+                lines: Vec::new(),
+                automatic: true,
+                charged: false,
+                location: None,
+            })
+            .unwrap();
+        repo.insert(&order, None).await.unwrap();
+
+        repo.save_tax(&order.id, &Price::from_cents(83, Currency::USD)).await.unwrap();
+        let stored = repo.get(&order.id).await.unwrap().unwrap();
+        let tax = stored.order.tax.as_ref().unwrap();
+        assert_eq!(tax.amount.amount, 83);
+        assert!(tax.charged);
+        assert_eq!(stored.order.total().unwrap().amount, 1083);
+
+        assert!(repo.save_tax(&order.id, &Price::from_cents(83, Currency::EUR)).await.is_err());
+
+        // Orders placed without tax are left alone
+        let untaxed = Order::new(Currency::USD);
+        repo.insert(&untaxed, None).await.unwrap();
+        repo.save_tax(&untaxed.id, &Price::from_cents(0, Currency::USD)).await.unwrap();
+        assert!(repo.get(&untaxed.id).await.unwrap().unwrap().order.tax.is_none());
+    }
+
+    #[test]
+    fn test_check_refund() {
+        let product = Product::one_time("p", "P", Price::from_cents(1000, Currency::USD));
+        let mut order = Order::new(Currency::USD);
+        order.add_product(&product, 1).unwrap();
+        let mut stored = StoredOrder::new(order, None);
+        assert!(stored.check_refund(None).is_err());
+
+        stored.apply(OrderTrigger::CheckoutCreated, None).unwrap();
+        stored.apply(OrderTrigger::PaymentSucceeded, None).unwrap();
+        assert_eq!(stored.check_refund(None).unwrap(), 1000);
+
+        let mut refund = Refund {
+            id: "re_1".into(),
+            provider: "stripe".into(),
+            payment_intent_id: None,
+            amount: Price::from_cents(400, Currency::USD),
+            status: RefundStatus::Succeeded,
+            reason: None,
+            metadata: Default::default(),
+            created_at: Utc::now(),
+        };
+        stored.record_refund(refund.clone());
+        assert_eq!(stored.check_refund(None).unwrap(), 600);
+        assert!(stored.check_refund(Some(601)).is_err());
+        assert!(stored.check_refund(Some(0)).is_err());
+
+        // A failed refund returns nothing, so it no longer counts
+        refund.status = RefundStatus::Failed;
+        stored.record_refund(refund);
+        assert_eq!(stored.refunds.len(), 1);
+        assert_eq!(stored.check_refund(None).unwrap(), 1000);
+    }
+
+    #[tokio::test]
+    async fn test_refund_limited_to_amount_paid() {
+        let repo = InMemoryOrderRepository::new();
+        let product = Product::one_time("p", "P", Price::from_cents(1000, Currency::USD));
+        let mut order = Order::new(Currency::USD);
+        order.add_product(&product, 1).unwrap();
+        repo.insert(&order, None).await.unwrap();
+        repo.transition(&order.id, OrderTrigger::CheckoutCreated, None).await.unwrap();
+        repo.transition(&order.id, OrderTrigger::PaymentSucceeded, None).await.unwrap();
+
+        // A discount applied at checkout means less was paid than the order total
+        repo.save_payment(&order.id, &Price::from_cents(800, Currency::USD)).await.unwrap();
+        let stored = repo.get(&order.id).await.unwrap().unwrap();
+        assert_eq!(stored.paid_amount().unwrap().amount, 800);
+        assert_eq!(stored.check_refund(None).unwrap(), 800);
+        assert!(stored.check_refund(Some(900)).is_err());
+
+        assert!(repo.save_payment(&order.id, &Price::from_cents(800, Currency::EUR)).await.is_err());
+
+        // A reservation counts until it is settled, so a second full refund is refused
+        let reservation = repo.reserve_refund(&order.id, None).await.unwrap();
+        assert_eq!(reservation.amount.amount, 800);
+        assert!(repo.reserve_refund(&order.id, Some(1)).await.is_err());
+        repo.settle_refund(&order.id, &reservation.id, None).await.unwrap();
+        let reservation = repo.reserve_refund(&order.id, Some(300)).await.unwrap();
+        let refund = Refund {
+            id: "re_1".into(),
+            provider: "stripe".into(),
+            status: RefundStatus::Succeeded,
+            ..reservation.clone()
+        };
+        repo.settle_refund(&order.id, &reservation.id, Some(&refund)).await.unwrap();
+        let stored = repo.get(&order.id).await.unwrap().unwrap();
+        assert_eq!(stored.refunds, [refund]);
+        assert_eq!(stored.check_refund(None).unwrap(), 500);
+
+        // Refunds recorded from the provider can exceed a later, lower payment record
+        repo.save_payment(&order.id, &Price::from_cents(200, Currency::USD)).await.unwrap();
+        let stored = repo.get(&order.id).await.unwrap().unwrap();
+        assert!(stored.check_refund(None).unwrap_err().to_string().contains("nothing left"));
+    }
+}
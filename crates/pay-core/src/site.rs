@@ -3,6 +3,10 @@
 //! Multi-tenant site configuration for lightning-cart.
 //! Each site has its own branding, URLs, and statement descriptor.
 
+use crate::format::Locale;
+use crate::outbound::OutboundWebhook;
+use crate::portal::PortalConfig;
+use crate::tax::TaxConfig;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -33,6 +37,22 @@ pub struct Site {
     #[serde(default)]
     pub support_email: Option<String>,
 
+    /// Locale for formatting prices on this site (e.g., "de-DE")
+    #[serde(default)]
+    pub locale: Option<String>,
+
+    /// Tax configuration (None = no tax)
+    #[serde(default)]
+    pub tax: Option<TaxConfig>,
+
+    /// Outbound webhook subscriptions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<OutboundWebhook>,
+
+    /// Billing portal (None = no portal)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub portal: Option<PortalConfig>,
+
     /// Whether this site is active
     #[serde(default = "default_true")]
     pub active: bool,
@@ -62,6 +82,10 @@ impl Site {
             success_url: format!("https://{}/checkout/success", domain_str),
             cancel_url: format!("https://{}/checkout/cancel", domain_str),
             support_email: None,
+            locale: None,
+            tax: None,
+            webhooks: Vec::new(),
+            portal: None,
             active: true,
             metadata: HashMap::new(),
         }
@@ -91,12 +115,49 @@ impl Site {
         self
     }
 
+    /// Builder: set price formatting locale
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    /// Builder: set tax configuration
+    pub fn with_tax(mut self, tax: TaxConfig) -> Self {
+        self.tax = Some(tax);
+        self
+    }
+
+    /// Builder: add an outbound webhook subscription
+    pub fn with_webhook(mut self, webhook: OutboundWebhook) -> Self {
+        self.webhooks.push(webhook);
+        self
+    }
+
+    /// Builder: enable the billing portal
+    pub fn with_portal(mut self, portal: PortalConfig) -> Self {
+        self.portal = Some(portal);
+        self
+    }
+
     /// Builder: add metadata
     pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
         self.metadata.insert(key.into(), value.into());
         self
     }
 
+    /// Where the billing portal returns to (portal `return_url`, else the home page)
+    pub fn portal_return_url(&self) -> String {
+        self.portal
+            .as_ref()
+            .and_then(|p| p.return_url.clone())
+            .unwrap_or_else(|| format!("https://{}", self.domain))
+    }
+
+    /// Get the price formatting locale for this site, if configured and known
+    pub fn price_locale(&self) -> Option<Locale> {
+        self.locale.as_deref().and_then(Locale::from_tag)
+    }
+
     /// Get the success URL with session_id placeholder for Stripe
     pub fn success_url_with_session(&self) -> String {
         if self.success_url.contains('?') {
@@ -152,6 +213,11 @@ impl SiteRegistry {
         self.default_site_id = Some(site_id.into());
     }
 
+    /// Get the default site ID, if set
+    pub fn default_site_id(&self) -> Option<&str> {
+        self.default_site_id.as_deref()
+    }
+
     /// Get a site by ID
     pub fn get(&self, site_id: &str) -> Option<&Site> {
         self.sites.iter().find(|s| s.id == site_id && s.active)
@@ -214,6 +280,10 @@ mod tests {
         assert_eq!(site.domain, "spokenhope.care");
         assert_eq!(site.statement_descriptor_suffix, "SPOKENHOPE");
         assert!(site.active);
+        assert!(site.price_locale().is_none());
+
+        let site = site.with_locale("de-DE");
+        assert_eq!(site.price_locale().unwrap().decimal_separator, ",");
     }
 
     #[test]
@@ -14,6 +14,9 @@
 //! │                    PaymentStrategy (trait)                  │
 //! │  ├── create_checkout()                                      │
 //! │  ├── verify_webhook()                                       │
+//! │  ├── retrieve_checkout()                                    │
+//! │  ├── ensure_customer()                                      │
+//! │  ├── refund()                                               │
 //! │  └── provider_name()                                        │
 //! └─────────────────────────────────────────────────────────────┘
 //!                            ▲
@@ -25,8 +28,11 @@
 //!  └───────────────┘ └───────────────┘ └───────────────┘
 //! ```
 
-use crate::error::PaymentResult;
+use crate::customer::Customer;
+use crate::error::{PaymentError, PaymentResult};
 use crate::order::{CheckoutSession, Order, WebhookEvent};
+use crate::receipt::OrderReceipt;
+use crate::refund::{Refund, RefundRequest};
 use async_trait::async_trait;
 use std::sync::Arc;
 
@@ -66,6 +72,34 @@ pub trait PaymentStrategy: Send + Sync {
         signature: &str,
     ) -> PaymentResult<WebhookEvent>;
 
+    /// Look up a checkout session at the provider.
+    ///
+    /// Used when the order store has no record of the session.
+    /// Default: `SessionNotFound` (the provider cannot look sessions up).
+    async fn retrieve_checkout(&self, session_id: &str) -> PaymentResult<OrderReceipt> {
+        Err(PaymentError::SessionNotFound {
+            session_id: session_id.to_string(),
+        })
+    }
+
+    /// Find or create the provider's customer for a customer record.
+    ///
+    /// Returns the provider customer ID to set on `Order::customer_id`.
+    /// Default: `None` (the provider has no customer objects).
+    async fn ensure_customer(&self, _customer: &Customer) -> PaymentResult<Option<String>> {
+        Ok(None)
+    }
+
+    /// Refund all or part of a payment.
+    ///
+    /// Default: `InvalidRequest` (the provider does not support refunds).
+    async fn refund(&self, _request: &RefundRequest) -> PaymentResult<Refund> {
+        Err(PaymentError::InvalidRequest(format!(
+            "{} does not support refunds",
+            self.provider_name()
+        )))
+    }
+
     /// Get the provider name (for logging and routing).
     fn provider_name(&self) -> &'static str;
 
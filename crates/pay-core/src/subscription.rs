@@ -0,0 +1,503 @@
+//! # Subscription Management
+//!
+//! `SubscriptionStrategy` manages subscriptions after checkout has started
+//! them: list and look up, cancel (now or at the end of the period), pause
+//! and resume collection, and change the plan or quantity with proration.
+//!
+//! Checkout stores the order and site IDs in the subscription's metadata, so
+//! subscriptions can be listed and checked per site.
+
+use crate::error::{PaymentError, PaymentResult};
+use crate::order::LineItem;
+use crate::product::{BillingInterval, Currency, Price, Product};
+use crate::usage::{UsageModel, UsageRecord, UsageReport, UsageType};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Subscription status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    /// First payment not completed yet
+    Incomplete,
+    /// First payment never completed
+    IncompleteExpired,
+    /// In a free trial
+    Trialing,
+    /// Paid and current
+    Active,
+    /// Latest payment failed, still retrying
+    PastDue,
+    /// Cancelled
+    Canceled,
+    /// Retries exhausted without payment
+    Unpaid,
+    /// Trial ended without a payment method
+    Paused,
+}
+
+impl SubscriptionStatus {
+    /// Get the status as a string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriptionStatus::Incomplete => "incomplete",
+            SubscriptionStatus::IncompleteExpired => "incomplete_expired",
+            SubscriptionStatus::Trialing => "trialing",
+            SubscriptionStatus::Active => "active",
+            SubscriptionStatus::PastDue => "past_due",
+            SubscriptionStatus::Canceled => "canceled",
+            SubscriptionStatus::Unpaid => "unpaid",
+            SubscriptionStatus::Paused => "paused",
+        }
+    }
+
+    /// Check if the subscription grants access
+    pub fn is_active(&self) -> bool {
+        matches!(self, SubscriptionStatus::Active | SubscriptionStatus::Trialing)
+    }
+}
+
+/// What happens to invoices while collection is paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseBehavior {
+    /// Keep invoices as drafts, to be finalized on resume
+    KeepAsDraft,
+    /// Finalize invoices and mark them uncollectible
+    MarkUncollectible,
+    /// Void invoices (the paused period is free)
+    #[default]
+    Void,
+}
+
+impl PauseBehavior {
+    /// Get the behavior as a string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PauseBehavior::KeepAsDraft => "keep_as_draft",
+            PauseBehavior::MarkUncollectible => "mark_uncollectible",
+            PauseBehavior::Void => "void",
+        }
+    }
+}
+
+/// Paused payment collection
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct PauseCollection {
+    /// What happens to invoices while paused
+    #[serde(default)]
+    pub behavior: PauseBehavior,
+
+    /// When collection resumes by itself (None = until resumed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resumes_at: Option<DateTime<Utc>>,
+}
+
+/// How a plan change is charged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProrationBehavior {
+    /// Prorate on the next invoice
+    #[default]
+    CreateProrations,
+    /// Prorate and invoice immediately
+    AlwaysInvoice,
+    /// No proration (the new price applies from the next period)
+    None,
+}
+
+impl ProrationBehavior {
+    /// Get the behavior as a string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProrationBehavior::CreateProrations => "create_prorations",
+            ProrationBehavior::AlwaysInvoice => "always_invoice",
+            ProrationBehavior::None => "none",
+        }
+    }
+}
+
+/// An item of a subscription
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionLine {
+    /// Provider's item ID (si_...)
+    pub item_id: String,
+
+    /// Provider's price ID
+    pub price_id: String,
+
+    /// Provider's product ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product_id: Option<String>,
+
+    /// Price per unit and period
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit_price: Option<Price>,
+
+    /// Billing interval
+    pub interval: BillingInterval,
+
+    /// Quantity
+    pub quantity: u32,
+
+    /// Licensed or metered (usage is reported for metered items)
+    #[serde(default)]
+    pub usage_type: UsageType,
+}
+
+/// A subscription at the provider
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Subscription {
+    /// Provider's subscription ID (sub_...)
+    pub id: String,
+
+    /// Provider name (e.g., "stripe")
+    pub provider: String,
+
+    /// Provider's customer ID
+    pub customer_id: String,
+
+    /// Status
+    pub status: SubscriptionStatus,
+
+    /// Subscribed items
+    pub lines: Vec<SubscriptionLine>,
+
+    /// End of the current period
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_period_end: Option<DateTime<Utc>>,
+
+    /// Whether the subscription ends at the end of the current period
+    pub cancel_at_period_end: bool,
+
+    /// When the subscription was cancelled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canceled_at: Option<DateTime<Utc>>,
+
+    /// Paused payment collection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_collection: Option<PauseCollection>,
+
+    /// Metadata (`order_id`, `site_id`, `product_id`)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+}
+
+impl Subscription {
+    /// Site the subscription was sold on
+    pub fn site_id(&self) -> Option<&str> {
+        self.metadata.get("site_id").map(String::as_str)
+    }
+
+    /// Check if the subscription grants access
+    pub fn is_active(&self) -> bool {
+        self.status.is_active()
+    }
+}
+
+/// Filter for listing subscriptions
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionQuery {
+    /// Only subscriptions sold on this site
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_id: Option<String>,
+
+    /// Only subscriptions of this provider customer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub customer_id: Option<String>,
+
+    /// Only subscriptions with this status
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<SubscriptionStatus>,
+
+    /// Maximum number of results (provider default if None)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+impl SubscriptionQuery {
+    /// Match every subscription
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only subscriptions sold on a site
+    pub fn with_site(mut self, site_id: impl Into<String>) -> Self {
+        self.site_id = Some(site_id.into());
+        self
+    }
+
+    /// Only subscriptions of a customer
+    pub fn with_customer(mut self, customer_id: impl Into<String>) -> Self {
+        self.customer_id = Some(customer_id.into());
+        self
+    }
+
+    /// Only subscriptions with a status
+    pub fn with_status(mut self, status: SubscriptionStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+}
+
+/// A catalog plan to switch a subscription item to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    /// Catalog product ID
+    pub product_id: String,
+
+    /// Product name ("Product – Variant" for variants)
+    pub name: String,
+
+    /// Price per unit and period
+    pub unit_price: Price,
+
+    /// Billing interval
+    pub interval: BillingInterval,
+
+    /// Seat, tiered or metered billing (None = licensed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<UsageModel>,
+}
+
+impl Plan {
+    /// Build a plan from a subscription product, priced in `currency`
+    pub fn from_product(product: &Product, currency: Currency) -> PaymentResult<Self> {
+        if !product.is_subscription() {
+            return Err(PaymentError::InvalidRequest(format!(
+                "{} is not a subscription product",
+                product.id
+            )));
+        }
+        if product.has_variants() {
+            return Err(PaymentError::InvalidRequest(format!(
+                "choose a variant of {}",
+                product.id
+            )));
+        }
+        let unit_price = product.price_in(currency).cloned().ok_or_else(|| {
+            PaymentError::UnsupportedCurrency {
+                currency: currency.to_string(),
+            }
+        })?;
+        Ok(Self {
+            product_id: product.id.clone(),
+            name: product.name.clone(),
+            unit_price,
+            interval: product.billing_interval,
+            usage: product.usage.clone(),
+        })
+    }
+
+    /// Build a plan from a variant of a subscription product, priced in `currency`
+    pub fn from_variant(product: &Product, variant_id: &str, currency: Currency) -> PaymentResult<Self> {
+        if !product.is_subscription() {
+            return Err(PaymentError::InvalidRequest(format!(
+                "{} is not a subscription product",
+                product.id
+            )));
+        }
+        let item = LineItem::from_variant_in(product, variant_id, 1, currency)?;
+        Ok(Self {
+            name: item.display_name(),
+            product_id: item.product_id,
+            unit_price: item.unit_price,
+            interval: item.billing_interval,
+            usage: item.usage,
+        })
+    }
+}
+
+/// A change of plan and/or quantity
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct PlanChange {
+    /// Item to change (None = the subscription's only item)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item_id: Option<String>,
+
+    /// New plan (None = keep the current price)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<Plan>,
+
+    /// New quantity (None = keep the current quantity)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<u32>,
+
+    /// How the change is charged
+    #[serde(default)]
+    pub proration: ProrationBehavior,
+}
+
+impl PlanChange {
+    /// Switch to another plan
+    pub fn to_plan(plan: Plan) -> Self {
+        Self {
+            plan: Some(plan),
+            ..Self::default()
+        }
+    }
+
+    /// Change the quantity only
+    pub fn to_quantity(quantity: u32) -> Self {
+        Self {
+            quantity: Some(quantity),
+            ..Self::default()
+        }
+    }
+
+    /// Change a specific item
+    pub fn with_item(mut self, item_id: impl Into<String>) -> Self {
+        self.item_id = Some(item_id.into());
+        self
+    }
+
+    /// Also change the quantity
+    pub fn with_quantity(mut self, quantity: u32) -> Self {
+        self.quantity = Some(quantity);
+        self
+    }
+
+    /// Set how the change is charged
+    pub fn with_proration(mut self, proration: ProrationBehavior) -> Self {
+        self.proration = proration;
+        self
+    }
+
+    /// Check that the change changes something
+    pub fn validate(&self) -> PaymentResult<()> {
+        if self.plan.is_none() && self.quantity.is_none() {
+            return Err(PaymentError::InvalidRequest(
+                "plan change needs a new plan or quantity".to_string(),
+            ));
+        }
+        if self.quantity == Some(0) {
+            return Err(PaymentError::InvalidRequest(
+                "quantity must be at least 1 (cancel the subscription instead)".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Subscription management at a payment provider
+#[async_trait]
+pub trait SubscriptionStrategy: Send + Sync {
+    /// List subscriptions matching a query
+    async fn list_subscriptions(&self, query: &SubscriptionQuery)
+        -> PaymentResult<Vec<Subscription>>;
+
+    /// Get a subscription (`SubscriptionNotFound` if it does not exist)
+    async fn retrieve_subscription(&self, subscription_id: &str) -> PaymentResult<Subscription>;
+
+    /// Cancel at the end of the current period (access continues until then)
+    async fn cancel_at_period_end(&self, subscription_id: &str) -> PaymentResult<Subscription>;
+
+    /// Cancel immediately
+    async fn cancel_now(&self, subscription_id: &str) -> PaymentResult<Subscription>;
+
+    /// Pause payment collection (the subscription stays active)
+    async fn pause(
+        &self,
+        subscription_id: &str,
+        pause: &PauseCollection,
+    ) -> PaymentResult<Subscription>;
+
+    /// Resume payment collection and undo a pending cancellation
+    async fn resume(&self, subscription_id: &str) -> PaymentResult<Subscription>;
+
+    /// Change the plan and/or quantity of an item
+    async fn change_plan(
+        &self,
+        subscription_id: &str,
+        change: &PlanChange,
+    ) -> PaymentResult<Subscription>;
+
+    /// Report usage of a metered item.
+    ///
+    /// Default: `InvalidRequest` (the provider does not support metered billing).
+    async fn report_usage(&self, _record: &UsageRecord) -> PaymentResult<UsageReport> {
+        Err(PaymentError::InvalidRequest(format!(
+            "{} does not support usage reporting",
+            self.provider_name()
+        )))
+    }
+
+    /// Get the provider name
+    fn provider_name(&self) -> &'static str;
+}
+
+/// Shared subscription strategy
+pub type BoxedSubscriptionStrategy = Arc<dyn SubscriptionStrategy>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variant::ProductVariant;
+
+    fn pro() -> Product {
+        Product::subscription(
+            "rang-play-rs-pro",
+            "Rang Play RS - Pro API",
+            Price::from_cents(2900, Currency::USD),
+            BillingInterval::MONTHLY,
+        )
+    }
+
+    #[test]
+    fn test_plan() {
+        let plan = Plan::from_product(&pro(), Currency::USD).unwrap();
+        assert_eq!(plan.product_id, "rang-play-rs-pro");
+        assert_eq!((plan.unit_price.amount, plan.interval), (2900, BillingInterval::MONTHLY));
+        assert!(Plan::from_product(&pro(), Currency::EUR).is_err());
+
+        let tiers = pro().with_variant(ProductVariant::new(
+            "team",
+            "Team",
+            "RPR-TEAM",
+            Price::from_cents(9900, Currency::USD),
+        ));
+        let team = Plan::from_variant(&tiers, "team", Currency::USD).unwrap();
+        assert_eq!((team.unit_price.amount, team.interval), (9900, BillingInterval::MONTHLY));
+        assert!(Plan::from_variant(&tiers, "enterprise", Currency::USD).is_err());
+        assert!(Plan::from_product(&tiers, Currency::USD).is_err());
+
+        let cli = Product::one_time("cli", "CLI", Price::from_cents(1999, Currency::USD));
+        assert!(Plan::from_product(&cli, Currency::USD).is_err());
+        assert!(Plan::from_variant(&cli, "personal", Currency::USD).is_err());
+    }
+
+    #[test]
+    fn test_plan_change() {
+        let plan = Plan::from_product(&pro(), Currency::USD).unwrap();
+        let change = PlanChange::to_plan(plan.clone()).with_quantity(3);
+        assert!(change.validate().is_ok());
+        assert_eq!(change.proration, ProrationBehavior::CreateProrations);
+        let change = change.with_proration(ProrationBehavior::AlwaysInvoice);
+        assert_eq!(change.proration.as_str(), "always_invoice");
+
+        // Needs a plan or a positive quantity
+        assert!(PlanChange::default().validate().is_err());
+        assert!(PlanChange::default().with_item("si_1").validate().is_err());
+        assert!(PlanChange::to_quantity(2).with_item("si_1").validate().is_ok());
+        assert!(PlanChange::to_quantity(0).validate().is_err());
+        assert!(PlanChange::to_plan(plan).with_quantity(0).validate().is_err());
+
+        let json = serde_json::to_value(PlanChange::to_quantity(2)).unwrap();
+        assert_eq!(json, serde_json::json!({"quantity": 2, "proration": "create_prorations"}));
+        let parsed: PlanChange =
+            serde_json::from_value(serde_json::json!({"quantity": 2, "proration": "none"})).unwrap();
+        assert_eq!(parsed.proration, ProrationBehavior::None);
+    }
+
+    #[test]
+    fn test_active_statuses() {
+        assert!(SubscriptionStatus::Active.is_active());
+        assert!(SubscriptionStatus::Trialing.is_active());
+        assert!(!SubscriptionStatus::PastDue.is_active());
+        assert!(!SubscriptionStatus::Paused.is_active());
+        assert!(!SubscriptionStatus::Canceled.is_active());
+    }
+}
@@ -0,0 +1,482 @@
+//! # Tax Calculation
+//!
+//! Pluggable tax calculation for lightning-cart.
+//!
+//! Each site picks a tax provider in `config/sites.toml`:
+//! - `table`: rates by country/region and product type, computed here
+//! - `stripe`: Stripe Tax computes tax on the hosted checkout page
+//!
+//! Prices are either tax-exclusive (tax is added on top) or tax-inclusive
+//! (tax is already part of the price), configured per site.
+//!
+//! The tax calculated before checkout is an estimate: when the payment
+//! completes, the order's tax is replaced by the amount the provider charged
+//! (see `StoredOrder::record_tax`).
+
+use crate::error::{PaymentError, PaymentResult};
+use crate::money::Money;
+use crate::order::Order;
+use crate::product::{Price, ProductType};
+use serde::{Deserialize, Serialize};
+
+/// Whether catalog prices include tax
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxMode {
+    /// Tax is added on top of the price
+    #[default]
+    Exclusive,
+    /// Tax is already included in the price
+    Inclusive,
+}
+
+impl TaxMode {
+    /// Get the mode as Stripe's `tax_behavior` value
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaxMode::Exclusive => "exclusive",
+            TaxMode::Inclusive => "inclusive",
+        }
+    }
+}
+
+/// Tax provider for a site
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxProvider {
+    /// No tax is calculated
+    #[default]
+    None,
+    /// Rates from the site's tax table
+    Table,
+    /// Stripe Tax (automatic tax on the checkout page)
+    Stripe,
+}
+
+/// A tax rate in a site's tax table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaxRate {
+    /// ISO 3166-1 alpha-2 country code (e.g., "DE")
+    pub country: String,
+
+    /// Region/state code (e.g., "CA"); None matches the whole country
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+
+    /// Product type; None matches all product types
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product_type: Option<ProductType>,
+
+    /// Rate in percent (e.g., "19" or "7.25")
+    pub rate: Money,
+
+    /// Display name (e.g., "VAT", "Sales tax")
+    #[serde(default = "default_tax_name")]
+    pub name: String,
+}
+
+fn default_tax_name() -> String {
+    "Tax".to_string()
+}
+
+impl TaxRate {
+    /// Create a country-wide rate for all product types
+    pub fn new(country: impl Into<String>, rate: Money, name: impl Into<String>) -> Self {
+        Self {
+            country: country.into(),
+            region: None,
+            product_type: None,
+            rate,
+            name: name.into(),
+        }
+    }
+
+    /// Builder: restrict to a region
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    /// Builder: restrict to a product type
+    pub fn with_product_type(mut self, product_type: ProductType) -> Self {
+        self.product_type = Some(product_type);
+        self
+    }
+
+    /// Specificity of this rate for a location and product type, if it applies
+    fn specificity(&self, location: &TaxLocation, product_type: ProductType) -> Option<u8> {
+        if !self.country.eq_ignore_ascii_case(&location.country) {
+            return None;
+        }
+        let region = match (&self.region, &location.region) {
+            (None, _) => 0,
+            (Some(r), Some(l)) if r.eq_ignore_ascii_case(l) => 2,
+            (Some(_), _) => return None,
+        };
+        let product = match self.product_type {
+            None => 0,
+            Some(t) if t == product_type => 1,
+            Some(_) => return None,
+        };
+        Some(region + product)
+    }
+}
+
+/// Per-site tax configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaxConfig {
+    /// Tax provider
+    #[serde(default)]
+    pub provider: TaxProvider,
+
+    /// Whether catalog prices include tax
+    #[serde(default)]
+    pub mode: TaxMode,
+
+    /// Country used when the customer's location is unknown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_country: Option<String>,
+
+    /// Tax table (used by the `table` provider)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rates: Vec<TaxRate>,
+}
+
+/// Where the customer is taxed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaxLocation {
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+
+    /// Region/state code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+}
+
+impl TaxLocation {
+    /// Create a location for a country
+    pub fn new(country: impl Into<String>) -> Self {
+        Self {
+            country: country.into(),
+            region: None,
+        }
+    }
+
+    /// Builder: set region
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+}
+
+/// Tax on a single line item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaxLine {
+    /// Index into `Order::line_items`
+    pub line_index: usize,
+
+    /// Tax rate applied
+    pub rate: TaxRate,
+
+    /// Tax amount
+    pub amount: Price,
+}
+
+/// Result of a tax calculation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaxBreakdown {
+    /// Whether prices include tax
+    pub mode: TaxMode,
+
+    /// Total tax (zero when calculated by the provider at checkout, until it is charged)
+    pub amount: Price,
+
+    /// Tax per line item, as calculated before checkout
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<TaxLine>,
+
+    /// Tax is calculated by the payment provider during checkout
+    #[serde(default)]
+    pub automatic: bool,
+
+    /// `amount` is what the provider charged, not an estimate
+    #[serde(default)]
+    pub charged: bool,
+
+    /// Location the tax was calculated for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<TaxLocation>,
+}
+
+/// Computes tax for an order
+pub trait TaxCalculator: Send + Sync {
+    /// Name of this calculator (e.g., "table", "stripe")
+    fn name(&self) -> &'static str;
+
+    /// Calculate tax for an order (discounts already applied)
+    fn calculate(&self, order: &Order, location: Option<&TaxLocation>)
+        -> PaymentResult<TaxBreakdown>;
+}
+
+/// Boxed tax calculator
+pub type BoxedTaxCalculator = Box<dyn TaxCalculator>;
+
+/// Table-driven tax calculator
+#[derive(Debug, Clone, Default)]
+pub struct TableTaxCalculator {
+    mode: TaxMode,
+    default_country: Option<String>,
+    rates: Vec<TaxRate>,
+}
+
+impl TableTaxCalculator {
+    /// Create a calculator with no rates
+    pub fn new(mode: TaxMode) -> Self {
+        Self {
+            mode,
+            default_country: None,
+            rates: Vec::new(),
+        }
+    }
+
+    /// Create from a site's tax config
+    pub fn from_config(config: &TaxConfig) -> Self {
+        Self {
+            mode: config.mode,
+            default_country: config.default_country.clone(),
+            rates: config.rates.clone(),
+        }
+    }
+
+    /// Builder: add a rate
+    pub fn with_rate(mut self, rate: TaxRate) -> Self {
+        self.rates.push(rate);
+        self
+    }
+
+    /// Builder: set the fallback country
+    pub fn with_default_country(mut self, country: impl Into<String>) -> Self {
+        self.default_country = Some(country.into());
+        self
+    }
+
+    /// Find the most specific rate for a location and product type
+    pub fn rate_for(&self, location: &TaxLocation, product_type: ProductType) -> Option<&TaxRate> {
+        self.rates
+            .iter()
+            .filter_map(|r| r.specificity(location, product_type).map(|s| (s, r)))
+            .max_by_key(|(s, _)| *s)
+            .map(|(_, r)| r)
+    }
+}
+
+impl TaxCalculator for TableTaxCalculator {
+    fn name(&self) -> &'static str {
+        "table"
+    }
+
+    fn calculate(
+        &self,
+        order: &Order,
+        location: Option<&TaxLocation>,
+    ) -> PaymentResult<TaxBreakdown> {
+        let location = location
+            .cloned()
+            .or_else(|| self.default_country.clone().map(TaxLocation::new));
+
+        let mut breakdown = TaxBreakdown {
+            mode: self.mode,
+            amount: Price::zero(order.currency),
+            lines: Vec::new(),
+            automatic: false,
+            charged: false,
+            location: location.clone(),
+        };
+        let Some(location) = location else {
+            return Ok(breakdown);
+        };
+
+        let subtotal = order.subtotal()?.amount as i128;
+        let discount = order.discount_total()?.amount as i128;
+
+        for (line_index, item) in order.line_items.iter().enumerate() {
+            let Some(rate) = self.rate_for(&location, item.product_type) else {
+                continue;
+            };
+
+            // Discounts reduce the taxable amount of each line pro rata
+            let line_total = item.total()?.amount as i128;
+            let line_discount = if subtotal > 0 {
+                discount * line_total / subtotal
+            } else {
+                0
+            };
+            let taxable = line_total - line_discount;
+
+            // Rate in ten-thousandths of a percent
+            let rate_units = rate.rate.to_minor_units(Money::SCALE)? as i128;
+            let hundred = 100 * 10_i128.pow(Money::SCALE as u32);
+            let tax = match self.mode {
+                TaxMode::Exclusive => div_round(taxable * rate_units, hundred),
+                TaxMode::Inclusive => div_round(taxable * rate_units, hundred + rate_units),
+            };
+            if tax == 0 {
+                continue;
+            }
+
+            let tax = i64::try_from(tax).map_err(|_| PaymentError::InvalidPrice {
+                message: "amount overflow in tax".to_string(),
+            })?;
+            let amount = Price::from_cents(tax, order.currency);
+            breakdown.amount = breakdown.amount.checked_add(&amount)?;
+            breakdown.lines.push(TaxLine {
+                line_index,
+                rate: rate.clone(),
+                amount,
+            });
+        }
+
+        Ok(breakdown)
+    }
+}
+
+/// Divide rounding half away from zero
+fn div_round(numerator: i128, denominator: i128) -> i128 {
+    let half = denominator / 2;
+    if numerator >= 0 {
+        (numerator + half) / denominator
+    } else {
+        (numerator - half) / denominator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::product::{Currency, Product};
+    use crate::promotion::AppliedDiscount;
+
+    fn pct(s: &str) -> Money {
+        s.parse().unwrap()
+    }
+
+    fn calculator(mode: TaxMode) -> TableTaxCalculator {
+        TableTaxCalculator::new(mode)
+            .with_rate(TaxRate::new("DE", pct("19"), "MwSt"))
+            .with_rate(TaxRate::new("DE", pct("7"), "MwSt").with_product_type(ProductType::Physical))
+            .with_rate(TaxRate::new("US", pct("0"), "Sales tax"))
+            .with_rate(TaxRate::new("US", pct("7.25"), "Sales tax").with_region("CA"))
+    }
+
+    fn order() -> Order {
+        let mut order = Order::new(Currency::EUR);
+        let digital = Product::one_time("d", "Digital", Price::from_cents(10000, Currency::EUR));
+        let mut physical = Product::one_time("p", "Book", Price::from_cents(2000, Currency::EUR));
+        physical.product_type = ProductType::Physical;
+        order.add_product(&digital, 1).unwrap();
+        order.add_product(&physical, 1).unwrap();
+        order
+    }
+
+    #[test]
+    fn test_rate_lookup() {
+        let calc = calculator(TaxMode::Exclusive);
+        let de = TaxLocation::new("de");
+        assert_eq!(calc.rate_for(&de, ProductType::Digital).unwrap().rate, pct("19"));
+        assert_eq!(calc.rate_for(&de, ProductType::Physical).unwrap().rate, pct("7"));
+
+        let ca = TaxLocation::new("US").with_region("CA");
+        assert_eq!(calc.rate_for(&ca, ProductType::Digital).unwrap().rate, pct("7.25"));
+        let ny = TaxLocation::new("US").with_region("NY");
+        assert_eq!(calc.rate_for(&ny, ProductType::Digital).unwrap().rate, pct("0"));
+
+        assert!(calc.rate_for(&TaxLocation::new("FR"), ProductType::Digital).is_none());
+    }
+
+    #[test]
+    fn test_exclusive_tax() {
+        let mut order = order();
+        let tax = calculator(TaxMode::Exclusive)
+            .calculate(&order, Some(&TaxLocation::new("DE")))
+            .unwrap();
+        assert_eq!(tax.amount.amount, 1900 + 140);
+        assert_eq!(tax.lines.len(), 2);
+
+        order.apply_tax(tax).unwrap();
+        let totals = order.totals().unwrap();
+        assert_eq!(totals.subtotal.amount, 12000);
+        assert_eq!(totals.tax.amount, 2040);
+        assert_eq!(totals.total.amount, 14040);
+    }
+
+    #[test]
+    fn test_inclusive_tax() {
+        let mut order = order();
+        let tax = calculator(TaxMode::Inclusive)
+            .calculate(&order, Some(&TaxLocation::new("DE")))
+            .unwrap();
+        // 100.00 incl. 19% = 15.97, 20.00 incl. 7% = 1.31
+        assert_eq!(tax.amount.amount, 1597 + 131);
+
+        order.apply_tax(tax).unwrap();
+        assert_eq!(order.total().unwrap().amount, 12000);
+    }
+
+    #[test]
+    fn test_tax_after_discount() {
+        let mut order = order();
+        order
+            .apply_discount(AppliedDiscount {
+                code: "HALF".into(),
+                description: String::new(),
+                amount: Price::from_cents(6000, Currency::EUR),
+            })
+            .unwrap();
+
+        let tax = calculator(TaxMode::Exclusive)
+            .calculate(&order, Some(&TaxLocation::new("DE")))
+            .unwrap();
+        assert_eq!(tax.amount.amount, 950 + 70);
+
+        order.apply_tax(tax).unwrap();
+        assert_eq!(order.total().unwrap().amount, 6000 + 1020);
+    }
+
+    #[test]
+    fn test_unknown_location_uses_default_country() {
+        let order = order();
+        let calc = calculator(TaxMode::Exclusive);
+        assert!(calc.calculate(&order, None).unwrap().amount.amount == 0);
+
+        let calc = calc.with_default_country("DE");
+        assert_eq!(calc.calculate(&order, None).unwrap().amount.amount, 2040);
+    }
+
+    #[test]
+    fn test_tax_config_from_toml() {
+        let config: TaxConfig = toml::from_str(
+            r#"
+            provider = "table"
+            mode = "inclusive"
+            default_country = "DE"
+
+            [[rates]]
+            country = "DE"
+            rate = "19"
+            name = "MwSt"
+
+            [[rates]]
+            country = "US"
+            region = "CA"
+            product_type = "physical"
+            rate = "7.25"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.provider, TaxProvider::Table);
+        assert_eq!(config.mode, TaxMode::Inclusive);
+        assert_eq!(config.rates[1].product_type, Some(ProductType::Physical));
+        assert_eq!(config.rates[1].name, "Tax");
+    }
+}
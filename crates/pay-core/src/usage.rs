@@ -0,0 +1,416 @@
+//! # Usage Billing
+//!
+//! How a subscription product is charged beyond a fixed price per unit:
+//!
+//! - **Licensed** (default): the price times a quantity, e.g. seats
+//! - **Metered**: usage reported during the period is billed at its end,
+//!   combined with an aggregation mode
+//! - **Tiered**: the unit price depends on the quantity, either per tier
+//!   (`graduated`) or for all units at the reached tier (`volume`)
+//!
+//! ```toml
+//! [products.usage]
+//! usage_type = "metered"
+//! aggregation = "sum"
+//! tiers_mode = "graduated"
+//!
+//! [[products.usage.tiers]]
+//! up_to = 10000
+//! unit_amount = 0       # first 10,000 calls free
+//!
+//! [[products.usage.tiers]]
+//! unit_amount = 1       # then 1 cent per call
+//! ```
+//!
+//! Tier amounts are in the smallest unit of the product's primary currency.
+
+use crate::error::{PaymentError, PaymentResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Whether the quantity is bought up front or reported as used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageType {
+    /// Quantity set at checkout (e.g., seats)
+    #[default]
+    Licensed,
+    /// Usage reported during the period, billed at its end
+    Metered,
+}
+
+impl UsageType {
+    /// Get the wire name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsageType::Licensed => "licensed",
+            UsageType::Metered => "metered",
+        }
+    }
+}
+
+/// How metered usage reported in a period is combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageAggregation {
+    /// Total of all reports
+    #[default]
+    Sum,
+    /// Largest report
+    Max,
+    /// Last report in the period
+    LastDuringPeriod,
+    /// Last report ever (carries over between periods)
+    LastEver,
+}
+
+impl UsageAggregation {
+    /// Get the wire name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsageAggregation::Sum => "sum",
+            UsageAggregation::Max => "max",
+            UsageAggregation::LastDuringPeriod => "last_during_period",
+            UsageAggregation::LastEver => "last_ever",
+        }
+    }
+}
+
+/// How tiers price a quantity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TiersMode {
+    /// Each unit is priced by the tier it falls in
+    #[default]
+    Graduated,
+    /// All units are priced by the tier the total falls in
+    Volume,
+}
+
+impl TiersMode {
+    /// Get the wire name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TiersMode::Graduated => "graduated",
+            TiersMode::Volume => "volume",
+        }
+    }
+}
+
+/// A row of a pricing table
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PriceTier {
+    /// Last quantity in this tier (None for the open-ended last tier)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub up_to: Option<u64>,
+
+    /// Price per unit (smallest currency unit)
+    #[serde(default)]
+    pub unit_amount: i64,
+
+    /// Fixed fee for reaching this tier (smallest currency unit)
+    #[serde(default)]
+    pub flat_amount: i64,
+}
+
+impl PriceTier {
+    /// Create a tier up to a quantity (None = no limit)
+    pub fn new(up_to: Option<u64>, unit_amount: i64) -> Self {
+        Self {
+            up_to,
+            unit_amount,
+            flat_amount: 0,
+        }
+    }
+
+    /// Builder: set a flat fee
+    pub fn with_flat_amount(mut self, flat_amount: i64) -> Self {
+        self.flat_amount = flat_amount;
+        self
+    }
+}
+
+/// How a product's usage is billed
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct UsageModel {
+    /// Licensed or metered
+    #[serde(default)]
+    pub usage_type: UsageType,
+
+    /// How metered usage is combined
+    #[serde(default)]
+    pub aggregation: UsageAggregation,
+
+    /// How tiers apply
+    #[serde(default)]
+    pub tiers_mode: TiersMode,
+
+    /// Pricing table (empty = the product price per unit)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tiers: Vec<PriceTier>,
+}
+
+impl UsageModel {
+    /// Fixed price per unit
+    pub fn licensed() -> Self {
+        Self::default()
+    }
+
+    /// Usage reported during the period
+    pub fn metered(aggregation: UsageAggregation) -> Self {
+        Self {
+            usage_type: UsageType::Metered,
+            aggregation,
+            ..Self::default()
+        }
+    }
+
+    /// Builder: price by a tier table
+    pub fn with_tiers(mut self, mode: TiersMode, tiers: Vec<PriceTier>) -> Self {
+        self.tiers_mode = mode;
+        self.tiers = tiers;
+        self
+    }
+
+    /// Check if usage is reported rather than bought up front
+    pub fn is_metered(&self) -> bool {
+        self.usage_type == UsageType::Metered
+    }
+
+    /// Check if a pricing table is used
+    pub fn is_tiered(&self) -> bool {
+        !self.tiers.is_empty()
+    }
+
+    /// Check the pricing table: increasing limits, non-negative amounts and
+    /// an open-ended last tier
+    pub fn validate(&self) -> PaymentResult<()> {
+        let invalid = |message: &str| {
+            Err(PaymentError::InvalidPrice {
+                message: format!("pricing tiers: {}", message),
+            })
+        };
+        let mut previous = 0;
+        for (i, tier) in self.tiers.iter().enumerate() {
+            if tier.unit_amount < 0 || tier.flat_amount < 0 {
+                return invalid("amounts must not be negative");
+            }
+            let last = i + 1 == self.tiers.len();
+            match tier.up_to {
+                Some(_) if last => return invalid("the last tier must have no up_to"),
+                None if !last => return invalid("only the last tier may omit up_to"),
+                Some(up_to) if up_to <= previous => {
+                    return invalid("up_to must increase from tier to tier")
+                }
+                Some(up_to) => previous = up_to,
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Amount charged up front for a quantity at `unit_amount` per unit.
+    ///
+    /// Metered usage is billed later, so it is charged nothing up front.
+    pub fn amount_for(&self, quantity: u32, unit_amount: i64) -> PaymentResult<i64> {
+        let overflow = || PaymentError::InvalidPrice {
+            message: "amount overflow in usage price".to_string(),
+        };
+        if self.is_metered() {
+            return Ok(0);
+        }
+        let units = u64::from(quantity);
+        let cost = |units: u64, tier: &PriceTier| {
+            i64::try_from(units)
+                .ok()
+                .and_then(|units| units.checked_mul(tier.unit_amount))
+                .and_then(|amount| amount.checked_add(tier.flat_amount))
+                .ok_or_else(overflow)
+        };
+
+        match self.tiers_mode {
+            _ if self.tiers.is_empty() => {
+                i64::from(quantity).checked_mul(unit_amount).ok_or_else(overflow)
+            }
+            TiersMode::Volume => {
+                let tier = self
+                    .tiers
+                    .iter()
+                    .find(|t| !matches!(t.up_to, Some(up_to) if units > up_to))
+                    .ok_or_else(overflow)?;
+                cost(units, tier)
+            }
+            TiersMode::Graduated => {
+                let mut total: i64 = 0;
+                let mut floor = 0;
+                for tier in &self.tiers {
+                    if units <= floor {
+                        break;
+                    }
+                    let ceiling = tier.up_to.unwrap_or(u64::MAX).min(units);
+                    total = total.checked_add(cost(ceiling - floor, tier)?).ok_or_else(overflow)?;
+                    floor = ceiling;
+                }
+                Ok(total)
+            }
+        }
+    }
+}
+
+/// How a usage report changes the recorded usage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageAction {
+    /// Add to the usage at this time
+    #[default]
+    Increment,
+    /// Replace the usage at this time
+    Set,
+}
+
+impl UsageAction {
+    /// Get the wire name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsageAction::Increment => "increment",
+            UsageAction::Set => "set",
+        }
+    }
+}
+
+/// Usage to report for a metered subscription item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// Provider's subscription item ID (si_...)
+    pub subscription_item_id: String,
+
+    /// Units used
+    pub quantity: u64,
+
+    /// Add to or replace the usage
+    #[serde(default)]
+    pub action: UsageAction,
+
+    /// When the usage happened (default: now)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+
+    /// Idempotency key (a retried report with the same key is recorded once)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+}
+
+impl UsageRecord {
+    /// Report `quantity` units used now
+    pub fn new(subscription_item_id: impl Into<String>, quantity: u64) -> Self {
+        Self {
+            subscription_item_id: subscription_item_id.into(),
+            quantity,
+            action: UsageAction::Increment,
+            timestamp: None,
+            idempotency_key: None,
+        }
+    }
+
+    /// Builder: set the action
+    pub fn with_action(mut self, action: UsageAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Builder: set when the usage happened
+    pub fn at(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Builder: set the idempotency key
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+}
+
+/// A usage report recorded by the provider
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageReport {
+    /// Provider's usage record ID
+    pub id: String,
+
+    /// Provider's subscription item ID
+    pub subscription_item_id: String,
+
+    /// Units recorded
+    pub quantity: u64,
+
+    /// When the usage happened
+    pub timestamp: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiers() -> Vec<PriceTier> {
+        vec![
+            PriceTier::new(Some(5), 1000),
+            PriceTier::new(Some(20), 800),
+            PriceTier::new(None, 500).with_flat_amount(2000),
+        ]
+    }
+
+    #[test]
+    fn test_tiered_amounts() {
+        let graduated = UsageModel::licensed().with_tiers(TiersMode::Graduated, tiers());
+        let volume = UsageModel::licensed().with_tiers(TiersMode::Volume, tiers());
+        assert!(graduated.validate().is_ok());
+
+        // 5 × 10.00 + 15 × 8.00 + (2 × 5.00 + 20.00)
+        assert_eq!(graduated.amount_for(22, 0).unwrap(), 5000 + 12000 + 3000);
+        assert_eq!(graduated.amount_for(3, 0).unwrap(), 3000);
+        assert_eq!(graduated.amount_for(0, 0).unwrap(), 0);
+        // All 22 seats at the third tier
+        assert_eq!(volume.amount_for(22, 0).unwrap(), 22 * 500 + 2000);
+        assert_eq!(volume.amount_for(6, 0).unwrap(), 6 * 800);
+        assert_eq!(volume.amount_for(5, 0).unwrap(), 5 * 1000);
+
+        assert_eq!(UsageModel::licensed().amount_for(3, 2900).unwrap(), 8700);
+        assert!(UsageModel::licensed().amount_for(u32::MAX, i64::MAX).is_err());
+        // Metered usage is billed in arrears
+        let metered = UsageModel::metered(UsageAggregation::Sum).with_tiers(TiersMode::Volume, tiers());
+        assert!(metered.is_metered());
+        assert_eq!(metered.amount_for(3, 2900).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_invalid_tiers() {
+        for tiers in [
+            // Open tier before the last, bounded last tier, limits not increasing
+            vec![PriceTier::new(None, 1), PriceTier::new(Some(10), 1)],
+            vec![PriceTier::new(Some(10), 1)],
+            vec![PriceTier::new(Some(10), 2), PriceTier::new(Some(10), 1), PriceTier::new(None, 1)],
+            // Negative amounts
+            vec![PriceTier::new(None, -1)],
+            vec![PriceTier::new(None, 1).with_flat_amount(-100)],
+        ] {
+            let model = UsageModel::licensed().with_tiers(TiersMode::Graduated, tiers.clone());
+            assert!(
+                matches!(model.validate(), Err(PaymentError::InvalidPrice { .. })),
+                "{:?}",
+                tiers
+            );
+        }
+    }
+
+    #[test]
+    fn test_usage_record_keeps_idempotency_key() {
+        let record = UsageRecord::new("si_1", 250).with_idempotency_key("batch-42");
+        assert_eq!(record.action, UsageAction::Increment);
+
+        // A retried report (e.g. re-sent by a queue) carries the same key
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(serde_json::from_str::<UsageRecord>(&json).unwrap(), record);
+        let fresh = UsageRecord::new("si_1", 250);
+        assert!(!serde_json::to_string(&fresh).unwrap().contains("idempotency_key"));
+    }
+}
@@ -0,0 +1,517 @@
+//! # Config Validation
+//!
+//! Checks the product catalog and site registry together, beyond what
+//! deserializing them catches.
+//!
+//! Errors make the config unusable (duplicate IDs, products of unknown
+//! sites, negative prices, subscriptions without an interval, variants with
+//! duplicate SKUs or unknown options); warnings are suspicious but allowed
+//! (free products, products of inactive sites).
+//!
+//! ```rust,ignore
+//! let report = validate_config(&catalog, &sites);
+//! for issue in report.warnings() {
+//!     tracing::warn!("{}", issue);
+//! }
+//! if report.has_errors() {
+//!     return Err(...);
+//! }
+//! ```
+
+use crate::product::{Price, Product, ProductCatalog, ProductType};
+use crate::site::SiteRegistry;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+
+/// How serious an issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The config must not be used
+    Error,
+    /// Allowed, but probably a mistake
+    Warning,
+}
+
+/// A problem found in the config
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigIssue {
+    /// Error or warning
+    pub severity: Severity,
+    /// What the issue is about (e.g., "product rang-play-rs-pro", "site chargegun")
+    pub subject: String,
+    /// What is wrong
+    pub message: String,
+}
+
+/// "error: product kit: price is negative"
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", severity, self.subject, self.message)
+    }
+}
+
+/// All issues found in a config
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    /// Issues in the order they were found
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl ValidationReport {
+    /// Issues that make the config unusable
+    pub fn errors(&self) -> impl Iterator<Item = &ConfigIssue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Error)
+    }
+
+    /// Issues that are allowed
+    pub fn warnings(&self) -> impl Iterator<Item = &ConfigIssue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Warning)
+    }
+
+    /// Check if any issue is an error
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    fn error(&mut self, subject: &str, message: impl Into<String>) {
+        self.push(Severity::Error, subject, message.into());
+    }
+
+    fn warning(&mut self, subject: &str, message: impl Into<String>) {
+        self.push(Severity::Warning, subject, message.into());
+    }
+
+    fn push(&mut self, severity: Severity, subject: &str, message: String) {
+        self.issues.push(ConfigIssue {
+            severity,
+            subject: subject.to_string(),
+            message,
+        });
+    }
+}
+
+/// Check a catalog and the registry its products are sold on
+pub fn validate_config(catalog: &ProductCatalog, sites: &SiteRegistry) -> ValidationReport {
+    let mut report = ValidationReport::default();
+
+    let mut site_ids = HashSet::new();
+    for site in &sites.sites {
+        let subject = format!("site {}", site.id);
+        if site.id.is_empty() {
+            report.error(&subject, "id is empty");
+        }
+        if !site_ids.insert(site.id.as_str()) {
+            report.error(&subject, "duplicate site id");
+        }
+        for (name, url) in [("success_url", &site.success_url), ("cancel_url", &site.cancel_url)] {
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                report.error(&subject, format!("{} is not an http(s) URL: {:?}", name, url));
+            }
+        }
+    }
+    if let Some(default_site) = sites.default_site_id() {
+        if !sites.has_site(default_site) {
+            report.warning(
+                &format!("site {}", default_site),
+                "default site is missing or inactive (legacy routes have no site)",
+            );
+        }
+    }
+
+    let mut product_ids = HashSet::new();
+    let mut skus = HashSet::new();
+    for product in &catalog.products {
+        let subject = format!("product {}", product.id);
+        if product.id.is_empty() {
+            report.error(&subject, "id is empty");
+        }
+        if !product_ids.insert(product.id.as_str()) {
+            report.error(&subject, "duplicate product id");
+        }
+        match sites.sites.iter().find(|s| s.id == product.site_id) {
+            None => report.error(&subject, format!("unknown site_id {:?}", product.site_id)),
+            Some(site) if !site.active && product.active => {
+                report.warning(&subject, format!("site {} is inactive", site.id))
+            }
+            Some(_) => {}
+        }
+        for shared in &product.shared_with {
+            if !site_ids.contains(shared.as_str()) {
+                report.error(&subject, format!("shared_with unknown site {:?}", shared));
+            }
+        }
+        validate_prices(&mut report, &subject, product);
+        validate_subscription_terms(&mut report, &subject, product);
+        validate_variants(&mut report, &subject, product, &mut skus);
+    }
+
+    report
+}
+
+/// Amounts and currencies
+fn validate_prices(report: &mut ValidationReport, subject: &str, product: &Product) {
+    validate_price_list(report, subject, &product.price, &product.prices);
+
+    // Metered and tiered products are priced by usage, so a zero base price is expected
+    let usage_priced = product
+        .usage
+        .as_ref()
+        .is_some_and(|u| u.is_metered() || u.is_tiered());
+    if product.price.amount == 0 && !usage_priced {
+        report.warning(subject, "price is zero");
+    }
+}
+
+/// One price per currency, none negative
+fn validate_price_list(report: &mut ValidationReport, subject: &str, price: &Price, prices: &[Price]) {
+    let mut currencies = HashSet::new();
+    for price in std::iter::once(price).chain(prices) {
+        if !currencies.insert(price.currency) {
+            report.error(subject, format!("more than one {} price", price.currency));
+        }
+        if price.amount < 0 {
+            report.error(subject, format!("{} price is negative", price.currency));
+        }
+    }
+}
+
+/// Option dimensions, and the variant IDs, SKUs, options and prices
+fn validate_variants<'a>(
+    report: &mut ValidationReport,
+    subject: &str,
+    product: &'a Product,
+    skus: &mut HashSet<&'a str>,
+) {
+    let mut option_names = HashSet::new();
+    for option in &product.options {
+        if !option_names.insert(option.name.as_str()) {
+            report.error(subject, format!("duplicate option {:?}", option.name));
+        }
+        if option.values.is_empty() {
+            report.error(subject, format!("option {:?} has no values", option.name));
+        }
+    }
+    if !product.options.is_empty() && !product.has_variants() {
+        report.warning(subject, "options are ignored without variants");
+    }
+
+    let mut variant_ids = HashSet::new();
+    let mut combinations = HashSet::new();
+    for variant in &product.variants {
+        let subject = format!("{} variant {}", subject, variant.id);
+        if variant.id.is_empty() {
+            report.error(&subject, "id is empty");
+        }
+        if !variant_ids.insert(variant.id.as_str()) {
+            report.error(&subject, "duplicate variant id");
+        }
+        if variant.sku.is_empty() {
+            report.error(&subject, "sku is empty");
+        } else if !skus.insert(variant.sku.as_str()) {
+            report.error(&subject, format!("duplicate sku {:?}", variant.sku));
+        }
+
+        for option in &product.options {
+            match variant.options.get(&option.name) {
+                None => report.error(&subject, format!("no value for option {:?}", option.name)),
+                Some(value) if !option.values.contains(value) => report.error(
+                    &subject,
+                    format!("{:?} is not a value of option {:?}", value, option.name),
+                ),
+                Some(_) => {}
+            }
+        }
+        for name in variant.options.keys() {
+            if !option_names.contains(name.as_str()) {
+                report.error(&subject, format!("unknown option {:?}", name));
+            }
+        }
+        if !variant.options.is_empty() && !combinations.insert(&variant.options) {
+            report.error(&subject, "same options as another variant");
+        }
+
+        validate_price_list(report, &subject, &variant.price, &variant.prices);
+        if let Some(ref intro) = product.intro_price {
+            if intro.price_in(variant.price.currency).is_some_and(|p| p.amount > variant.price.amount) {
+                report.warning(&subject, "intro_price is above the variant price and is not offered");
+            }
+        }
+    }
+    if product.active && product.has_variants() && !product.variants.iter().any(|v| v.active) {
+        report.warning(subject, "no active variants");
+    }
+}
+
+/// Interval, trial, intro price and usage model
+fn validate_subscription_terms(report: &mut ValidationReport, subject: &str, product: &Product) {
+    let recurring = product.billing_interval.is_recurring();
+    if product.product_type == ProductType::Subscription && !recurring {
+        report.error(subject, "subscription product has billing_interval \"onetime\"");
+    }
+    if let Err(e) = product.billing_interval.validate() {
+        report.error(subject, e.to_string());
+    }
+
+    if let Some(days) = product.trial_days {
+        if !recurring {
+            report.warning(subject, "trial_days is ignored for one-time products");
+        } else if days == 0 {
+            report.error(subject, "trial_days must be at least 1");
+        }
+    }
+
+    if let Some(ref intro) = product.intro_price {
+        if !recurring {
+            report.warning(subject, "intro_price is ignored for one-time products");
+        }
+        if intro.periods == 0 {
+            report.error(subject, "intro_price periods must be at least 1");
+        }
+        for price in std::iter::once(&intro.price).chain(&intro.prices) {
+            match product.price_in(price.currency) {
+                Some(regular) if price.amount < 0 || price.amount > regular.amount => {
+                    report.error(subject, intro_error(price, "between zero and the regular price"))
+                }
+                None => report.warning(subject, intro_error(price, "in a currency the product is not sold in")),
+                Some(_) => {}
+            }
+        }
+    }
+
+    if let Some(ref usage) = product.usage {
+        if !recurring {
+            report.error(subject, "usage billing needs a recurring billing_interval");
+        }
+        if let Err(e) = usage.validate() {
+            report.error(subject, e.to_string());
+        }
+    }
+}
+
+fn intro_error(price: &Price, problem: &str) -> String {
+    format!("{} intro_price must be {}", price.currency, problem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::product::{BillingInterval, Currency, IntroPrice, TrialPaymentMethod};
+    use crate::site::Site;
+    use crate::usage::{PriceTier, TiersMode, UsageAggregation, UsageModel};
+    use crate::variant::{ProductOption, ProductVariant};
+
+    fn usd(amount: i64) -> Price {
+        Price::from_cents(amount, Currency::USD)
+    }
+
+    fn sites() -> SiteRegistry {
+        let mut luckydrone = Site::new("luckydrone", "LuckyDrone", "luckydrone.io");
+        luckydrone.active = false;
+        SiteRegistry::with_default("chargegun")
+            .with_site(Site::new("chargegun", "ChargeGun", "chargegun.io"))
+            .with_site(luckydrone)
+    }
+
+    fn issues_with(sites: &SiteRegistry, products: Vec<Product>) -> Vec<String> {
+        validate_config(&ProductCatalog { products }, sites)
+            .issues
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    fn one_time(id: &str, amount: i64) -> Product {
+        Product::one_time(id, id, usd(amount))
+    }
+
+    fn monthly(id: &str, amount: i64) -> Product {
+        Product::subscription(id, id, usd(amount), BillingInterval::MONTHLY)
+    }
+
+    fn licensed(id: &str, options: &[(&str, &[&str])], variants: Vec<ProductVariant>) -> Product {
+        let mut product = one_time(id, 1999);
+        for (name, values) in options {
+            product = product.with_option(ProductOption::new(*name, values.iter().copied()));
+        }
+        variants.into_iter().fold(product, Product::with_variant)
+    }
+
+    fn variant(id: &str, sku: &str, license: &str) -> ProductVariant {
+        ProductVariant::new(id, id, sku, usd(1999)).with_option("license", license)
+    }
+
+    const LICENSE: (&str, &[&str]) = ("license", &["personal", "team"]);
+
+    #[test]
+    fn test_validate_products() {
+        let valid = ProductCatalog {
+            products: vec![monthly("pro", 2900), one_time("kit", 1000)],
+        };
+        let report = validate_config(&valid, &sites());
+        assert!(report.issues.is_empty());
+        assert!(!report.has_errors());
+
+        let mut retired = one_time("retired", 1000).with_site("luckydrone");
+        retired.active = false;
+        let mut double = one_time("double", 1000);
+        double.prices.push(usd(1200));
+        let mut sub = Product::subscription("sub", "Sub", usd(900), BillingInterval::OneTime);
+        sub.product_type = ProductType::Subscription;
+        let eur_intro = IntroPrice::new(usd(900), 3).with_price(Price::from_cents(800, Currency::EUR));
+        let products = vec![
+            one_time("kit", 1000),
+            one_time("kit", 1000),
+            one_time("orphan", 1000).with_site("nowhere"),
+            one_time("shelved", 1000).with_site("luckydrone"),
+            retired,
+            one_time("shared", 1000).shared_with("luckydrone").shared_with("nowhere"),
+            one_time("refund", -5),
+            double,
+            one_time("free", 0),
+            // Usage-priced products may list a zero price
+            monthly("api", 0).with_usage(UsageModel::metered(UsageAggregation::Sum)),
+            monthly("seats", 0).with_usage(
+                UsageModel::licensed().with_tiers(TiersMode::Graduated, vec![PriceTier::new(None, 500)]),
+            ),
+            sub,
+            one_time("trial", 1000).with_trial(7, TrialPaymentMethod::Always),
+            monthly("no-trial", 2900).with_trial(0, TrialPaymentMethod::Always),
+            one_time("intro", 1000).with_intro_price(IntroPrice::new(usd(500), 1)),
+            monthly("no-periods", 2900).with_intro_price(IntroPrice::new(usd(900), 0)),
+            monthly("above", 2900).with_intro_price(IntroPrice::new(usd(3900), 3)),
+            monthly("negative", 2900).with_intro_price(IntroPrice::new(usd(-1), 3)),
+            monthly("eur-intro", 2900).with_intro_price(eur_intro),
+            one_time("usage", 1000).with_usage(UsageModel::licensed()),
+            monthly("open-tier", 0).with_usage(
+                UsageModel::licensed().with_tiers(TiersMode::Volume, vec![PriceTier::new(Some(10), 500)]),
+            ),
+        ];
+        let report = validate_config(&ProductCatalog { products }, &sites());
+        assert!(report.has_errors());
+        let issues: Vec<String> = report.issues.iter().map(ToString::to_string).collect();
+        let (tiers, issues) = issues.split_last().unwrap();
+        assert_eq!(
+            issues,
+            [
+                "error: product kit: duplicate product id",
+                "error: product orphan: unknown site_id \"nowhere\"",
+                "warning: product shelved: site luckydrone is inactive",
+                "error: product shared: shared_with unknown site \"nowhere\"",
+                "error: product refund: USD price is negative",
+                "error: product double: more than one USD price",
+                "warning: product free: price is zero",
+                "error: product sub: subscription product has billing_interval \"onetime\"",
+                "warning: product trial: trial_days is ignored for one-time products",
+                "error: product no-trial: trial_days must be at least 1",
+                "warning: product intro: intro_price is ignored for one-time products",
+                "error: product no-periods: intro_price periods must be at least 1",
+                "error: product above: USD intro_price must be between zero and the regular price",
+                "error: product negative: USD intro_price must be between zero and the regular price",
+                "warning: product eur-intro: EUR intro_price must be in a currency the product is not sold in",
+                "error: product usage: usage billing needs a recurring billing_interval",
+            ]
+        );
+        assert!(tiers.starts_with("error: product open-tier: "));
+        assert!(tiers.contains("the last tier must have no up_to"));
+    }
+
+    #[test]
+    fn test_validate_sites() {
+        let mut chargegun = Site::new("chargegun", "ChargeGun", "chargegun.io");
+        chargegun.success_url = "chargegun.io/thanks".to_string();
+        let mut sites = SiteRegistry::with_default("gone")
+            .with_site(chargegun)
+            .with_site(Site::new("chargegun", "ChargeGun again", "chargegun.io"));
+        let message = "default site is missing or inactive (legacy routes have no site)";
+        assert_eq!(
+            issues_with(&sites, vec![]),
+            [
+                "error: site chargegun: success_url is not an http(s) URL: \"chargegun.io/thanks\"".to_string(),
+                "error: site chargegun: duplicate site id".to_string(),
+                format!("warning: site gone: {}", message),
+            ]
+        );
+
+        sites = self::sites();
+        sites.set_default("luckydrone");
+        assert_eq!(issues_with(&sites, vec![]), [format!("warning: site luckydrone: {}", message)]);
+    }
+
+    #[test]
+    fn test_validate_variants() {
+        let docker =
+            ProductVariant::new("docker", "Docker", "DOCKER", usd(1999)).with_option("format", "docker");
+        let inactive = |sku| vec![variant("personal", sku, "personal").inactive()];
+        let mut retired = licensed("retired", &[LICENSE], inactive("RET-P"));
+        retired.active = false;
+        let products = vec![
+            licensed(
+                "rang",
+                &[LICENSE],
+                vec![variant("personal", "RPR-P", "personal"), variant("team", "RPR-P", "team")],
+            ),
+            // SKUs are unique across the catalog
+            licensed("rang-2", &[LICENSE], vec![variant("personal", "RPR-P", "personal")]),
+            licensed(
+                "ids",
+                &[LICENSE],
+                vec![variant("personal", "IDS-P", "personal"), variant("personal", "IDS-T", "team")],
+            ),
+            licensed("nameless", &[], vec![ProductVariant::new("", "Nameless", "", usd(1999))]),
+            licensed("values", &[LICENSE], vec![variant("site", "VAL-S", "site")]),
+            licensed("formats", &[LICENSE], vec![docker]),
+            licensed(
+                "twins",
+                &[LICENSE],
+                vec![variant("personal", "TW-P", "personal"), variant("solo", "TW-S", "personal")],
+            ),
+            licensed("cheap", &[], vec![ProductVariant::new("personal", "Personal", "CHEAP-P", usd(-1))]),
+            monthly("pro", 2900)
+                .with_intro_price(IntroPrice::new(usd(1900), 3))
+                .with_variant(ProductVariant::new("personal", "Personal", "PRO-P", usd(900)))
+                .with_variant(ProductVariant::new("team", "Team", "PRO-T", usd(4900))),
+            licensed("no-variants", &[LICENSE], vec![]),
+            licensed("off-sale", &[LICENSE], inactive("OFF-P")),
+            // Not worth a warning when the product is off sale too
+            retired,
+        ];
+        assert_eq!(
+            issues_with(&sites(), products),
+            [
+                "error: product rang variant team: duplicate sku \"RPR-P\"",
+                "error: product rang-2 variant personal: duplicate sku \"RPR-P\"",
+                "error: product ids variant personal: duplicate variant id",
+                "error: product nameless variant : id is empty",
+                "error: product nameless variant : sku is empty",
+                "error: product values variant site: \"site\" is not a value of option \"license\"",
+                "error: product formats variant docker: no value for option \"license\"",
+                "error: product formats variant docker: unknown option \"format\"",
+                "error: product twins variant solo: same options as another variant",
+                "error: product cheap variant personal: USD price is negative",
+                "warning: product pro variant personal: intro_price is above the variant price and is not offered",
+                "warning: product no-variants: options are ignored without variants",
+                "warning: product off-sale: no active variants",
+            ]
+        );
+
+        let options = licensed(
+            "options",
+            &[LICENSE, ("license", &["site"]), ("format", &[])],
+            vec![variant("personal", "OPT-P", "personal").with_option("format", "docker")],
+        );
+        let issues = issues_with(&sites(), vec![options]);
+        assert_eq!(
+            &issues[..2],
+            [
+                "error: product options: duplicate option \"license\"",
+                "error: product options: option \"format\" has no values",
+            ]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! # Product Variants
+//!
+//! A product can come in variants, e.g. license tiers and delivery formats:
+//! the product lists its option dimensions, and each variant picks one value
+//! per dimension and has its own price, SKU, active flag and metadata.
+//!
+//! ```toml
+//! [[products.options]]
+//! name = "license"
+//! values = ["personal", "team"]
+//!
+//! [[products.variants]]
+//! id = "team"
+//! name = "Team"
+//! sku = "RPR-TEAM"
+//! options = { license = "team" }
+//! price = { amount = 9900, currency = "usd" }
+//! ```
+//!
+//! Checkout items pick a variant with `variant_id`; the product's own price
+//! is only the "from" price shown in listings.
+
+use crate::product::{Currency, Price};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// An option dimension of a product (e.g., "license": personal, team, enterprise)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProductOption {
+    /// Dimension name (e.g., "license")
+    pub name: String,
+
+    /// Allowed values, in display order
+    pub values: Vec<String>,
+}
+
+impl ProductOption {
+    /// Create an option dimension
+    pub fn new<I, S>(name: impl Into<String>, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// A purchasable variant of a product
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProductVariant {
+    /// Variant identifier, unique within the product (e.g., "team-docker")
+    pub id: String,
+
+    /// Display name (e.g., "Team, Docker")
+    pub name: String,
+
+    /// Stock keeping unit, unique within the catalog
+    pub sku: String,
+
+    /// Chosen value per option dimension
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub options: BTreeMap<String, String>,
+
+    /// Price (primary currency)
+    pub price: Price,
+
+    /// Additional prices in other currencies (one per currency)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prices: Vec<Price>,
+
+    /// Whether this variant is available for purchase
+    #[serde(default = "default_true")]
+    pub active: bool,
+
+    /// Optional metadata (delivery format, seat limit, etc.)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl ProductVariant {
+    /// Create an active variant
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        sku: impl Into<String>,
+        price: Price,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            sku: sku.into(),
+            options: BTreeMap::new(),
+            price,
+            prices: Vec::new(),
+            active: true,
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Builder: choose a value for an option dimension
+    pub fn with_option(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options.insert(name.into(), value.into());
+        self
+    }
+
+    /// Builder: add a price in another currency (replaces any existing price in that currency)
+    pub fn with_price(mut self, price: Price) -> Self {
+        if price.currency == self.price.currency {
+            self.price = price;
+        } else {
+            self.prices.retain(|p| p.currency != price.currency);
+            self.prices.push(price);
+        }
+        self
+    }
+
+    /// Builder: add metadata
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Builder: take the variant off sale
+    pub fn inactive(mut self) -> Self {
+        self.active = false;
+        self
+    }
+
+    /// Get the price in a specific currency, if this variant is sold in it
+    pub fn price_in(&self, currency: Currency) -> Option<&Price> {
+        std::iter::once(&self.price)
+            .chain(self.prices.iter())
+            .find(|p| p.currency == currency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::product::ProductCatalog;
+
+    #[test]
+    fn test_variants_from_toml() {
+        let toml = r#"
+            [[products]]
+            id = "rang-play-rs"
+            name = "Rang Play RS"
+            description = "RNG service"
+            price = { amount = 1999, currency = "usd" }
+
+            [[products.options]]
+            name = "license"
+            values = ["personal", "team"]
+
+            [[products.variants]]
+            id = "personal"
+            name = "Personal"
+            sku = "RPR-PERSONAL"
+            options = { license = "personal" }
+            price = { amount = 1999, currency = "usd" }
+            prices = [{ amount = 1899, currency = "eur" }]
+
+            [[products.variants]]
+            id = "team"
+            name = "Team"
+            sku = "RPR-TEAM"
+            options = { license = "team" }
+            price = { amount = 9900, currency = "usd" }
+            active = false
+            metadata = { seats = "10" }
+        "#;
+        let catalog = ProductCatalog::from_toml(toml).unwrap();
+        let product = catalog.get("rang-play-rs").unwrap();
+        assert_eq!(product.options, vec![ProductOption::new("license", ["personal", "team"])]);
+
+        // Active by default, priced per currency
+        let personal = product.variant("personal").unwrap();
+        assert!(personal.active);
+        assert_eq!(personal.price_in(Currency::USD).unwrap().amount, 1999);
+        assert_eq!(personal.price_in(Currency::EUR).unwrap().amount, 1899);
+        assert!(personal.price_in(Currency::GBP).is_none());
+
+        // The builders produce the same variant as the TOML
+        let team = ProductVariant::new("team", "Team", "RPR-TEAM", Price::from_cents(9900, Currency::USD))
+            .with_option("license", "team")
+            .with_metadata("seats", "10")
+            .inactive();
+        assert_eq!(product.variant("team"), Some(&team));
+        assert!(product.variant("enterprise").is_none());
+        assert!(product.variant("").is_none());
+    }
+
+    #[test]
+    fn test_with_price_replaces_currency() {
+        let variant = ProductVariant::new("team", "Team", "RPR-TEAM", Price::from_cents(9900, Currency::USD))
+            .with_price(Price::from_cents(8900, Currency::EUR))
+            .with_price(Price::from_cents(8500, Currency::EUR))
+            .with_price(Price::from_cents(9500, Currency::USD));
+        assert_eq!(variant.price.amount, 9500);
+        assert_eq!(variant.prices, vec![Price::from_cents(8500, Currency::EUR)]);
+    }
+}
@@ -0,0 +1,205 @@
+//! # SQLite Customer Repository
+//!
+//! `CustomerRepository` backed by SQLite.
+//!
+//! Customers are stored as JSON keyed by ID and (unique) email; provider
+//! IDs are copied to `customer_provider_ids` for lookups.
+
+use crate::db::{storage_error, Database};
+use async_trait::async_trait;
+use pay_core::customer::normalize_email;
+use pay_core::{Customer, CustomerRepository, PaymentError, PaymentResult};
+use rusqlite::{params, Connection, OptionalExtension};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS customers (
+    id            TEXT PRIMARY KEY,
+    email         TEXT NOT NULL UNIQUE,
+    customer_json TEXT NOT NULL,
+    created_at    TEXT NOT NULL,
+    updated_at    TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS customer_provider_ids (
+    provider             TEXT NOT NULL,
+    provider_customer_id TEXT NOT NULL,
+    customer_id          TEXT NOT NULL REFERENCES customers (id),
+    PRIMARY KEY (provider, provider_customer_id)
+);
+CREATE INDEX IF NOT EXISTS idx_customer_provider_ids_customer_id
+    ON customer_provider_ids (customer_id);
+";
+
+/// SQLite customer repository
+#[derive(Clone)]
+pub struct SqliteCustomerRepository {
+    db: Database,
+}
+
+impl SqliteCustomerRepository {
+    /// Create the repository, creating tables if needed
+    pub fn new(db: Database) -> PaymentResult<Self> {
+        db.call_sync(|conn| conn.execute_batch(SCHEMA).map_err(storage_error))?;
+        Ok(Self { db })
+    }
+}
+
+#[async_trait]
+impl CustomerRepository for SqliteCustomerRepository {
+    async fn save(&self, customer: &Customer) -> PaymentResult<()> {
+        let customer = customer.clone();
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                save(&tx, &customer)?;
+                tx.commit().map_err(storage_error)
+            })
+            .await
+    }
+
+    async fn get(&self, customer_id: &str) -> PaymentResult<Option<Customer>> {
+        let customer_id = customer_id.to_string();
+        self.db
+            .call(move |conn| {
+                load(conn, "SELECT customer_json FROM customers WHERE id = ?1", &[&customer_id])
+            })
+            .await
+    }
+
+    async fn find_by_email(&self, email: &str) -> PaymentResult<Option<Customer>> {
+        let email = normalize_email(email);
+        self.db
+            .call(move |conn| {
+                load(conn, "SELECT customer_json FROM customers WHERE email = ?1", &[&email])
+            })
+            .await
+    }
+
+    async fn find_by_provider_id(
+        &self,
+        provider: &str,
+        provider_customer_id: &str,
+    ) -> PaymentResult<Option<Customer>> {
+        let provider = provider.to_string();
+        let provider_customer_id = provider_customer_id.to_string();
+        self.db
+            .call(move |conn| {
+                load(
+                    conn,
+                    "SELECT c.customer_json FROM customers c
+                     JOIN customer_provider_ids p ON p.customer_id = c.id
+                     WHERE p.provider = ?1 AND p.provider_customer_id = ?2",
+                    &[&provider, &provider_customer_id],
+                )
+            })
+            .await
+    }
+
+    /// Find, merge and save in one transaction
+    async fn upsert(&self, customer: &Customer) -> PaymentResult<Customer> {
+        let customer = customer.clone();
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                let stored = match load(
+                    &tx,
+                    "SELECT customer_json FROM customers WHERE email = ?1",
+                    &[&customer.email],
+                )? {
+                    Some(mut existing) => {
+                        if existing.merge(&customer) {
+                            save(&tx, &existing)?;
+                        }
+                        existing
+                    }
+                    None => {
+                        save(&tx, &customer)?;
+                        customer
+                    }
+                };
+                tx.commit().map_err(storage_error)?;
+                Ok(stored)
+            })
+            .await
+    }
+}
+
+fn load(conn: &Connection, sql: &str, values: &[&String]) -> PaymentResult<Option<Customer>> {
+    let json: Option<String> = conn
+        .query_row(sql, rusqlite::params_from_iter(values), |row| row.get(0))
+        .optional()
+        .map_err(storage_error)?;
+    json.map(|json| {
+        serde_json::from_str(&json).map_err(|e| PaymentError::Serialization(e.to_string()))
+    })
+    .transpose()
+}
+
+fn save(conn: &Connection, customer: &Customer) -> PaymentResult<()> {
+    let json =
+        serde_json::to_string(customer).map_err(|e| PaymentError::Serialization(e.to_string()))?;
+    conn.execute(
+        "INSERT INTO customers (id, email, customer_json, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT (id) DO UPDATE SET
+             email = excluded.email,
+             customer_json = excluded.customer_json,
+             updated_at = excluded.updated_at",
+        params![
+            customer.id,
+            customer.email,
+            json,
+            customer.created_at.to_rfc3339(),
+            customer.updated_at.to_rfc3339(),
+        ],
+    )
+    .map_err(storage_error)?;
+
+    conn.execute(
+        "DELETE FROM customer_provider_ids WHERE customer_id = ?1",
+        params![customer.id],
+    )
+    .map_err(storage_error)?;
+    for (provider, provider_customer_id) in &customer.provider_ids {
+        // A provider ID belongs to one customer; the latest record wins
+        conn.execute(
+            "INSERT INTO customer_provider_ids (provider, provider_customer_id, customer_id)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT (provider, provider_customer_id) DO UPDATE SET
+                 customer_id = excluded.customer_id",
+            params![provider, provider_customer_id, customer.id],
+        )
+        .map_err(storage_error)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_customer_roundtrip() {
+        let repo = SqliteCustomerRepository::new(Database::open_in_memory().unwrap()).unwrap();
+
+        let created = repo
+            .upsert(&Customer::new("buyer@example.com").with_site("chargegun"))
+            .await
+            .unwrap();
+        let merged = repo
+            .upsert(
+                &Customer::new("BUYER@example.com")
+                    .with_site("luckydrone")
+                    .with_provider_id("stripe", "cus_1"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(merged.id, created.id);
+        assert_eq!(merged.sites, vec!["chargegun", "luckydrone"]);
+
+        let found = repo.find_by_provider_id("stripe", "cus_1").await.unwrap().unwrap();
+        assert_eq!(found, merged);
+        assert_eq!(repo.get(&created.id).await.unwrap(), Some(merged));
+        assert!(repo.find_by_email("other@example.com").await.unwrap().is_none());
+    }
+}
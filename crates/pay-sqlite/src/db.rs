@@ -0,0 +1,86 @@
+//! # Database Handle
+//!
+//! Shared SQLite connection used by all stores in this crate.
+
+use chrono::{DateTime, Utc};
+use pay_core::{PaymentError, PaymentResult};
+use rusqlite::Connection;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Shared SQLite connection
+#[derive(Clone)]
+pub struct Database {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl Database {
+    /// Open (or create) a database file
+    pub fn open(path: impl AsRef<Path>) -> PaymentResult<Self> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                PaymentError::Storage(format!("Failed to create {}: {}", dir.display(), e))
+            })?;
+        }
+        let conn = Connection::open(path).map_err(storage_error)?;
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;")
+            .map_err(storage_error)?;
+        Ok(Self::from_connection(conn))
+    }
+
+    /// Open a private in-memory database (for tests)
+    pub fn open_in_memory() -> PaymentResult<Self> {
+        let conn = Connection::open_in_memory().map_err(storage_error)?;
+        conn.execute_batch("PRAGMA foreign_keys = ON;")
+            .map_err(storage_error)?;
+        Ok(Self::from_connection(conn))
+    }
+
+    fn from_connection(conn: Connection) -> Self {
+        Self {
+            conn: Arc::new(Mutex::new(conn)),
+        }
+    }
+
+    /// Run a closure with the connection on the blocking thread pool
+    pub async fn call<T, F>(&self, f: F) -> PaymentResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> PaymentResult<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn
+                .lock()
+                .map_err(|_| PaymentError::Storage("database lock poisoned".to_string()))?;
+            f(&mut conn)
+        })
+        .await
+        .map_err(|e| PaymentError::Storage(format!("database task failed: {}", e)))?
+    }
+
+    /// Run a closure with the connection on the current thread (for setup)
+    pub fn call_sync<T>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> PaymentResult<T>,
+    ) -> PaymentResult<T> {
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| PaymentError::Storage("database lock poisoned".to_string()))?;
+        f(&mut conn)
+    }
+}
+
+/// Convert a SQLite error into a `PaymentError`
+pub fn storage_error(e: rusqlite::Error) -> PaymentError {
+    PaymentError::Storage(e.to_string())
+}
+
+/// Parse an RFC 3339 timestamp column
+pub(crate) fn parse_time(s: &str) -> PaymentResult<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| PaymentError::Serialization(format!("invalid timestamp {:?}: {}", s, e)))
+}
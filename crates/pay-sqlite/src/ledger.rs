@@ -0,0 +1,227 @@
+//! # SQLite Webhook Ledger
+//!
+//! `WebhookLedger` backed by SQLite.
+//!
+//! Timestamps are stored as fixed-width RFC 3339 strings so the retention
+//! purge can compare them as text.
+
+use crate::db::{parse_time, storage_error, Database};
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use pay_core::ledger::{claim_existing, record_outcome};
+use pay_core::{
+    EventClaim, EventOutcome, LedgerEntry, PaymentError, PaymentResult, WebhookEvent,
+    WebhookLedger,
+};
+use rusqlite::{params, Connection, OptionalExtension};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS webhook_events (
+    event_id    TEXT PRIMARY KEY,
+    provider    TEXT NOT NULL,
+    event_type  TEXT NOT NULL,
+    outcome     TEXT NOT NULL,
+    error       TEXT,
+    attempts    INTEGER NOT NULL,
+    received_at TEXT NOT NULL,
+    updated_at  TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_webhook_events_received_at ON webhook_events (received_at);
+";
+
+/// SQLite webhook ledger
+#[derive(Clone)]
+pub struct SqliteWebhookLedger {
+    db: Database,
+}
+
+impl SqliteWebhookLedger {
+    /// Create the ledger, creating tables if needed
+    pub fn new(db: Database) -> PaymentResult<Self> {
+        db.call_sync(|conn| conn.execute_batch(SCHEMA).map_err(storage_error))?;
+        Ok(Self { db })
+    }
+}
+
+#[async_trait]
+impl WebhookLedger for SqliteWebhookLedger {
+    async fn begin(&self, event: &WebhookEvent) -> PaymentResult<EventClaim> {
+        let entry = LedgerEntry::new(event);
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                let claim = match load(&tx, &entry.event_id)? {
+                    Some(mut existing) => {
+                        let claim = claim_existing(&mut existing, entry.received_at);
+                        if claim == EventClaim::New {
+                            save(&tx, &existing)?;
+                        }
+                        claim
+                    }
+                    None => {
+                        save(&tx, &entry)?;
+                        EventClaim::New
+                    }
+                };
+                tx.commit().map_err(storage_error)?;
+                Ok(claim)
+            })
+            .await
+    }
+
+    async fn record(
+        &self,
+        event_id: &str,
+        outcome: EventOutcome,
+        error: Option<&str>,
+    ) -> PaymentResult<()> {
+        let event_id = event_id.to_string();
+        let error = error.map(String::from);
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                let mut entry = load(&tx, &event_id)?.ok_or_else(|| {
+                    PaymentError::Storage(format!("webhook event {} was not claimed", event_id))
+                })?;
+                record_outcome(&mut entry, outcome, error.as_deref(), Utc::now());
+                save(&tx, &entry)?;
+                tx.commit().map_err(storage_error)
+            })
+            .await
+    }
+
+    async fn get(&self, event_id: &str) -> PaymentResult<Option<LedgerEntry>> {
+        let event_id = event_id.to_string();
+        self.db.call(move |conn| load(conn, &event_id)).await
+    }
+
+    async fn purge_before(&self, cutoff: DateTime<Utc>) -> PaymentResult<usize> {
+        self.db
+            .call(move |conn| {
+                conn.execute(
+                    "DELETE FROM webhook_events WHERE received_at < ?1",
+                    params![format_time(cutoff)],
+                )
+                .map_err(storage_error)
+            })
+            .await
+    }
+}
+
+fn load(conn: &Connection, event_id: &str) -> PaymentResult<Option<LedgerEntry>> {
+    let row = conn
+        .query_row(
+            "SELECT provider, event_type, outcome, error, attempts, received_at, updated_at
+             FROM webhook_events WHERE event_id = ?1",
+            params![event_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, u32>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, String>(6)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(storage_error)?;
+
+    let Some((provider, event_type, outcome, error, attempts, received_at, updated_at)) = row
+    else {
+        return Ok(None);
+    };
+    Ok(Some(LedgerEntry {
+        event_id: event_id.to_string(),
+        provider,
+        event_type,
+        outcome: outcome.parse()?,
+        error,
+        attempts,
+        received_at: parse_time(&received_at)?,
+        updated_at: parse_time(&updated_at)?,
+    }))
+}
+
+fn save(conn: &Connection, entry: &LedgerEntry) -> PaymentResult<()> {
+    conn.execute(
+        "INSERT INTO webhook_events
+             (event_id, provider, event_type, outcome, error, attempts, received_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT (event_id) DO UPDATE SET
+             outcome = excluded.outcome,
+             error = excluded.error,
+             attempts = excluded.attempts,
+             updated_at = excluded.updated_at",
+        params![
+            entry.event_id,
+            entry.provider,
+            entry.event_type,
+            entry.outcome.as_str(),
+            entry.error,
+            entry.attempts,
+            format_time(entry.received_at),
+            format_time(entry.updated_at),
+        ],
+    )
+    .map_err(storage_error)?;
+    Ok(())
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use pay_core::WebhookEventType;
+
+    fn event(id: &str) -> WebhookEvent {
+        WebhookEvent {
+            event_id: id.into(),
+            event_type: WebhookEventType::Unknown("charge.dispute.created".into()),
+            provider: "stripe".into(),
+            session_id: None,
+            payment_intent_id: None,
+            customer_email: None,
+            amount_paid: None,
+            currency: None,
+            raw_data: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ledger_round_trip() {
+        let ledger = SqliteWebhookLedger::new(Database::open_in_memory().unwrap()).unwrap();
+
+        assert_eq!(ledger.begin(&event("evt_1")).await.unwrap(), EventClaim::New);
+        ledger
+            .record("evt_1", EventOutcome::Failed, Some("store down"))
+            .await
+            .unwrap();
+        let failed = ledger.get("evt_1").await.unwrap().unwrap();
+        assert_eq!(failed.outcome, EventOutcome::Failed);
+        assert_eq!(failed.error.as_deref(), Some("store down"));
+
+        assert_eq!(ledger.begin(&event("evt_1")).await.unwrap(), EventClaim::New);
+        ledger.record("evt_1", EventOutcome::Processed, None).await.unwrap();
+        let EventClaim::Duplicate(entry) = ledger.begin(&event("evt_1")).await.unwrap() else {
+            panic!("expected duplicate");
+        };
+        assert_eq!(entry.attempts, 2);
+        assert_eq!(entry.event_type, "charge.dispute.created");
+        assert_eq!(entry.error, None);
+
+        assert!(ledger
+            .record("evt_unknown", EventOutcome::Processed, None)
+            .await
+            .is_err());
+        assert_eq!(ledger.purge_before(Utc::now() - Duration::days(30)).await.unwrap(), 0);
+        assert_eq!(ledger.purge_before(Utc::now() + Duration::seconds(1)).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,31 @@
+//! # pay-sqlite
+//!
+//! SQLite storage backends for lightning-cart-rs.
+//!
+//! All stores share one `Database` handle, so a single SQLite file holds
+//! every table. Calls run on tokio's blocking thread pool.
+//!
+//! ```rust,ignore
+//! use pay_sqlite::{Database, SqliteOrderRepository, SqliteWebhookLedger};
+//!
+//! let db = Database::open("data/lightning-cart.db")?;
+//! let orders = SqliteOrderRepository::new(db.clone())?;
+//! let ledger = SqliteWebhookLedger::new(db)?;
+//! ```
+
+pub mod customers;
+pub mod db;
+pub mod ledger;
+pub mod orders;
+pub mod outbound;
+pub mod promotions;
+pub mod queue;
+
+// Re-exports
+pub use customers::SqliteCustomerRepository;
+pub use db::Database;
+pub use ledger::SqliteWebhookLedger;
+pub use orders::SqliteOrderRepository;
+pub use outbound::{SqliteDeliveryLog, SqliteDeliveryQueue};
+pub use promotions::SqliteRedemptionStore;
+pub use queue::SqliteWebhookQueue;
@@ -0,0 +1,583 @@
+//! # SQLite Order Repository
+//!
+//! `OrderRepository` backed by SQLite.
+//!
+//! Orders and sessions are stored as JSON next to the columns used for
+//! lookups; status changes go to `order_status_history`, refunds to
+//! `order_refunds` and the amount charged to `order_payments`.
+
+use crate::db::{parse_time, storage_error, Database};
+use async_trait::async_trait;
+use chrono::Utc;
+use pay_core::{
+    CheckoutSession, Order, OrderRepository, OrderTransition, OrderTrigger, PaymentError,
+    PaymentResult, Price, Refund, StatusChange, StoredOrder,
+};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS orders (
+    id                TEXT PRIMARY KEY,
+    site_id           TEXT,
+    status            TEXT NOT NULL,
+    order_json        TEXT NOT NULL,
+    session_id        TEXT,
+    payment_intent_id TEXT,
+    session_json      TEXT,
+    created_at        TEXT NOT NULL,
+    updated_at        TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_orders_session_id ON orders (session_id);
+CREATE INDEX IF NOT EXISTS idx_orders_payment_intent_id ON orders (payment_intent_id);
+
+CREATE TABLE IF NOT EXISTS order_status_history (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    order_id    TEXT NOT NULL REFERENCES orders (id),
+    from_status TEXT,
+    to_status   TEXT NOT NULL,
+    reason      TEXT,
+    changed_at  TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_order_status_history_order_id ON order_status_history (order_id);
+
+CREATE TABLE IF NOT EXISTS order_refunds (
+    id          TEXT PRIMARY KEY,
+    order_id    TEXT NOT NULL REFERENCES orders (id),
+    refund_json TEXT NOT NULL,
+    created_at  TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_order_refunds_order_id ON order_refunds (order_id);
+
+CREATE TABLE IF NOT EXISTS order_payments (
+    order_id    TEXT PRIMARY KEY REFERENCES orders (id),
+    amount_json TEXT NOT NULL,
+    paid_at     TEXT NOT NULL
+);
+";
+
+/// SQLite order repository
+#[derive(Clone)]
+pub struct SqliteOrderRepository {
+    db: Database,
+}
+
+impl SqliteOrderRepository {
+    /// Create the repository, creating tables if needed
+    pub fn new(db: Database) -> PaymentResult<Self> {
+        db.call_sync(|conn| conn.execute_batch(SCHEMA).map_err(storage_error))?;
+        Ok(Self { db })
+    }
+
+    /// Load an order by a column value
+    async fn find_by(&self, column: &'static str, value: &str) -> PaymentResult<Option<StoredOrder>> {
+        let value = value.to_string();
+        self.db
+            .call(move |conn| {
+                let id: Option<String> = conn
+                    .query_row(
+                        &format!("SELECT id FROM orders WHERE {} = ?1 LIMIT 1", column),
+                        params![value],
+                        |row| row.get(0),
+                    )
+                    .optional()
+                    .map_err(storage_error)?;
+                match id {
+                    Some(id) => load(conn, &id),
+                    None => Ok(None),
+                }
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl OrderRepository for SqliteOrderRepository {
+    async fn insert(&self, order: &Order, site_id: Option<&str>) -> PaymentResult<()> {
+        let stored = StoredOrder::new(order.clone(), site_id);
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                tx.execute(
+                    "INSERT INTO orders (id, site_id, status, order_json, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+                    params![
+                        stored.order.id,
+                        stored.site_id,
+                        stored.status.as_str(),
+                        to_json(&stored.order)?,
+                        stored.updated_at.to_rfc3339(),
+                    ],
+                )
+                .map_err(storage_error)?;
+                for change in &stored.history {
+                    insert_change(&tx, &stored.order.id, change)?;
+                }
+                tx.commit().map_err(storage_error)
+            })
+            .await
+    }
+
+    async fn save_session(&self, order_id: &str, session: &CheckoutSession) -> PaymentResult<()> {
+        let order_id = order_id.to_string();
+        let session = session.clone();
+        self.db
+            .call(move |conn| {
+                let updated = conn
+                    .execute(
+                        "UPDATE orders SET session_id = ?2, payment_intent_id = ?3, session_json = ?4,
+                         updated_at = ?5 WHERE id = ?1",
+                        params![
+                            order_id,
+                            session.session_id,
+                            session.payment_intent_id,
+                            to_json(&session)?,
+                            Utc::now().to_rfc3339(),
+                        ],
+                    )
+                    .map_err(storage_error)?;
+                if updated == 0 {
+                    return Err(not_found(&order_id));
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    async fn transition(
+        &self,
+        order_id: &str,
+        trigger: OrderTrigger,
+        reason: Option<&str>,
+    ) -> PaymentResult<Option<OrderTransition>> {
+        let order_id = order_id.to_string();
+        let reason = reason.map(String::from);
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                let mut stored = load(&tx, &order_id)?.ok_or_else(|| not_found(&order_id))?;
+                let transition = stored.apply(trigger, reason.as_deref())?;
+                if transition.is_some() {
+                    save_status(&tx, &stored)?;
+                }
+                tx.commit().map_err(storage_error)?;
+                Ok(transition)
+            })
+            .await
+    }
+
+    async fn save_refund(&self, order_id: &str, refund: &Refund) -> PaymentResult<()> {
+        let order_id = order_id.to_string();
+        let refund = refund.clone();
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                let updated = tx
+                    .execute(
+                        "UPDATE orders SET updated_at = ?2 WHERE id = ?1",
+                        params![order_id, Utc::now().to_rfc3339()],
+                    )
+                    .map_err(storage_error)?;
+                if updated == 0 {
+                    return Err(not_found(&order_id));
+                }
+                save_refund_row(&tx, &order_id, &refund)?;
+                tx.commit().map_err(storage_error)
+            })
+            .await
+    }
+
+    async fn reserve_refund(&self, order_id: &str, amount: Option<i64>) -> PaymentResult<Refund> {
+        let order_id = order_id.to_string();
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                let mut stored = load(&tx, &order_id)?.ok_or_else(|| not_found(&order_id))?;
+                let reservation = stored.reserve_refund(amount)?;
+                save_refund_row(&tx, &order_id, &reservation)?;
+                tx.execute(
+                    "UPDATE orders SET updated_at = ?2 WHERE id = ?1",
+                    params![order_id, stored.updated_at.to_rfc3339()],
+                )
+                .map_err(storage_error)?;
+                tx.commit().map_err(storage_error)?;
+                Ok(reservation)
+            })
+            .await
+    }
+
+    async fn settle_refund(
+        &self,
+        order_id: &str,
+        reservation_id: &str,
+        refund: Option<&Refund>,
+    ) -> PaymentResult<()> {
+        let order_id = order_id.to_string();
+        let reservation_id = reservation_id.to_string();
+        let refund = refund.cloned();
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                let updated = tx
+                    .execute(
+                        "UPDATE orders SET updated_at = ?2 WHERE id = ?1",
+                        params![order_id, Utc::now().to_rfc3339()],
+                    )
+                    .map_err(storage_error)?;
+                if updated == 0 {
+                    return Err(not_found(&order_id));
+                }
+                tx.execute(
+                    "DELETE FROM order_refunds WHERE id = ?1 AND order_id = ?2",
+                    params![reservation_id, order_id],
+                )
+                .map_err(storage_error)?;
+                if let Some(ref refund) = refund {
+                    save_refund_row(&tx, &order_id, refund)?;
+                }
+                tx.commit().map_err(storage_error)
+            })
+            .await
+    }
+
+    async fn save_tax(&self, order_id: &str, amount: &Price) -> PaymentResult<()> {
+        let order_id = order_id.to_string();
+        let amount = amount.clone();
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                let mut stored = load(&tx, &order_id)?.ok_or_else(|| not_found(&order_id))?;
+                if stored.record_tax(amount)? {
+                    tx.execute(
+                        "UPDATE orders SET order_json = ?2, updated_at = ?3 WHERE id = ?1",
+                        params![
+                            order_id,
+                            to_json(&stored.order)?,
+                            stored.updated_at.to_rfc3339()
+                        ],
+                    )
+                    .map_err(storage_error)?;
+                }
+                tx.commit().map_err(storage_error)
+            })
+            .await
+    }
+
+    async fn save_payment(&self, order_id: &str, amount: &Price) -> PaymentResult<()> {
+        let order_id = order_id.to_string();
+        let amount = amount.clone();
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                let mut stored = load(&tx, &order_id)?.ok_or_else(|| not_found(&order_id))?;
+                stored.record_payment(amount.clone())?;
+                tx.execute(
+                    "INSERT INTO order_payments (order_id, amount_json, paid_at)
+                     VALUES (?1, ?2, ?3)
+                     ON CONFLICT (order_id) DO UPDATE SET
+                         amount_json = excluded.amount_json, paid_at = excluded.paid_at",
+                    params![
+                        order_id,
+                        to_json(&amount)?,
+                        stored.updated_at.to_rfc3339()
+                    ],
+                )
+                .map_err(storage_error)?;
+                tx.execute(
+                    "UPDATE orders SET updated_at = ?2 WHERE id = ?1",
+                    params![order_id, stored.updated_at.to_rfc3339()],
+                )
+                .map_err(storage_error)?;
+                tx.commit().map_err(storage_error)
+            })
+            .await
+    }
+
+    async fn get(&self, order_id: &str) -> PaymentResult<Option<StoredOrder>> {
+        let order_id = order_id.to_string();
+        self.db.call(move |conn| load(conn, &order_id)).await
+    }
+
+    async fn find_by_session(&self, session_id: &str) -> PaymentResult<Option<StoredOrder>> {
+        self.find_by("session_id", session_id).await
+    }
+
+    async fn find_by_payment_intent(
+        &self,
+        payment_intent_id: &str,
+    ) -> PaymentResult<Option<StoredOrder>> {
+        self.find_by("payment_intent_id", payment_intent_id).await
+    }
+}
+
+/// Load an order with its history and refunds
+fn load(conn: &Connection, order_id: &str) -> PaymentResult<Option<StoredOrder>> {
+    let row = conn
+        .query_row(
+            "SELECT site_id, status, order_json, session_json, updated_at FROM orders WHERE id = ?1",
+            params![order_id],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(storage_error)?;
+
+    let Some((site_id, status, order_json, session_json, updated_at)) = row else {
+        return Ok(None);
+    };
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT from_status, to_status, reason, changed_at FROM order_status_history
+             WHERE order_id = ?1 ORDER BY id",
+        )
+        .map_err(storage_error)?;
+    let history = stmt
+        .query_map(params![order_id], |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })
+        .map_err(storage_error)?
+        .map(|row| {
+            let (from, to, reason, at) = row.map_err(storage_error)?;
+            Ok(StatusChange {
+                from: from.map(|s| s.parse()).transpose()?,
+                to: to.parse()?,
+                reason,
+                at: parse_time(&at)?,
+            })
+        })
+        .collect::<PaymentResult<Vec<_>>>()?;
+
+    let mut stmt = conn
+        .prepare("SELECT refund_json FROM order_refunds WHERE order_id = ?1 ORDER BY created_at")
+        .map_err(storage_error)?;
+    let refunds = stmt
+        .query_map(params![order_id], |row| row.get::<_, String>(0))
+        .map_err(storage_error)?
+        .map(|row| from_json(&row.map_err(storage_error)?))
+        .collect::<PaymentResult<Vec<Refund>>>()?;
+
+    let amount_paid = conn
+        .query_row(
+            "SELECT amount_json FROM order_payments WHERE order_id = ?1",
+            params![order_id],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(storage_error)?;
+
+    Ok(Some(StoredOrder {
+        order: from_json(&order_json)?,
+        site_id,
+        status: status.parse()?,
+        session: session_json.as_deref().map(from_json).transpose()?,
+        history,
+        refunds,
+        amount_paid: amount_paid.as_deref().map(from_json).transpose()?,
+        updated_at: parse_time(&updated_at)?,
+    }))
+}
+
+/// Write the current status and its latest history entry
+fn save_refund_row(tx: &Transaction<'_>, order_id: &str, refund: &Refund) -> PaymentResult<()> {
+    tx.execute(
+        "INSERT INTO order_refunds (id, order_id, refund_json, created_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (id) DO UPDATE SET refund_json = excluded.refund_json",
+        params![
+            refund.id,
+            order_id,
+            to_json(refund)?,
+            refund.created_at.to_rfc3339(),
+        ],
+    )
+    .map_err(storage_error)?;
+    Ok(())
+}
+
+fn save_status(tx: &Transaction<'_>, stored: &StoredOrder) -> PaymentResult<()> {
+    tx.execute(
+        "UPDATE orders SET status = ?2, updated_at = ?3 WHERE id = ?1",
+        params![
+            stored.order.id,
+            stored.status.as_str(),
+            stored.updated_at.to_rfc3339()
+        ],
+    )
+    .map_err(storage_error)?;
+    if let Some(change) = stored.history.last() {
+        insert_change(tx, &stored.order.id, change)?;
+    }
+    Ok(())
+}
+
+fn insert_change(tx: &Transaction<'_>, order_id: &str, change: &StatusChange) -> PaymentResult<()> {
+    tx.execute(
+        "INSERT INTO order_status_history (order_id, from_status, to_status, reason, changed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            order_id,
+            change.from.map(|s| s.as_str()),
+            change.to.as_str(),
+            change.reason,
+            change.at.to_rfc3339(),
+        ],
+    )
+    .map_err(storage_error)?;
+    Ok(())
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> PaymentResult<String> {
+    serde_json::to_string(value).map_err(|e| PaymentError::Serialization(e.to_string()))
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(json: &str) -> PaymentResult<T> {
+    serde_json::from_str(json).map_err(|e| PaymentError::Serialization(e.to_string()))
+}
+
+fn not_found(order_id: &str) -> PaymentError {
+    PaymentError::OrderNotFound {
+        order_id: order_id.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pay_core::{Currency, OrderStatus, Price, Product, RefundStatus};
+
+    #[tokio::test]
+    async fn test_order_lifecycle() {
+        let repo = SqliteOrderRepository::new(Database::open_in_memory().unwrap()).unwrap();
+
+        let mut order = Order::new(Currency::EUR);
+        order
+            .add_product(&Product::one_time("p", "P", Price::from_cents(1999, Currency::EUR)), 2)
+            .unwrap();
+        repo.insert(&order, Some("spokenhope")).await.unwrap();
+        assert!(repo.insert(&order, None).await.is_err());
+
+        let mut session = CheckoutSession::new("cs_1", &order.id, "stripe", "https://x");
+        repo.save_session(&order.id, &session).await.unwrap();
+        repo.transition(&order.id, OrderTrigger::CheckoutCreated, Some("cs_1"))
+            .await
+            .unwrap();
+        session.payment_intent_id = Some("pi_1".into());
+        repo.save_session(&order.id, &session).await.unwrap();
+
+        let paid = repo
+            .transition(&order.id, OrderTrigger::PaymentSucceeded, Some("evt_1"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(paid.to, OrderStatus::Paid);
+        // Unchanged status records nothing
+        assert!(repo
+            .transition(&order.id, OrderTrigger::PaymentSucceeded, Some("evt_2"))
+            .await
+            .unwrap()
+            .is_none());
+        // Illegal jumps are rejected and leave the order untouched
+        assert!(matches!(
+            repo.transition(&order.id, OrderTrigger::SessionExpired, None).await,
+            Err(PaymentError::InvalidTransition { .. })
+        ));
+
+        let stored = repo.find_by_payment_intent("pi_1").await.unwrap().unwrap();
+        assert_eq!(stored.status, OrderStatus::Paid);
+        assert_eq!(stored.site_id.as_deref(), Some("spokenhope"));
+        assert_eq!(stored.order.total().unwrap().amount, 3998);
+        assert_eq!(stored.session.unwrap().session_id, "cs_1");
+        assert!(stored.amount_paid.is_none());
+
+        repo.save_payment(&order.id, &Price::from_cents(3500, Currency::EUR)).await.unwrap();
+        repo.save_payment(&order.id, &Price::from_cents(3600, Currency::EUR)).await.unwrap();
+        let stored = repo.get(&order.id).await.unwrap().unwrap();
+        assert_eq!(stored.paid_amount().unwrap().amount, 3600);
+
+        let history: Vec<_> = stored.history.iter().map(|c| (c.from, c.to)).collect();
+        assert_eq!(
+            history,
+            [
+                (None, OrderStatus::Pending),
+                (Some(OrderStatus::Pending), OrderStatus::AwaitingPayment),
+                (Some(OrderStatus::AwaitingPayment), OrderStatus::Paid),
+            ]
+        );
+
+        let mut refund = Refund {
+            id: "re_1".into(),
+            provider: "stripe".into(),
+            payment_intent_id: Some("pi_1".into()),
+            amount: Price::from_cents(1000, Currency::EUR),
+            status: RefundStatus::Pending,
+            reason: None,
+            metadata: Default::default(),
+            created_at: chrono::Utc::now(),
+        };
+        repo.save_refund(&order.id, &refund).await.unwrap();
+        refund.status = RefundStatus::Succeeded;
+        repo.save_refund(&order.id, &refund).await.unwrap();
+        let refunds = repo.get(&order.id).await.unwrap().unwrap().refunds;
+        assert_eq!(refunds.len(), 1);
+        assert_eq!(refunds[0].status, RefundStatus::Succeeded);
+        assert!(matches!(
+            repo.save_refund("missing", &refund).await,
+            Err(PaymentError::OrderNotFound { .. })
+        ));
+
+        // Reservations hold the rest of the payment until settled
+        let reservation = repo.reserve_refund(&order.id, None).await.unwrap();
+        assert_eq!(reservation.amount.amount, 2600);
+        assert!(repo.reserve_refund(&order.id, Some(1)).await.is_err());
+        repo.settle_refund(&order.id, &reservation.id, None).await.unwrap();
+        let reservation = repo.reserve_refund(&order.id, Some(600)).await.unwrap();
+        refund.id = "re_2".into();
+        refund.amount = reservation.amount.clone();
+        refund.created_at = reservation.created_at;
+        repo.settle_refund(&order.id, &reservation.id, Some(&refund)).await.unwrap();
+        let stored = repo.get(&order.id).await.unwrap().unwrap();
+        let ids: Vec<_> = stored.refunds.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["re_1", "re_2"]);
+        assert_eq!(stored.check_refund(None).unwrap(), 2000);
+
+        assert!(repo.find_by_session("cs_1").await.unwrap().is_some());
+        assert!(repo.get("missing").await.unwrap().is_none());
+        assert!(matches!(
+            repo.save_session("missing", &session).await,
+            Err(PaymentError::OrderNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_persists_across_connections() {
+        let order = Order::new(Currency::USD);
+        let dir = std::env::temp_dir().join(format!("pay-sqlite-{}", order.id));
+        let path = dir.join("orders.db");
+
+        {
+            let repo = SqliteOrderRepository::new(Database::open(&path).unwrap()).unwrap();
+            repo.insert(&order, None).await.unwrap();
+            repo.transition(&order.id, OrderTrigger::CheckoutFailed, None)
+                .await
+                .unwrap();
+        }
+
+        let repo = SqliteOrderRepository::new(Database::open(&path).unwrap()).unwrap();
+        let stored = repo.get(&order.id).await.unwrap().unwrap();
+        assert_eq!(stored.status, OrderStatus::Failed);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,398 @@
+//! # SQLite Outbound Deliveries
+//!
+//! `DeliveryLog` backed by SQLite: one row per outbound webhook attempt.
+//!
+//! `DeliveryQueue` backed by SQLite: deliveries wait in `outbound_jobs`
+//! until they are sent or their retries run out, at most one per event and
+//! subscription.
+
+use crate::db::{parse_time, storage_error, Database};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use pay_core::queue::JOB_LEASE_SECS;
+use pay_core::{
+    DeliveryAttempt, DeliveryJob, DeliveryLog, DeliveryQueue, PaymentError, PaymentResult,
+};
+use rusqlite::{params, OptionalExtension, Row};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS outbound_deliveries (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    site_id      TEXT NOT NULL,
+    webhook_id   TEXT NOT NULL,
+    event_id     TEXT NOT NULL,
+    url          TEXT NOT NULL,
+    attempt      INTEGER NOT NULL,
+    status_code  INTEGER,
+    error        TEXT,
+    duration_ms  INTEGER NOT NULL,
+    attempted_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_outbound_deliveries_event_id ON outbound_deliveries (event_id);
+CREATE INDEX IF NOT EXISTS idx_outbound_deliveries_attempted_at
+    ON outbound_deliveries (attempted_at);
+";
+
+const QUEUE_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS outbound_jobs (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    site_id      TEXT NOT NULL,
+    webhook_id   TEXT NOT NULL,
+    event_id     TEXT NOT NULL,
+    event_type   TEXT NOT NULL,
+    body         TEXT NOT NULL,
+    attempts     INTEGER NOT NULL DEFAULT 0,
+    run_at       TEXT NOT NULL,
+    locked_until TEXT,
+    last_error   TEXT,
+    created_at   TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_outbound_jobs_run_at ON outbound_jobs (run_at);
+DELETE FROM outbound_jobs WHERE id NOT IN (
+    SELECT MIN(id) FROM outbound_jobs GROUP BY site_id, webhook_id, event_id
+);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_outbound_jobs_delivery
+    ON outbound_jobs (site_id, webhook_id, event_id);
+";
+
+const JOB_COLUMNS: &str =
+    "id, site_id, webhook_id, event_id, event_type, body, attempts, run_at, last_error, created_at";
+
+/// SQLite delivery log
+#[derive(Clone)]
+pub struct SqliteDeliveryLog {
+    db: Database,
+}
+
+impl SqliteDeliveryLog {
+    /// Create the log, creating tables if needed
+    pub fn new(db: Database) -> PaymentResult<Self> {
+        db.call_sync(|conn| conn.execute_batch(SCHEMA).map_err(storage_error))?;
+        Ok(Self { db })
+    }
+}
+
+#[async_trait]
+impl DeliveryLog for SqliteDeliveryLog {
+    async fn record(&self, attempt: &DeliveryAttempt) -> PaymentResult<()> {
+        let attempt = attempt.clone();
+        self.db
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO outbound_deliveries
+                         (site_id, webhook_id, event_id, url, attempt, status_code, error,
+                          duration_ms, attempted_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        attempt.site_id,
+                        attempt.webhook_id,
+                        attempt.event_id,
+                        attempt.url,
+                        attempt.attempt,
+                        attempt.status_code,
+                        attempt.error,
+                        attempt.duration_ms as i64,
+                        format_time(attempt.attempted_at),
+                    ],
+                )
+                .map_err(storage_error)?;
+                Ok(())
+            })
+            .await
+    }
+
+    async fn attempts(&self, event_id: &str) -> PaymentResult<Vec<DeliveryAttempt>> {
+        let event_id = event_id.to_string();
+        self.db
+            .call(move |conn| {
+                let mut stmt = conn
+                    .prepare(
+                        "SELECT site_id, webhook_id, url, attempt, status_code, error,
+                                duration_ms, attempted_at
+                         FROM outbound_deliveries WHERE event_id = ?1 ORDER BY id",
+                    )
+                    .map_err(storage_error)?;
+                let rows = stmt
+                    .query_map(params![event_id], |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, String>(2)?,
+                            row.get::<_, u32>(3)?,
+                            row.get::<_, Option<u16>>(4)?,
+                            row.get::<_, Option<String>>(5)?,
+                            row.get::<_, i64>(6)?,
+                            row.get::<_, String>(7)?,
+                        ))
+                    })
+                    .map_err(storage_error)?;
+
+                rows.map(|row| {
+                    let (site_id, webhook_id, url, attempt, status_code, error, duration_ms, at) =
+                        row.map_err(storage_error)?;
+                    Ok(DeliveryAttempt {
+                        site_id,
+                        webhook_id,
+                        event_id: event_id.clone(),
+                        url,
+                        attempt,
+                        status_code,
+                        error,
+                        duration_ms: duration_ms as u64,
+                        attempted_at: parse_time(&at)?,
+                    })
+                })
+                .collect()
+            })
+            .await
+    }
+
+    async fn purge_before(&self, cutoff: DateTime<Utc>) -> PaymentResult<usize> {
+        self.db
+            .call(move |conn| {
+                conn.execute(
+                    "DELETE FROM outbound_deliveries WHERE attempted_at < ?1",
+                    params![format_time(cutoff)],
+                )
+                .map_err(storage_error)
+            })
+            .await
+    }
+}
+
+/// SQLite delivery queue
+#[derive(Clone)]
+pub struct SqliteDeliveryQueue {
+    db: Database,
+}
+
+impl SqliteDeliveryQueue {
+    /// Create the queue, creating tables if needed
+    pub fn new(db: Database) -> PaymentResult<Self> {
+        db.call_sync(|conn| conn.execute_batch(QUEUE_SCHEMA).map_err(storage_error))?;
+        Ok(Self { db })
+    }
+}
+
+#[async_trait]
+impl DeliveryQueue for SqliteDeliveryQueue {
+    async fn enqueue(&self, job: &DeliveryJob) -> PaymentResult<Option<DeliveryJob>> {
+        let mut job = job.clone();
+        self.db
+            .call(move |conn| {
+                let inserted = conn.execute(
+                    "INSERT INTO outbound_jobs
+                         (site_id, webhook_id, event_id, event_type, body, attempts, run_at,
+                          last_error, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                     ON CONFLICT (site_id, webhook_id, event_id) DO NOTHING",
+                    params![
+                        job.site_id,
+                        job.webhook_id,
+                        job.event_id,
+                        job.event_type,
+                        job.body,
+                        job.attempts,
+                        format_time(job.run_at),
+                        job.last_error,
+                        format_time(job.created_at),
+                    ],
+                )
+                .map_err(storage_error)?;
+                if inserted == 0 {
+                    return Ok(None);
+                }
+                job.id = conn.last_insert_rowid();
+                Ok(Some(job))
+            })
+            .await
+    }
+
+    async fn claim(&self, now: DateTime<Utc>) -> PaymentResult<Option<DeliveryJob>> {
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                let job = tx
+                    .query_row(
+                        &format!(
+                            "SELECT {} FROM outbound_jobs
+                             WHERE run_at <= ?1 AND (locked_until IS NULL OR locked_until <= ?1)
+                             ORDER BY run_at, id LIMIT 1",
+                            JOB_COLUMNS
+                        ),
+                        params![format_time(now)],
+                        read_job,
+                    )
+                    .optional()
+                    .map_err(storage_error)?;
+
+                let Some(job) = job else {
+                    return Ok(None);
+                };
+                let mut job = job?;
+                job.attempts += 1;
+                tx.execute(
+                    "UPDATE outbound_jobs SET attempts = ?2, locked_until = ?3 WHERE id = ?1",
+                    params![
+                        job.id,
+                        job.attempts,
+                        format_time(now + Duration::seconds(JOB_LEASE_SECS))
+                    ],
+                )
+                .map_err(storage_error)?;
+                tx.commit().map_err(storage_error)?;
+                Ok(Some(job))
+            })
+            .await
+    }
+
+    async fn complete(&self, job_id: i64) -> PaymentResult<()> {
+        self.db
+            .call(move |conn| {
+                conn.execute("DELETE FROM outbound_jobs WHERE id = ?1", params![job_id])
+                    .map_err(storage_error)?;
+                Ok(())
+            })
+            .await
+    }
+
+    async fn fail(
+        &self,
+        job_id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> PaymentResult<()> {
+        let error = error.to_string();
+        self.db
+            .call(move |conn| {
+                let updated = match retry_at {
+                    Some(run_at) => conn.execute(
+                        "UPDATE outbound_jobs
+                         SET run_at = ?2, locked_until = NULL, last_error = ?3 WHERE id = ?1",
+                        params![job_id, format_time(run_at), error],
+                    ),
+                    None => conn.execute("DELETE FROM outbound_jobs WHERE id = ?1", params![job_id]),
+                }
+                .map_err(storage_error)?;
+                if updated == 0 {
+                    return Err(PaymentError::Storage(format!(
+                        "delivery job {} not found",
+                        job_id
+                    )));
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    async fn pending(&self) -> PaymentResult<usize> {
+        self.db
+            .call(|conn| {
+                conn.query_row("SELECT COUNT(*) FROM outbound_jobs", [], |row| {
+                    row.get::<_, i64>(0)
+                })
+                .map(|count| count as usize)
+                .map_err(storage_error)
+            })
+            .await
+    }
+}
+
+/// Read a queued delivery (times are parsed after the row is read)
+fn read_job(row: &Row<'_>) -> rusqlite::Result<PaymentResult<DeliveryJob>> {
+    let run_at: String = row.get(7)?;
+    let created_at: String = row.get(9)?;
+    let (run_at, created_at) = match (parse_time(&run_at), parse_time(&created_at)) {
+        (Ok(run_at), Ok(created_at)) => (run_at, created_at),
+        (Err(e), _) | (_, Err(e)) => return Ok(Err(e)),
+    };
+    Ok(Ok(DeliveryJob {
+        id: row.get(0)?,
+        site_id: row.get(1)?,
+        webhook_id: row.get(2)?,
+        event_id: row.get(3)?,
+        event_type: row.get(4)?,
+        body: row.get(5)?,
+        attempts: row.get(6)?,
+        run_at,
+        last_error: row.get(8)?,
+        created_at,
+    }))
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn test_delivery_log_round_trip() {
+        let log = SqliteDeliveryLog::new(Database::open_in_memory().unwrap()).unwrap();
+        let mut attempt = DeliveryAttempt {
+            site_id: "chargegun".into(),
+            webhook_id: "consultation".into(),
+            event_id: "evt_1".into(),
+            url: "https://example.com/hook".into(),
+            attempt: 1,
+            status_code: None,
+            error: Some("connection refused".into()),
+            duration_ms: 12,
+            attempted_at: Utc::now(),
+        };
+        log.record(&attempt).await.unwrap();
+        attempt.attempt = 2;
+        attempt.status_code = Some(200);
+        attempt.error = None;
+        log.record(&attempt).await.unwrap();
+
+        let attempts = log.attempts("evt_1").await.unwrap();
+        assert_eq!(attempts.len(), 2);
+        assert!(!attempts[0].succeeded());
+        assert_eq!((attempts[1].attempt, attempts[1].status_code), (2, Some(200)));
+        assert!(attempts[1].succeeded());
+        assert!(log.attempts("evt_2").await.unwrap().is_empty());
+
+        assert_eq!(log.purge_before(Utc::now() + Duration::seconds(1)).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_delivery_queue_round_trip() {
+        let queue = SqliteDeliveryQueue::new(Database::open_in_memory().unwrap()).unwrap();
+        let event = pay_core::WebhookEvent {
+            event_id: "evt_1".into(),
+            event_type: pay_core::WebhookEventType::CheckoutCompleted,
+            provider: "stripe".into(),
+            session_id: Some("cs_1".into()),
+            payment_intent_id: None,
+            customer_email: None,
+            amount_paid: None,
+            currency: None,
+            raw_data: None,
+            timestamp: Utc::now(),
+        };
+        let job = DeliveryJob::new("chargegun", "consultation", &event, r#"{"a":1}"#);
+        let job = queue.enqueue(&job).await.unwrap().unwrap();
+        assert!(queue.enqueue(&job).await.unwrap().is_none());
+        let now = Utc::now();
+
+        let claimed = queue.claim(now).await.unwrap().unwrap();
+        assert_eq!((claimed.id, claimed.attempts), (job.id, 1));
+        assert_eq!(claimed.body, r#"{"a":1}"#);
+        assert!(queue.claim(now).await.unwrap().is_none());
+
+        let retry_at = now + Duration::seconds(30);
+        queue.fail(job.id, "HTTP 503", Some(retry_at)).await.unwrap();
+        assert!(queue.claim(now).await.unwrap().is_none());
+        let retried = queue.claim(retry_at).await.unwrap().unwrap();
+        assert_eq!(retried.attempts, 2);
+        assert_eq!(retried.last_error.as_deref(), Some("HTTP 503"));
+
+        queue.complete(job.id).await.unwrap();
+        assert_eq!(queue.pending().await.unwrap(), 0);
+        assert!(queue.fail(job.id, "HTTP 503", None).await.is_err());
+    }
+}
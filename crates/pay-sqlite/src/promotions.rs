@@ -0,0 +1,120 @@
+//! # SQLite Redemption Store
+//!
+//! `RedemptionStore` backed by SQLite: one row per order holding a
+//! promotion code, counted per code.
+
+use crate::db::{storage_error, Database};
+use async_trait::async_trait;
+use chrono::Utc;
+use pay_core::{PaymentResult, RedemptionStore};
+use rusqlite::{params, Connection};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS promotion_redemptions (
+    order_id    TEXT PRIMARY KEY,
+    code        TEXT NOT NULL,
+    redeemed_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_promotion_redemptions_code
+    ON promotion_redemptions (code);
+";
+
+/// SQLite redemption store
+#[derive(Clone)]
+pub struct SqliteRedemptionStore {
+    db: Database,
+}
+
+impl SqliteRedemptionStore {
+    /// Create the store, creating tables if needed
+    pub fn new(db: Database) -> PaymentResult<Self> {
+        db.call_sync(|conn| conn.execute_batch(SCHEMA).map_err(storage_error))?;
+        Ok(Self { db })
+    }
+}
+
+fn count(conn: &Connection, code: &str) -> PaymentResult<u32> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM promotion_redemptions WHERE code = ?1",
+        params![code],
+        |row| row.get(0),
+    )
+    .map_err(storage_error)
+}
+
+#[async_trait]
+impl RedemptionStore for SqliteRedemptionStore {
+    async fn count(&self, code: &str) -> PaymentResult<u32> {
+        let code = code.to_string();
+        self.db.call(move |conn| count(conn, &code)).await
+    }
+
+    /// Count and insert in one transaction
+    async fn reserve(&self, code: &str, order_id: &str, limit: Option<u32>) -> PaymentResult<bool> {
+        let code = code.to_string();
+        let order_id = order_id.to_string();
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                let held: bool = tx
+                    .query_row(
+                        "SELECT EXISTS (SELECT 1 FROM promotion_redemptions WHERE order_id = ?1)",
+                        params![order_id],
+                        |row| row.get(0),
+                    )
+                    .map_err(storage_error)?;
+                if held {
+                    return Ok(true);
+                }
+                if let Some(max) = limit {
+                    if count(&tx, &code)? >= max {
+                        return Ok(false);
+                    }
+                }
+                tx.execute(
+                    "INSERT INTO promotion_redemptions (order_id, code, redeemed_at)
+                     VALUES (?1, ?2, ?3)",
+                    params![order_id, code, Utc::now().to_rfc3339()],
+                )
+                .map_err(storage_error)?;
+                tx.commit().map_err(storage_error)?;
+                Ok(true)
+            })
+            .await
+    }
+
+    async fn release(&self, order_id: &str) -> PaymentResult<()> {
+        let order_id = order_id.to_string();
+        self.db
+            .call(move |conn| {
+                conn.execute(
+                    "DELETE FROM promotion_redemptions WHERE order_id = ?1",
+                    params![order_id],
+                )
+                .map_err(storage_error)?;
+                Ok(())
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_redemptions_survive_reopen() {
+        let db = Database::open_in_memory().unwrap();
+        let store = SqliteRedemptionStore::new(db.clone()).unwrap();
+        assert!(store.reserve("LAUNCH20", "order_1", Some(1)).await.unwrap());
+        assert!(store.reserve("LAUNCH20", "order_1", Some(1)).await.unwrap());
+
+        let reopened = SqliteRedemptionStore::new(db).unwrap();
+        assert_eq!(reopened.count("LAUNCH20").await.unwrap(), 1);
+        assert!(!reopened.reserve("LAUNCH20", "order_2", Some(1)).await.unwrap());
+
+        reopened.release("order_1").await.unwrap();
+        assert!(reopened.reserve("LAUNCH20", "order_2", Some(1)).await.unwrap());
+        assert_eq!(reopened.count("LAUNCH20").await.unwrap(), 1);
+    }
+}
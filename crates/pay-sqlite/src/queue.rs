@@ -0,0 +1,268 @@
+//! # SQLite Webhook Queue
+//!
+//! `WebhookQueue` backed by SQLite.
+//!
+//! Pending jobs live in `webhook_jobs`; jobs that used up their attempts are
+//! moved to `webhook_dead_letters` for inspection and manual replay.
+
+use crate::db::{parse_time, storage_error, Database};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use pay_core::queue::JOB_LEASE_SECS;
+use pay_core::{PaymentError, PaymentResult, WebhookEvent, WebhookJob, WebhookQueue};
+use rusqlite::{params, OptionalExtension, Row};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS webhook_jobs (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_id     TEXT NOT NULL,
+    event_json   TEXT NOT NULL,
+    attempts     INTEGER NOT NULL DEFAULT 0,
+    run_at       TEXT NOT NULL,
+    locked_until TEXT,
+    last_error   TEXT,
+    created_at   TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_webhook_jobs_run_at ON webhook_jobs (run_at);
+
+CREATE TABLE IF NOT EXISTS webhook_dead_letters (
+    id         INTEGER PRIMARY KEY,
+    event_id   TEXT NOT NULL,
+    event_json TEXT NOT NULL,
+    attempts   INTEGER NOT NULL,
+    last_error TEXT,
+    created_at TEXT NOT NULL,
+    failed_at  TEXT NOT NULL
+);
+";
+
+const JOB_COLUMNS: &str = "id, event_json, attempts, run_at, last_error, created_at";
+
+/// SQLite webhook queue
+#[derive(Clone)]
+pub struct SqliteWebhookQueue {
+    db: Database,
+}
+
+impl SqliteWebhookQueue {
+    /// Create the queue, creating tables if needed
+    pub fn new(db: Database) -> PaymentResult<Self> {
+        db.call_sync(|conn| conn.execute_batch(SCHEMA).map_err(storage_error))?;
+        Ok(Self { db })
+    }
+}
+
+#[async_trait]
+impl WebhookQueue for SqliteWebhookQueue {
+    async fn enqueue(&self, event: &WebhookEvent) -> PaymentResult<WebhookJob> {
+        let event = event.clone();
+        self.db
+            .call(move |conn| {
+                let now = Utc::now();
+                conn.execute(
+                    "INSERT INTO webhook_jobs (event_id, event_json, run_at, created_at)
+                     VALUES (?1, ?2, ?3, ?3)",
+                    params![event.event_id, to_json(&event)?, format_time(now)],
+                )
+                .map_err(storage_error)?;
+                Ok(WebhookJob {
+                    id: conn.last_insert_rowid(),
+                    event,
+                    attempts: 0,
+                    run_at: now,
+                    last_error: None,
+                    created_at: now,
+                })
+            })
+            .await
+    }
+
+    async fn claim(&self, now: DateTime<Utc>) -> PaymentResult<Option<WebhookJob>> {
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                let now_text = format_time(now);
+                let row = tx
+                    .query_row(
+                        &format!(
+                            "SELECT {} FROM webhook_jobs
+                             WHERE run_at <= ?1 AND (locked_until IS NULL OR locked_until <= ?1)
+                             ORDER BY run_at, id LIMIT 1",
+                            JOB_COLUMNS
+                        ),
+                        params![now_text],
+                        read_row,
+                    )
+                    .optional()
+                    .map_err(storage_error)?;
+
+                let Some(row) = row else {
+                    return Ok(None);
+                };
+                let mut job = job_from_row(row)?;
+                job.attempts += 1;
+                tx.execute(
+                    "UPDATE webhook_jobs SET attempts = ?2, locked_until = ?3 WHERE id = ?1",
+                    params![
+                        job.id,
+                        job.attempts,
+                        format_time(now + Duration::seconds(JOB_LEASE_SECS))
+                    ],
+                )
+                .map_err(storage_error)?;
+                tx.commit().map_err(storage_error)?;
+                Ok(Some(job))
+            })
+            .await
+    }
+
+    async fn complete(&self, job_id: i64) -> PaymentResult<()> {
+        self.db
+            .call(move |conn| {
+                conn.execute("DELETE FROM webhook_jobs WHERE id = ?1", params![job_id])
+                    .map_err(storage_error)?;
+                Ok(())
+            })
+            .await
+    }
+
+    async fn fail(
+        &self,
+        job_id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> PaymentResult<()> {
+        let error = error.to_string();
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction().map_err(storage_error)?;
+                let updated = match retry_at {
+                    Some(run_at) => tx.execute(
+                        "UPDATE webhook_jobs
+                         SET run_at = ?2, locked_until = NULL, last_error = ?3 WHERE id = ?1",
+                        params![job_id, format_time(run_at), error],
+                    ),
+                    None => tx
+                        .execute(
+                            "INSERT INTO webhook_dead_letters
+                                 (id, event_id, event_json, attempts, last_error, created_at, failed_at)
+                             SELECT id, event_id, event_json, attempts, ?2, created_at, ?3
+                             FROM webhook_jobs WHERE id = ?1",
+                            params![job_id, error, format_time(Utc::now())],
+                        )
+                        .and_then(|moved| {
+                            tx.execute("DELETE FROM webhook_jobs WHERE id = ?1", params![job_id])?;
+                            Ok(moved)
+                        }),
+                }
+                .map_err(storage_error)?;
+                if updated == 0 {
+                    return Err(PaymentError::Storage(format!(
+                        "webhook job {} not found",
+                        job_id
+                    )));
+                }
+                tx.commit().map_err(storage_error)
+            })
+            .await
+    }
+
+    async fn dead_letters(&self) -> PaymentResult<Vec<WebhookJob>> {
+        self.db
+            .call(|conn| {
+                let mut stmt = conn
+                    .prepare(
+                        "SELECT id, event_json, attempts, failed_at, last_error, created_at
+                         FROM webhook_dead_letters ORDER BY failed_at, id",
+                    )
+                    .map_err(storage_error)?;
+                let rows = stmt.query_map([], read_row).map_err(storage_error)?;
+                rows.map(|row| job_from_row(row.map_err(storage_error)?))
+                    .collect()
+            })
+            .await
+    }
+}
+
+/// id, event_json, attempts, run_at, last_error, created_at
+type JobRow = (i64, String, u32, String, Option<String>, String);
+
+/// Read a job row (`run_at` is `failed_at` for dead letters)
+fn read_row(row: &Row<'_>) -> rusqlite::Result<JobRow> {
+    Ok((
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+        row.get(5)?,
+    ))
+}
+
+fn job_from_row(row: JobRow) -> PaymentResult<WebhookJob> {
+    let (id, event_json, attempts, run_at, last_error, created_at) = row;
+    Ok(WebhookJob {
+        id,
+        event: serde_json::from_str(&event_json)
+            .map_err(|e| PaymentError::Serialization(e.to_string()))?,
+        attempts,
+        run_at: parse_time(&run_at)?,
+        last_error,
+        created_at: parse_time(&created_at)?,
+    })
+}
+
+fn to_json(event: &WebhookEvent) -> PaymentResult<String> {
+    serde_json::to_string(event).map_err(|e| PaymentError::Serialization(e.to_string()))
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pay_core::WebhookEventType;
+
+    #[tokio::test]
+    async fn test_queue_round_trip() {
+        let queue = SqliteWebhookQueue::new(Database::open_in_memory().unwrap()).unwrap();
+        let event = WebhookEvent {
+            event_id: "evt_1".into(),
+            event_type: WebhookEventType::CheckoutCompleted,
+            provider: "stripe".into(),
+            session_id: Some("cs_1".into()),
+            payment_intent_id: None,
+            customer_email: None,
+            amount_paid: Some(1000),
+            currency: None,
+            raw_data: Some(serde_json::json!({"id": "cs_1"})),
+            timestamp: Utc::now(),
+        };
+        let job = queue.enqueue(&event).await.unwrap();
+        let now = Utc::now();
+
+        let claimed = queue.claim(now).await.unwrap().unwrap();
+        assert_eq!((claimed.id, claimed.attempts), (job.id, 1));
+        assert_eq!(claimed.event.session_id.as_deref(), Some("cs_1"));
+        assert!(queue.claim(now).await.unwrap().is_none());
+
+        let retry_at = now + Duration::seconds(10);
+        queue.fail(job.id, "timeout", Some(retry_at)).await.unwrap();
+        assert!(queue.claim(now).await.unwrap().is_none());
+        assert_eq!(queue.claim(retry_at).await.unwrap().unwrap().attempts, 2);
+
+        queue.fail(job.id, "gave up", None).await.unwrap();
+        assert!(queue.claim(retry_at + Duration::hours(1)).await.unwrap().is_none());
+        let dead = queue.dead_letters().await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!((dead[0].attempts, dead[0].last_error.as_deref()), (2, Some("gave up")));
+        assert!(queue.fail(job.id, "again", None).await.is_err());
+
+        let second = queue.enqueue(&event).await.unwrap();
+        queue.claim(Utc::now()).await.unwrap().unwrap();
+        queue.complete(second.id).await.unwrap();
+        assert!(queue.claim(now + Duration::hours(1)).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,97 @@
+//! # Stripe Amounts
+//!
+//! Converts between ISO 4217 minor units (what `Price` stores) and the
+//! integer amounts Stripe's API expects, which differ for a few currencies:
+//!
+//! - ISK and UGX are zero-decimal in ISO 4217 but two-decimal at Stripe,
+//!   and the amount must be a whole number of krónur/shillings
+//! - MGA is two-decimal in ISO 4217 but zero-decimal at Stripe
+//! - HUF and TWD are two-decimal, but Stripe only pays out whole units, so
+//!   charges are kept to whole units
+//! - BHD, JOD, KWD, OMR and TND are three-decimal, and the last digit must be 0
+//!
+//! See <https://docs.stripe.com/currencies#special-cases>.
+
+use pay_core::{Currency, Money, PaymentError, PaymentResult, Price};
+
+/// Currencies Stripe represents with two decimals but only accepts whole units of
+const WHOLE_UNITS_ONLY: &[Currency] = &[Currency::ISK, Currency::UGX, Currency::HUF, Currency::TWD];
+
+/// Three-decimal currencies whose Stripe amounts must end in 0
+const ROUNDED_TO_TENS: &[Currency] = &[
+    Currency::BHD,
+    Currency::JOD,
+    Currency::KWD,
+    Currency::OMR,
+    Currency::TND,
+];
+
+/// Number of decimal places in Stripe amounts of this currency
+pub fn stripe_decimal_places(currency: Currency) -> u8 {
+    match currency {
+        Currency::ISK | Currency::UGX => 2,
+        Currency::MGA => 0,
+        _ => currency.decimal_places(),
+    }
+}
+
+/// Convert a price to the amount Stripe expects, or fail if Stripe cannot charge it
+pub fn to_stripe_amount(price: &Price) -> PaymentResult<i64> {
+    let currency = price.currency;
+    let decimal = price.as_decimal();
+    let amount = decimal
+        .to_minor_units(stripe_decimal_places(currency))
+        .map_err(|_| unsupported(price, "more decimal places than Stripe accepts"))?;
+
+    if WHOLE_UNITS_ONLY.contains(&currency) && amount % 100 != 0 {
+        return Err(unsupported(price, "a fractional amount; Stripe only accepts whole units"));
+    }
+    if ROUNDED_TO_TENS.contains(&currency) && amount % 10 != 0 {
+        return Err(unsupported(price, "three significant decimals; Stripe needs the last digit to be 0"));
+    }
+    Ok(amount)
+}
+
+/// Convert an amount reported by Stripe to a price in ISO 4217 minor units
+pub fn from_stripe_amount(amount: i64, currency: Currency) -> PaymentResult<Price> {
+    let decimal = Money::from_minor_units(amount, stripe_decimal_places(currency))?;
+    Price::new(decimal, currency)
+}
+
+fn unsupported(price: &Price, problem: &str) -> PaymentError {
+    PaymentError::InvalidPrice {
+        message: format!("{} {} has {}", price.as_decimal(), price.currency.code(), problem),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stripe_exponent_exceptions() {
+        // ISO exponent 0, Stripe two-decimal
+        assert_eq!(to_stripe_amount(&Price::from_cents(500, Currency::ISK)).unwrap(), 50000);
+        assert_eq!(to_stripe_amount(&Price::from_cents(7, Currency::UGX)).unwrap(), 700);
+        // ISO exponent 2, Stripe zero-decimal
+        assert_eq!(to_stripe_amount(&Price::from_cents(150000, Currency::MGA)).unwrap(), 1500);
+        assert!(to_stripe_amount(&Price::from_cents(150050, Currency::MGA)).is_err());
+        // Unchanged
+        assert_eq!(to_stripe_amount(&Price::from_cents(1999, Currency::USD)).unwrap(), 1999);
+        assert_eq!(to_stripe_amount(&Price::from_cents(500, Currency::JPY)).unwrap(), 500);
+
+        assert_eq!(from_stripe_amount(50000, Currency::ISK).unwrap().amount, 500);
+        assert_eq!(from_stripe_amount(1500, Currency::MGA).unwrap().amount, 150000);
+    }
+
+    #[test]
+    fn test_stripe_rounding_rules() {
+        assert_eq!(to_stripe_amount(&Price::from_cents(12340, Currency::KWD)).unwrap(), 12340);
+        assert!(to_stripe_amount(&Price::from_cents(12345, Currency::KWD)).is_err());
+        assert!(to_stripe_amount(&Price::from_cents(1001, Currency::OMR)).is_err());
+
+        assert_eq!(to_stripe_amount(&Price::from_cents(10000, Currency::HUF)).unwrap(), 10000);
+        assert!(to_stripe_amount(&Price::from_cents(10050, Currency::HUF)).is_err());
+        assert!(to_stripe_amount(&Price::from_cents(99, Currency::TWD)).is_err());
+    }
+}
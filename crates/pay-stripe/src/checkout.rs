@@ -3,17 +3,27 @@
 //! Implementation of Stripe Checkout Sessions API.
 //! This is the primary payment flow for lightning-cart.
 
-use crate::config::StripeConfig;
+use crate::amount::{from_stripe_amount, to_stripe_amount};
+use crate::config::{StripeConfig, WebhookSecret};
+use crate::events::{RefundData, StripeList};
+use crate::usage::UsagePrice;
 use async_trait::async_trait;
 use chrono::{DateTime, Duration, Utc};
 use pay_core::{
-    BillingInterval, CheckoutMode, CheckoutSession, CheckoutStatus, Order, PaymentError,
-    PaymentResult, PaymentStrategy, WebhookEvent, WebhookEventType,
+    CheckoutMode, CheckoutSession, CheckoutStatus, Currency, Customer, FulfillmentStatus, Order,
+    OrderReceipt, OrderStatus, OrderTotals, PaymentError, PaymentResult, PaymentStrategy, Price,
+    ReceiptLine, Refund, RefundRequest, RefundTarget, TaxLine, TrialPaymentMethod, WebhookEvent,
+    WebhookEventType,
 };
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
 use tracing::{debug, error, info, instrument};
 
+/// Longest free trial Stripe accepts
+const MAX_TRIAL_DAYS: u32 = 730;
+
 /// Stripe Checkout Session strategy
 ///
 /// Uses Stripe's hosted checkout page for secure payments.
@@ -21,6 +31,12 @@ use tracing::{debug, error, info, instrument};
 pub struct StripeCheckoutStrategy {
     config: StripeConfig,
     client: Client,
+    /// Tax rate IDs created by this strategy (rate key → txr_...)
+    tax_rate_ids: Mutex<HashMap<String, String>>,
+    /// Portal configurations created by this strategy (feature key → bpc_...)
+    pub(crate) portal_configuration_ids: Mutex<HashMap<String, String>>,
+    /// Usage-billed prices found or created by this strategy (lookup key → price_...)
+    pub(crate) usage_price_ids: Mutex<HashMap<String, String>>,
 }
 
 impl StripeCheckoutStrategy {
@@ -31,7 +47,13 @@ impl StripeCheckoutStrategy {
             .build()
             .expect("Failed to create HTTP client");
 
-        Self { config, client }
+        Self {
+            config,
+            client,
+            tax_rate_ids: Mutex::new(HashMap::new()),
+            portal_configuration_ids: Mutex::new(HashMap::new()),
+            usage_price_ids: Mutex::new(HashMap::new()),
+        }
     }
 
     /// Create from environment variables
@@ -40,45 +62,297 @@ impl StripeCheckoutStrategy {
         Ok(Self::new(config))
     }
 
-    /// Build line items for Stripe API
-    fn build_line_items(&self, order: &Order) -> Vec<StripeLineItem> {
+    /// Verify a `Stripe-Signature` header, returning the secret that matched
+    pub fn verify_signature(&self, payload: &[u8], signature: &str) -> PaymentResult<SecretMatch> {
+        verify_signature(
+            &self.config.webhook_secrets,
+            self.config.webhook_tolerance_secs,
+            payload,
+            signature,
+            Utc::now().timestamp(),
+        )
+    }
+
+    /// Build line items for Stripe API (fails on amounts Stripe cannot charge)
+    fn build_line_items(&self, order: &Order) -> PaymentResult<Vec<StripeLineItem>> {
         order
             .line_items
             .iter()
             .map(|item| {
-                let recurring = match item.billing_interval {
-                    BillingInterval::OneTime => None,
-                    BillingInterval::Weekly => Some(StripeRecurring {
-                        interval: "week".to_string(),
-                        interval_count: 1,
-                    }),
-                    BillingInterval::Monthly => Some(StripeRecurring {
-                        interval: "month".to_string(),
-                        interval_count: 1,
-                    }),
-                    BillingInterval::Yearly => Some(StripeRecurring {
-                        interval: "year".to_string(),
-                        interval_count: 1,
-                    }),
-                };
+                let recurring = item.billing_interval.unit().map(|unit| StripeRecurring {
+                    interval: unit.as_str().to_string(),
+                    interval_count: item.billing_interval.count() as i64,
+                });
+                // Variants show as "Product – Variant" and carry their SKU
+                let mut metadata = Vec::new();
+                if let Some(ref variant_id) = item.variant_id {
+                    metadata.push(("variant_id".to_string(), variant_id.clone()));
+                }
+                if let Some(ref sku) = item.sku {
+                    metadata.push(("sku".to_string(), sku.clone()));
+                }
 
-                StripeLineItem {
+                Ok(StripeLineItem {
                     price_data: StripePriceData {
                         currency: item.unit_price.currency.as_str().to_string(),
-                        unit_amount: item.unit_price.amount,
+                        unit_amount: to_stripe_amount(&item.unit_price)?,
                         product_data: StripeProductData {
-                            name: item.name.clone(),
+                            name: item.display_name(),
                             description: item.description.clone(),
                             images: item.image_url.clone().map(|url| vec![url]),
+                            metadata,
                         },
                         recurring,
                     },
                     quantity: item.quantity as i64,
-                }
+                })
             })
             .collect()
     }
 
+    /// POST a form to the Stripe API and return the response body
+    pub(crate) async fn post_form(
+        &self,
+        path: &str,
+        idempotency_key: &str,
+        form_params: &[(String, String)],
+    ) -> PaymentResult<String> {
+        let url = format!("{}{}", self.config.api_base_url, path);
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", self.config.auth_header())
+            .header("Stripe-Version", &self.config.api_version)
+            .header("Idempotency-Key", idempotency_key)
+            .form(form_params)
+            .send()
+            .await
+            .map_err(|e| PaymentError::NetworkError(e.to_string()))?;
+
+        read_response(response).await
+    }
+
+    /// GET an object from the Stripe API (None if it does not exist)
+    pub(crate) async fn get(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> PaymentResult<Option<String>> {
+        let url = format!("{}{}", self.config.api_base_url, path);
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", self.config.auth_header())
+            .header("Stripe-Version", &self.config.api_version)
+            .query(query)
+            .send()
+            .await
+            .map_err(|e| PaymentError::NetworkError(e.to_string()))?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        read_response(response).await.map(Some)
+    }
+
+    /// DELETE an object through the Stripe API (None if it does not exist)
+    pub(crate) async fn delete(&self, path: &str) -> PaymentResult<Option<String>> {
+        let url = format!("{}{}", self.config.api_base_url, path);
+
+        let response = self
+            .client
+            .delete(&url)
+            .header("Authorization", self.config.auth_header())
+            .header("Stripe-Version", &self.config.api_version)
+            .send()
+            .await
+            .map_err(|e| PaymentError::NetworkError(e.to_string()))?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        read_response(response).await.map(Some)
+    }
+
+    /// Create a single-use coupon for the order's discount total.
+    ///
+    /// Coupons apply to the first payment only (`duration=once`).
+    async fn create_coupon(&self, order: &Order, idempotency_key: &str) -> PaymentResult<String> {
+        let discount = order.discount_total()?;
+        let codes: Vec<&str> = order.discounts.iter().map(|d| d.code.as_str()).collect();
+        // Stripe limits coupon names to 40 characters
+        let name: String = codes.join(", ").chars().take(40).collect();
+
+        let form_params = vec![
+            ("amount_off".to_string(), to_stripe_amount(&discount)?.to_string()),
+            ("currency".to_string(), discount.currency.as_str().to_string()),
+            ("duration".to_string(), "once".to_string()),
+            ("max_redemptions".to_string(), "1".to_string()),
+            ("name".to_string(), name),
+            ("metadata[order_id]".to_string(), order.id.clone()),
+        ];
+
+        let body = self
+            .post_form(
+                "/v1/coupons",
+                &format!("{}-coupon", idempotency_key),
+                &form_params,
+            )
+            .await?;
+
+        let coupon: StripeIdResponse = serde_json::from_str(&body).map_err(|e| {
+            PaymentError::Serialization(format!("Failed to parse Stripe coupon: {}", e))
+        })?;
+
+        debug!("Created Stripe coupon {} for order {}", coupon.id, order.id);
+        Ok(coupon.id)
+    }
+
+    /// Create a coupon that bills the first periods at the introductory prices
+    async fn create_intro_coupon(
+        &self,
+        order: &Order,
+        discount: &Price,
+        periods: u32,
+        idempotency_key: &str,
+    ) -> PaymentResult<String> {
+        let mut form_params = vec![
+            ("amount_off".to_string(), to_stripe_amount(discount)?.to_string()),
+            ("currency".to_string(), discount.currency.as_str().to_string()),
+            ("max_redemptions".to_string(), "1".to_string()),
+            ("name".to_string(), "Introductory price".to_string()),
+            ("metadata[order_id]".to_string(), order.id.clone()),
+        ];
+        if periods == 1 {
+            form_params.push(("duration".to_string(), "once".to_string()));
+        } else {
+            // Repeating coupons last whole months
+            let months = order
+                .line_items
+                .iter()
+                .find(|i| i.intro_price.is_some())
+                .and_then(|i| i.billing_interval.months())
+                .ok_or_else(|| {
+                    PaymentError::InvalidRequest(
+                        "introductory prices over several periods need a monthly or yearly interval"
+                            .to_string(),
+                    )
+                })?;
+            form_params.push(("duration".to_string(), "repeating".to_string()));
+            form_params.push((
+                "duration_in_months".to_string(),
+                (months * periods).to_string(),
+            ));
+        }
+
+        let body = self
+            .post_form(
+                "/v1/coupons",
+                &format!("{}-intro-coupon", idempotency_key),
+                &form_params,
+            )
+            .await?;
+        let coupon: StripeIdResponse = serde_json::from_str(&body).map_err(|e| {
+            PaymentError::Serialization(format!("Failed to parse Stripe coupon: {}", e))
+        })?;
+
+        debug!("Created Stripe intro coupon {} for order {}", coupon.id, order.id);
+        Ok(coupon.id)
+    }
+
+    /// Get (or create) the Stripe tax rate for a table tax line.
+    ///
+    /// Created rates are cached for the lifetime of the strategy.
+    async fn tax_rate_id(&self, line: &TaxLine, tax_behavior: &str) -> PaymentResult<String> {
+        let rate = &line.rate;
+        let key = format!(
+            "{}|{}|{}|{}|{}",
+            rate.name,
+            rate.rate,
+            tax_behavior,
+            rate.country.to_ascii_uppercase(),
+            rate.region.as_deref().unwrap_or_default().to_ascii_uppercase()
+        );
+        if let Some(id) = self.tax_rate_ids.lock().ok().and_then(|ids| ids.get(&key).cloned()) {
+            return Ok(id);
+        }
+
+        let mut form_params = vec![
+            ("display_name".to_string(), rate.name.clone()),
+            ("percentage".to_string(), rate.rate.to_string()),
+            ("inclusive".to_string(), (tax_behavior == "inclusive").to_string()),
+            ("country".to_string(), rate.country.to_ascii_uppercase()),
+        ];
+        if let Some(ref region) = rate.region {
+            form_params.push(("state".to_string(), region.to_ascii_uppercase()));
+        }
+
+        let idempotency_key = format!("tax-rate-{}", key.replace(['|', ' '], "-"));
+        let body = self
+            .post_form("/v1/tax_rates", &idempotency_key, &form_params)
+            .await?;
+        let tax_rate: StripeIdResponse = serde_json::from_str(&body).map_err(|e| {
+            PaymentError::Serialization(format!("Failed to parse Stripe tax rate: {}", e))
+        })?;
+
+        debug!("Created Stripe tax rate {} for {}", tax_rate.id, key);
+        if let Ok(mut ids) = self.tax_rate_ids.lock() {
+            ids.insert(key, tax_rate.id.clone());
+        }
+        Ok(tax_rate.id)
+    }
+
+    /// Find the payment intent a refund applies to.
+    ///
+    /// Orders are found by the `order_id` metadata checkout puts on the
+    /// payment intent, so only payment-mode orders can be refunded by order ID.
+    async fn refund_payment_intent(&self, target: &RefundTarget) -> PaymentResult<String> {
+        match target {
+            RefundTarget::PaymentIntent(id) => Ok(id.clone()),
+            RefundTarget::Session(session_id) => {
+                let path = format!("/v1/checkout/sessions/{}", session_id);
+                let body = self.get(&path, &[]).await?.ok_or_else(|| {
+                    PaymentError::SessionNotFound {
+                        session_id: session_id.clone(),
+                    }
+                })?;
+                let session: StripeSessionDetails = serde_json::from_str(&body).map_err(|e| {
+                    PaymentError::Serialization(format!("Failed to parse Stripe session: {}", e))
+                })?;
+                session.payment_intent.ok_or_else(|| {
+                    PaymentError::InvalidRequest(format!(
+                        "checkout session {} has no payment to refund",
+                        session_id
+                    ))
+                })
+            }
+            RefundTarget::Order(order_id) => {
+                let query = format!("metadata['order_id']:'{}'", search_literal(order_id));
+                let body = self
+                    .get("/v1/payment_intents/search", &[("query", query.as_str())])
+                    .await?
+                    .unwrap_or_default();
+                let intents: StripeList<StripeIdResponse> =
+                    serde_json::from_str(&body).map_err(|e| {
+                        PaymentError::Serialization(format!(
+                            "Failed to parse Stripe payment intents: {}",
+                            e
+                        ))
+                    })?;
+                intents
+                    .data
+                    .into_iter()
+                    .next()
+                    .map(|intent| intent.id)
+                    .ok_or_else(|| PaymentError::OrderNotFound {
+                        order_id: order_id.clone(),
+                    })
+            }
+        }
+    }
+
     /// Convert our checkout mode to Stripe's mode
     fn stripe_mode(mode: CheckoutMode) -> &'static str {
         match mode {
@@ -104,7 +378,7 @@ impl PaymentStrategy for StripeCheckoutStrategy {
             ));
         }
 
-        let line_items = self.build_line_items(order);
+        let line_items = self.build_line_items(order)?;
         let mode = Self::stripe_mode(order.mode);
 
         debug!(
@@ -121,7 +395,30 @@ impl PaymentStrategy for StripeCheckoutStrategy {
         ];
 
         // Add line items
-        for (i, item) in line_items.iter().enumerate() {
+        for (i, (item, line)) in line_items.iter().zip(&order.line_items).enumerate() {
+            // Seat, tiered and metered items need a real price
+            if let Some(ref usage) = line.usage {
+                let price = UsagePrice {
+                    product_id: &line.product_id,
+                    name: &line.name,
+                    unit_price: &line.unit_price,
+                    interval: line.billing_interval,
+                    usage,
+                    tax_behavior: order.tax.as_ref().map(|t| t.mode.as_str()),
+                };
+                form_params.push((
+                    format!("line_items[{}][price]", i),
+                    self.usage_price_id(&price).await?,
+                ));
+                // Metered usage is reported later, so there is no quantity
+                if !usage.is_metered() {
+                    form_params.push((
+                        format!("line_items[{}][quantity]", i),
+                        item.quantity.to_string(),
+                    ));
+                }
+                continue;
+            }
             form_params.push((
                 format!("line_items[{}][price_data][currency]", i),
                 item.price_data.currency.clone(),
@@ -148,6 +445,18 @@ impl PaymentStrategy for StripeCheckoutStrategy {
                     ));
                 }
             }
+            for (key, value) in &item.price_data.product_data.metadata {
+                form_params.push((
+                    format!("line_items[{}][price_data][product_data][metadata][{}]", i, key),
+                    value.clone(),
+                ));
+            }
+            if let Some(ref tax) = order.tax {
+                form_params.push((
+                    format!("line_items[{}][price_data][tax_behavior]", i),
+                    tax.mode.as_str().to_string(),
+                ));
+            }
             if let Some(ref recurring) = item.price_data.recurring {
                 form_params.push((
                     format!("line_items[{}][price_data][recurring][interval]", i),
@@ -164,8 +473,37 @@ impl PaymentStrategy for StripeCheckoutStrategy {
             ));
         }
 
-        // Add customer email if provided
-        if let Some(ref email) = order.customer_email {
+        // Tax: Stripe Tax computes it, or attach our table rates per line item
+        if let Some(ref tax) = order.tax {
+            if tax.automatic {
+                form_params.push(("automatic_tax[enabled]".to_string(), "true".to_string()));
+            } else {
+                for line in &tax.lines {
+                    let tax_rate_id = self.tax_rate_id(line, tax.mode.as_str()).await?;
+                    form_params.push((
+                        format!("line_items[{}][tax_rates][0]", line.line_index),
+                        tax_rate_id,
+                    ));
+                }
+                // The rates follow the location the client sent; collect the billing
+                // address so the webhook can check it against that location
+                if !tax.lines.is_empty() {
+                    form_params.push((
+                        "billing_address_collection".to_string(),
+                        "required".to_string(),
+                    ));
+                }
+            }
+        }
+
+        // Reuse the Stripe customer if known, otherwise prefill the email
+        if let Some(ref customer_id) = order.customer_id {
+            form_params.push(("customer".to_string(), customer_id.clone()));
+            // Stripe Tax needs the address collected at checkout on the customer
+            if order.tax.as_ref().is_some_and(|t| t.automatic) {
+                form_params.push(("customer_update[address]".to_string(), "auto".to_string()));
+            }
+        } else if let Some(ref email) = order.customer_email {
             form_params.push(("customer_email".to_string(), email.clone()));
         }
 
@@ -175,9 +513,81 @@ impl PaymentStrategy for StripeCheckoutStrategy {
             .clone()
             .unwrap_or_else(|| order.id.clone());
 
-        // Add metadata
+        // Free trial and introductory price (subscriptions only)
+        if order.mode == CheckoutMode::Subscription {
+            if let Some(days) = order.trial_days() {
+                if !(1..=MAX_TRIAL_DAYS).contains(&days) {
+                    return Err(PaymentError::InvalidRequest(format!(
+                        "trial_days must be between 1 and {}",
+                        MAX_TRIAL_DAYS
+                    )));
+                }
+                form_params.push((
+                    "subscription_data[trial_period_days]".to_string(),
+                    days.to_string(),
+                ));
+                if order.trial_payment_method() == TrialPaymentMethod::IfRequired {
+                    form_params.push((
+                        "payment_method_collection".to_string(),
+                        "if_required".to_string(),
+                    ));
+                    form_params.push((
+                        "subscription_data[trial_settings][end_behavior][missing_payment_method]"
+                            .to_string(),
+                        "cancel".to_string(),
+                    ));
+                }
+            }
+
+            if let Some((discount, periods)) = order.intro_discount()?.filter(|(d, _)| d.amount > 0) {
+                // Checkout takes one discount, and coupon periods would start during the trial
+                if !order.discounts.is_empty() || order.trial_days().is_some() {
+                    return Err(PaymentError::InvalidRequest(
+                        "an introductory price cannot be combined with a promotion code or free trial"
+                            .to_string(),
+                    ));
+                }
+                let coupon_id = self
+                    .create_intro_coupon(order, &discount, periods, &idempotency_key)
+                    .await?;
+                form_params.push(("discounts[0][coupon]".to_string(), coupon_id));
+            }
+        }
+
+        // Discounts are passed as a single-use coupon for the discount total
+        if !order.discounts.is_empty() {
+            let coupon_id = self.create_coupon(order, &idempotency_key).await?;
+            form_params.push(("discounts[0][coupon]".to_string(), coupon_id));
+            let codes: Vec<&str> = order.discounts.iter().map(|d| d.code.as_str()).collect();
+            form_params.push(("metadata[promo_code]".to_string(), codes.join(",")));
+        }
+
+        // Add metadata (the payment intent gets the order ID too, so its events map back)
         form_params.push(("metadata[order_id]".to_string(), order.id.clone()));
+        if order.mode == CheckoutMode::Payment {
+            form_params.push((
+                "payment_intent_data[metadata][order_id]".to_string(),
+                order.id.clone(),
+            ));
+        }
+        if order.mode == CheckoutMode::Subscription {
+            form_params.push((
+                "subscription_data[metadata][order_id]".to_string(),
+                order.id.clone(),
+            ));
+            if let Some(site_id) = order.metadata.get("site_id") {
+                form_params.push((
+                    "subscription_data[metadata][site_id]".to_string(),
+                    site_id.clone(),
+                ));
+            }
+        }
         for (key, value) in &order.metadata {
+            let param = format!("metadata[{}]", key);
+            // Never let order metadata replace the order ID or promo codes set above
+            if form_params.iter().any(|(k, _)| *k == param) {
+                continue;
+            }
             // Handle statement_descriptor_suffix specially - it goes to payment_intent_data
             if key == "statement_descriptor_suffix" {
                 form_params.push((
@@ -185,44 +595,12 @@ impl PaymentStrategy for StripeCheckoutStrategy {
                     value.clone(),
                 ));
             }
-            form_params.push((format!("metadata[{}]", key), value.clone()));
+            form_params.push((param, value.clone()));
         }
 
-        let url = format!("{}/v1/checkout/sessions", self.config.api_base_url);
-
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", self.config.auth_header())
-            .header("Stripe-Version", &self.config.api_version)
-            .header("Idempotency-Key", &idempotency_key)
-            .form(&form_params)
-            .send()
-            .await
-            .map_err(|e| PaymentError::NetworkError(e.to_string()))?;
-
-        let status = response.status();
-        let body = response
-            .text()
-            .await
-            .map_err(|e| PaymentError::NetworkError(e.to_string()))?;
-
-        if !status.is_success() {
-            error!("Stripe API error: status={}, body={}", status, body);
-
-            // Parse Stripe error
-            if let Ok(error_response) = serde_json::from_str::<StripeErrorResponse>(&body) {
-                return Err(PaymentError::ProviderError {
-                    provider: "stripe".to_string(),
-                    message: error_response.error.message,
-                });
-            }
-
-            return Err(PaymentError::ProviderError {
-                provider: "stripe".to_string(),
-                message: format!("HTTP {}: {}", status, body),
-            });
-        }
+        let body = self
+            .post_form("/v1/checkout/sessions", &idempotency_key, &form_params)
+            .await?;
 
         let session_response: StripeCheckoutSessionResponse =
             serde_json::from_str(&body).map_err(|e| {
@@ -260,50 +638,29 @@ impl PaymentStrategy for StripeCheckoutStrategy {
         payload: &[u8],
         signature: &str,
     ) -> PaymentResult<WebhookEvent> {
-        // Parse signature header
-        let sig_parts = parse_signature_header(signature)?;
-
-        // Verify timestamp is within tolerance (5 minutes)
-        let timestamp = sig_parts.timestamp;
-        let now = Utc::now().timestamp();
-        let tolerance = 300; // 5 minutes
-
-        if (now - timestamp).abs() > tolerance {
-            return Err(PaymentError::WebhookVerificationFailed(
-                "Timestamp outside tolerance".to_string(),
-            ));
-        }
-
-        // Compute expected signature
-        let signed_payload = format!("{}.{}", timestamp, String::from_utf8_lossy(payload));
-        let expected_sig = compute_hmac_sha256(&self.config.webhook_secret, &signed_payload);
-
-        // Compare signatures (constant-time)
-        let valid = sig_parts
-            .signatures
-            .iter()
-            .any(|sig| constant_time_compare(sig, &expected_sig));
-
-        if !valid {
-            return Err(PaymentError::WebhookVerificationFailed(
-                "Signature mismatch".to_string(),
-            ));
-        }
+        let secret = self.verify_signature(payload, signature)?;
 
         // Parse the event
         let event: StripeWebhookEvent = serde_json::from_slice(payload).map_err(|e| {
             PaymentError::WebhookParseError(format!("Failed to parse webhook: {}", e))
         })?;
 
-        debug!("Verified Stripe webhook: type={}", event.event_type);
+        debug!(
+            "Verified Stripe webhook: type={} secret={}",
+            event.event_type,
+            secret.name()
+        );
 
         let event_type = match event.event_type.as_str() {
             "checkout.session.completed" => WebhookEventType::CheckoutCompleted,
+            "checkout.session.expired" => WebhookEventType::CheckoutExpired,
             "payment_intent.succeeded" => WebhookEventType::PaymentSucceeded,
             "payment_intent.payment_failed" => WebhookEventType::PaymentFailed,
             "customer.subscription.created" => WebhookEventType::SubscriptionCreated,
+            "customer.subscription.updated" => WebhookEventType::SubscriptionUpdated,
             "customer.subscription.deleted" => WebhookEventType::SubscriptionCancelled,
             "invoice.paid" => WebhookEventType::SubscriptionRenewed,
+            "invoice.payment_failed" => WebhookEventType::InvoicePaymentFailed,
             "charge.refunded" => WebhookEventType::RefundIssued,
             other => WebhookEventType::Unknown(other.to_string()),
         };
@@ -331,11 +688,21 @@ impl PaymentStrategy for StripeCheckoutStrategy {
             .and_then(|v| v.as_str())
             .map(String::from);
 
+        let currency = event
+            .data
+            .object
+            .get("currency")
+            .and_then(|v| v.as_str())
+            .and_then(Currency::from_code);
+
         let amount_paid = event
             .data
             .object
             .get("amount_total")
-            .and_then(|v| v.as_i64());
+            .and_then(|v| v.as_i64())
+            .zip(currency)
+            .and_then(|(amount, currency)| from_stripe_amount(amount, currency).ok())
+            .map(|price| price.amount);
 
         Ok(WebhookEvent {
             event_id: event.id,
@@ -345,12 +712,77 @@ impl PaymentStrategy for StripeCheckoutStrategy {
             payment_intent_id,
             customer_email,
             amount_paid,
-            currency: None, // Could parse from event if needed
+            currency,
             raw_data: Some(serde_json::Value::Object(event.data.object)),
             timestamp: DateTime::from_timestamp(event.created, 0).unwrap_or(Utc::now()),
         })
     }
 
+    #[instrument(skip(self))]
+    async fn retrieve_checkout(&self, session_id: &str) -> PaymentResult<OrderReceipt> {
+        let path = format!("/v1/checkout/sessions/{}", session_id);
+        let body = self
+            .get(&path, &[("expand[]", "line_items")])
+            .await?
+            .ok_or_else(|| PaymentError::SessionNotFound {
+                session_id: session_id.to_string(),
+            })?;
+
+        let session: StripeSessionDetails = serde_json::from_str(&body).map_err(|e| {
+            PaymentError::Serialization(format!("Failed to parse Stripe session: {}", e))
+        })?;
+        session.into_receipt()
+    }
+
+    #[instrument(skip(self, customer), fields(customer_id = %customer.id))]
+    async fn ensure_customer(&self, customer: &Customer) -> PaymentResult<Option<String>> {
+        self.stripe_customer_id(customer).await.map(Some)
+    }
+
+    #[instrument(skip(self, request), fields(target = ?request.target))]
+    async fn refund(&self, request: &RefundRequest) -> PaymentResult<Refund> {
+        let payment_intent = self.refund_payment_intent(&request.target).await?;
+
+        let mut form_params = vec![("payment_intent".to_string(), payment_intent.clone())];
+        if let Some(ref amount) = request.amount {
+            form_params.push(("amount".to_string(), to_stripe_amount(amount)?.to_string()));
+        }
+        if let Some(reason) = request.reason {
+            form_params.push(("reason".to_string(), reason.as_str().to_string()));
+        }
+        if let RefundTarget::Order(ref order_id) = request.target {
+            form_params.push(("metadata[order_id]".to_string(), order_id.clone()));
+        }
+        for (key, value) in &request.metadata {
+            let param = format!("metadata[{}]", key);
+            if !form_params.iter().any(|(k, _)| *k == param) {
+                form_params.push((param, value.clone()));
+            }
+        }
+
+        // Without a key every call is a new refund; callers pass one to make retries safe
+        let idempotency_key = request
+            .idempotency_key
+            .clone()
+            .unwrap_or_else(|| format!("refund-{}", uuid::Uuid::new_v4()));
+
+        let body = self
+            .post_form("/v1/refunds", &idempotency_key, &form_params)
+            .await?;
+        let refund: RefundData = serde_json::from_str(&body).map_err(|e| {
+            PaymentError::Serialization(format!("Failed to parse Stripe refund: {}", e))
+        })?;
+
+        info!(
+            "Created Stripe refund {} of {} for {} ({})",
+            refund.refund_id,
+            refund.amount,
+            payment_intent,
+            refund.status.as_deref().unwrap_or("pending")
+        );
+        refund.to_refund()
+    }
+
     fn provider_name(&self) -> &'static str {
         "stripe"
     }
@@ -360,6 +792,39 @@ impl PaymentStrategy for StripeCheckoutStrategy {
     }
 }
 
+/// Escape a value for a quoted string in a Stripe search query
+pub(crate) fn search_literal(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+/// Return the response body, or the Stripe error as a `ProviderError`
+async fn read_response(response: reqwest::Response) -> PaymentResult<String> {
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|e| PaymentError::NetworkError(e.to_string()))?;
+
+    if !status.is_success() {
+        error!("Stripe API error: status={}, body={}", status, body);
+
+        // Parse Stripe error
+        if let Ok(error_response) = serde_json::from_str::<StripeErrorResponse>(&body) {
+            return Err(PaymentError::ProviderError {
+                provider: "stripe".to_string(),
+                message: error_response.error.message,
+            });
+        }
+
+        return Err(PaymentError::ProviderError {
+            provider: "stripe".to_string(),
+            message: format!("HTTP {}: {}", status, body),
+        });
+    }
+
+    Ok(body)
+}
+
 // =============================================================================
 // Stripe API Types
 // =============================================================================
@@ -386,6 +851,8 @@ struct StripeProductData {
     description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     images: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    metadata: Vec<(String, String)>,
 }
 
 #[derive(Debug, Serialize)]
@@ -406,12 +873,147 @@ struct StripeCheckoutSessionResponse {
     expires_at: Option<i64>,
 }
 
+/// Checkout session as returned by `GET /v1/checkout/sessions/{id}`
+#[derive(Debug, Deserialize)]
+struct StripeSessionDetails {
+    id: String,
+    #[serde(default)]
+    status: Option<String>,
+    payment_status: String,
+    currency: Option<String>,
+    #[serde(default)]
+    amount_subtotal: Option<i64>,
+    #[serde(default)]
+    amount_total: Option<i64>,
+    #[serde(default)]
+    total_details: Option<StripeTotalDetails>,
+    #[serde(default)]
+    customer_details: Option<StripeCustomerDetails>,
+    #[serde(default)]
+    customer_email: Option<String>,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+    #[serde(default)]
+    payment_intent: Option<String>,
+    created: i64,
+    #[serde(default)]
+    line_items: Option<StripeList<StripeSessionLineItem>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StripeTotalDetails {
+    #[serde(default)]
+    amount_discount: i64,
+    #[serde(default)]
+    amount_tax: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct StripeCustomerDetails {
+    #[serde(default)]
+    email: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StripeSessionLineItem {
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    quantity: Option<u32>,
+    amount_subtotal: i64,
+    #[serde(default)]
+    price: Option<StripeSessionPrice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StripeSessionPrice {
+    #[serde(default)]
+    unit_amount: Option<i64>,
+}
+
+impl StripeSessionDetails {
+    fn into_receipt(self) -> PaymentResult<OrderReceipt> {
+        let currency: Currency = self
+            .currency
+            .as_deref()
+            .ok_or_else(|| {
+                PaymentError::Serialization(format!("Stripe session {} has no currency", self.id))
+            })?
+            .parse()?;
+        let price = |amount: i64| from_stripe_amount(amount, currency);
+
+        let status = match (self.status.as_deref(), self.payment_status.as_str()) {
+            (Some("complete"), "paid" | "no_payment_required") => OrderStatus::Paid,
+            (Some("expired"), _) => OrderStatus::Expired,
+            _ => OrderStatus::AwaitingPayment,
+        };
+
+        let line_items = self
+            .line_items
+            .map(|list| list.data)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| {
+                let quantity = item.quantity.unwrap_or(1).max(1);
+                let unit_amount = item
+                    .price
+                    .and_then(|p| p.unit_amount)
+                    .unwrap_or(item.amount_subtotal / quantity as i64);
+                Ok(ReceiptLine {
+                    name: item.description.unwrap_or_default(),
+                    quantity,
+                    unit_price: price(unit_amount)?,
+                    amount: price(item.amount_subtotal)?,
+                })
+            })
+            .collect::<PaymentResult<_>>()?;
+
+        let details = self.total_details.unwrap_or_default();
+        let subtotal = self.amount_subtotal.unwrap_or_default();
+        let total = self.amount_total.unwrap_or(subtotal);
+        // Inclusive tax is already in the subtotal, so it does not add to the total
+        let tax_inclusive =
+            details.amount_tax > 0 && total == subtotal - details.amount_discount;
+
+        let created_at = DateTime::from_timestamp(self.created, 0).unwrap_or_else(Utc::now);
+
+        Ok(OrderReceipt {
+            order_id: self.metadata.get("order_id").cloned(),
+            site_id: self.metadata.get("site_id").cloned(),
+            session_id: Some(self.id),
+            status,
+            payment_status: status.into(),
+            fulfillment_status: FulfillmentStatus::Unfulfilled,
+            customer_email: self
+                .customer_details
+                .and_then(|c| c.email)
+                .or(self.customer_email),
+            currency,
+            line_items,
+            totals: OrderTotals {
+                subtotal: price(subtotal)?,
+                discount: price(details.amount_discount)?,
+                tax: price(details.amount_tax)?,
+                tax_inclusive,
+                total: price(total)?,
+            },
+            created_at,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct StripeIdResponse {
+    pub(crate) id: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct StripeErrorResponse {
     error: StripeError,
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(dead_code)] // code/param are kept for Debug output
 struct StripeError {
     message: String,
     #[serde(default)]
@@ -438,6 +1040,69 @@ struct StripeEventData {
 // Webhook Signature Verification
 // =============================================================================
 
+/// The webhook secret that verified a signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretMatch {
+    /// Position in `StripeConfig::webhook_secrets`
+    pub index: usize,
+
+    /// The secret's label
+    pub label: Option<String>,
+}
+
+impl SecretMatch {
+    /// Label, or `#<index>` for unlabelled secrets (for logs)
+    pub fn name(&self) -> String {
+        self.label
+            .clone()
+            .unwrap_or_else(|| format!("#{}", self.index))
+    }
+}
+
+/// Check a signature header against each secret in turn
+fn verify_signature(
+    secrets: &[WebhookSecret],
+    tolerance_secs: i64,
+    payload: &[u8],
+    header: &str,
+    now: i64,
+) -> PaymentResult<SecretMatch> {
+    let sig_parts = parse_signature_header(header)?;
+
+    let timestamp = sig_parts.timestamp;
+    if (now - timestamp).abs() > tolerance_secs {
+        return Err(PaymentError::WebhookVerificationFailed(
+            "Timestamp outside tolerance".to_string(),
+        ));
+    }
+
+    let signed_payload = format!("{}.{}", timestamp, String::from_utf8_lossy(payload));
+    secrets
+        .iter()
+        .enumerate()
+        .find(|(_, secret)| {
+            // Compare signatures (constant-time)
+            let expected_sig = compute_hmac_sha256(&secret.secret, &signed_payload);
+            sig_parts
+                .signatures
+                .iter()
+                .any(|sig| constant_time_compare(sig, &expected_sig))
+        })
+        .map(|(index, secret)| SecretMatch {
+            index,
+            label: secret.label.clone(),
+        })
+        .ok_or_else(|| PaymentError::WebhookVerificationFailed("Signature mismatch".to_string()))
+}
+
+/// Build a `Stripe-Signature` header for a payload, as Stripe would send it.
+///
+/// Use it to generate signed webhook fixtures in tests.
+pub fn generate_test_header(payload: &[u8], secret: &str, timestamp: i64) -> String {
+    let signed_payload = format!("{}.{}", timestamp, String::from_utf8_lossy(payload));
+    format!("t={},v1={}", timestamp, compute_hmac_sha256(secret, &signed_payload))
+}
+
 struct SignatureHeader {
     timestamp: i64,
     signatures: Vec<String>,
@@ -505,7 +1170,7 @@ fn constant_time_compare(a: &str, b: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use pay_core::{Currency, LineItem, Price};
+    use pay_core::BillingInterval;
 
     #[test]
     fn test_stripe_mode_conversion() {
@@ -523,6 +1188,439 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_build_line_items_currency() {
+        use pay_core::{Price, Product};
+
+        let strategy = StripeCheckoutStrategy::new(StripeConfig::new(
+            "sk_test_abc",
+            "pk_test_xyz",
+            "whsec_123",
+        ));
+        let kwd_order = |amount: &str| {
+            let mut order = Order::new(Currency::KWD);
+            let price = Price::parse(amount, Currency::KWD).unwrap();
+            order.add_product(&Product::one_time("kw", "Kuwait Item", price), 1).unwrap();
+            order
+        };
+
+        let items = strategy.build_line_items(&kwd_order("12.340")).unwrap();
+        assert_eq!(items[0].price_data.currency, "kwd");
+        assert_eq!(items[0].price_data.unit_amount, 12340);
+
+        // Stripe needs three-decimal amounts to end in 0
+        assert!(strategy.build_line_items(&kwd_order("12.345")).is_err());
+    }
+
+    #[test]
+    fn test_build_line_items_interval_count() {
+        use pay_core::{Price, Product};
+
+        let strategy = StripeCheckoutStrategy::new(StripeConfig::new(
+            "sk_test_abc",
+            "pk_test_xyz",
+            "whsec_123",
+        ));
+        let mut order = Order::new(Currency::USD);
+        order
+            .add_product(
+                &Product::subscription(
+                    "q",
+                    "Quarterly",
+                    Price::from_cents(7500, Currency::USD),
+                    BillingInterval::QUARTERLY,
+                ),
+                1,
+            )
+            .unwrap();
+
+        let items = strategy.build_line_items(&order).unwrap();
+        let recurring = items[0].price_data.recurring.as_ref().unwrap();
+        assert_eq!(recurring.interval, "month");
+        assert_eq!(recurring.interval_count, 3);
+    }
+
+    #[test]
+    fn test_build_line_items_variant_name() {
+        use pay_core::{Price, Product, ProductVariant};
+
+        let strategy = StripeCheckoutStrategy::new(StripeConfig::new(
+            "sk_test_abc",
+            "pk_test_xyz",
+            "whsec_123",
+        ));
+        let product = Product::one_time("rang", "Rang Play RS", Price::from_cents(1999, Currency::USD))
+            .with_variant(ProductVariant::new(
+                "team",
+                "Team",
+                "RPR-TEAM",
+                Price::from_cents(9900, Currency::USD),
+            ));
+        let mut order = Order::new(Currency::USD);
+        order.add_variant(&product, "team", 1).unwrap();
+
+        let items = strategy.build_line_items(&order).unwrap();
+        let product_data = &items[0].price_data.product_data;
+        assert_eq!(product_data.name, "Rang Play RS – Team");
+        assert_eq!(items[0].price_data.unit_amount, 9900);
+        assert!(product_data.metadata.contains(&("sku".to_string(), "RPR-TEAM".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_checkout_with_discount_creates_coupon() {
+        use pay_core::{AppliedDiscount, Price, Product};
+        use wiremock::matchers::{body_string_contains, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/coupons"))
+            .and(body_string_contains("amount_off=500"))
+            .and(body_string_contains("duration=once"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"id": "co_123"})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/checkout/sessions"))
+            .and(body_string_contains("discounts%5B0%5D%5Bcoupon%5D=co_123"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "cs_test_1",
+                "url": "https://checkout.stripe.com/c/pay/cs_test_1"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let strategy = StripeCheckoutStrategy::new(
+            StripeConfig::new("sk_test_abc", "pk_test_xyz", "whsec_123")
+                .with_api_base_url(server.uri()),
+        );
+        let mut order = Order::new(Currency::USD);
+        order
+            .add_product(&Product::one_time("p", "P", Price::from_cents(2000, Currency::USD)), 1)
+            .unwrap();
+        order
+            .apply_discount(AppliedDiscount {
+                code: "FIVEOFF".into(),
+                description: String::new(),
+                amount: Price::from_cents(500, Currency::USD),
+            })
+            .unwrap();
+        order.metadata.insert("order_id".into(), "someone-elses-order".into());
+        order.metadata.insert("promo_code".into(), "NOTUSED".into());
+
+        let session = strategy
+            .create_checkout(&order, "https://example.com/ok", "https://example.com/cancel")
+            .await
+            .unwrap();
+        assert_eq!(session.session_id, "cs_test_1");
+
+        // Metadata cannot replace the order ID or the codes actually applied
+        let requests = server.received_requests().await.unwrap();
+        let body = String::from_utf8_lossy(&requests.last().unwrap().body);
+        assert!(body.contains(&format!("metadata%5Border_id%5D={}", order.id)));
+        assert!(body.contains("metadata%5Bpromo_code%5D=FIVEOFF"));
+        assert!(!body.contains("someone-elses-order"));
+        assert!(!body.contains("NOTUSED"));
+    }
+
+    #[tokio::test]
+    async fn test_subscription_trial_and_intro_price() {
+        use pay_core::{IntroPrice, Price, Product};
+        use wiremock::matchers::{body_string_contains, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/checkout/sessions"))
+            .and(body_string_contains("subscription_data%5Btrial_period_days%5D=14"))
+            .and(body_string_contains("payment_method_collection=if_required"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "cs_trial",
+                "url": "https://checkout.stripe.com/c/pay/cs_trial"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/coupons"))
+            .and(body_string_contains("amount_off=1500"))
+            .and(body_string_contains("duration=repeating"))
+            .and(body_string_contains("duration_in_months=3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"id": "co_intro"})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/checkout/sessions"))
+            .and(body_string_contains("discounts%5B0%5D%5Bcoupon%5D=co_intro"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "cs_intro",
+                "url": "https://checkout.stripe.com/c/pay/cs_intro"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let strategy = StripeCheckoutStrategy::new(
+            StripeConfig::new("sk_test_abc", "pk_test_xyz", "whsec_123")
+                .with_api_base_url(server.uri()),
+        );
+        let plan = Product::subscription(
+            "saas",
+            "SaaS",
+            Price::from_cents(2000, Currency::USD),
+            BillingInterval::MONTHLY,
+        );
+
+        let mut order = Order::new(Currency::USD);
+        order
+            .add_product(&plan.clone().with_trial(14, TrialPaymentMethod::IfRequired), 1)
+            .unwrap();
+        let session = strategy
+            .create_checkout(&order, "https://example.com/ok", "https://example.com/cancel")
+            .await
+            .unwrap();
+        assert_eq!(session.session_id, "cs_trial");
+
+        let intro = IntroPrice::new(Price::from_cents(500, Currency::USD), 3);
+        let mut order = Order::new(Currency::USD);
+        order.add_product(&plan.with_intro_price(intro), 1).unwrap();
+        let session = strategy
+            .create_checkout(&order, "https://example.com/ok", "https://example.com/cancel")
+            .await
+            .unwrap();
+        assert_eq!(session.session_id, "cs_intro");
+    }
+
+    #[tokio::test]
+    async fn test_checkout_with_table_tax_reuses_tax_rate() {
+        use pay_core::{Price, Product, TableTaxCalculator, TaxCalculator, TaxLocation, TaxMode, TaxRate};
+        use wiremock::matchers::{body_string_contains, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/tax_rates"))
+            .and(body_string_contains("percentage=19"))
+            .and(body_string_contains("inclusive=true"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"id": "txr_1"})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/checkout/sessions"))
+            .and(body_string_contains("line_items%5B0%5D%5Btax_rates%5D%5B0%5D=txr_1"))
+            .and(body_string_contains("tax_behavior%5D=inclusive"))
+            .and(body_string_contains("billing_address_collection=required"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "cs_test_1",
+                "url": "https://checkout.stripe.com/c/pay/cs_test_1"
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let strategy = StripeCheckoutStrategy::new(
+            StripeConfig::new("sk_test_abc", "pk_test_xyz", "whsec_123")
+                .with_api_base_url(server.uri()),
+        );
+        let calculator = TableTaxCalculator::new(TaxMode::Inclusive)
+            .with_rate(TaxRate::new("DE", "19".parse().unwrap(), "MwSt"));
+
+        for _ in 0..2 {
+            let mut order = Order::new(Currency::EUR);
+            order
+                .add_product(&Product::one_time("p", "P", Price::from_cents(1190, Currency::EUR)), 1)
+                .unwrap();
+            let tax = calculator.calculate(&order, Some(&TaxLocation::new("DE"))).unwrap();
+            order.apply_tax(tax).unwrap();
+
+            strategy
+                .create_checkout(&order, "https://example.com/ok", "https://example.com/cancel")
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_checkout() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/checkout/sessions/cs_test_1"))
+            .and(query_param("expand[]", "line_items"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "cs_test_1",
+                "status": "complete",
+                "payment_status": "paid",
+                "currency": "eur",
+                "amount_subtotal": 2380,
+                "amount_total": 2380,
+                "total_details": {"amount_discount": 0, "amount_tax": 380},
+                "customer_details": {"email": "buyer@example.com"},
+                "metadata": {"order_id": "order-1", "site_id": "chargegun"},
+                "created": 1700000000,
+                "line_items": {"data": [{
+                    "description": "Starter Kit",
+                    "quantity": 2,
+                    "amount_subtotal": 2380,
+                    "price": {"unit_amount": 1190}
+                }]}
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/checkout/sessions/cs_no_currency"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "cs_no_currency",
+                "status": "open",
+                "payment_status": "unpaid",
+                "created": 1700000000
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/checkout/sessions/cs_missing"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({
+                "error": {"message": "No such checkout.session", "code": "resource_missing"}
+            })))
+            .mount(&server)
+            .await;
+
+        let strategy = StripeCheckoutStrategy::new(
+            StripeConfig::new("sk_test_abc", "pk_test_xyz", "whsec_123")
+                .with_api_base_url(server.uri()),
+        );
+
+        let receipt = strategy.retrieve_checkout("cs_test_1").await.unwrap();
+        assert_eq!(receipt.order_id.as_deref(), Some("order-1"));
+        assert_eq!(receipt.status, OrderStatus::Paid);
+        assert_eq!(receipt.currency, Currency::EUR);
+        assert_eq!(receipt.line_items[0].unit_price.amount, 1190);
+        assert!(receipt.totals.tax_inclusive);
+        assert_eq!(receipt.customer_email.as_deref(), Some("buyer@example.com"));
+
+        assert!(matches!(
+            strategy.retrieve_checkout("cs_missing").await,
+            Err(PaymentError::SessionNotFound { .. })
+        ));
+        // No guessing at the currency of the amounts
+        assert!(matches!(
+            strategy.retrieve_checkout("cs_no_currency").await,
+            Err(PaymentError::Serialization(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_partial_refund_by_session() {
+        use pay_core::{RefundReason, RefundStatus};
+        use wiremock::matchers::{body_string_contains, header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/checkout/sessions/cs_test_1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "cs_test_1",
+                "payment_status": "paid",
+                "currency": "usd",
+                "payment_intent": "pi_1",
+                "created": 1700000000
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/refunds"))
+            .and(header("Idempotency-Key", "refund-order-1-1"))
+            .and(body_string_contains("payment_intent=pi_1"))
+            .and(body_string_contains("amount=500"))
+            .and(body_string_contains("reason=requested_by_customer"))
+            .and(body_string_contains("metadata%5Bticket%5D=T-42"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "re_1",
+                "amount": 500,
+                "currency": "usd",
+                "status": "succeeded",
+                "reason": "requested_by_customer",
+                "payment_intent": "pi_1",
+                "created": 1700000100,
+                "metadata": {"ticket": "T-42"}
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let strategy = StripeCheckoutStrategy::new(
+            StripeConfig::new("sk_test_abc", "pk_test_xyz", "whsec_123")
+                .with_api_base_url(server.uri()),
+        );
+        let request = RefundRequest::new(RefundTarget::Session("cs_test_1".into()))
+            .with_amount(Price::from_cents(500, Currency::USD))
+            .with_reason(RefundReason::RequestedByCustomer)
+            .with_metadata("ticket", "T-42")
+            .with_idempotency_key("refund-order-1-1");
+
+        let refund = strategy.refund(&request).await.unwrap();
+        assert_eq!(refund.id, "re_1");
+        assert_eq!(refund.amount, Price::from_cents(500, Currency::USD));
+        assert_eq!(refund.status, RefundStatus::Succeeded);
+        assert_eq!(refund.payment_intent_id.as_deref(), Some("pi_1"));
+        assert_eq!(refund.reason, Some(RefundReason::RequestedByCustomer));
+    }
+
+    #[tokio::test]
+    async fn test_refund_order_escapes_search_and_generates_key() {
+        use pay_core::{RefundRequest, RefundTarget};
+        use wiremock::matchers::{header_exists, method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/payment_intents/search"))
+            .and(query_param("query", r"metadata['order_id']:'o\'1\\'"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": [{"id": "pi_9"}]
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/refunds"))
+            .and(header_exists("Idempotency-Key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "re_9",
+                "amount": 100,
+                "currency": "usd",
+                "status": "succeeded",
+                "payment_intent": "pi_9",
+                "created": 1700000100
+            })))
+            .mount(&server)
+            .await;
+
+        let strategy = StripeCheckoutStrategy::new(
+            StripeConfig::new("sk_test_abc", "pk_test_xyz", "whsec_123")
+                .with_api_base_url(server.uri()),
+        );
+        let request = RefundRequest::new(RefundTarget::Order(r"o'1\".into()))
+            .with_amount(Price::from_cents(100, Currency::USD));
+        strategy.refund(&request).await.unwrap();
+        strategy.refund(&request).await.unwrap();
+
+        // Two refunds of the same amount are two refunds, not one retried
+        let requests = server.received_requests().await.unwrap();
+        let keys: Vec<_> = requests
+            .iter()
+            .filter_map(|r| r.headers.get("Idempotency-Key"))
+            .collect();
+        assert_eq!(keys.len(), 2);
+        assert_ne!(keys[0], keys[1]);
+    }
+
     #[test]
     fn test_parse_signature_header() {
         let header = "t=1234567890,v1=abc123,v1=def456";
@@ -533,6 +1631,65 @@ mod tests {
         assert_eq!(parsed.signatures[0], "abc123");
     }
 
+    /// Signed `checkout.session.completed` fixture
+    fn signed_fixture(secret: &str, timestamp: i64) -> (Vec<u8>, String) {
+        let payload = serde_json::json!({
+            "id": "evt_fixture",
+            "type": "checkout.session.completed",
+            "created": timestamp,
+            "data": {"object": {"id": "cs_fixture", "amount_total": 2500}}
+        })
+        .to_string()
+        .into_bytes();
+        let header = generate_test_header(&payload, secret, timestamp);
+        (payload, header)
+    }
+
+    #[test]
+    fn test_verify_signature_with_rotated_secrets() {
+        let secrets = [
+            WebhookSecret::new("whsec_old"),
+            WebhookSecret::new("whsec_connect").with_label("connect"),
+        ];
+        let now = 1_700_000_000;
+
+        let (payload, header) = signed_fixture("whsec_old", now);
+        let matched = verify_signature(&secrets, 300, &payload, &header, now).unwrap();
+        assert_eq!((matched.index, matched.name()), (0, "#0".to_string()));
+
+        let (payload, header) = signed_fixture("whsec_connect", now - 60);
+        let matched = verify_signature(&secrets, 300, &payload, &header, now).unwrap();
+        assert_eq!(matched.label.as_deref(), Some("connect"));
+
+        // Rolling header: Stripe sends one v1 per active secret
+        let (_, new_header) = signed_fixture("whsec_new", now - 60);
+        let rolling = format!("{},{}", new_header, header.split(',').nth(1).unwrap());
+        assert_eq!(verify_signature(&secrets, 300, &payload, &rolling, now).unwrap().index, 1);
+
+        let (payload, header) = signed_fixture("whsec_unknown", now);
+        assert!(verify_signature(&secrets, 300, &payload, &header, now).is_err());
+
+        // Tolerance is configurable
+        let (payload, header) = signed_fixture("whsec_old", now - 120);
+        assert!(verify_signature(&secrets, 60, &payload, &header, now).is_err());
+        assert!(verify_signature(&secrets, 600, &payload, &header, now).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_verify_webhook_fixture() {
+        let strategy = StripeCheckoutStrategy::new(
+            StripeConfig::new("sk_test_abc", "pk_test_xyz", "whsec_account")
+                .with_webhook_secret(WebhookSecret::new("whsec_connect").with_label("connect")),
+        );
+        let (payload, header) = signed_fixture("whsec_connect", Utc::now().timestamp());
+
+        assert_eq!(strategy.verify_signature(&payload, &header).unwrap().index, 1);
+        let event = strategy.verify_webhook(&payload, &header).await.unwrap();
+        assert_eq!(event.event_id, "evt_fixture");
+        assert_eq!(event.session_id.as_deref(), Some("cs_fixture"));
+        assert_eq!(event.event_type, WebhookEventType::CheckoutCompleted);
+    }
+
     #[test]
     fn test_hmac_sha256() {
         let secret = "whsec_test";
@@ -6,6 +6,61 @@
 use pay_core::PaymentError;
 use std::env;
 
+/// Default webhook timestamp tolerance (seconds)
+pub const DEFAULT_WEBHOOK_TOLERANCE_SECS: i64 = 300;
+
+/// Stripe API version sent with every request.
+///
+/// Pinned to the last version with legacy usage records: metered usage is
+/// reported to `/v1/subscription_items/{id}/usage_records`, which 2025-03-31.basil
+/// removed in favour of Billing Meters. Moving past acacia means reporting
+/// usage as meter events first.
+pub const STRIPE_API_VERSION: &str = "2024-12-18.acacia";
+
+/// A webhook signing secret, optionally labelled (e.g. "account", "connect")
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookSecret {
+    /// Label reported when this secret verifies a webhook
+    pub label: Option<String>,
+
+    /// Signing secret (whsec_...)
+    pub secret: String,
+}
+
+impl WebhookSecret {
+    /// Create an unlabelled secret
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self {
+            label: None,
+            secret: secret.into(),
+        }
+    }
+
+    /// Builder: set the label
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Parse `whsec_...` or `label:whsec_...`
+    pub fn parse(value: &str) -> Result<Self, PaymentError> {
+        let value = value.trim();
+        let (label, secret) = match value.split_once(':') {
+            Some((label, secret)) => (Some(label.trim()), secret.trim()),
+            None => (None, value),
+        };
+        if !secret.starts_with("whsec_") {
+            return Err(PaymentError::Configuration(
+                "STRIPE_WEBHOOK_SECRET entries must start with whsec_".to_string(),
+            ));
+        }
+        Ok(Self {
+            label: label.filter(|l| !l.is_empty()).map(String::from),
+            secret: secret.to_string(),
+        })
+    }
+}
+
 /// Stripe API configuration
 #[derive(Debug, Clone)]
 pub struct StripeConfig {
@@ -15,13 +70,17 @@ pub struct StripeConfig {
     /// Publishable key (pk_test_... or pk_live_...)
     pub publishable_key: String,
 
-    /// Webhook signing secret (whsec_...)
-    pub webhook_secret: String,
+    /// Webhook signing secrets, tried in order (several during rotation or
+    /// with separate account and Connect endpoints)
+    pub webhook_secrets: Vec<WebhookSecret>,
+
+    /// Maximum age of a webhook signature timestamp (seconds)
+    pub webhook_tolerance_secs: i64,
 
     /// API base URL (for testing/mocking)
     pub api_base_url: String,
 
-    /// API version
+    /// API version (see [`STRIPE_API_VERSION`])
     pub api_version: String,
 }
 
@@ -31,7 +90,10 @@ impl StripeConfig {
     /// Required env vars:
     /// - `STRIPE_SECRET_KEY`
     /// - `STRIPE_PUBLISHABLE_KEY`
-    /// - `STRIPE_WEBHOOK_SECRET`
+    /// - `STRIPE_WEBHOOK_SECRET` (comma-separated, each `whsec_...` or `label:whsec_...`)
+    ///
+    /// Optional:
+    /// - `STRIPE_WEBHOOK_TOLERANCE_SECS` (default 300)
     pub fn from_env() -> Result<Self, PaymentError> {
         dotenvy::dotenv().ok(); // Load .env file if present
 
@@ -60,18 +122,33 @@ impl StripeConfig {
             ));
         }
 
-        if !webhook_secret.starts_with("whsec_") {
+        let webhook_secrets = webhook_secret
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(WebhookSecret::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if webhook_secrets.is_empty() {
             return Err(PaymentError::Configuration(
-                "STRIPE_WEBHOOK_SECRET must start with whsec_".to_string(),
+                "STRIPE_WEBHOOK_SECRET is empty".to_string(),
             ));
         }
 
+        let webhook_tolerance_secs = match env::var("STRIPE_WEBHOOK_TOLERANCE_SECS") {
+            Ok(secs) => secs.parse().map_err(|_| {
+                PaymentError::Configuration(
+                    "STRIPE_WEBHOOK_TOLERANCE_SECS must be a number of seconds".to_string(),
+                )
+            })?,
+            Err(_) => DEFAULT_WEBHOOK_TOLERANCE_SECS,
+        };
+
         Ok(Self {
             secret_key,
             publishable_key,
-            webhook_secret,
+            webhook_secrets,
+            webhook_tolerance_secs,
             api_base_url: "https://api.stripe.com".to_string(),
-            api_version: "2024-12-18.acacia".to_string(),
+            api_version: STRIPE_API_VERSION.to_string(),
         })
     }
 
@@ -84,9 +161,10 @@ impl StripeConfig {
         Self {
             secret_key: secret_key.into(),
             publishable_key: publishable_key.into(),
-            webhook_secret: webhook_secret.into(),
+            webhook_secrets: vec![WebhookSecret::new(webhook_secret)],
+            webhook_tolerance_secs: DEFAULT_WEBHOOK_TOLERANCE_SECS,
             api_base_url: "https://api.stripe.com".to_string(),
-            api_version: "2024-12-18.acacia".to_string(),
+            api_version: STRIPE_API_VERSION.to_string(),
         }
     }
 
@@ -105,6 +183,18 @@ impl StripeConfig {
         format!("Bearer {}", self.secret_key)
     }
 
+    /// Builder: accept another webhook signing secret
+    pub fn with_webhook_secret(mut self, secret: WebhookSecret) -> Self {
+        self.webhook_secrets.push(secret);
+        self
+    }
+
+    /// Builder: set the webhook timestamp tolerance (seconds)
+    pub fn with_webhook_tolerance(mut self, secs: i64) -> Self {
+        self.webhook_tolerance_secs = secs;
+        self
+    }
+
     /// Builder: set custom API base URL (for testing)
     pub fn with_api_base_url(mut self, url: impl Into<String>) -> Self {
         self.api_base_url = url.into();
@@ -154,6 +244,19 @@ mod tests {
         assert_eq!(config.auth_header(), "Bearer sk_test_abc123");
     }
 
+    #[test]
+    fn test_parse_webhook_secret() {
+        assert_eq!(
+            WebhookSecret::parse("whsec_a").unwrap(),
+            WebhookSecret::new("whsec_a")
+        );
+        assert_eq!(
+            WebhookSecret::parse(" connect:whsec_b ").unwrap(),
+            WebhookSecret::new("whsec_b").with_label("connect")
+        );
+        assert!(WebhookSecret::parse("account:sk_test_a").is_err());
+    }
+
     #[test]
     fn test_from_env_missing_key() {
         // Clear any existing env vars
@@ -0,0 +1,117 @@
+//! # Stripe Customers
+//!
+//! Finds or creates the Stripe customer for a `Customer` record, so
+//! checkouts pass `customer=cus_...` instead of creating a guest customer
+//! from `customer_email` every time.
+
+use crate::checkout::{StripeCheckoutStrategy, StripeIdResponse};
+use crate::events::StripeList;
+use pay_core::{Customer, PaymentError, PaymentResult};
+use tracing::{debug, info};
+
+impl StripeCheckoutStrategy {
+    /// Find the newest Stripe customer with an email address
+    pub(crate) async fn search_customer(&self, email: &str) -> PaymentResult<Option<String>> {
+        let body = self
+            .get("/v1/customers", &[("email", email), ("limit", "1")])
+            .await?
+            .unwrap_or_default();
+        let customers: StripeList<StripeIdResponse> = serde_json::from_str(&body).map_err(|e| {
+            PaymentError::Serialization(format!("Failed to parse Stripe customers: {}", e))
+        })?;
+        Ok(customers.data.into_iter().next().map(|c| c.id))
+    }
+
+    /// Get the Stripe customer ID for a record: stored, found by email, or created
+    pub(crate) async fn stripe_customer_id(&self, customer: &Customer) -> PaymentResult<String> {
+        if let Some(id) = customer.provider_id("stripe") {
+            return Ok(id.to_string());
+        }
+        if let Some(id) = self.search_customer(&customer.email).await? {
+            debug!("Reusing Stripe customer {} for {}", id, customer.id);
+            return Ok(id);
+        }
+
+        let mut form_params = vec![
+            ("email".to_string(), customer.email.clone()),
+            ("metadata[customer_id]".to_string(), customer.id.clone()),
+        ];
+        if let Some(ref name) = customer.name {
+            form_params.push(("name".to_string(), name.clone()));
+        }
+        let idempotency_key = format!("customer-{}", customer.id);
+        let body = self
+            .post_form("/v1/customers", &idempotency_key, &form_params)
+            .await?;
+        let created: StripeIdResponse = serde_json::from_str(&body).map_err(|e| {
+            PaymentError::Serialization(format!("Failed to parse Stripe customer: {}", e))
+        })?;
+
+        info!("Created Stripe customer {} for {}", created.id, customer.id);
+        Ok(created.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StripeConfig;
+    use pay_core::{Currency, Order, PaymentStrategy, Price, Product};
+    use serde_json::json;
+    use wiremock::matchers::{body_string_contains, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_checkout_reuses_customer() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/customers"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [], "has_more": false
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/customers"))
+            .and(body_string_contains("email=buyer%40example.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "cus_1"})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/checkout/sessions"))
+            .and(body_string_contains("customer=cus_1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "cs_test_1",
+                "url": "https://checkout.stripe.com/c/pay/cs_test_1",
+                "customer": "cus_1"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let strategy = StripeCheckoutStrategy::new(
+            StripeConfig::new("sk_test_abc", "pk_test_xyz", "whsec_123")
+                .with_api_base_url(server.uri()),
+        );
+        let customer = Customer::new("buyer@example.com");
+        let customer_id = strategy.ensure_customer(&customer).await.unwrap();
+        assert_eq!(customer_id.as_deref(), Some("cus_1"));
+
+        // A stored ID is used without asking Stripe again
+        let customer = customer.with_provider_id("stripe", "cus_1");
+        assert_eq!(strategy.ensure_customer(&customer).await.unwrap(), customer_id);
+
+        let mut order = Order::new(Currency::USD);
+        order
+            .add_product(&Product::one_time("p", "P", Price::from_cents(500, Currency::USD)), 1)
+            .unwrap();
+        order.customer_email = Some(customer.email.clone());
+        order.customer_id = customer_id;
+        let session = strategy
+            .create_checkout(&order, "https://x/success", "https://x/cancel")
+            .await
+            .unwrap();
+        assert_eq!(session.customer_id.as_deref(), Some("cus_1"));
+    }
+}
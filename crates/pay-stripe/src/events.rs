@@ -0,0 +1,719 @@
+//! # Typed Stripe Event Payloads
+//!
+//! Structs for the `data.object` of the events in `REQUIRED_WEBHOOK_EVENTS`
+//! (except `checkout.session.completed`, see `CheckoutCompletedData`).
+//! Each is parsed from `WebhookEvent::raw_data` with `from_event`; fields
+//! keep Stripe's names unless they are IDs of other objects (`customer` →
+//! `customer_id`). Unknown fields are ignored.
+//!
+//! Integer amounts are in Stripe's minor units (see `crate::amount`); the
+//! `to_*` conversions return `Price`s in ISO 4217 minor units.
+
+use crate::amount::from_stripe_amount;
+use chrono::{DateTime, Utc};
+use pay_core::{
+    BillingInterval, Currency, PauseBehavior, PauseCollection, PaymentError, PaymentResult,
+    Refund, RefundReason, RefundStatus, Subscription, SubscriptionLine, SubscriptionStatus,
+    UsageType, WebhookEvent,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// A Stripe list object (`{"object": "list", "data": [...]}`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct StripeList<T> {
+    /// Items on this page
+    pub data: Vec<T>,
+
+    /// Whether more items exist beyond this page
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+impl<T> Default for StripeList<T> {
+    fn default() -> Self {
+        Self {
+            data: Vec::new(),
+            has_more: false,
+        }
+    }
+}
+
+/// Parse the event's Stripe object into a typed payload
+fn parse_object<T: DeserializeOwned>(event: &WebhookEvent, kind: &str) -> PaymentResult<T> {
+    let raw = event
+        .raw_data
+        .clone()
+        .ok_or_else(|| PaymentError::WebhookParseError("Missing raw data".to_string()))?;
+    serde_json::from_value(raw)
+        .map_err(|e| PaymentError::WebhookParseError(format!("Invalid {} object: {}", kind, e)))
+}
+
+/// Customer details collected by Checkout
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CustomerDetails {
+    /// Email address
+    #[serde(default)]
+    pub email: Option<String>,
+
+    /// Full name
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Parsed checkout.session.expired event data
+#[derive(Debug, Clone, Deserialize)]
+pub struct CheckoutExpiredData {
+    /// Checkout session ID (cs_...)
+    #[serde(rename = "id")]
+    pub session_id: String,
+
+    /// Customer ID, if one was created or passed in
+    #[serde(default, rename = "customer")]
+    pub customer_id: Option<String>,
+
+    /// Details the customer entered before abandoning the session
+    #[serde(default)]
+    pub customer_details: Option<CustomerDetails>,
+
+    /// Total the session would have charged (smallest unit)
+    #[serde(default)]
+    pub amount_total: Option<i64>,
+
+    /// Currency
+    #[serde(default)]
+    pub currency: Option<Currency>,
+
+    /// When the session expired (Unix time)
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+
+    /// Session metadata
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+impl CheckoutExpiredData {
+    /// Parse from a webhook event
+    pub fn from_event(event: &WebhookEvent) -> PaymentResult<Self> {
+        parse_object(event, "checkout session")
+    }
+
+    /// Get the customer's email, if entered
+    pub fn customer_email(&self) -> Option<&str> {
+        self.customer_details.as_ref()?.email.as_deref()
+    }
+
+    /// Get the internal order ID from metadata
+    pub fn order_id(&self) -> Option<&str> {
+        self.metadata.get("order_id").map(|s| s.as_str())
+    }
+}
+
+/// Recurring component of a price
+#[derive(Debug, Clone, Deserialize)]
+pub struct Recurring {
+    /// Billing interval ("day", "week", "month", "year")
+    pub interval: String,
+
+    /// Number of intervals between bills
+    #[serde(default = "default_interval_count")]
+    pub interval_count: u32,
+
+    /// "licensed" or "metered"
+    #[serde(default)]
+    pub usage_type: UsageType,
+}
+
+fn default_interval_count() -> u32 {
+    1
+}
+
+impl Recurring {
+    /// Get the matching billing interval (`OneTime` if there is none)
+    pub fn billing_interval(&self) -> BillingInterval {
+        match self.interval.parse() {
+            Ok(unit) => BillingInterval::Every {
+                unit,
+                count: self.interval_count,
+            },
+            Err(_) => BillingInterval::OneTime,
+        }
+    }
+}
+
+/// Price of a subscription item
+#[derive(Debug, Clone, Deserialize)]
+pub struct ItemPrice {
+    /// Price ID (price_...)
+    pub id: String,
+
+    /// Product ID (prod_...)
+    #[serde(default)]
+    pub product: Option<String>,
+
+    /// Unit amount (smallest unit)
+    #[serde(default)]
+    pub unit_amount: Option<i64>,
+
+    /// Currency
+    #[serde(default)]
+    pub currency: Option<Currency>,
+
+    /// Billing interval (None for one-time prices)
+    #[serde(default)]
+    pub recurring: Option<Recurring>,
+}
+
+/// An item of a subscription
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubscriptionItem {
+    /// Subscription item ID (si_...)
+    pub id: String,
+
+    /// The item's price
+    pub price: ItemPrice,
+
+    /// Quantity
+    #[serde(default)]
+    pub quantity: Option<u32>,
+}
+
+/// Parsed customer.subscription.* event data
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubscriptionData {
+    /// Subscription ID (sub_...)
+    #[serde(rename = "id")]
+    pub subscription_id: String,
+
+    /// Customer ID (cus_...)
+    #[serde(rename = "customer")]
+    pub customer_id: String,
+
+    /// Status ("trialing", "active", "past_due", "canceled", ...)
+    pub status: String,
+
+    /// Subscribed items
+    #[serde(default)]
+    pub items: StripeList<SubscriptionItem>,
+
+    /// Start of the current period (Unix time)
+    #[serde(default)]
+    pub current_period_start: Option<i64>,
+
+    /// End of the current period (Unix time)
+    #[serde(default)]
+    pub current_period_end: Option<i64>,
+
+    /// Whether the subscription ends at the end of the current period
+    #[serde(default)]
+    pub cancel_at_period_end: bool,
+
+    /// When the subscription was cancelled (Unix time)
+    #[serde(default)]
+    pub canceled_at: Option<i64>,
+
+    /// End of the trial (Unix time)
+    #[serde(default)]
+    pub trial_end: Option<i64>,
+
+    /// Paused payment collection
+    #[serde(default)]
+    pub pause_collection: Option<PauseCollectionData>,
+
+    /// Subscription metadata
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+impl SubscriptionData {
+    /// Parse from a webhook event
+    pub fn from_event(event: &WebhookEvent) -> PaymentResult<Self> {
+        parse_object(event, "subscription")
+    }
+
+    /// Check if the subscription currently grants access
+    pub fn is_active(&self) -> bool {
+        matches!(self.status.as_str(), "active" | "trialing")
+    }
+
+    /// Get the price IDs of the subscribed items
+    pub fn price_ids(&self) -> Vec<&str> {
+        self.items.data.iter().map(|i| i.price.id.as_str()).collect()
+    }
+
+    /// Convert to a provider-neutral `Subscription`
+    pub fn to_subscription(&self) -> PaymentResult<Subscription> {
+        let status: SubscriptionStatus =
+            serde_json::from_value(serde_json::Value::String(self.status.clone())).map_err(|e| {
+                PaymentError::Serialization(format!("Unknown subscription status: {}", e))
+            })?;
+        let lines = self
+            .items
+            .data
+            .iter()
+            .map(|item| -> PaymentResult<SubscriptionLine> {
+                Ok(SubscriptionLine {
+                    item_id: item.id.clone(),
+                    price_id: item.price.id.clone(),
+                    product_id: item.price.product.clone(),
+                    unit_price: item
+                        .price
+                        .unit_amount
+                        .zip(item.price.currency)
+                        .map(|(amount, currency)| from_stripe_amount(amount, currency))
+                        .transpose()?,
+                    interval: item
+                        .price
+                        .recurring
+                        .as_ref()
+                        .map_or(BillingInterval::OneTime, Recurring::billing_interval),
+                    quantity: item.quantity.unwrap_or(1),
+                    usage_type: item
+                        .price
+                        .recurring
+                        .as_ref()
+                        .map_or(UsageType::Licensed, |r| r.usage_type),
+                })
+            })
+            .collect::<PaymentResult<_>>()?;
+        let pause_collection = self.pause_collection.as_ref().map(|pause| PauseCollection {
+            behavior: match pause.behavior.as_str() {
+                "keep_as_draft" => PauseBehavior::KeepAsDraft,
+                "mark_uncollectible" => PauseBehavior::MarkUncollectible,
+                _ => PauseBehavior::Void,
+            },
+            resumes_at: pause.resumes_at.and_then(|t| DateTime::from_timestamp(t, 0)),
+        });
+
+        Ok(Subscription {
+            id: self.subscription_id.clone(),
+            provider: "stripe".to_string(),
+            customer_id: self.customer_id.clone(),
+            status,
+            lines,
+            current_period_end: self.current_period_end.and_then(|t| DateTime::from_timestamp(t, 0)),
+            cancel_at_period_end: self.cancel_at_period_end,
+            canceled_at: self.canceled_at.and_then(|t| DateTime::from_timestamp(t, 0)),
+            pause_collection,
+            metadata: self.metadata.clone(),
+        })
+    }
+}
+
+/// Paused payment collection of a subscription
+#[derive(Debug, Clone, Deserialize)]
+pub struct PauseCollectionData {
+    /// "keep_as_draft", "mark_uncollectible" or "void"
+    pub behavior: String,
+
+    /// When collection resumes (Unix time)
+    #[serde(default)]
+    pub resumes_at: Option<i64>,
+}
+
+/// Parsed invoice.* event data
+#[derive(Debug, Clone, Deserialize)]
+pub struct InvoiceData {
+    /// Invoice ID (in_...)
+    #[serde(rename = "id")]
+    pub invoice_id: String,
+
+    /// Customer ID (cus_...)
+    #[serde(default, rename = "customer")]
+    pub customer_id: Option<String>,
+
+    /// Customer email at the time of invoicing
+    #[serde(default)]
+    pub customer_email: Option<String>,
+
+    /// Subscription ID, for subscription invoices
+    #[serde(default, rename = "subscription")]
+    pub subscription_id: Option<String>,
+
+    /// Payment intent ID
+    #[serde(default, rename = "payment_intent")]
+    pub payment_intent_id: Option<String>,
+
+    /// Status ("draft", "open", "paid", "uncollectible", "void")
+    #[serde(default)]
+    pub status: Option<String>,
+
+    /// Why the invoice was created ("subscription_create", "subscription_cycle", ...)
+    #[serde(default)]
+    pub billing_reason: Option<String>,
+
+    /// Amount due (smallest unit)
+    pub amount_due: i64,
+
+    /// Amount paid so far (smallest unit)
+    #[serde(default)]
+    pub amount_paid: i64,
+
+    /// Currency
+    pub currency: Currency,
+
+    /// Payment attempts so far
+    #[serde(default)]
+    pub attempt_count: u32,
+
+    /// When Stripe retries the payment (Unix time, None = no more retries)
+    #[serde(default)]
+    pub next_payment_attempt: Option<i64>,
+
+    /// Hosted page where the customer can pay the invoice
+    #[serde(default)]
+    pub hosted_invoice_url: Option<String>,
+
+    /// Invoice metadata
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+impl InvoiceData {
+    /// Parse from a webhook event
+    pub fn from_event(event: &WebhookEvent) -> PaymentResult<Self> {
+        parse_object(event, "invoice")
+    }
+
+    /// Check if the invoice is paid
+    pub fn is_paid(&self) -> bool {
+        self.status.as_deref() == Some("paid")
+    }
+
+    /// Check if this is the first invoice of a subscription
+    pub fn is_first_invoice(&self) -> bool {
+        self.billing_reason.as_deref() == Some("subscription_create")
+    }
+}
+
+/// Parsed refund data
+#[derive(Debug, Clone, Deserialize)]
+pub struct RefundData {
+    /// Refund ID (re_...)
+    #[serde(rename = "id")]
+    pub refund_id: String,
+
+    /// Refunded amount (smallest unit)
+    pub amount: i64,
+
+    /// Currency
+    pub currency: Currency,
+
+    /// Status ("pending", "succeeded", "failed", "canceled", ...)
+    #[serde(default)]
+    pub status: Option<String>,
+
+    /// Reason ("duplicate", "fraudulent", "requested_by_customer")
+    #[serde(default)]
+    pub reason: Option<String>,
+
+    /// Refunded charge ID (ch_...)
+    #[serde(default, rename = "charge")]
+    pub charge_id: Option<String>,
+
+    /// Refunded payment intent ID (pi_...)
+    #[serde(default, rename = "payment_intent")]
+    pub payment_intent_id: Option<String>,
+
+    /// When the refund was created (Unix time)
+    #[serde(default)]
+    pub created: i64,
+
+    /// Refund metadata
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+impl RefundData {
+    /// Convert to a provider-neutral `Refund`
+    pub fn to_refund(&self) -> PaymentResult<Refund> {
+        let status = match self.status.as_deref() {
+            Some("succeeded") => RefundStatus::Succeeded,
+            Some("failed") => RefundStatus::Failed,
+            Some("canceled") => RefundStatus::Canceled,
+            Some("requires_action") => RefundStatus::RequiresAction,
+            _ => RefundStatus::Pending,
+        };
+        let reason = match self.reason.as_deref() {
+            Some("duplicate") => Some(RefundReason::Duplicate),
+            Some("fraudulent") => Some(RefundReason::Fraudulent),
+            Some("requested_by_customer") => Some(RefundReason::RequestedByCustomer),
+            _ => None,
+        };
+        Ok(Refund {
+            id: self.refund_id.clone(),
+            provider: "stripe".to_string(),
+            payment_intent_id: self.payment_intent_id.clone(),
+            amount: from_stripe_amount(self.amount, self.currency)?,
+            status,
+            reason,
+            metadata: self.metadata.clone(),
+            created_at: DateTime::from_timestamp(self.created, 0).unwrap_or_else(Utc::now),
+        })
+    }
+}
+
+/// Parsed charge.* event data
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChargeData {
+    /// Charge ID (ch_...)
+    #[serde(rename = "id")]
+    pub charge_id: String,
+
+    /// Payment intent ID (pi_...)
+    #[serde(default, rename = "payment_intent")]
+    pub payment_intent_id: Option<String>,
+
+    /// Customer ID (cus_...)
+    #[serde(default, rename = "customer")]
+    pub customer_id: Option<String>,
+
+    /// Charged amount (smallest unit)
+    pub amount: i64,
+
+    /// Total refunded so far (smallest unit)
+    #[serde(default)]
+    pub amount_refunded: i64,
+
+    /// Currency
+    pub currency: Currency,
+
+    /// Whether the charge is fully refunded
+    #[serde(default)]
+    pub refunded: bool,
+
+    /// Refunds of this charge (only included when Stripe expands them)
+    #[serde(default)]
+    pub refunds: Option<StripeList<RefundData>>,
+
+    /// Charge metadata
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+impl ChargeData {
+    /// Parse from a webhook event
+    pub fn from_event(event: &WebhookEvent) -> PaymentResult<Self> {
+        parse_object(event, "charge")
+    }
+
+    /// Check if only part of the charge was refunded
+    pub fn is_partially_refunded(&self) -> bool {
+        !self.refunded && self.amount_refunded > 0
+    }
+
+    /// Get the refunds included in the event
+    pub fn refunds(&self) -> &[RefundData] {
+        self.refunds.as_ref().map_or(&[], |list| list.data.as_slice())
+    }
+}
+
+/// Error of the last failed payment attempt
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LastPaymentError {
+    /// Error code (e.g. "card_declined")
+    #[serde(default)]
+    pub code: Option<String>,
+
+    /// Decline code from the card issuer
+    #[serde(default)]
+    pub decline_code: Option<String>,
+
+    /// Message that can be shown to the customer
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Parsed payment_intent.* event data
+#[derive(Debug, Clone, Deserialize)]
+pub struct PaymentIntentData {
+    /// Payment intent ID (pi_...)
+    #[serde(rename = "id")]
+    pub payment_intent_id: String,
+
+    /// Amount to collect (smallest unit)
+    pub amount: i64,
+
+    /// Amount collected (smallest unit)
+    #[serde(default)]
+    pub amount_received: i64,
+
+    /// Currency
+    pub currency: Currency,
+
+    /// Status ("succeeded", "requires_payment_method", "processing", ...)
+    pub status: String,
+
+    /// Customer ID (cus_...)
+    #[serde(default, rename = "customer")]
+    pub customer_id: Option<String>,
+
+    /// Email the receipt is sent to
+    #[serde(default)]
+    pub receipt_email: Option<String>,
+
+    /// Why the last attempt failed
+    #[serde(default)]
+    pub last_payment_error: Option<LastPaymentError>,
+
+    /// Payment intent metadata
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+impl PaymentIntentData {
+    /// Parse from a webhook event
+    pub fn from_event(event: &WebhookEvent) -> PaymentResult<Self> {
+        parse_object(event, "payment intent")
+    }
+
+    /// Check if the payment succeeded
+    pub fn is_succeeded(&self) -> bool {
+        self.status == "succeeded"
+    }
+
+    /// Get the failure message of the last attempt
+    pub fn failure_message(&self) -> Option<&str> {
+        self.last_payment_error.as_ref()?.message.as_deref()
+    }
+
+    /// Get the internal order ID from metadata
+    pub fn order_id(&self) -> Option<&str> {
+        self.metadata.get("order_id").map(|s| s.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use pay_core::WebhookEventType;
+    use serde_json::json;
+
+    fn event(event_type: WebhookEventType, object: serde_json::Value) -> WebhookEvent {
+        WebhookEvent {
+            event_id: "evt_test".to_string(),
+            event_type,
+            provider: "stripe".to_string(),
+            session_id: None,
+            payment_intent_id: None,
+            customer_email: None,
+            amount_paid: None,
+            currency: None,
+            raw_data: Some(object),
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_parse_subscription_and_invoice() {
+        let subscription = SubscriptionData::from_event(&event(
+            WebhookEventType::SubscriptionUpdated,
+            json!({
+                "id": "sub_1",
+                "object": "subscription",
+                "customer": "cus_1",
+                "status": "past_due",
+                "cancel_at_period_end": true,
+                "current_period_end": 1_700_000_000,
+                "items": {"object": "list", "data": [{
+                    "id": "si_1",
+                    "quantity": 2,
+                    "price": {"id": "price_1", "unit_amount": 900,
+                              "recurring": {"interval": "month", "interval_count": 3}}
+                }]},
+                "metadata": {"site_id": "chargegun"}
+            }),
+        ))
+        .unwrap();
+        assert!(!subscription.is_active());
+        assert!(subscription.cancel_at_period_end);
+        assert_eq!(subscription.price_ids(), vec!["price_1"]);
+        let recurring = subscription.items.data[0].price.recurring.as_ref().unwrap();
+        assert_eq!((recurring.interval.as_str(), recurring.interval_count), ("month", 3));
+
+        let invoice = InvoiceData::from_event(&event(
+            WebhookEventType::InvoicePaymentFailed,
+            json!({
+                "id": "in_1",
+                "customer": "cus_1",
+                "subscription": "sub_1",
+                "status": "open",
+                "billing_reason": "subscription_cycle",
+                "amount_due": 2700,
+                "amount_paid": 0,
+                "currency": "eur",
+                "attempt_count": 2,
+                "next_payment_attempt": 1_700_086_400
+            }),
+        ))
+        .unwrap();
+        assert_eq!(invoice.currency, Currency::EUR);
+        assert_eq!(invoice.subscription_id.as_deref(), Some("sub_1"));
+        assert!(!invoice.is_paid() && !invoice.is_first_invoice());
+        assert_eq!(invoice.attempt_count, 2);
+    }
+
+    #[test]
+    fn test_parse_charge_payment_intent_and_expired_session() {
+        let charge = ChargeData::from_event(&event(
+            WebhookEventType::RefundIssued,
+            json!({
+                "id": "ch_1",
+                "payment_intent": "pi_1",
+                "amount": 5000,
+                "amount_refunded": 1500,
+                "currency": "usd",
+                "refunded": false,
+                "refunds": {"data": [{
+                    "id": "re_1", "amount": 1500, "currency": "usd",
+                    "status": "succeeded", "reason": "requested_by_customer", "charge": "ch_1"
+                }]}
+            }),
+        ))
+        .unwrap();
+        assert!(charge.is_partially_refunded());
+        assert_eq!(charge.refunds()[0].refund_id, "re_1");
+        assert_eq!(charge.refunds()[0].charge_id.as_deref(), Some("ch_1"));
+
+        let intent = PaymentIntentData::from_event(&event(
+            WebhookEventType::PaymentFailed,
+            json!({
+                "id": "pi_1",
+                "amount": 5000,
+                "currency": "usd",
+                "status": "requires_payment_method",
+                "last_payment_error": {"code": "card_declined", "message": "Your card was declined."},
+                "metadata": {"order_id": "ord_1"}
+            }),
+        ))
+        .unwrap();
+        assert!(!intent.is_succeeded());
+        assert_eq!(intent.failure_message(), Some("Your card was declined."));
+        assert_eq!(intent.order_id(), Some("ord_1"));
+
+        let expired = CheckoutExpiredData::from_event(&event(
+            WebhookEventType::CheckoutExpired,
+            json!({
+                "id": "cs_1",
+                "status": "expired",
+                "customer_details": {"email": "a@b.co"},
+                "amount_total": 5000,
+                "currency": "usd",
+                "metadata": {"order_id": "ord_1"}
+            }),
+        ))
+        .unwrap();
+        assert_eq!(expired.customer_email(), Some("a@b.co"));
+        assert_eq!(expired.order_id(), Some("ord_1"));
+
+        // Wrong object shape is a parse error, not a panic
+        let wrong = event(WebhookEventType::PaymentFailed, json!({"id": "cs_1"}));
+        assert!(matches!(
+            PaymentIntentData::from_event(&wrong),
+            Err(PaymentError::WebhookParseError(_))
+        ));
+    }
+}
@@ -6,8 +6,12 @@
 //!
 //! 1. **StripeCheckoutStrategy** - Full Checkout Sessions API
 //!    - Dynamic line items
-//!    - Customer email prefill
+//!    - Customer reuse (or email prefill)
 //!    - Metadata support
+//!    - Tax via Stripe Tax or site tax tables
+//!    - Refunds, subscription management and the customer portal
+//!    - Seat-based, tiered and metered prices with usage reporting
+//!    - Amounts converted to Stripe's minor units (ISK, UGX, MGA, ...)
 //!    - Best for: e-commerce, dynamic pricing
 //!
 //! 2. **StripeLinksStrategy** - Payment Links API
@@ -41,8 +45,9 @@
 //!
 //! struct MyHandler;
 //!
+//! #[async_trait]
 //! impl WebhookHandler for MyHandler {
-//!     fn on_checkout_completed(&self, data: CheckoutCompletedData) -> PaymentResult<()> {
+//!     async fn on_checkout_completed(&self, data: CheckoutCompletedData) -> PaymentResult<()> {
 //!         // Fulfill the order
 //!         println!("Order {} paid!", data.order_id().unwrap_or("unknown"));
 //!         Ok(())
@@ -51,18 +56,30 @@
 //!
 //! // In your webhook endpoint:
 //! let event = strategy.verify_webhook(payload, signature).await?;
-//! dispatch_webhook_event(&MyHandler, event)?;
+//! dispatch_webhook_event(&MyHandler, event).await?;
 //! ```
 
+pub mod amount;
 pub mod checkout;
 pub mod config;
+pub mod customers;
+pub mod events;
 pub mod links;
+pub mod portal;
+pub mod subscriptions;
+pub mod tax;
+pub mod usage;
 pub mod webhook;
 
 // Re-exports
-pub use checkout::StripeCheckoutStrategy;
-pub use config::StripeConfig;
+pub use amount::{from_stripe_amount, stripe_decimal_places, to_stripe_amount};
+pub use checkout::{generate_test_header, SecretMatch, StripeCheckoutStrategy};
+pub use config::{StripeConfig, WebhookSecret, STRIPE_API_VERSION};
+pub use events::{
+    ChargeData, CheckoutExpiredData, InvoiceData, PaymentIntentData, RefundData, SubscriptionData,
+};
 pub use links::{PaymentLinkResponse, StripeLinksStrategy};
+pub use tax::StripeTaxCalculator;
 pub use webhook::{
     dispatch_webhook_event, CheckoutCompletedData, LoggingWebhookHandler, WebhookHandler,
     REQUIRED_WEBHOOK_EVENTS,
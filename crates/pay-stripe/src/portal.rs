@@ -0,0 +1,224 @@
+//! # Stripe Customer Portal
+//!
+//! `BillingPortal` for `StripeCheckoutStrategy`, on
+//! `/v1/billing_portal/sessions`.
+//!
+//! Sites that do not name a `configuration_id` get a portal configuration
+//! built from their `PortalFeatures`. It is created on first use and cached
+//! per feature set and return URL.
+
+use crate::checkout::{StripeCheckoutStrategy, StripeIdResponse};
+use async_trait::async_trait;
+use pay_core::{BillingPortal, PaymentError, PaymentResult, PortalConfig, PortalSession};
+use serde::Deserialize;
+use tracing::{debug, info, instrument};
+
+impl StripeCheckoutStrategy {
+    /// Get (or create) the portal configuration for a site's features
+    async fn portal_configuration_id(
+        &self,
+        config: &PortalConfig,
+        return_url: &str,
+    ) -> PaymentResult<String> {
+        if let Some(ref id) = config.configuration_id {
+            return Ok(id.clone());
+        }
+
+        let features = config.features;
+        let key = format!(
+            "{}{}{}{}{}|{}",
+            features.update_payment_method as u8,
+            features.invoice_history as u8,
+            features.update_customer as u8,
+            features.cancel_subscription as u8,
+            features.update_quantity as u8,
+            return_url
+        );
+        if let Some(id) = self
+            .portal_configuration_ids
+            .lock()
+            .ok()
+            .and_then(|ids| ids.get(&key).cloned())
+        {
+            return Ok(id);
+        }
+
+        let enabled = |on: bool| on.to_string();
+        let mut form_params = vec![
+            ("default_return_url".to_string(), return_url.to_string()),
+            (
+                "features[payment_method_update][enabled]".to_string(),
+                enabled(features.update_payment_method),
+            ),
+            (
+                "features[invoice_history][enabled]".to_string(),
+                enabled(features.invoice_history),
+            ),
+            (
+                "features[customer_update][enabled]".to_string(),
+                enabled(features.update_customer),
+            ),
+            (
+                "features[subscription_cancel][enabled]".to_string(),
+                enabled(features.cancel_subscription),
+            ),
+            (
+                "features[subscription_update][enabled]".to_string(),
+                enabled(features.update_quantity),
+            ),
+        ];
+        if features.update_customer {
+            for (i, field) in ["email", "name", "address"].iter().enumerate() {
+                form_params.push((
+                    format!("features[customer_update][allowed_updates][{}]", i),
+                    field.to_string(),
+                ));
+            }
+        }
+        if features.cancel_subscription {
+            form_params.push((
+                "features[subscription_cancel][mode]".to_string(),
+                "at_period_end".to_string(),
+            ));
+        }
+        if features.update_quantity {
+            form_params.extend([
+                (
+                    "features[subscription_update][default_allowed_updates][0]".to_string(),
+                    "quantity".to_string(),
+                ),
+                (
+                    "features[subscription_update][proration_behavior]".to_string(),
+                    "create_prorations".to_string(),
+                ),
+            ]);
+        }
+
+        let idempotency_key = format!("portal-config-{}", key);
+        let body = self
+            .post_form("/v1/billing_portal/configurations", &idempotency_key, &form_params)
+            .await?;
+        let configuration: StripeIdResponse = serde_json::from_str(&body).map_err(|e| {
+            PaymentError::Serialization(format!("Failed to parse Stripe portal configuration: {}", e))
+        })?;
+
+        debug!("Created Stripe portal configuration {}", configuration.id);
+        if let Ok(mut ids) = self.portal_configuration_ids.lock() {
+            ids.insert(key, configuration.id.clone());
+        }
+        Ok(configuration.id)
+    }
+}
+
+#[async_trait]
+impl BillingPortal for StripeCheckoutStrategy {
+    #[instrument(skip(self, email))]
+    async fn find_customer_by_email(&self, email: &str) -> PaymentResult<Option<String>> {
+        self.search_customer(email).await
+    }
+
+    #[instrument(skip(self, config))]
+    async fn create_portal_session(
+        &self,
+        customer_id: &str,
+        config: &PortalConfig,
+        return_url: &str,
+    ) -> PaymentResult<PortalSession> {
+        let configuration = self.portal_configuration_id(config, return_url).await?;
+        let form_params = vec![
+            ("customer".to_string(), customer_id.to_string()),
+            ("return_url".to_string(), return_url.to_string()),
+            ("configuration".to_string(), configuration),
+        ];
+
+        // Sessions are single-use, so every request gets a new one
+        let idempotency_key = format!("portal-{}-{}", customer_id, uuid::Uuid::new_v4());
+        let body = self
+            .post_form("/v1/billing_portal/sessions", &idempotency_key, &form_params)
+            .await?;
+        let session: StripePortalSession = serde_json::from_str(&body).map_err(|e| {
+            PaymentError::Serialization(format!("Failed to parse Stripe portal session: {}", e))
+        })?;
+
+        info!("Created Stripe portal session {} for {}", session.id, customer_id);
+        Ok(PortalSession {
+            id: session.id,
+            customer_id: customer_id.to_string(),
+            url: session.url,
+            return_url: return_url.to_string(),
+        })
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "stripe"
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct StripePortalSession {
+    id: String,
+    url: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StripeConfig;
+    use pay_core::PortalFeatures;
+    use serde_json::json;
+    use wiremock::matchers::{body_string_contains, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_portal_session_creates_configuration_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/customers"))
+            .and(query_param("email", "buyer@example.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{"id": "cus_1"}], "has_more": false
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/billing_portal/configurations"))
+            .and(body_string_contains("features%5Bsubscription_update%5D%5Benabled%5D=true"))
+            .and(body_string_contains("features%5Bsubscription_cancel%5D%5Bmode%5D=at_period_end"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "bpc_1"})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/billing_portal/sessions"))
+            .and(body_string_contains("customer=cus_1"))
+            .and(body_string_contains("configuration=bpc_1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "bps_1",
+                "url": "https://billing.stripe.com/p/session/test_1"
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let strategy = StripeCheckoutStrategy::new(
+            StripeConfig::new("sk_test_abc", "pk_test_xyz", "whsec_123")
+                .with_api_base_url(server.uri()),
+        );
+        let customer = strategy
+            .find_customer_by_email("buyer@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        let config = PortalConfig::new().with_features(PortalFeatures {
+            update_quantity: true,
+            ..PortalFeatures::default()
+        });
+        for _ in 0..2 {
+            let session = strategy
+                .create_portal_session(&customer, &config, "https://chargegun.io/account")
+                .await
+                .unwrap();
+            assert_eq!(session.url, "https://billing.stripe.com/p/session/test_1");
+        }
+    }
+}
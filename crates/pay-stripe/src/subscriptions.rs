@@ -0,0 +1,419 @@
+//! # Stripe Subscriptions
+//!
+//! `SubscriptionStrategy` for `StripeCheckoutStrategy`, on the
+//! `/v1/subscriptions` API.
+//!
+//! Plan changes use inline `price_data`, like checkout, or a usage price for
+//! seat-based, tiered and metered plans. Stripe needs a product for those,
+//! so each catalog product gets a Stripe product with the same ID, created
+//! on first use.
+
+use crate::amount::to_stripe_amount;
+use crate::checkout::{search_literal, StripeCheckoutStrategy};
+use crate::events::{StripeList, SubscriptionData};
+use crate::usage::UsagePrice;
+use async_trait::async_trait;
+use pay_core::{
+    PauseCollection, PaymentError, PaymentResult, PlanChange, Subscription, SubscriptionQuery,
+    SubscriptionStrategy, UsageRecord, UsageReport,
+};
+use tracing::{debug, info, instrument};
+
+impl StripeCheckoutStrategy {
+    /// POST to a subscription (fresh idempotency key: repeated changes are intended)
+    async fn update_subscription(
+        &self,
+        subscription_id: &str,
+        form_params: &[(String, String)],
+    ) -> PaymentResult<Subscription> {
+        let path = format!("/v1/subscriptions/{}", subscription_id);
+        let idempotency_key = format!("{}-{}", subscription_id, uuid::Uuid::new_v4());
+        let body = self.post_form(&path, &idempotency_key, form_params).await?;
+        parse_subscription(&body)
+    }
+
+    /// Make sure the Stripe product for a catalog product exists
+    pub(crate) async fn ensure_product(&self, product_id: &str, name: &str) -> PaymentResult<()> {
+        let path = format!("/v1/products/{}", product_id);
+        if self.get(&path, &[]).await?.is_some() {
+            return Ok(());
+        }
+
+        let form_params = vec![
+            ("id".to_string(), product_id.to_string()),
+            ("name".to_string(), name.to_string()),
+        ];
+        self.post_form("/v1/products", &format!("product-{}", product_id), &form_params)
+            .await?;
+        debug!("Created Stripe product {}", product_id);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SubscriptionStrategy for StripeCheckoutStrategy {
+    #[instrument(skip(self))]
+    async fn list_subscriptions(
+        &self,
+        query: &SubscriptionQuery,
+    ) -> PaymentResult<Vec<Subscription>> {
+        let limit = query.limit.map(|l| l.clamp(1, 100).to_string());
+        let mut params: Vec<(&str, &str)> = Vec::new();
+        if let Some(ref limit) = limit {
+            params.push(("limit", limit));
+        }
+
+        // Metadata can only be filtered with the Search API, which has no customer filter
+        let body = match query.site_id {
+            Some(ref site_id) => {
+                let mut clauses = vec![format!("metadata['site_id']:'{}'", search_literal(site_id))];
+                if let Some(status) = query.status {
+                    clauses.push(format!("status:'{}'", status.as_str()));
+                }
+                let search = clauses.join(" AND ");
+                params.push(("query", &search));
+                self.get("/v1/subscriptions/search", &params).await?
+            }
+            None => {
+                if let Some(ref customer_id) = query.customer_id {
+                    params.push(("customer", customer_id));
+                }
+                if let Some(status) = query.status {
+                    params.push(("status", status.as_str()));
+                }
+                self.get("/v1/subscriptions", &params).await?
+            }
+        }
+        .unwrap_or_default();
+
+        let list: StripeList<SubscriptionData> = serde_json::from_str(&body).map_err(|e| {
+            PaymentError::Serialization(format!("Failed to parse Stripe subscriptions: {}", e))
+        })?;
+        list.data
+            .iter()
+            .filter(|s| query.customer_id.as_ref().map_or(true, |c| *c == s.customer_id))
+            .map(SubscriptionData::to_subscription)
+            .collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn retrieve_subscription(&self, subscription_id: &str) -> PaymentResult<Subscription> {
+        let path = format!("/v1/subscriptions/{}", subscription_id);
+        let body = self
+            .get(&path, &[])
+            .await?
+            .ok_or_else(|| not_found(subscription_id))?;
+        parse_subscription(&body)
+    }
+
+    #[instrument(skip(self))]
+    async fn cancel_at_period_end(&self, subscription_id: &str) -> PaymentResult<Subscription> {
+        let form_params = vec![("cancel_at_period_end".to_string(), "true".to_string())];
+        let subscription = self.update_subscription(subscription_id, &form_params).await?;
+        info!("Stripe subscription {} cancels at period end", subscription_id);
+        Ok(subscription)
+    }
+
+    #[instrument(skip(self))]
+    async fn cancel_now(&self, subscription_id: &str) -> PaymentResult<Subscription> {
+        let path = format!("/v1/subscriptions/{}", subscription_id);
+        let body = self
+            .delete(&path)
+            .await?
+            .ok_or_else(|| not_found(subscription_id))?;
+        info!("Cancelled Stripe subscription {}", subscription_id);
+        parse_subscription(&body)
+    }
+
+    #[instrument(skip(self))]
+    async fn pause(
+        &self,
+        subscription_id: &str,
+        pause: &PauseCollection,
+    ) -> PaymentResult<Subscription> {
+        let mut form_params = vec![(
+            "pause_collection[behavior]".to_string(),
+            pause.behavior.as_str().to_string(),
+        )];
+        if let Some(resumes_at) = pause.resumes_at {
+            form_params.push((
+                "pause_collection[resumes_at]".to_string(),
+                resumes_at.timestamp().to_string(),
+            ));
+        }
+        let subscription = self.update_subscription(subscription_id, &form_params).await?;
+        info!("Paused collection of Stripe subscription {}", subscription_id);
+        Ok(subscription)
+    }
+
+    #[instrument(skip(self))]
+    async fn resume(&self, subscription_id: &str) -> PaymentResult<Subscription> {
+        // An empty value unsets pause_collection
+        let form_params = vec![
+            ("pause_collection".to_string(), String::new()),
+            ("cancel_at_period_end".to_string(), "false".to_string()),
+        ];
+        let subscription = self.update_subscription(subscription_id, &form_params).await?;
+        info!("Resumed Stripe subscription {}", subscription_id);
+        Ok(subscription)
+    }
+
+    #[instrument(skip(self, change))]
+    async fn change_plan(
+        &self,
+        subscription_id: &str,
+        change: &PlanChange,
+    ) -> PaymentResult<Subscription> {
+        change.validate()?;
+
+        let current = self.retrieve_subscription(subscription_id).await?;
+        let line = match change.item_id {
+            Some(ref item_id) => current.lines.iter().find(|l| l.item_id == *item_id),
+            None if current.lines.len() == 1 => current.lines.first(),
+            None => {
+                return Err(PaymentError::InvalidRequest(format!(
+                    "subscription {} has {} items, choose one with item_id",
+                    subscription_id,
+                    current.lines.len()
+                )))
+            }
+        }
+        .ok_or_else(|| {
+            PaymentError::InvalidRequest(format!(
+                "subscription {} has no item {}",
+                subscription_id,
+                change.item_id.as_deref().unwrap_or_default()
+            ))
+        })?;
+
+        let mut form_params = vec![
+            ("items[0][id]".to_string(), line.item_id.clone()),
+            (
+                "proration_behavior".to_string(),
+                change.proration.as_str().to_string(),
+            ),
+        ];
+        if let Some(quantity) = change.quantity {
+            form_params.push(("items[0][quantity]".to_string(), quantity.to_string()));
+        }
+        if let Some(ref plan) = change.plan {
+            let unit = plan.interval.unit().ok_or_else(|| {
+                PaymentError::InvalidRequest(format!(
+                    "{} is not a subscription product",
+                    plan.product_id
+                ))
+            })?;
+            if let Some(ref usage) = plan.usage {
+                let price = UsagePrice {
+                    product_id: &plan.product_id,
+                    name: &plan.name,
+                    unit_price: &plan.unit_price,
+                    interval: plan.interval,
+                    usage,
+                    tax_behavior: None,
+                };
+                form_params.push(("items[0][price]".to_string(), self.usage_price_id(&price).await?));
+                // Metered items have no quantity
+                if usage.is_metered() {
+                    form_params.retain(|(key, _)| key != "items[0][quantity]");
+                }
+            } else {
+                self.ensure_product(&plan.product_id, &plan.name).await?;
+                form_params.extend([
+                    (
+                        "items[0][price_data][currency]".to_string(),
+                        plan.unit_price.currency.as_str().to_string(),
+                    ),
+                    ("items[0][price_data][product]".to_string(), plan.product_id.clone()),
+                    (
+                        "items[0][price_data][unit_amount]".to_string(),
+                        to_stripe_amount(&plan.unit_price)?.to_string(),
+                    ),
+                    (
+                        "items[0][price_data][recurring][interval]".to_string(),
+                        unit.as_str().to_string(),
+                    ),
+                    (
+                        "items[0][price_data][recurring][interval_count]".to_string(),
+                        plan.interval.count().to_string(),
+                    ),
+                ]);
+            }
+            form_params.push(("metadata[product_id]".to_string(), plan.product_id.clone()));
+        }
+
+        let subscription = self.update_subscription(subscription_id, &form_params).await?;
+        info!(
+            "Changed Stripe subscription {} (plan {}, quantity {:?})",
+            subscription_id,
+            change.plan.as_ref().map_or("unchanged", |p| p.product_id.as_str()),
+            change.quantity
+        );
+        Ok(subscription)
+    }
+
+    #[instrument(skip(self, record), fields(item_id = %record.subscription_item_id))]
+    async fn report_usage(&self, record: &UsageRecord) -> PaymentResult<UsageReport> {
+        self.create_usage_record(record).await
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "stripe"
+    }
+}
+
+fn parse_subscription(body: &str) -> PaymentResult<Subscription> {
+    let data: SubscriptionData = serde_json::from_str(body).map_err(|e| {
+        PaymentError::Serialization(format!("Failed to parse Stripe subscription: {}", e))
+    })?;
+    data.to_subscription()
+}
+
+fn not_found(subscription_id: &str) -> PaymentError {
+    PaymentError::SubscriptionNotFound {
+        subscription_id: subscription_id.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StripeConfig;
+    use pay_core::{
+        BillingInterval, Currency, Plan, Price, ProrationBehavior, SubscriptionStatus,
+    };
+    use serde_json::json;
+    use wiremock::matchers::{body_string_contains, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn subscription(price: &str, amount: i64, quantity: u32) -> serde_json::Value {
+        json!({
+            "id": "sub_1",
+            "customer": "cus_1",
+            "status": "active",
+            "cancel_at_period_end": false,
+            "current_period_end": 1700000000,
+            "metadata": {"site_id": "chargegun"},
+            "items": {"data": [{
+                "id": "si_1",
+                "quantity": quantity,
+                "price": {
+                    "id": price,
+                    "product": "rang-play-rs-pro",
+                    "unit_amount": amount,
+                    "currency": "usd",
+                    "recurring": {"interval": "month"}
+                }
+            }]}
+        })
+    }
+
+    fn strategy(server: &MockServer) -> StripeCheckoutStrategy {
+        StripeCheckoutStrategy::new(
+            StripeConfig::new("sk_test_abc", "pk_test_xyz", "whsec_123")
+                .with_api_base_url(server.uri()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_change_plan_with_proration() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/subscriptions/sub_1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(subscription("price_old", 2900, 1)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/products/site-ranker-rs-saas"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "error": {"message": "No such product", "code": "resource_missing"}
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/products"))
+            .and(body_string_contains("id=site-ranker-rs-saas"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "site-ranker-rs-saas"})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/subscriptions/sub_1"))
+            .and(body_string_contains("items%5B0%5D%5Bid%5D=si_1"))
+            .and(body_string_contains("items%5B0%5D%5Bquantity%5D=2"))
+            .and(body_string_contains("items%5B0%5D%5Bprice_data%5D%5Bunit_amount%5D=4900"))
+            .and(body_string_contains("proration_behavior=always_invoice"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(subscription("price_new", 4900, 2)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let plan = Plan {
+            product_id: "site-ranker-rs-saas".into(),
+            name: "Site Ranker RS - SaaS".into(),
+            unit_price: Price::from_cents(4900, Currency::USD),
+            interval: BillingInterval::MONTHLY,
+            usage: None,
+        };
+        let change = PlanChange::to_plan(plan)
+            .with_quantity(2)
+            .with_proration(ProrationBehavior::AlwaysInvoice);
+        let updated = strategy(&server).change_plan("sub_1", &change).await.unwrap();
+
+        assert_eq!(updated.status, SubscriptionStatus::Active);
+        assert_eq!(updated.site_id(), Some("chargegun"));
+        assert_eq!(updated.lines[0].quantity, 2);
+        assert_eq!(updated.lines[0].unit_price, Some(Price::from_cents(4900, Currency::USD)));
+        assert_eq!(updated.lines[0].interval, BillingInterval::MONTHLY);
+    }
+
+    #[tokio::test]
+    async fn test_list_by_site_and_cancel() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/subscriptions/search"))
+            .and(query_param("query", "metadata['site_id']:'chargegun' AND status:'active'"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [subscription("price_1", 2900, 1)],
+                "has_more": false
+            })))
+            .mount(&server)
+            .await;
+        let mut cancelled = subscription("price_1", 2900, 1);
+        cancelled["status"] = json!("canceled");
+        cancelled["canceled_at"] = json!(1700000000);
+        Mock::given(method("DELETE"))
+            .and(path("/v1/subscriptions/sub_1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(cancelled))
+            .mount(&server)
+            .await;
+
+        let strategy = strategy(&server);
+        let query = SubscriptionQuery::new()
+            .with_site("chargegun")
+            .with_status(pay_core::SubscriptionStatus::Active);
+        let subscriptions = strategy.list_subscriptions(&query).await.unwrap();
+        assert_eq!(subscriptions.len(), 1);
+        let other = query.clone().with_customer("cus_2");
+        assert!(strategy.list_subscriptions(&other).await.unwrap().is_empty());
+
+        // Site IDs are escaped in the search query
+        Mock::given(method("GET"))
+            .and(path("/v1/subscriptions/search"))
+            .and(query_param("query", r"metadata['site_id']:'o\'1\\'"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": [], "has_more": false})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let quoted = SubscriptionQuery::new().with_site(r"o'1\");
+        assert!(strategy.list_subscriptions(&quoted).await.unwrap().is_empty());
+
+        let cancelled = strategy.cancel_now("sub_1").await.unwrap();
+        assert_eq!(cancelled.status, SubscriptionStatus::Canceled);
+        assert!(cancelled.canceled_at.is_some());
+        assert!(matches!(
+            strategy.cancel_now("sub_missing").await,
+            Err(PaymentError::SubscriptionNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,44 @@
+//! # Stripe Tax
+//!
+//! Tax pass-through to Stripe Tax.
+//! Tax is calculated by Stripe on the hosted checkout page from the
+//! customer's address, so the order only records that tax is automatic.
+//! `StripeCheckoutStrategy` then sets `automatic_tax[enabled]=true`, and the
+//! `checkout.session.completed` webhook fills in the tax Stripe charged
+//! (`CheckoutCompletedData::amount_tax`).
+
+use pay_core::{Order, PaymentResult, Price, TaxBreakdown, TaxCalculator, TaxLocation, TaxMode};
+
+/// Stripe Tax calculator
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripeTaxCalculator {
+    mode: TaxMode,
+}
+
+impl StripeTaxCalculator {
+    /// Create a Stripe Tax calculator
+    pub fn new(mode: TaxMode) -> Self {
+        Self { mode }
+    }
+}
+
+impl TaxCalculator for StripeTaxCalculator {
+    fn name(&self) -> &'static str {
+        "stripe"
+    }
+
+    fn calculate(
+        &self,
+        order: &Order,
+        location: Option<&TaxLocation>,
+    ) -> PaymentResult<TaxBreakdown> {
+        Ok(TaxBreakdown {
+            mode: self.mode,
+            amount: Price::zero(order.currency),
+            lines: Vec::new(),
+            automatic: true,
+            charged: false,
+            location: location.cloned(),
+        })
+    }
+}
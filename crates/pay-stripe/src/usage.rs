@@ -0,0 +1,331 @@
+//! # Stripe Usage Billing
+//!
+//! Recurring prices for seat-based, tiered and metered products, and usage
+//! records for metered subscription items.
+//!
+//! Checkout's inline `price_data` cannot express tiers or metered usage, so
+//! these items get a real Stripe price. Each price has a `lookup_key` derived
+//! from its parameters: an existing price is reused, and changing the
+//! catalog creates a new price instead of altering the old one.
+//!
+//! Usage records only exist up to API version 2024-12-18.acacia, which is
+//! why [`STRIPE_API_VERSION`](crate::config::STRIPE_API_VERSION) is pinned there.
+
+use crate::amount::to_stripe_amount;
+use crate::checkout::{StripeCheckoutStrategy, StripeIdResponse};
+use crate::events::StripeList;
+use chrono::{DateTime, Utc};
+use pay_core::{
+    BillingInterval, PaymentError, PaymentResult, Price, UsageModel, UsageRecord, UsageReport,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::{debug, info};
+
+/// A Stripe price for a usage-billed item
+pub(crate) struct UsagePrice<'a> {
+    pub product_id: &'a str,
+    pub name: &'a str,
+    pub unit_price: &'a Price,
+    pub interval: BillingInterval,
+    pub usage: &'a UsageModel,
+    /// "inclusive" or "exclusive" (None = not set)
+    pub tax_behavior: Option<&'a str>,
+}
+
+impl UsagePrice<'_> {
+    /// Price parameters, without the lookup key
+    fn form_params(&self) -> PaymentResult<Vec<(String, String)>> {
+        let unit = self.interval.unit().ok_or_else(|| {
+            PaymentError::InvalidRequest(format!(
+                "usage billing needs a subscription: {}",
+                self.product_id
+            ))
+        })?;
+        self.usage.validate()?;
+
+        let mut form_params = vec![
+            ("currency".to_string(), self.unit_price.currency.as_str().to_string()),
+            ("product".to_string(), self.product_id.to_string()),
+            ("recurring[interval]".to_string(), unit.as_str().to_string()),
+            ("recurring[interval_count]".to_string(), self.interval.count().to_string()),
+            ("recurring[usage_type]".to_string(), self.usage.usage_type.as_str().to_string()),
+        ];
+        if self.usage.is_metered() {
+            form_params.push((
+                "recurring[aggregate_usage]".to_string(),
+                self.usage.aggregation.as_str().to_string(),
+            ));
+        }
+        if let Some(tax_behavior) = self.tax_behavior {
+            form_params.push(("tax_behavior".to_string(), tax_behavior.to_string()));
+        }
+
+        if !self.usage.is_tiered() {
+            form_params.push(("unit_amount".to_string(), to_stripe_amount(self.unit_price)?.to_string()));
+            return Ok(form_params);
+        }
+        form_params.push(("billing_scheme".to_string(), "tiered".to_string()));
+        form_params.push(("tiers_mode".to_string(), self.usage.tiers_mode.as_str().to_string()));
+        let currency = self.unit_price.currency;
+        let amount = |minor: i64| to_stripe_amount(&Price::from_cents(minor, currency));
+        for (i, tier) in self.usage.tiers.iter().enumerate() {
+            let up_to = tier.up_to.map_or_else(|| "inf".to_string(), |n| n.to_string());
+            form_params.push((format!("tiers[{}][up_to]", i), up_to));
+            form_params.push((
+                format!("tiers[{}][unit_amount]", i),
+                amount(tier.unit_amount)?.to_string(),
+            ));
+            if tier.flat_amount > 0 {
+                form_params.push((
+                    format!("tiers[{}][flat_amount]", i),
+                    amount(tier.flat_amount)?.to_string(),
+                ));
+            }
+        }
+        Ok(form_params)
+    }
+}
+
+/// Lookup key for a price: the product ID and a hash of the parameters
+fn lookup_key(product_id: &str, form_params: &[(String, String)]) -> String {
+    let mut hasher = Sha256::new();
+    for (key, value) in form_params {
+        hasher.update(key.as_bytes());
+        hasher.update(b"=");
+        hasher.update(value.as_bytes());
+        hasher.update(b"&");
+    }
+    let digest = hex::encode(hasher.finalize());
+    format!("lc_{}_{}", product_id, &digest[..16])
+}
+
+impl StripeCheckoutStrategy {
+    /// Get (or create) the Stripe price for a usage-billed item.
+    ///
+    /// Prices are cached for the lifetime of the strategy.
+    pub(crate) async fn usage_price_id(&self, price: &UsagePrice<'_>) -> PaymentResult<String> {
+        let mut form_params = price.form_params()?;
+        let key = lookup_key(price.product_id, &form_params);
+        if let Some(id) = self.usage_price_ids.lock().ok().and_then(|ids| ids.get(&key).cloned()) {
+            return Ok(id);
+        }
+
+        let body = self
+            .get(
+                "/v1/prices",
+                &[("lookup_keys[]", key.as_str()), ("active", "true"), ("limit", "1")],
+            )
+            .await?
+            .unwrap_or_default();
+        let existing: StripeList<StripeIdResponse> = serde_json::from_str(&body).map_err(|e| {
+            PaymentError::Serialization(format!("Failed to parse Stripe prices: {}", e))
+        })?;
+        let id = match existing.data.into_iter().next() {
+            Some(existing) => existing.id,
+            None => {
+                self.ensure_product(price.product_id, price.name).await?;
+                form_params.push(("lookup_key".to_string(), key.clone()));
+                let body = self
+                    .post_form("/v1/prices", &format!("price-{}", key), &form_params)
+                    .await?;
+                let created: StripeIdResponse = serde_json::from_str(&body).map_err(|e| {
+                    PaymentError::Serialization(format!("Failed to parse Stripe price: {}", e))
+                })?;
+                debug!("Created Stripe price {} for {}", created.id, key);
+                created.id
+            }
+        };
+
+        if let Ok(mut ids) = self.usage_price_ids.lock() {
+            ids.insert(key, id.clone());
+        }
+        Ok(id)
+    }
+
+    /// Record usage of a metered subscription item
+    pub(crate) async fn create_usage_record(
+        &self,
+        record: &UsageRecord,
+    ) -> PaymentResult<UsageReport> {
+        let timestamp = record
+            .timestamp
+            .map_or_else(|| "now".to_string(), |t| t.timestamp().to_string());
+        let form_params = vec![
+            ("quantity".to_string(), record.quantity.to_string()),
+            ("timestamp".to_string(), timestamp),
+            ("action".to_string(), record.action.as_str().to_string()),
+        ];
+        // Without a client key every report is new; with one, retries are recorded once
+        let idempotency_key = match record.idempotency_key {
+            Some(ref key) => format!("usage-{}-{}", record.subscription_item_id, key),
+            None => format!("usage-{}", uuid::Uuid::new_v4()),
+        };
+
+        let path = format!(
+            "/v1/subscription_items/{}/usage_records",
+            record.subscription_item_id
+        );
+        let body = self.post_form(&path, &idempotency_key, &form_params).await?;
+        let created: StripeUsageRecord = serde_json::from_str(&body).map_err(|e| {
+            PaymentError::Serialization(format!("Failed to parse Stripe usage record: {}", e))
+        })?;
+
+        info!(
+            "Reported usage {} for Stripe item {}",
+            record.quantity, record.subscription_item_id
+        );
+        Ok(UsageReport {
+            id: created.id,
+            subscription_item_id: created.subscription_item,
+            quantity: created.quantity,
+            timestamp: DateTime::from_timestamp(created.timestamp, 0).unwrap_or_else(Utc::now),
+        })
+    }
+}
+
+/// Stripe usage record response
+#[derive(Debug, Deserialize)]
+struct StripeUsageRecord {
+    id: String,
+    subscription_item: String,
+    quantity: u64,
+    timestamp: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{StripeConfig, STRIPE_API_VERSION};
+    use pay_core::{
+        Currency, Order, PaymentStrategy, PriceTier, Product, SubscriptionStrategy, TiersMode,
+        UsageAction, UsageAggregation,
+    };
+    use serde_json::json;
+    use wiremock::matchers::{body_string_contains, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn strategy(server: &MockServer) -> StripeCheckoutStrategy {
+        StripeCheckoutStrategy::new(
+            StripeConfig::new("sk_test_abc", "pk_test_xyz", "whsec_123")
+                .with_api_base_url(server.uri()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_checkout_creates_metered_tiered_price() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/prices"))
+            .and(query_param("active", "true"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [], "has_more": false
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/products/api-access"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "api-access"})))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/prices"))
+            .and(body_string_contains("recurring%5Busage_type%5D=metered"))
+            .and(body_string_contains("recurring%5Baggregate_usage%5D=sum"))
+            .and(body_string_contains("billing_scheme=tiered"))
+            .and(body_string_contains("tiers_mode=graduated"))
+            .and(body_string_contains("tiers%5B0%5D%5Bup_to%5D=10000"))
+            .and(body_string_contains("tiers%5B1%5D%5Bup_to%5D=inf"))
+            .and(body_string_contains("lookup_key=lc_api-access_"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "price_metered"})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/checkout/sessions"))
+            .and(body_string_contains("line_items%5B0%5D%5Bprice%5D=price_metered"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "cs_test_1",
+                "url": "https://checkout.stripe.com/c/pay/cs_test_1"
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let product = Product::subscription(
+            "api-access",
+            "API Access",
+            Price::from_cents(0, Currency::USD),
+            BillingInterval::MONTHLY,
+        )
+        .with_usage(UsageModel::metered(UsageAggregation::Sum).with_tiers(
+            TiersMode::Graduated,
+            vec![PriceTier::new(Some(10000), 0), PriceTier::new(None, 1)],
+        ));
+        let mut order = Order::new(Currency::USD);
+        order.add_product(&product, 1).unwrap();
+        assert_eq!(order.subtotal().unwrap().amount, 0);
+
+        // The second checkout reuses the cached price
+        let strategy = strategy(&server);
+        for _ in 0..2 {
+            strategy
+                .create_checkout(&order, "https://x/success", "https://x/cancel")
+                .await
+                .unwrap();
+        }
+        let requests = server.received_requests().await.unwrap();
+        let session = requests.iter().find(|r| r.url.path() == "/v1/checkout/sessions").unwrap();
+        // Metered items have no quantity
+        assert!(!String::from_utf8_lossy(&session.body).contains("%5Bquantity%5D"));
+    }
+
+    #[tokio::test]
+    async fn test_report_usage() {
+        let server = MockServer::start().await;
+        let recorded = json!({
+            "id": "mbur_1",
+            "subscription_item": "si_1",
+            "quantity": 250,
+            "timestamp": 1700000000
+        });
+        Mock::given(method("POST"))
+            .and(path("/v1/subscription_items/si_1/usage_records"))
+            .and(header("Stripe-Version", STRIPE_API_VERSION))
+            .and(header("Idempotency-Key", "usage-si_1-batch-42"))
+            .and(body_string_contains("quantity=250"))
+            .and(body_string_contains("timestamp=1700000000"))
+            .and(body_string_contains("action=set"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(recorded.clone()))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/subscription_items/si_1/usage_records"))
+            .and(body_string_contains("action=increment"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(recorded))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let strategy = strategy(&server);
+        let record = UsageRecord::new("si_1", 250)
+            .with_action(UsageAction::Set)
+            .at(DateTime::from_timestamp(1_700_000_000, 0).unwrap())
+            .with_idempotency_key("batch-42");
+        let report = strategy.report_usage(&record).await.unwrap();
+        assert_eq!(report.id, "mbur_1");
+        assert_eq!(report.quantity, 250);
+        assert_eq!(report.timestamp.timestamp(), 1_700_000_000);
+
+        // A retry reuses the record's key; reports without one never collide
+        let unkeyed = UsageRecord::new("si_1", 250);
+        for record in [&record, &unkeyed, &unkeyed] {
+            strategy.report_usage(record).await.unwrap();
+        }
+        let requests = server.received_requests().await.unwrap();
+        let key = |i: usize| requests[i].headers.get("Idempotency-Key").unwrap().to_str().unwrap();
+        assert_ne!(key(2), key(3));
+    }
+}
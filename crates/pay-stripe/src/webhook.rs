@@ -3,8 +3,12 @@
 //! Utilities for handling Stripe webhooks.
 //! Webhooks notify your server of events (payments completed, subscriptions changed, etc.)
 
+use crate::amount::from_stripe_amount;
+use crate::events::{
+    ChargeData, CheckoutExpiredData, InvoiceData, PaymentIntentData, SubscriptionData,
+};
+use async_trait::async_trait;
 use pay_core::{Currency, PaymentError, PaymentResult, WebhookEvent, WebhookEventType};
-use serde::Deserialize;
 use tracing::{debug, info, warn};
 
 /// Parsed checkout.session.completed event data
@@ -15,7 +19,12 @@ pub struct CheckoutCompletedData {
     pub subscription_id: Option<String>,
     pub customer_id: Option<String>,
     pub customer_email: Option<String>,
+    /// Billing address country (ISO 3166-1 alpha-2), if collected
+    pub billing_country: Option<String>,
+    /// Total charged, in ISO 4217 minor units of `currency`
     pub amount_total: i64,
+    /// Tax charged (part of `amount_total`), in ISO 4217 minor units of `currency`
+    pub amount_tax: i64,
     pub currency: Currency,
     pub payment_status: String,
     pub metadata: std::collections::HashMap<String, String>,
@@ -59,27 +68,33 @@ impl CheckoutCompletedData {
             .and_then(|v| v.as_str())
             .map(String::from);
 
+        let billing_country = obj
+            .get("customer_details")
+            .and_then(|cd| cd.get("address"))
+            .and_then(|a| a.get("country"))
+            .and_then(|v| v.as_str())
+            .map(String::from);
+
+        let currency: Currency = obj
+            .get("currency")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| PaymentError::WebhookParseError("Missing currency".to_string()))?
+            .parse()?;
+
         let amount_total = obj
             .get("amount_total")
             .and_then(|v| v.as_i64())
-            .unwrap_or(0);
+            .map(|amount| from_stripe_amount(amount, currency))
+            .transpose()?
+            .map_or(0, |price| price.amount);
 
-        let currency_str = obj
-            .get("currency")
-            .and_then(|v| v.as_str())
-            .unwrap_or("usd");
-
-        let currency = match currency_str.to_lowercase().as_str() {
-            "usd" => Currency::USD,
-            "eur" => Currency::EUR,
-            "gbp" => Currency::GBP,
-            "jpy" => Currency::JPY,
-            "cad" => Currency::CAD,
-            "aud" => Currency::AUD,
-            "chf" => Currency::CHF,
-            "mxn" => Currency::MXN,
-            _ => Currency::USD,
-        };
+        let amount_tax = obj
+            .get("total_details")
+            .and_then(|td| td.get("amount_tax"))
+            .and_then(|v| v.as_i64())
+            .map(|amount| from_stripe_amount(amount, currency))
+            .transpose()?
+            .map_or(0, |price| price.amount);
 
         let payment_status = obj
             .get("payment_status")
@@ -103,7 +118,9 @@ impl CheckoutCompletedData {
             subscription_id,
             customer_id,
             customer_email,
+            billing_country,
             amount_total,
+            amount_tax,
             currency,
             payment_status,
             metadata,
@@ -123,11 +140,14 @@ impl CheckoutCompletedData {
 
 /// Webhook event handler trait
 ///
-/// Implement this trait to handle different webhook events.
+/// Implement this trait to handle different webhook events. Handlers run in
+/// the webhook worker pool, off the HTTP request: an error makes the event
+/// retriable, so handlers must tolerate seeing an event more than once.
 #[allow(unused_variables)]
+#[async_trait]
 pub trait WebhookHandler: Send + Sync {
     /// Called when a checkout session is completed
-    fn on_checkout_completed(&self, data: CheckoutCompletedData) -> PaymentResult<()> {
+    async fn on_checkout_completed(&self, data: CheckoutCompletedData) -> PaymentResult<()> {
         info!(
             "Checkout completed: session={}, amount={}",
             data.session_id, data.amount_total
@@ -135,44 +155,78 @@ pub trait WebhookHandler: Send + Sync {
         Ok(())
     }
 
+    /// Called when a checkout session expires unpaid
+    async fn on_checkout_expired(&self, data: CheckoutExpiredData) -> PaymentResult<()> {
+        info!("Checkout expired: session={}", data.session_id);
+        Ok(())
+    }
+
     /// Called when a payment succeeds
-    fn on_payment_succeeded(&self, event: &WebhookEvent) -> PaymentResult<()> {
-        info!("Payment succeeded: {:?}", event.payment_intent_id);
+    async fn on_payment_succeeded(&self, data: PaymentIntentData) -> PaymentResult<()> {
+        info!(
+            "Payment succeeded: {}, amount={}",
+            data.payment_intent_id, data.amount_received
+        );
         Ok(())
     }
 
     /// Called when a payment fails
-    fn on_payment_failed(&self, event: &WebhookEvent) -> PaymentResult<()> {
-        warn!("Payment failed: {:?}", event.payment_intent_id);
+    async fn on_payment_failed(&self, data: PaymentIntentData) -> PaymentResult<()> {
+        warn!(
+            "Payment failed: {}: {}",
+            data.payment_intent_id,
+            data.failure_message().unwrap_or("unknown error")
+        );
         Ok(())
     }
 
     /// Called when a subscription is created
-    fn on_subscription_created(&self, event: &WebhookEvent) -> PaymentResult<()> {
-        info!("Subscription created: {:?}", event.session_id);
+    async fn on_subscription_created(&self, data: SubscriptionData) -> PaymentResult<()> {
+        info!("Subscription created: {} ({})", data.subscription_id, data.status);
+        Ok(())
+    }
+
+    /// Called when a subscription changes
+    async fn on_subscription_updated(&self, data: SubscriptionData) -> PaymentResult<()> {
+        info!("Subscription updated: {} ({})", data.subscription_id, data.status);
         Ok(())
     }
 
     /// Called when a subscription is cancelled
-    fn on_subscription_cancelled(&self, event: &WebhookEvent) -> PaymentResult<()> {
-        info!("Subscription cancelled: {:?}", event.session_id);
+    async fn on_subscription_cancelled(&self, data: SubscriptionData) -> PaymentResult<()> {
+        info!("Subscription cancelled: {}", data.subscription_id);
         Ok(())
     }
 
-    /// Called when a subscription renews
-    fn on_subscription_renewed(&self, event: &WebhookEvent) -> PaymentResult<()> {
-        info!("Subscription renewed: {:?}", event.session_id);
+    /// Called when an invoice is paid (including subscription renewals)
+    async fn on_subscription_renewed(&self, data: InvoiceData) -> PaymentResult<()> {
+        info!(
+            "Invoice paid: {}, subscription={:?}, amount={}",
+            data.invoice_id, data.subscription_id, data.amount_paid
+        );
+        Ok(())
+    }
+
+    /// Called when an invoice payment fails
+    async fn on_invoice_payment_failed(&self, data: InvoiceData) -> PaymentResult<()> {
+        warn!(
+            "Invoice payment failed: {}, subscription={:?}, attempt={}",
+            data.invoice_id, data.subscription_id, data.attempt_count
+        );
         Ok(())
     }
 
-    /// Called when a refund is issued
-    fn on_refund_issued(&self, event: &WebhookEvent) -> PaymentResult<()> {
-        info!("Refund issued: {:?}", event.payment_intent_id);
+    /// Called when a charge is refunded (fully or partially)
+    async fn on_refund_issued(&self, data: ChargeData) -> PaymentResult<()> {
+        info!(
+            "Refund issued: {}, refunded={} of {}",
+            data.charge_id, data.amount_refunded, data.amount
+        );
         Ok(())
     }
 
     /// Called for unknown/unhandled events
-    fn on_unknown_event(&self, event: &WebhookEvent) -> PaymentResult<()> {
+    async fn on_unknown_event(&self, event: &WebhookEvent) -> PaymentResult<()> {
         debug!("Unhandled webhook event: {:?}", event.event_type);
         Ok(())
     }
@@ -181,25 +235,47 @@ pub trait WebhookHandler: Send + Sync {
 /// Default no-op webhook handler (just logs events)
 pub struct LoggingWebhookHandler;
 
+#[async_trait]
 impl WebhookHandler for LoggingWebhookHandler {}
 
 /// Dispatch a webhook event to the appropriate handler method
-pub fn dispatch_webhook_event(
+pub async fn dispatch_webhook_event(
     handler: &dyn WebhookHandler,
     event: WebhookEvent,
 ) -> PaymentResult<()> {
     match &event.event_type {
         WebhookEventType::CheckoutCompleted => {
             let data = CheckoutCompletedData::from_event(&event)?;
-            handler.on_checkout_completed(data)
+            handler.on_checkout_completed(data).await
+        }
+        WebhookEventType::CheckoutExpired => {
+            handler.on_checkout_expired(CheckoutExpiredData::from_event(&event)?).await
+        }
+        WebhookEventType::PaymentSucceeded => {
+            handler.on_payment_succeeded(PaymentIntentData::from_event(&event)?).await
+        }
+        WebhookEventType::PaymentFailed => {
+            handler.on_payment_failed(PaymentIntentData::from_event(&event)?).await
+        }
+        WebhookEventType::SubscriptionCreated => {
+            handler.on_subscription_created(SubscriptionData::from_event(&event)?).await
+        }
+        WebhookEventType::SubscriptionUpdated => {
+            handler.on_subscription_updated(SubscriptionData::from_event(&event)?).await
+        }
+        WebhookEventType::SubscriptionCancelled => {
+            handler.on_subscription_cancelled(SubscriptionData::from_event(&event)?).await
         }
-        WebhookEventType::PaymentSucceeded => handler.on_payment_succeeded(&event),
-        WebhookEventType::PaymentFailed => handler.on_payment_failed(&event),
-        WebhookEventType::SubscriptionCreated => handler.on_subscription_created(&event),
-        WebhookEventType::SubscriptionCancelled => handler.on_subscription_cancelled(&event),
-        WebhookEventType::SubscriptionRenewed => handler.on_subscription_renewed(&event),
-        WebhookEventType::RefundIssued => handler.on_refund_issued(&event),
-        WebhookEventType::Unknown(_) => handler.on_unknown_event(&event),
+        WebhookEventType::SubscriptionRenewed => {
+            handler.on_subscription_renewed(InvoiceData::from_event(&event)?).await
+        }
+        WebhookEventType::InvoicePaymentFailed => {
+            handler.on_invoice_payment_failed(InvoiceData::from_event(&event)?).await
+        }
+        WebhookEventType::RefundIssued => {
+            handler.on_refund_issued(ChargeData::from_event(&event)?).await
+        }
+        WebhookEventType::Unknown(_) => handler.on_unknown_event(&event).await,
     }
 }
 
@@ -253,9 +329,11 @@ mod tests {
                 "payment_intent": "pi_test_456",
                 "customer": "cus_test_789",
                 "customer_details": {
-                    "email": "test@example.com"
+                    "email": "test@example.com",
+                    "address": {"country": "US"}
                 },
                 "amount_total": 1000,
+                "total_details": {"amount_discount": 0, "amount_tax": 83},
                 "currency": "usd",
                 "payment_status": "paid",
                 "metadata": {
@@ -274,19 +352,40 @@ mod tests {
         assert_eq!(data.session_id, "cs_test_123");
         assert_eq!(data.payment_intent_id, Some("pi_test_456".to_string()));
         assert_eq!(data.customer_email, Some("test@example.com".to_string()));
+        assert_eq!(data.billing_country.as_deref(), Some("US"));
         assert_eq!(data.amount_total, 1000);
+        assert_eq!(data.amount_tax, 83);
+        assert_eq!(data.currency, Currency::USD);
         assert!(data.is_paid());
         assert_eq!(data.order_id(), Some("ord_test_abc"));
     }
 
     #[test]
-    fn test_dispatch_webhook() {
+    fn test_parse_checkout_completed_currency() {
+        let mut event = mock_checkout_event();
+        event.raw_data.as_mut().unwrap()["currency"] = json!("sek");
+        let data = CheckoutCompletedData::from_event(&event).unwrap();
+        assert_eq!(data.currency, Currency::SEK);
+
+        event.raw_data.as_mut().unwrap()["currency"] = json!("zzz");
+        assert!(matches!(
+            CheckoutCompletedData::from_event(&event),
+            Err(PaymentError::UnsupportedCurrency { .. })
+        ));
+
+        event.raw_data.as_mut().unwrap().as_object_mut().unwrap().remove("currency");
+        assert!(CheckoutCompletedData::from_event(&event).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_webhook() {
         struct TestHandler {
             called: std::sync::atomic::AtomicBool,
         }
 
+        #[async_trait]
         impl WebhookHandler for TestHandler {
-            fn on_checkout_completed(&self, _data: CheckoutCompletedData) -> PaymentResult<()> {
+            async fn on_checkout_completed(&self, _data: CheckoutCompletedData) -> PaymentResult<()> {
                 self.called.store(true, std::sync::atomic::Ordering::SeqCst);
                 Ok(())
             }
@@ -297,8 +396,44 @@ mod tests {
         };
 
         let event = mock_checkout_event();
-        dispatch_webhook_event(&handler, event).unwrap();
+        dispatch_webhook_event(&handler, event).await.unwrap();
 
         assert!(handler.called.load(std::sync::atomic::Ordering::SeqCst));
     }
+
+    #[tokio::test]
+    async fn test_dispatch_typed_invoice_failure() {
+        struct TestHandler {
+            failed: std::sync::Mutex<Option<InvoiceData>>,
+        }
+
+        #[async_trait]
+        impl WebhookHandler for TestHandler {
+            async fn on_invoice_payment_failed(&self, data: InvoiceData) -> PaymentResult<()> {
+                *self.failed.lock().unwrap() = Some(data);
+                Ok(())
+            }
+        }
+
+        let handler = TestHandler {
+            failed: std::sync::Mutex::new(None),
+        };
+        let mut event = mock_checkout_event();
+        event.event_type = WebhookEventType::InvoicePaymentFailed;
+        event.raw_data = Some(json!({
+            "id": "in_test",
+            "subscription": "sub_test",
+            "amount_due": 900,
+            "currency": "usd",
+            "attempt_count": 1
+        }));
+        dispatch_webhook_event(&handler, event.clone()).await.unwrap();
+
+        let failed = handler.failed.lock().unwrap().take().unwrap();
+        assert_eq!(failed.subscription_id.as_deref(), Some("sub_test"));
+
+        // Payloads that do not match the event type are rejected
+        event.raw_data = Some(json!({"id": "in_test"}));
+        assert!(dispatch_webhook_event(&handler, event).await.is_err());
+    }
 }
@@ -5,7 +5,7 @@
 //! This crate provides WASM-compatible functions for:
 //! - Creating checkout sessions from browser/edge
 //! - Validating cart data client-side
-//! - Price calculations
+//! - Price calculations and locale-aware formatting
 //!
 //! ## Usage (JavaScript)
 //!
@@ -27,7 +27,7 @@
 //! wasm-pack build --target web
 //! ```
 
-use pay_core::{Currency, LineItem, Order, Price, Product};
+use pay_core::{Currency, CurrencyDisplay, Locale, PaymentResult, Price};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
@@ -83,20 +83,27 @@ impl WasmCartItem {
 
     /// Calculate line item total in cents
     #[wasm_bindgen]
-    pub fn total_cents(&self) -> i64 {
-        self.price_cents * self.quantity as i64
+    pub fn total_cents(&self) -> Result<i64, JsValue> {
+        self.total().map(|p| p.amount).map_err(to_js_error)
     }
 
     /// Format price for display
     #[wasm_bindgen]
     pub fn format_price(&self) -> String {
-        format!("${:.2}", self.price_cents as f64 / 100.0)
+        format_price(self.price_cents)
     }
 
     /// Format total for display
     #[wasm_bindgen]
-    pub fn format_total(&self) -> String {
-        format!("${:.2}", self.total_cents() as f64 / 100.0)
+    pub fn format_total(&self) -> Result<String, JsValue> {
+        self.total().map(|p| p.display()).map_err(to_js_error)
+    }
+}
+
+impl WasmCartItem {
+    /// Line item total with overflow checking
+    fn total(&self) -> PaymentResult<Price> {
+        Price::from_cents(self.price_cents, Currency::USD).checked_mul(self.quantity)
     }
 }
 
@@ -106,14 +113,56 @@ pub fn calculate_cart_total(items: JsValue) -> Result<i64, JsValue> {
     let items: Vec<WasmCartItem> = serde_wasm_bindgen::from_value(items)
         .map_err(|e| JsValue::from_str(&format!("Invalid cart items: {}", e)))?;
 
-    let total: i64 = items.iter().map(|item| item.total_cents()).sum();
-    Ok(total)
+    cart_total(&items).map(|p| p.amount).map_err(to_js_error)
+}
+
+fn cart_total(items: &[WasmCartItem]) -> PaymentResult<Price> {
+    items.iter().try_fold(Price::zero(Currency::USD), |acc, item| {
+        acc.checked_add(&item.total()?)
+    })
 }
 
 /// Format a price in cents to display string
 #[wasm_bindgen]
 pub fn format_price(cents: i64) -> String {
-    format!("${:.2}", cents as f64 / 100.0)
+    Price::from_cents(cents, Currency::USD).display()
+}
+
+/// Format an amount in minor units for a currency and locale
+///
+/// `locale` is a BCP 47 tag such as "de-DE" (falls back to en-US);
+/// `show_code` displays "EUR" instead of "€".
+#[wasm_bindgen]
+pub fn format_price_locale(
+    amount: i64,
+    currency: &str,
+    locale: &str,
+    show_code: bool,
+) -> Result<String, JsValue> {
+    let currency: Currency = currency.parse().map_err(to_js_error)?;
+    Ok(format_minor_units(amount, currency, locale, show_code))
+}
+
+fn format_minor_units(amount: i64, currency: Currency, locale: &str, show_code: bool) -> String {
+    let locale = Locale::from_tag(locale).unwrap_or_default();
+    let display = if show_code {
+        CurrencyDisplay::Code
+    } else {
+        CurrencyDisplay::Symbol
+    };
+    Price::from_cents(amount, currency).format(&locale, display)
+}
+
+/// Convert a decimal string (e.g., "19.99") to cents without floating point
+#[wasm_bindgen]
+pub fn parse_price_cents(amount: &str) -> Result<i64, JsValue> {
+    Price::parse(amount, Currency::USD)
+        .map(|p| p.amount)
+        .map_err(to_js_error)
+}
+
+fn to_js_error(err: pay_core::PaymentError) -> JsValue {
+    JsValue::from_str(&err.to_string())
 }
 
 /// Validate a product ID format
@@ -150,13 +199,33 @@ mod tests {
             1999,
             2,
         );
-        assert_eq!(item.total_cents(), 3998);
+        assert_eq!(item.total().unwrap().amount, 3998);
+    }
+
+    #[test]
+    fn test_cart_total_overflow() {
+        let items = vec![
+            WasmCartItem::new("a".to_string(), "A".to_string(), i64::MAX / 2, 1),
+            WasmCartItem::new("b".to_string(), "B".to_string(), i64::MAX / 2, 1),
+        ];
+        assert!(cart_total(&items).is_ok());
+
+        let overflow = vec![WasmCartItem::new("c".to_string(), "C".to_string(), i64::MAX, 2)];
+        assert!(cart_total(&overflow).is_err());
     }
 
     #[test]
     fn test_format_price() {
         assert_eq!(format_price(1999), "$19.99");
         assert_eq!(format_price(100), "$1.00");
+        assert_eq!(format_price(29), "$0.29");
+    }
+
+    #[test]
+    fn test_format_price_locale() {
+        assert_eq!(format_minor_units(123456, Currency::EUR, "de-DE", false), "1.234,56 €");
+        assert_eq!(format_minor_units(123456, Currency::EUR, "de-DE", true), "1.234,56 EUR");
+        assert_eq!(format_minor_units(123456, Currency::USD, "zz", false), "$1,234.56");
     }
 
     #[test]